@@ -1,20 +1,123 @@
 //! Application state and core logic
 
 use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
 
-use crate::prefetch::{PrefetchManager, PrefetchStatus};
-use crate::search::SearchResult;
+use crate::bookmarks::{Bookmark, Bookmarks};
+use crate::cache_index::unix_now;
+use crate::citation::{self, Citation};
+use crate::clipboard::copy_to_clipboard;
+use crate::config::{
+    system_opener, CitationConfig, CitationDestination, Config, DigitKeys, EditorConfig,
+    Engine, OpenMode,
+};
+use crate::cooldown::{countdown_secs, Cooldowns};
+use crate::engine_stats::EngineStatsTable;
+use crate::export::{format_utc, Digest, ExportFormat};
+use crate::extract_clean_md::PageMetadata;
+use crate::goggles::{self, Goggle, GoggleMenu};
+use crate::history::{SearchHistory, SearchSnapshot};
+use crate::hooks;
+use crate::input::InputLine;
+use crate::keymap::{format_keys, Action, KeyChord, KeyContext, Keymap, Lookup};
+use crate::library::{load_library, LibraryEntry};
+use crate::local_search::{self, LocalMatch};
+use crate::metrics::{self, PrefetchTiming, SearchTiming};
+use crate::notes::{self, Note};
+use crate::obsidian;
+use crate::open_with::OpenWithMenu;
+use crate::outline::{read_headings, Outline};
+use crate::paths;
+use crate::prefetch::{remove_page, PageStats, PrefetchManager, PrefetchStatus};
+use crate::query_filters::QueryFilters;
+use crate::quickfix;
+use crate::read_log::ReadLog;
+use crate::row_cache::RowCache;
+use crate::search::{
+    self, site_host, site_query, Progress, RateLimited, SearchMeta, SearchProviders, SearchResult,
+};
+use crate::selection::Selection;
+use crate::session::Session;
+use crate::sort::{sort_results, SortMode};
+use crate::suggestions::{with_operators, SuggestionMenu};
 
 /// Messages sent from background tasks to the main app
 #[derive(Debug)]
 pub enum AppMessage {
     /// Search completed with results
-    SearchComplete(Vec<SearchResult>),
+    SearchComplete {
+        /// Search generation the results belong to
+        generation: u64,
+        results: Vec<SearchResult>,
+        /// Corrected or suggested queries, if the engine had any
+        meta: Option<SearchMeta>,
+    },
     /// Search failed with error
-    SearchError(String),
+    SearchError { generation: u64, error: String },
+    /// What the search is trying, like `trying searx.be…`
+    SearchProgress { generation: u64, message: String },
+    /// A further page of results of the search arrived
+    PageComplete {
+        generation: u64,
+        results: Vec<SearchResult>,
+    },
+    /// A further page of results couldn't be fetched
+    PageError { generation: u64, error: String },
+    /// A search or page request finished, with its result count or error
+    EngineReport {
+        engine: Engine,
+        /// Set when the engine refused the search as too many
+        rate_limited: Option<RateLimited>,
+        elapsed: Duration,
+        outcome: Result<usize, String>,
+    },
+    /// Prefetch status of a URL changed
+    PrefetchUpdate { url: String, status: PrefetchStatus },
+    /// A page was downloaded and saved
+    PageStats { url: String, stats: PageStats },
+    /// All prefetch tasks of the current batch finished
+    PrefetchDone {
+        /// Cached pages evicted to stay under the cache size cap
+        evicted: usize,
+    },
+    /// A search of the cached pages got further
+    LocalSearchProgress {
+        generation: u64,
+        scanned: usize,
+        total: usize,
+    },
+    /// A search of the cached pages finished
+    LocalSearchDone {
+        generation: u64,
+        matches: Vec<LocalMatch>,
+    },
+    /// A command started from the "open with" menu failed
+    OpenWithFailed { label: String, error: String },
+}
+
+impl AppMessage {
+    /// Report of a search with `engine` started at `started`
+    fn engine_report(
+        engine: Engine,
+        started: Instant,
+        result: Result<&[SearchResult], &anyhow::Error>,
+    ) -> Self {
+        AppMessage::EngineReport {
+            engine,
+            rate_limited: result.err().and_then(search::rate_limited).cloned(),
+            elapsed: started.elapsed(),
+            outcome: result.map(<[_]>::len).map_err(|e| e.to_string()),
+        }
+    }
 }
 
 /// Application state
@@ -28,170 +131,1153 @@ pub enum AppState {
     Results,
     /// Showing error message
     Error,
+    /// Browsing previously fetched pages
+    Library,
+    /// Browsing saved bookmarks
+    Bookmarks,
+    /// Browsing cached pages containing the query
+    LocalSearch,
+}
+
+/// How an error is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Nothing to show behind it; takes over the results pane
+    Fatal,
+    /// Shown over the current view for a few seconds, leaving it untouched
+    Recoverable,
 }
 
 /// Main application structure
 pub struct App {
+    /// Settings from `config.toml` and the environment
+    pub config: Config,
+    /// Backend of each search engine
+    pub providers: SearchProviders,
     pub state: AppState,
-    pub input: String,
-    pub cursor_pos: usize, // NEW: cursor position in chars not in bytes
+    pub input: InputLine,
     pub results: Vec<SearchResult>,
     pub selected_index: usize,
     pub scroll_offset: usize,
-    pub selected_items: HashSet<usize>,
+    pub selected_items: Selection,
+    /// Order of the results list, cycled with `s`
+    pub sort_mode: SortMode,
+    /// URLs of the results in the engine's order
+    original_order: Vec<String>,
+    /// Results removed with `d` and where they were, latest last
+    deleted: Vec<(usize, SearchResult)>,
     pub error_message: Option<String>,
+    /// Recoverable error shown over the current view, and when it appeared
+    pub error_popup: Option<(String, Instant)>,
     pub prefetch_manager: PrefetchManager,
+    /// Mirror of prefetch statuses, kept up to date by `PrefetchUpdate` messages
+    pub prefetch_statuses: HashMap<String, PrefetchStatus>,
+    /// Size, word count and fetch time of the pages downloaded for this search
+    pub page_stats: HashMap<String, PageStats>,
     /// Status message shown in UI
     pub status_message: String,
+    /// When the status message was set (for auto-expiry)
+    pub status_set_at: Option<Instant>,
+    /// The status message is about prefetching, so progress may replace it
+    status_is_progress: bool,
+    /// Pending multi-key input in the results list (counts, `gg`)
+    pub key_sequence: KeySequence,
+    /// Number of whole results visible in the list (updated on draw)
+    pub visible_items: usize,
+    /// Screen areas of the search box and the results list (updated on draw)
+    pub input_area: Rect,
+    pub results_area: Rect,
+    /// Result and time of the last left click, to detect double clicks
+    last_click: Option<(usize, Instant)>,
+    /// Handle of the running search task, if any
+    search_task: Option<JoinHandle<()>>,
+    /// Handle of the task fetching the next page of results, if any
+    page_task: Option<JoinHandle<()>>,
+    /// Pages of results of the search on screen fetched so far
+    pages_loaded: usize,
+    /// Incremented for every search; stale task messages are ignored
+    pub search_generation: u64,
+    /// Generation of the search given on the command line
+    launch_generation: Option<u64>,
+    /// Configuration problems found at startup, shown until a key press
+    pub notices: Vec<String>,
+    /// Engines greyed out in the help bar: no API key, or a rejected one
+    pub unusable_engines: Vec<Engine>,
+    /// Pages opened before, dimmed in the results
+    pub read_log: ReadLog,
+    /// Built lines of the result rows, reused while they don't change
+    pub row_cache: RowCache,
+    /// Latest progress of the running search, shown while searching
+    pub search_progress: Option<String>,
+    /// Corrected or suggested queries of the results on screen
+    pub search_meta: Option<SearchMeta>,
+    /// When the running search started
+    search_started: Option<Instant>,
+    /// How long the search on screen and its prefetching took
+    pub search_timing: Option<SearchTiming>,
+    /// Suggestion picker, shown over the results with Ctrl+Y
+    pub suggestion_menu: Option<SuggestionMenu>,
+    /// Query and engine of the latest search, saved with the session
+    pub last_search: Option<(String, Engine)>,
+    /// Latest outcome of each engine, shown in the engine status panel
+    pub engine_stats: EngineStatsTable,
+    /// Engines resting after rate limiting this client
+    pub cooldowns: Cooldowns,
+    /// Whether the engine status panel is shown, toggled with Ctrl+S
+    pub engine_status: bool,
+    /// Whether the full title, URL and description of the selected result
+    /// are shown, toggled with `i`
+    pub preview: bool,
+    /// Goggle picker, shown over the results with Ctrl+G
+    pub goggle_menu: Option<GoggleMenu>,
+    /// Goggle Alt+G turns back on
+    goggle_choice: Option<Goggle>,
+    /// Name of the goggle that ranked the results on screen
+    pub results_goggle: Option<String>,
+    /// Earlier result sets, for `[` and `]`
+    pub history: SearchHistory,
+    /// Set while Enter waits for the selected page to finish prefetching
+    pub open_wait_since: Option<Instant>,
+    /// URLs waiting for `y` before they are opened in the browser
+    pub pending_open: Option<Vec<String>>,
+    /// Headings of the selected page, shown over the results with `:`
+    pub outline: Option<Outline>,
+    /// Commands for the selected result, shown over the results with `o`
+    pub open_with: Option<OpenWithMenu>,
+    /// Note being typed for the selected page, and the page's URL
+    pub note_input: Option<(String, InputLine)>,
+    /// Notes for pages not fetched yet, written once they are
+    pending_notes: HashMap<String, Vec<Note>>,
+    /// Cached pages shown in the Library
+    pub library: Vec<LibraryEntry>,
+    /// Selected position within the filtered Library list
+    pub library_selected: usize,
+    /// Substring filter for the Library
+    pub library_filter: InputLine,
+    /// Whether keys currently edit the Library filter
+    pub library_filtering: bool,
+    /// Results saved with `m`
+    pub bookmarks: Bookmarks,
+    /// Selected position in the Bookmarks view
+    pub bookmark_selected: usize,
+    /// Prefetch statuses of bookmarked pages, shown in the Bookmarks view
+    pub bookmark_statuses: HashMap<String, PrefetchStatus>,
+    /// Query of the search of the cached pages
+    pub local_query: String,
+    /// Cached pages containing the query, best first
+    pub local_matches: Vec<LocalMatch>,
+    /// Selected position in the cached page matches
+    pub local_selected: usize,
+    /// Files scanned and to scan while the cached pages are searched
+    pub local_progress: Option<(usize, usize)>,
+    /// Incremented for every search of the cached pages
+    local_generation: u64,
+}
+
+/// Cursor motions in the results list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    /// `j` / Down
+    Down,
+    /// `k` / Up
+    Up,
+    /// `gg` (or `{count}gg` to jump to a result number)
+    Top,
+    /// `G` (or `{count}G` to jump to a result number)
+    Bottom,
+    /// Ctrl+D
+    HalfPageDown,
+    /// Ctrl+U
+    HalfPageUp,
+    /// `H`: top of the visible window
+    WindowTop,
+    /// `M`: middle of the visible window
+    WindowMiddle,
+    /// `L`: bottom of the visible window
+    WindowBottom,
+}
+
+/// Result of feeding a key into a [`KeySequence`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    /// A complete motion with its count (1 when no prefix was typed)
+    Motion(Motion, usize),
+    /// A complete binding of any other action
+    Command(Action),
+    /// Key was consumed, waiting for more input
+    Pending,
+    /// A digit picked result `number` (1-based), see [`DigitKeys::Jump`];
+    /// `open` when it was the same digit again
+    Jump { number: usize, open: bool },
+    /// Esc cleared a pending sequence
+    Cancelled,
+    /// No binding matches, caller should handle the key
+    Unhandled,
+}
+
+/// Vim-style multi-key state machine over the keymap
+///
+/// Accumulates count prefixes (`5j`, results list only) and multi-key
+/// bindings like `gg`. With `ui.digit_keys = "jump"` digits pick results
+/// instead of starting a count.
+#[derive(Debug, Default)]
+pub struct KeySequence {
+    keymap: Keymap,
+    digit_keys: DigitKeys,
+    count: Option<usize>,
+    /// Result picked by the last digit, and when, for the second press
+    jump: Option<(usize, Instant)>,
+    /// Keys of an unfinished binding
+    pending: Vec<KeyChord>,
+    pending_since: Option<Instant>,
+}
+
+/// Maximum delay between the keys of a binding like `gg`
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Maximum delay between two presses of a digit that open its result
+const DOUBLE_PRESS: Duration = Duration::from_millis(400);
+
+/// Files scanned between progress updates of a cached page search
+const LOCAL_PROGRESS_EVERY: usize = 20;
+
+/// How long Enter waits for a page that is still loading
+const OPEN_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most removed results `u` can bring back
+const MAX_UNDO: usize = 20;
+
+/// Most tabs opened in the browser without asking first
+const MAX_TABS_WITHOUT_CONFIRM: usize = 5;
+
+/// Frame duration of the "waiting for page" spinner
+pub const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+impl KeySequence {
+    pub fn new(keymap: Keymap, digit_keys: DigitKeys) -> Self {
+        Self {
+            keymap,
+            digit_keys,
+            ..Self::default()
+        }
+    }
+
+    /// Feed a key press in the results list and get the resulting action
+    pub fn feed(&mut self, key: KeyEvent) -> KeyAction {
+        self.feed_in(KeyContext::Results, key)
+    }
+
+    /// Feed a key press on the given screen and get the resulting action
+    ///
+    /// Motions only come back as [`KeyAction::Motion`] in the results list;
+    /// elsewhere they are plain commands.
+    pub fn feed_in(&mut self, context: KeyContext, key: KeyEvent) -> KeyAction {
+        let chord = KeyChord::from_event(&key);
+
+        // An unfinished binding is dropped once it times out
+        if self.pending_since.is_some_and(|t| t.elapsed() >= SEQUENCE_TIMEOUT) {
+            self.pending.clear();
+            self.pending_since = None;
+        }
+
+        let jump = self.jump.take();
+        if context == KeyContext::Results
+            && self.digit_keys == DigitKeys::Jump
+            && let Some(digit) = chord.digit()
+        {
+            self.reset();
+            let number = if digit == 0 { 10 } else { digit as usize };
+            let open = jump.is_some_and(|(last, at)| last == number && at.elapsed() < DOUBLE_PRESS);
+            if !open {
+                self.jump = Some((number, Instant::now()));
+            }
+            return KeyAction::Jump { number, open };
+        }
+
+        if context == KeyContext::Results
+            && let Some(digit) = chord.digit()
+            && (digit != 0 || self.count.is_some())
+        {
+            self.pending.clear();
+            self.pending_since = None;
+            self.count = Some(
+                self.count
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit as usize),
+            );
+            return KeyAction::Pending;
+        }
+
+        if key.code == KeyCode::Esc && (self.count.is_some() || !self.pending.is_empty()) {
+            self.reset();
+            return KeyAction::Cancelled;
+        }
+
+        self.pending.push(chord);
+        let mut lookup = self.keymap.lookup(context, &self.pending);
+        if lookup == Lookup::None && self.pending.len() > 1 {
+            // The key broke a binding; try it on its own
+            self.pending = vec![chord];
+            lookup = self.keymap.lookup(context, &self.pending);
+        }
+
+        match lookup {
+            Lookup::Prefix => {
+                self.pending_since = Some(Instant::now());
+                KeyAction::Pending
+            }
+            Lookup::Action(action) => {
+                let count = self.count;
+                self.reset();
+                match action.motion().filter(|_| context == KeyContext::Results) {
+                    // Without a count, gg/G go to the ends; with one they jump to that result
+                    Some(motion @ (Motion::Top | Motion::Bottom)) => {
+                        KeyAction::Motion(motion, count.unwrap_or(0))
+                    }
+                    Some(motion) => KeyAction::Motion(motion, count.unwrap_or(1)),
+                    None => KeyAction::Command(action),
+                }
+            }
+            Lookup::None => {
+                self.reset();
+                KeyAction::Unhandled
+            }
+        }
+    }
+
+    /// Pending keys for display (e.g. "5" or "g"), if any
+    pub fn pending(&self) -> Option<String> {
+        let mut pending = String::new();
+        if let Some(count) = self.count {
+            pending.push_str(&count.to_string());
+        }
+        if self.pending_since.is_some_and(|t| t.elapsed() < SEQUENCE_TIMEOUT) {
+            pending.push_str(&format_keys(&self.pending));
+        }
+        (!pending.is_empty()).then_some(pending)
+    }
+
+    /// When an unfinished binding times out, if one is pending
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending_since.map(|t| t + SEQUENCE_TIMEOUT)
+    }
+
+    /// Forget any pending input
+    pub fn reset(&mut self) {
+        self.count = None;
+        self.jump = None;
+        self.pending.clear();
+        self.pending_since = None;
+    }
 }
 
 impl App {
     /// Insert char to the cursor position
     pub fn insert_char(&mut self, c: char) {
-        let byte_pos = self.char_to_byte_pos(self.cursor_pos);
-        self.input.insert(byte_pos, c);
-        self.cursor_pos += 1;
+        self.input.insert_char(c);
+    }
+
+    /// Insert pasted text at the cursor
+    ///
+    /// Line breaks become single spaces; other control characters are dropped
+    /// so a paste can never trigger keybindings.
+    pub fn paste(&mut self, text: &str) {
+        let joined = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.input.insert_str(&joined);
     }
 
     /// Delete char before cursor (Backspace)
     pub fn delete_char_before(&mut self) {
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
-            let byte_pos = self.char_to_byte_pos(self.cursor_pos);
-            let next_byte_pos = self.char_to_byte_pos(self.cursor_pos + 1);
-            self.input.drain(byte_pos..next_byte_pos);
-        }
+        self.input.delete_before();
     }
 
     /// Delete char after cursor (Delete)
     pub fn delete_char_after(&mut self) {
-        let char_count = self.input.chars().count();
-        if self.cursor_pos < char_count {
-            let byte_pos = self.char_to_byte_pos(self.cursor_pos);
-            let next_byte_pos = self.char_to_byte_pos(self.cursor_pos + 1);
-            self.input.drain(byte_pos..next_byte_pos);
-        }
+        self.input.delete_after();
     }
 
     /// Move cursor left
     pub fn cursor_left(&mut self) {
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
-        }
+        self.input.move_left();
     }
 
     /// Move cursor right
     pub fn cursor_right(&mut self) {
-        let char_count = self.input.chars().count();
-        if self.cursor_pos < char_count {
-            self.cursor_pos += 1;
-        }
+        self.input.move_right();
     }
 
     /// Move cursor to start of line (Home)
     pub fn cursor_home(&mut self) {
-        self.cursor_pos = 0;
+        self.input.move_home();
     }
 
     /// Move cursor to end of line (End)
     pub fn cursor_end(&mut self) {
-        self.cursor_pos = self.input.chars().count();
+        self.input.move_end();
+    }
+
+    /// Move cursor to the previous word (Alt+B, Ctrl+Left)
+    pub fn move_word_left(&mut self) {
+        self.input.move_word_left();
+    }
+
+    /// Move cursor to the next word end (Alt+F, Ctrl+Right)
+    pub fn move_word_right(&mut self) {
+        self.input.move_word_right();
+    }
+
+    /// Delete the word before the cursor (Ctrl+W)
+    pub fn delete_word_before(&mut self) {
+        self.input.delete_word_before();
+    }
+
+    /// Delete everything before the cursor (Ctrl+U)
+    pub fn kill_to_start(&mut self) {
+        self.input.kill_to_start();
+    }
+
+    /// Delete everything after the cursor (Ctrl+K)
+    pub fn kill_to_end(&mut self) {
+        self.input.kill_to_end();
     }
 
     /// Clear input
     pub fn clear_input(&mut self) {
         self.input.clear();
-        self.cursor_pos = 0;
-    }
-
-    /// Convert cursor position to byte position
-    fn char_to_byte_pos(&self, char_pos: usize) -> usize {
-        self.input
-            .char_indices()
-            .nth(char_pos)
-            .map(|(byte_pos, _)| byte_pos)
-            .unwrap_or(self.input.len())
     }
 
     /// Create new app instance
-    pub fn new() -> Result<Self> {
-        let base_dir = PathBuf::from("websearch");
-        let prefetch_manager = PrefetchManager::new(base_dir)?;
+    ///
+    /// Background tasks report back through `tx`.
+    pub fn new(config: Config, tx: UnboundedSender<AppMessage>) -> Result<Self> {
+        let base_dir = paths::data_dir().to_path_buf();
+        let prefetch_manager = PrefetchManager::new(base_dir, &config, tx)?;
 
-        // Spawn background cleanup task (removes files older than 5 days)
+        // Spawn background cleanup task (removes files older than `cache.max_age_days`)
         let pm_clone = prefetch_manager.clone();
         tokio::spawn(async move {
-            if let Ok(count) = pm_clone.cleanup_old_files().await {
-                if count > 0 {
-                    eprintln!("🧹 Cleaned up {} old cache files", count);
-                }
+            if let Ok(count) = pm_clone.cleanup_old_files().await
+                && count > 0
+            {
+                tracing::info!(count, "cleaned up old cache files");
             }
         });
 
-        Ok(Self {
+        let key_sequence = KeySequence::new(config.keymap.clone(), config.ui.digit_keys);
+        let providers = SearchProviders::new(&config.search);
+        let goggle_choice = providers.goggle();
+        let mut app = Self {
+            config,
+            providers,
             state: AppState::Input,
-            input: String::new(),
-            cursor_pos: 0, // NEW
+            input: InputLine::default(),
             results: Vec::new(),
             selected_index: 0,
             scroll_offset: 0,
-            selected_items: HashSet::new(),
+            selected_items: Selection::default(),
+            sort_mode: SortMode::default(),
+            original_order: Vec::new(),
+            deleted: Vec::new(),
             error_message: None,
+            error_popup: None,
             prefetch_manager,
+            prefetch_statuses: HashMap::new(),
+            page_stats: HashMap::new(),
             status_message: String::new(),
-        })
+            status_set_at: None,
+            status_is_progress: false,
+            key_sequence,
+            visible_items: 1,
+            input_area: Rect::default(),
+            results_area: Rect::default(),
+            last_click: None,
+            search_task: None,
+            page_task: None,
+            pages_loaded: 0,
+            search_generation: 0,
+            launch_generation: None,
+            notices: Vec::new(),
+            unusable_engines: Vec::new(),
+            read_log: ReadLog::new(ReadLog::path()),
+            row_cache: RowCache::default(),
+            search_progress: None,
+            last_search: None,
+            engine_stats: EngineStatsTable::default(),
+            search_meta: None,
+            search_started: None,
+            search_timing: None,
+            suggestion_menu: None,
+            cooldowns: Cooldowns::default(),
+            engine_status: false,
+            preview: false,
+            goggle_menu: None,
+            goggle_choice,
+            results_goggle: None,
+            history: SearchHistory::default(),
+            open_wait_since: None,
+            pending_open: None,
+            outline: None,
+            open_with: None,
+            note_input: None,
+            pending_notes: HashMap::new(),
+            library: Vec::new(),
+            library_selected: 0,
+            library_filter: InputLine::default(),
+            library_filtering: false,
+            bookmarks: Bookmarks::load(Bookmarks::path()),
+            bookmark_selected: 0,
+            bookmark_statuses: HashMap::new(),
+            local_query: String::new(),
+            local_matches: Vec::new(),
+            local_selected: 0,
+            local_progress: None,
+            local_generation: 0,
+        };
+        if let Some(backup) = &app.bookmarks.backup {
+            let message = format!("⚠ Bookmarks were unreadable, moved to {}", backup.display());
+            app.set_status(message);
+        }
+        Ok(app)
     }
 
-    /// Start search operation
-    pub async fn start_search(&mut self) {
+    /// Start search operation for `query`
+    pub async fn start_search(&mut self, query: &str) {
+        if let Some(snapshot) = self.snapshot() {
+            self.history.push(snapshot);
+        }
         self.state = AppState::Searching;
+        self.abort_page_task();
         self.results.clear();
         self.selected_index = 0;
         self.scroll_offset = 0;
         self.selected_items.clear();
-        self.status_message = "Searching...".to_string();
+        self.deleted.clear();
+        self.search_progress = None;
+        self.search_meta = None;
+        self.suggestion_menu = None;
+        self.end_timing();
+        self.search_started = Some(Instant::now());
+        self.set_status("Searching...");
+
+        // Pages go into the new search's own directory
+        self.prefetch_statuses.clear();
+        self.page_stats.clear();
+        if let Err(e) = self
+            .prefetch_manager
+            .begin_search(query, self.history.urls())
+            .await
+        {
+            self.set_status(format!("Warning: {}", e));
+        }
+    }
+
+    /// Start a search for the current input on a background task
+    ///
+    /// `engine`'s provider runs on its own task; its outcome is sent back
+    /// through `tx` tagged with this search's generation. Any search still
+    /// running is cancelled first. `engine` is recorded for the saved
+    /// session.
+    pub async fn spawn_search(&mut self, tx: &UnboundedSender<AppMessage>, engine: Engine) {
+        self.spawn_search_with(tx, engine, false).await;
+    }
+
+    /// Like [`spawn_search`](Self::spawn_search), `verbatim` searching the
+    /// query as typed where the engine would correct its spelling
+    async fn spawn_search_with(
+        &mut self,
+        tx: &UnboundedSender<AppMessage>,
+        engine: Engine,
+        verbatim: bool,
+    ) {
+        let query = self.input.text().trim().to_string();
+        if query.is_empty() {
+            return;
+        }
+        // `-site:` and `+site:` are applied to the results, not sent
+        let sent = QueryFilters::parse(&query).query;
+        if sent.is_empty() {
+            self.set_status("⚠ Add search terms to the site operators");
+            return;
+        }
+        if self.config.network.offline {
+            let keys = &self.config.keymap;
+            let message = format!(
+                "⚠ Offline: searching needs the network ({}: Library, {}: Search cached pages)",
+                keys.label(Action::Library),
+                keys.label(Action::LocalSearch)
+            );
+            self.set_status(message);
+            return;
+        }
+
+        if self.refuse_resting(engine) {
+            return;
+        }
+
+        self.abort_search_task();
+        self.search_generation += 1;
+        self.start_search(&query).await;
+        self.last_search = Some((query.clone(), engine));
+        self.prefetch_manager.set_engine(Some(engine));
+
+        let generation = self.search_generation;
+        let tx = tx.clone();
+        let provider = self.providers.get(engine);
+        // A failed SearXNG search points to DuckDuckGo, by its configured key
+        let fallback = (engine == Engine::SearXNG).then(|| {
+            let action = if self.config.search.default_engine == Engine::DuckDuckGo {
+                Action::Search
+            } else {
+                Action::SearchDdg
+            };
+            format!(" — try DuckDuckGo ({})", self.config.keymap.label(action))
+        });
+        let progress_tx = tx.clone();
+        let progress: Progress = Arc::new(move |message| {
+            let _ = progress_tx.send(AppMessage::SearchProgress {
+                generation,
+                message,
+            });
+        });
+        self.search_task = Some(tokio::spawn(async move {
+            let started = Instant::now();
+            let result = provider.search_with_meta(&sent, verbatim, progress).await;
+            let results = result.as_ref().map(|(results, _)| results.as_slice());
+            let _ = tx.send(AppMessage::engine_report(engine, started, results));
+            let msg = match result {
+                Ok((results, meta)) => AppMessage::SearchComplete {
+                    generation,
+                    results,
+                    meta: (!meta.is_empty()).then_some(meta),
+                },
+                Err(e) if search::is_unreachable(&e) => AppMessage::SearchError {
+                    generation,
+                    error: format!("{} (no network? --offline browses the cached pages)", e),
+                },
+                Err(e) => AppMessage::SearchError {
+                    generation,
+                    error: format!("{}{}", e, fallback.unwrap_or_default()),
+                },
+            };
+            let _ = tx.send(msg);
+        }));
+    }
+
+    /// Refuse a search with `engine` while it rests after a rate limit
+    ///
+    /// The status says when it's back and which engine to use meanwhile.
+    fn refuse_resting(&mut self, engine: Engine) -> bool {
+        let now = Instant::now();
+        let Some(left) = self.cooldowns.remaining(engine, now) else {
+            return false;
+        };
+        let mut message = format!(
+            "⚠ {} is rate-limiting searches, available in {}s",
+            engine.name(),
+            countdown_secs(left)
+        );
+        let alternative = Engine::ALL.into_iter().find_map(|other| {
+            let usable = other != engine
+                && self.cooldowns.remaining(other, now).is_none()
+                && !self.providers.get(other).requires_key();
+            let action = match other {
+                _ if other == self.config.search.default_engine => Action::Search,
+                Engine::DuckDuckGo => Action::SearchDdg,
+                Engine::SearXNG => Action::SearchSearxng,
+                Engine::Startpage => Action::SearchStartpage,
+                // Brave has no key of its own when it isn't the default
+                Engine::Brave => return None,
+            };
+            usable.then_some((other, action))
+        });
+        if let Some((other, action)) = alternative {
+            message.push_str(&format!(
+                " — try {} ({})",
+                other.name(),
+                self.config.keymap.label(action)
+            ));
+        }
+        self.set_status(message);
+        true
+    }
+
+    /// Search for `query` right away, as given on the command line
+    ///
+    /// The query goes into the input first, so it can be edited if the
+    /// search fails.
+    pub async fn launch_search(
+        &mut self,
+        tx: &UnboundedSender<AppMessage>,
+        query: &str,
+        engine: Engine,
+    ) {
+        self.input.insert_str(query);
+        self.spawn_search(tx, engine).await;
+        self.launch_generation = Some(self.search_generation);
+    }
+
+    /// Handle a message from a background task
+    pub async fn handle_message(&mut self, msg: AppMessage) {
+        match msg {
+            // Results from cancelled or superseded searches are dropped
+            AppMessage::SearchComplete {
+                generation,
+                results,
+                meta,
+            } if generation == self.search_generation => {
+                let elapsed = self.search_started.take().map(|started| started.elapsed());
+                self.finish_search(results).await;
+                if self.state == AppState::Results {
+                    self.search_meta = meta;
+                    if let Some(elapsed) = elapsed {
+                        self.time_search(elapsed);
+                    }
+                }
+            }
+            AppMessage::SearchError { generation, error }
+                if generation == self.search_generation =>
+            {
+                self.fail_search(&format!("Search failed: {}", error));
+            }
+            AppMessage::SearchProgress {
+                generation,
+                message,
+            } if generation == self.search_generation && self.state == AppState::Searching => {
+                self.search_progress = Some(message);
+            }
+            AppMessage::SearchComplete { .. }
+            | AppMessage::SearchError { .. }
+            | AppMessage::SearchProgress { .. } => {}
+            AppMessage::PageComplete {
+                generation,
+                results,
+            } if generation == self.search_generation => {
+                self.append_page(results).await;
+            }
+            AppMessage::PageError { generation, error } if generation == self.search_generation => {
+                self.page_task = None;
+                self.set_status(format!("⚠ {}", error));
+            }
+            AppMessage::PageComplete { .. } | AppMessage::PageError { .. } => {}
+            AppMessage::EngineReport {
+                engine,
+                rate_limited,
+                elapsed,
+                outcome,
+            } => {
+                match &rate_limited {
+                    Some(limit) => {
+                        self.cooldowns.limit(engine, limit.retry_after, Instant::now());
+                    }
+                    None if outcome.is_ok() => self.cooldowns.succeed(engine),
+                    None => {}
+                }
+                self.engine_stats.record(engine, elapsed, outcome, unix_now());
+            }
+            AppMessage::PrefetchUpdate { url, status } => {
+                self.update_prefetch_status(url, status);
+                // Every result has a status, and none is still loading
+                let (completed, total) = self.prefetch_progress();
+                if completed == total && total >= self.results.len() {
+                    self.time_prefetch();
+                } else {
+                    self.update_prefetch_progress(completed, total);
+                }
+            }
+            AppMessage::PageStats { url, stats } => {
+                if self.results.iter().any(|r| r.url == url) {
+                    self.page_stats.insert(url, stats);
+                }
+            }
+            AppMessage::PrefetchDone { evicted } => {
+                self.finish_prefetch(evicted);
+            }
+            AppMessage::LocalSearchProgress {
+                generation,
+                scanned,
+                total,
+            } if generation == self.local_generation => {
+                self.local_progress = Some((scanned, total));
+            }
+            AppMessage::LocalSearchDone {
+                generation,
+                matches,
+            } if generation == self.local_generation => {
+                self.finish_local_search(matches);
+            }
+            AppMessage::LocalSearchProgress { .. } | AppMessage::LocalSearchDone { .. } => {}
+            AppMessage::OpenWithFailed { label, error } => {
+                self.set_status(format!("⚠ {} failed: {}", label, error));
+            }
+        }
+    }
+
+    /// Abort the running search and return to input
+    pub fn cancel_search(&mut self) {
+        self.abort_search_task();
+        // Invalidate anything the aborted task may already have sent
+        self.search_generation += 1;
+        self.state = AppState::Input;
+        self.set_status("Search cancelled");
+    }
+
+    /// Abort the search task, if one is running
+    fn abort_search_task(&mut self) {
+        if let Some(task) = self.search_task.take() {
+            task.abort();
+        }
+    }
+
+    /// Abort the fetch of the next page, if one is running
+    fn abort_page_task(&mut self) {
+        if let Some(task) = self.page_task.take() {
+            task.abort();
+        }
+    }
+
+    /// Fetch the next page of results of the search on screen
+    ///
+    /// The results are added below the list when they arrive, see
+    /// [`Self::append_page`]. Engines without paging report an error.
+    pub fn load_next_page(&mut self, tx: &UnboundedSender<AppMessage>) {
+        let Some((query, engine)) = self.last_search.clone() else {
+            return;
+        };
+        if self.page_task.is_some() {
+            self.set_status("⏳ Still loading the next page");
+            return;
+        }
+        if self.config.network.offline {
+            self.set_status("⚠ Offline: more results need the network");
+            return;
+        }
+        if self.refuse_resting(engine) {
+            return;
+        }
 
-        // Clear previous search cache
-        if let Err(e) = self.prefetch_manager.clear_current_search().await {
-            self.status_message = format!("Warning: {}", e);
+        let query = QueryFilters::parse(&query).query;
+        let page = self.pages_loaded;
+        let generation = self.search_generation;
+        let provider = self.providers.get(engine);
+        let tx = tx.clone();
+        self.set_status(format!("⏳ Loading page {} from {}", page + 1, engine.name()));
+        self.page_task = Some(tokio::spawn(async move {
+            let started = Instant::now();
+            let result = provider.search_page(&query, page).await;
+            let _ = tx.send(AppMessage::engine_report(engine, started, result.as_deref()));
+            let msg = match result {
+                Ok(results) => AppMessage::PageComplete {
+                    generation,
+                    results,
+                },
+                Err(e) => AppMessage::PageError {
+                    generation,
+                    error: e.to_string(),
+                },
+            };
+            let _ = tx.send(msg);
+        }));
+    }
+
+    /// Add a further page of results below the list and prefetch them
+    ///
+    /// Results already listed, or removed with `d`, are left out.
+    async fn append_page(&mut self, mut results: Vec<SearchResult>) {
+        self.page_task = None;
+        self.pages_loaded += 1;
+        let page = self.pages_loaded;
+        self.query_filters().apply(&mut results);
+        results.truncate(self.config.search.max_results);
+        let mut seen: HashSet<String> = self.original_order.iter().cloned().collect();
+        results.retain(|result| seen.insert(result.url.clone()));
+        if results.is_empty() {
+            self.set_status(format!("No new results on page {}", page));
+            return;
         }
+
+        self.original_order.extend(results.iter().map(|result| result.url.clone()));
+        self.results.extend(results.iter().cloned());
+        self.apply_sort();
+        self.set_progress_status(format!(
+            "Added {} results from page {}. Prefetching...",
+            results.len(),
+            page
+        ));
+        self.save_session();
+        self.prefetch_manager.prefetch_all(&results).await;
     }
 
     /// Finish search with results and start prefetching
-    pub async fn finish_search(&mut self, results: Vec<SearchResult>) {
+    pub async fn finish_search(&mut self, mut results: Vec<SearchResult>) {
+        let found = results.len();
+        self.query_filters().apply(&mut results);
+        results.truncate(self.config.search.max_results);
         if results.is_empty() {
-            self.error_message = Some("No results found".to_string());
-            self.state = AppState::Error;
+            let message = if found > 0 {
+                "No results outside the excluded sites"
+            } else {
+                "No results found"
+            };
+            self.fail_search(message);
             return;
         }
 
         let count = results.len();
-        self.results = results;
+        self.show_results(results, 0);
+        if let Some((_, Engine::Brave)) = self.last_search {
+            self.results_goggle = self.providers.goggle().map(|goggle| goggle.name);
+        }
         self.state = AppState::Results;
-        self.selected_index = 0;
         self.scroll_offset = 0;
-        self.status_message = format!("Found {} results. Prefetching...", count);
+        self.set_progress_status(format!("Found {} results. Prefetching...", count));
+        self.save_session();
 
         // Start prefetching all results in background (with caching)
         self.prefetch_manager.prefetch_all(&self.results).await;
     }
 
-    /// Update prefetch progress
-    pub fn update_prefetch_progress(&mut self, completed: usize, total: usize) {
-        if completed == total {
-            self.status_message = format!("✓ All {} pages ready!", total);
+    /// Site operators of the latest search
+    fn query_filters(&self) -> QueryFilters {
+        self.last_search
+            .as_ref()
+            .map(|(query, _)| QueryFilters::parse(query))
+            .unwrap_or_default()
+    }
+
+    /// Show why the current search produced no results
+    ///
+    /// The search from the command line has no screen to go back to, so
+    /// it lands in the input with the query instead of on the error screen.
+    fn fail_search(&mut self, message: &str) {
+        if self.launch_generation == Some(self.search_generation) {
+            self.state = AppState::Input;
+            self.show_error(Severity::Recoverable, message);
         } else {
-            self.status_message = format!("Prefetching: {}/{}", completed, total);
+            self.show_error(Severity::Fatal, message);
+        }
+    }
+
+    /// Save the results and selection for `--resume`
+    pub fn save_session(&self) {
+        let Some((query, engine)) = &self.last_search else {
+            return;
+        };
+        if self.results.is_empty() {
+            return;
+        }
+        let (results, selected) = self.results_in_original_order();
+        let session = Session::new(query.clone(), *engine, results, selected);
+        if let Err(e) = session.save(&Session::path()) {
+            tracing::warn!("Failed to save session: {:#}", e);
+        }
+    }
+
+    /// Show a saved session as if its search had just completed
+    pub async fn restore_session(&mut self, session: Session) {
+        self.input.clear();
+        self.input.insert_str(&session.query);
+        self.show_results(session.results, session.selected);
+        self.scroll_offset = 0;
+        self.state = AppState::Results;
+        self.prefetch_manager.set_engine(Some(session.engine));
+        self.last_search = Some((session.query.clone(), session.engine));
+        self.set_status(format!(
+            "Resumed {} results from {}",
+            self.results.len(),
+            session.engine.name()
+        ));
+
+        // Cached pages come back as ready; the rest are fetched again
+        if let Err(e) = self
+            .prefetch_manager
+            .begin_search(&session.query, self.history.urls())
+            .await
+        {
+            tracing::warn!("{:#}", e);
+        }
+        self.prefetch_manager.prefetch_all(&self.results).await;
+    }
+
+    /// The result set on screen, for the history
+    fn snapshot(&self) -> Option<SearchSnapshot> {
+        let (query, engine) = self.last_search.clone()?;
+        if self.results.is_empty() {
+            return None;
+        }
+        let (results, selected) = self.results_in_original_order();
+        Some(SearchSnapshot {
+            query,
+            engine,
+            results,
+            selected,
+            statuses: self.prefetch_statuses.clone(),
+        })
+    }
+
+    /// The results in the engine's order and the selection's place in it
+    fn results_in_original_order(&self) -> (Vec<SearchResult>, usize) {
+        let mut results = self.results.clone();
+        sort_results(
+            &mut results,
+            SortMode::Original,
+            &self.prefetch_statuses,
+            &self.original_order,
+        );
+        let selected = self
+            .results
+            .get(self.selected_index)
+            .and_then(|current| results.iter().position(|r| r.url == current.url))
+            .unwrap_or(0);
+        (results, selected)
+    }
+
+    /// Show a new result set in the engine's order, sorted by the current mode
+    fn show_results(&mut self, results: Vec<SearchResult>, selected: usize) {
+        self.abort_page_task();
+        self.pages_loaded = 1;
+        self.results_goggle = None;
+        self.original_order = results.iter().map(|result| result.url.clone()).collect();
+        self.selected_index = selected.min(results.len().saturating_sub(1));
+        self.results = results;
+        self.apply_sort();
+    }
+
+    /// Switch to the next sort order
+    pub fn cycle_sort(&mut self) {
+        self.sort_mode = self.sort_mode.next();
+        self.apply_sort();
+        self.set_status(format!("Sorted {}", self.sort_mode.label()));
+    }
+
+    /// Sort the results, keeping the selected result selected
+    fn apply_sort(&mut self) {
+        let selected_url = self
+            .results
+            .get(self.selected_index)
+            .map(|result| result.url.clone());
+        sort_results(
+            &mut self.results,
+            self.sort_mode,
+            &self.prefetch_statuses,
+            &self.original_order,
+        );
+        self.selected_index = selected_url
+            .and_then(|url| self.results.iter().position(|result| result.url == url))
+            .unwrap_or(0);
+    }
+
+    /// Go back to the previous result set
+    pub async fn history_back(&mut self) {
+        let Some(current) = self.snapshot() else {
+            return;
+        };
+        match self.history.back(current) {
+            Some(previous) => self.show_snapshot(previous).await,
+            None => self.set_status("No earlier search"),
+        }
+    }
+
+    /// Go forward to the result set left with `history_back`
+    pub async fn history_forward(&mut self) {
+        let Some(current) = self.snapshot() else {
+            return;
+        };
+        match self.history.forward(current) {
+            Some(next) => self.show_snapshot(next).await,
+            None => self.set_status("No later search"),
+        }
+    }
+
+    /// Show a result set from the history without searching again
+    async fn show_snapshot(&mut self, snapshot: SearchSnapshot) {
+        self.key_sequence.reset();
+        self.input.clear();
+        self.input.insert_str(&snapshot.query);
+        self.show_results(snapshot.results, snapshot.selected);
+        self.search_meta = None;
+        self.end_timing();
+        self.scroll_offset = 0;
+        self.selected_items.clear();
+        self.deleted.clear();
+        self.set_status(format!(
+            "{} results for \"{}\"",
+            self.results.len(),
+            snapshot.query
+        ));
+        self.prefetch_manager.set_engine(Some(snapshot.engine));
+        self.last_search = Some((snapshot.query.clone(), snapshot.engine));
+        self.save_session();
+
+        // Prefetch updates correct any page that changed since
+        self.prefetch_manager
+            .restore(&snapshot.query, &self.results, &snapshot.statuses)
+            .await;
+        self.prefetch_statuses = snapshot.statuses;
+    }
+
+    /// Show a message in the status line
+    pub fn set_status(&mut self, message: impl Into<String>) {
+        self.status_message = message.into();
+        self.status_set_at = Some(Instant::now());
+        self.status_is_progress = false;
+    }
+
+    /// Show a prefetch message that later progress may replace
+    fn set_progress_status(&mut self, message: String) {
+        self.set_status(message);
+        self.status_is_progress = true;
+    }
+
+    /// Show how many pages are done, unless the status line says something else
+    fn update_prefetch_progress(&mut self, completed: usize, total: usize) {
+        if completed < total && (self.status_is_progress || self.status_message.is_empty()) {
+            self.set_progress_status(format!("Prefetching: {}/{}", completed, total));
+        }
+    }
+
+    /// Clear the status line and any error popup
+    pub fn clear_status(&mut self) {
+        self.status_message.clear();
+        self.status_set_at = None;
+        self.error_popup = None;
+    }
+
+    /// Clear the status line and error popup once shown long enough
+    pub fn expire_status(&mut self) {
+        let timeout = self.config.ui.status_timeout();
+        if self.status_set_at.is_some_and(|t| t.elapsed() >= timeout) {
+            self.status_message.clear();
+            self.status_set_at = None;
+        }
+        if self
+            .error_popup
+            .as_ref()
+            .is_some_and(|(_, t)| t.elapsed() >= timeout)
+        {
+            self.error_popup = None;
         }
     }
 
-    /// Show error message
-    pub fn show_error(&mut self, message: &str) {
-        self.error_message = Some(message.to_string());
-        self.state = AppState::Error;
+    /// Show an error, full-screen or over the current view by `severity`
+    pub fn show_error(&mut self, severity: Severity, message: &str) {
+        match severity {
+            Severity::Fatal => {
+                self.error_message = Some(message.to_string());
+                self.state = AppState::Error;
+            }
+            Severity::Recoverable => {
+                self.error_popup = Some((message.to_string(), Instant::now()));
+            }
+        }
     }
 
-    /// Dismiss error
+    /// Dismiss the full-screen error
     pub fn dismiss_error(&mut self) {
         self.error_message = None;
         self.state = if self.results.is_empty() {
@@ -232,131 +1318,2050 @@ impl App {
         }
     }
 
-    /// Get scroll offset for rendering
-    pub fn get_scroll_offset(&self, visible_height: usize) -> usize {
-        let items_per_screen = visible_height.saturating_sub(2) / 4;
-
-        if self.selected_index >= items_per_screen {
-            self.selected_index.saturating_sub(items_per_screen - 1)
-        } else {
-            0
+    /// Apply a list motion repeated `count` times
+    ///
+    /// For [`Motion::Top`] and [`Motion::Bottom`] a non-zero count is a
+    /// 1-based result number to jump to.
+    pub fn apply_motion(&mut self, motion: Motion, count: usize) {
+        if self.results.is_empty() {
+            return;
         }
-    }
 
-    /// Toggle selection of current item
-    pub fn toggle_selection(&mut self) {
-        if self.selected_items.contains(&self.selected_index) {
-            self.selected_items.remove(&self.selected_index);
-        } else {
-            self.selected_items.insert(self.selected_index);
+        let last = self.results.len() - 1;
+        let visible = self.visible_items.max(1);
+        let window_end = (self.scroll_offset + visible - 1).min(last);
+        let half_page = (visible / 2).max(1);
+
+        match motion {
+            Motion::Down if count <= 1 => self.next_result(),
+            Motion::Up if count <= 1 => self.previous_result(),
+            Motion::Down => self.selected_index = (self.selected_index + count).min(last),
+            Motion::Up => self.selected_index = self.selected_index.saturating_sub(count),
+            Motion::Top if count == 0 => self.first_result(),
+            Motion::Bottom if count == 0 => self.last_result(),
+            Motion::Top | Motion::Bottom => self.selected_index = (count - 1).min(last),
+            Motion::HalfPageDown => {
+                self.selected_index = (self.selected_index + half_page * count).min(last)
+            }
+            Motion::HalfPageUp => {
+                self.selected_index = self.selected_index.saturating_sub(half_page * count)
+            }
+            Motion::WindowTop => {
+                self.selected_index = (self.scroll_offset + count - 1).min(window_end)
+            }
+            Motion::WindowMiddle => {
+                self.selected_index = self.scroll_offset + (window_end - self.scroll_offset) / 2
+            }
+            Motion::WindowBottom => {
+                self.selected_index = window_end
+                    .saturating_sub(count - 1)
+                    .max(self.scroll_offset)
+            }
         }
-    }
 
-    /// Open selected items in browser
-    pub fn open_in_browser(&mut self) {
-        let indices: Vec<usize> = if self.selected_items.is_empty() {
-            vec![self.selected_index]
-        } else {
-            self.selected_items.iter().copied().collect()
-        };
+        self.boost_prefetch();
+    }
 
-        for &idx in &indices {
-            if let Some(result) = self.results.get(idx) {
-                if let Err(e) = open_url(&result.url) {
-                    self.show_error(&format!("Failed to open URL: {}", e));
-                    return;
-                }
+    /// Switch to the next [`Density`] and save it as `ui.density`
+    pub fn cycle_density(&mut self) {
+        let density = self.config.ui.density.next();
+        self.config.ui.density = density;
+        let value = format!("\"{}\"", density.name());
+        match Config::save_value("ui.density", &value) {
+            Ok(_) => self.set_status(format!("Density: {}", density.name())),
+            Err(e) => {
+                self.set_status(format!("⚠ Density: {}, not saved: {:#}", density.name(), e))
             }
         }
-
-        self.selected_items.clear();
-        self.status_message = format!("Opened {} URL(s) in browser", indices.len());
     }
 
-    /// Open current result in neovim
+    /// Select result `number` (1-based) picked with a digit key
+    ///
+    /// Returns false when there is no such result.
+    pub fn jump_to_result(&mut self, number: usize) -> bool {
+        if number > self.results.len() {
+            self.set_status(format!("No result {}", number));
+            return false;
+        }
+        self.apply_motion(Motion::Top, number);
+        let open = self.config.keymap.label(Action::OpenEditor);
+        self.set_status(format!(
+            "→ Result {} · press {} again or {} to open it",
+            number,
+            number % 10,
+            open
+        ));
+        true
+    }
+
+    /// Fetch the selected result and the next few below it first
+    fn boost_prefetch(&self) {
+        let end = (self.selected_index + BOOST_AHEAD + 1).min(self.results.len());
+        // Boosting moves to the front, so go bottom-up to keep list order
+        for result in self.results[self.selected_index..end].iter().rev() {
+            self.prefetch_manager.boost(&result.url);
+        }
+    }
+
+    /// Update and return the scroll offset so the selection stays fully visible
+    ///
+    /// `visible_items` is how many whole result items fit in the list area.
+    pub fn update_scroll_offset(&mut self, visible_items: usize) -> usize {
+        self.visible_items = visible_items.max(1);
+        self.scroll_offset = compute_scroll_offset(
+            self.scroll_offset,
+            self.selected_index,
+            self.results.len(),
+            visible_items,
+        );
+        self.scroll_offset
+    }
+
+    /// Handle a mouse event in the search box or the results list
+    ///
+    /// Clicks and the wheel move the selection here; a double click returns
+    /// [`Action::OpenEditor`] and a right or middle click
+    /// [`Action::OpenBrowser`] for the caller to run.
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) -> Option<Action> {
+        if !matches!(self.state, AppState::Input | AppState::Results)
+            || self.open_wait_since.is_some()
+        {
+            return None;
+        }
+        let position = Position::new(mouse.column, mouse.row);
+
+        if self.input_area.contains(position) {
+            if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
+                self.back_to_input();
+                let inner_width = self.input_area.width.saturating_sub(2) as usize;
+                let column = mouse.column.saturating_sub(self.input_area.x + 1);
+                self.input.move_to_column(column as usize, inner_width);
+            }
+            return None;
+        }
+        if self.results.is_empty() || !self.results_area.contains(position) {
+            return None;
+        }
+
+        match mouse.kind {
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                let motion = if mouse.kind == MouseEventKind::ScrollDown {
+                    Motion::Down
+                } else {
+                    Motion::Up
+                };
+                self.focus_results();
+                self.apply_motion(motion, SCROLL_STEP);
+                None
+            }
+            MouseEventKind::Down(button) => {
+                let index = result_at_row(
+                    self.results_area,
+                    mouse.row,
+                    self.scroll_offset,
+                    self.results.len(),
+                    self.config.ui.density.item_height(),
+                )?;
+                self.focus_results();
+                self.selected_index = index;
+                self.boost_prefetch();
+
+                if button != MouseButton::Left {
+                    self.last_click = None;
+                    return Some(Action::OpenBrowser);
+                }
+                let double = self.last_click.is_some_and(|(last, at)| {
+                    last == index && at.elapsed() <= DOUBLE_CLICK_INTERVAL
+                });
+                self.last_click = (!double).then(|| (index, Instant::now()));
+                double.then_some(Action::OpenEditor)
+            }
+            _ => None,
+        }
+    }
+
+    /// Move focus from the search box to the results list
+    fn focus_results(&mut self) {
+        if self.state == AppState::Input {
+            self.key_sequence.reset();
+            self.state = AppState::Results;
+        }
+    }
+
+    /// Toggle selection of current item
+    pub fn toggle_selection(&mut self) {
+        if let Some(result) = self.results.get(self.selected_index) {
+            self.selected_items.toggle(&result.url);
+        }
+    }
+
+    /// Select every result
+    pub fn select_all(&mut self) {
+        self.selected_items.select_all(&self.results);
+    }
+
+    /// Select the unselected results and unselect the others
+    pub fn invert_selection(&mut self) {
+        self.selected_items.invert(&self.results);
+    }
+
+    /// Esc in the results: drop the selection, or go back to the input
+    pub fn clear_selection_or_back(&mut self) {
+        if self.selected_items.is_empty(&self.results) {
+            self.back_to_input();
+        } else {
+            self.selected_items.clear();
+            self.set_status("Selection cleared");
+        }
+    }
+
+    /// Go back to the input with the query limited to the selected result's site
+    pub fn search_selected_site(&mut self) {
+        let Some(host) = self
+            .results
+            .get(self.selected_index)
+            .and_then(|result| site_host(&result.url))
+        else {
+            return;
+        };
+        let query = match &self.last_search {
+            Some((query, _)) => query.as_str(),
+            None => self.input.text(),
+        };
+        let text = site_query(&host, query);
+        self.input.clear();
+        self.input.insert_str(&text);
+        self.back_to_input();
+    }
+
+    /// Remove the selected result from the list
+    ///
+    /// Its page stops loading and its file in `current_search/` is deleted,
+    /// so the progress bar only counts what is left. `u` brings it back.
+    pub async fn delete_result(&mut self) {
+        if self.selected_index >= self.results.len() {
+            return;
+        }
+        let result = remove_result(&mut self.results, &mut self.deleted, self.selected_index);
+        self.prefetch_statuses.remove(&result.url);
+        self.page_stats.remove(&result.url);
+        self.selected_items.remove(&result.url);
+        self.prefetch_manager.forget(&result.url).await;
+        self.selected_index = self.selected_index.min(self.results.len().saturating_sub(1));
+
+        self.set_status(format!("Removed \"{}\" (u: Undo)", result.title));
+    }
+
+    /// Put back the result removed last and select it
+    pub async fn undo_delete(&mut self) {
+        let Some(index) = restore_result(&mut self.results, &mut self.deleted) else {
+            self.set_status("Nothing to undo");
+            return;
+        };
+        let result = self.results[index].clone();
+        self.prefetch_statuses
+            .insert(result.url.clone(), PrefetchStatus::Pending);
+        self.prefetch_manager
+            .prefetch_all(std::slice::from_ref(&result))
+            .await;
+        self.set_status(format!("Restored \"{}\"", result.title));
+        self.selected_index = index;
+    }
+
+    /// Results marked with Tab, in list order
+    fn marked_indices(&self) -> Vec<usize> {
+        self.selected_items.indices(&self.results)
+    }
+
+    /// Open selected items in browser
+    pub fn open_in_browser(&mut self) {
+        let single =
+            self.state == AppState::Bookmarks || self.selected_items.is_empty(&self.results);
+        let urls: Vec<String> = if single {
+            self.selected_url().map(str::to_string).into_iter().collect()
+        } else {
+            self.marked_indices()
+                .into_iter()
+                .map(|idx| self.results[idx].url.clone())
+                .collect()
+        };
+
+        if urls.len() > MAX_TABS_WITHOUT_CONFIRM {
+            self.pending_open = Some(urls);
+        } else {
+            self.open_urls(&urls);
+        }
+    }
+
+    /// Answer the prompt for opening many tabs
+    pub fn confirm_open(&mut self, confirmed: bool) {
+        let Some(urls) = self.pending_open.take() else {
+            return;
+        };
+        if confirmed {
+            self.open_urls(&urls);
+        } else {
+            self.set_status("Not opened");
+        }
+    }
+
+    fn open_urls(&mut self, urls: &[String]) {
+        for url in urls {
+            if let Err(e) = open_url(&self.config.browser.argv(url)) {
+                self.show_error(Severity::Recoverable, &format!("Failed to open URL: {}", e));
+                return;
+            }
+        }
+        self.mark_read(urls);
+
+        if self.state != AppState::Bookmarks {
+            self.selected_items.clear();
+        }
+        self.set_status(format!("Opened {} URL(s) in browser", urls.len()));
+    }
+
+    /// Copy the URL of the selected result (or all Tab-selected ones)
+    pub fn copy_urls(&mut self) {
+        let indices: Vec<usize> = if self.selected_items.is_empty(&self.results) {
+            vec![self.selected_index]
+        } else {
+            self.marked_indices()
+        };
+
+        let urls: Vec<&str> = indices
+            .iter()
+            .filter_map(|&idx| self.results.get(idx))
+            .map(|r| r.url.as_str())
+            .collect();
+
+        if urls.is_empty() {
+            return;
+        }
+
+        match copy_to_clipboard(&urls.join("\n")) {
+            Ok(_) if urls.len() == 1 => self.set_status("Copied URL"),
+            Ok(_) => self.set_status(format!("Copied {} URLs", urls.len())),
+            Err(e) => self.set_status(format!("⚠ {}", e)),
+        }
+    }
+
+    /// Copy the path of the selected result's prefetched markdown file
+    pub async fn copy_markdown_path(&mut self) {
+        let Some(result) = self.results.get(self.selected_index) else {
+            return;
+        };
+
+        let path = match self.prefetch_manager.get_status(&result.url).await {
+            PrefetchStatus::Ready(path)
+            | PrefetchStatus::ReadyArchived(path)
+            | PrefetchStatus::Cached(path)
+            | PrefetchStatus::Binary(path) => path,
+            _ => {
+                self.set_status("⏳ Page is not prefetched yet");
+                return;
+            }
+        };
+
+        match copy_to_clipboard(&path.to_string_lossy()) {
+            Ok(_) => self.set_status(format!("Copied {}", path.display())),
+            Err(e) => self.set_status(format!("⚠ {}", e)),
+        }
+    }
+
+    /// Retry prefetching failed and timed-out results
+    ///
+    /// With `all`, every failed result is retried; otherwise the selected
+    /// result (or all Tab-selected ones). Pages that timed out before get
+    /// the longer retry timeout; pages that are gone (404, 410) are skipped.
+    pub async fn retry_prefetch(&mut self, all: bool) {
+        let indices: Vec<usize> = if all {
+            (0..self.results.len()).collect()
+        } else if self.selected_items.is_empty(&self.results) {
+            vec![self.selected_index]
+        } else {
+            self.marked_indices()
+        };
+
+        let mut failed = Vec::new();
+        let mut timed_out = Vec::new();
+        let mut gone = 0;
+        for result in indices.iter().filter_map(|&idx| self.results.get(idx)) {
+            match self.prefetch_statuses.get(&result.url) {
+                Some(PrefetchStatus::Failed(failure)) if !failure.is_retryable() => gone += 1,
+                Some(PrefetchStatus::Failed(_)) => failed.push(result.clone()),
+                Some(PrefetchStatus::Timeout) => timed_out.push(result.clone()),
+                _ => {}
+            }
+        }
+
+        let count = failed.len() + timed_out.len();
+        if count == 0 {
+            if gone > 0 {
+                self.set_status(format!("⚠ Not retrying {} page(s) that are gone", gone));
+            } else {
+                self.set_status("Nothing to retry");
+            }
+            return;
+        }
+
+        if !failed.is_empty() {
+            self.prefetch_manager.retry(&failed, false).await;
+        }
+        if !timed_out.is_empty() {
+            self.prefetch_manager.retry(&timed_out, true).await;
+        }
+        if gone > 0 {
+            self.set_status(format!("⏳ Retrying {} page(s), {} gone", count, gone));
+        } else {
+            self.set_status(format!("⏳ Retrying {} page(s)", count));
+        }
+    }
+
+    /// Delete the selected result's cached page and download it again
+    pub async fn refresh_selected(&mut self) {
+        let Some(result) = self.results.get(self.selected_index).cloned() else {
+            return;
+        };
+
+        match self.prefetch_manager.refresh(&result).await {
+            Ok(()) => self.set_status("⏳ Refreshing page"),
+            Err(e) => self.set_status(format!("⚠ {}", e)),
+        }
+    }
+
+    /// Extract the selected result's page again from its kept HTML
+    pub async fn re_extract_selected(&mut self) {
+        let Some(result) = self.results.get(self.selected_index).cloned() else {
+            return;
+        };
+
+        match self.prefetch_manager.re_extract(&result.url).await {
+            Ok(_) => self.set_status("✓ Extracted page again"),
+            Err(e) => self.set_status(format!("⚠ {}", e)),
+        }
+    }
+
+    /// Pin or unpin the selected result's page (exempts it from cleanup)
+    pub async fn toggle_pin(&mut self) {
+        let Some(result) = self.results.get(self.selected_index) else {
+            return;
+        };
+
+        match self.prefetch_manager.toggle_pin(&result.url).await {
+            Ok(true) => self.set_status("📌 Pinned page"),
+            Ok(false) => self.set_status("Unpinned page"),
+            Err(e) => self.set_status(format!("⚠ {}", e)),
+        }
+    }
+
+    /// Open current result in neovim
     ///
     /// This activates the page (moves from current_search to active_tabs)
     /// and returns the filepath to open.
     pub async fn prepare_neovim_open(&mut self) -> Result<PathBuf> {
-        let result = self
-            .results
-            .get(self.selected_index)
-            .context("No result selected")?;
+        let url = self.selected_url().context("No result selected")?.to_string();
+        if let Some(reason) = self.selected_status().and_then(|s| self.unavailable_reason(s)) {
+            anyhow::bail!(reason);
+        }
 
         // Activate the page (move to active_tabs)
         let filepath = self
             .prefetch_manager
-            .activate_page(&result.url)
+            .activate_page(&url)
             .await
             .context("Failed to activate page")?;
 
+        self.mark_read(&[url]);
         Ok(filepath)
     }
 
+    /// Activate the pages of all Tab-selected results that are ready
+    ///
+    /// Opened results are unmarked. Also returns a status message like
+    /// `2 of 4 still loading` when some pages can't be opened yet.
+    pub async fn prepare_neovim_open_selected(&mut self) -> (Vec<PathBuf>, Option<String>) {
+        let indices = self.marked_indices();
+        let total = indices.len();
+
+        let mut paths = Vec::new();
+        let mut opened = Vec::new();
+        let (mut loading, mut unavailable) = (0, 0);
+        for index in indices {
+            let Some(result) = self.results.get(index) else {
+                continue;
+            };
+            let status = self.prefetch_statuses.get(&result.url);
+            if matches!(
+                status,
+                None | Some(PrefetchStatus::Pending | PrefetchStatus::InProgress)
+            ) {
+                self.prefetch_manager.boost(&result.url);
+                loading += 1;
+                continue;
+            }
+            match self.prefetch_manager.activate_page(&result.url).await {
+                Ok(path) => {
+                    paths.push(path);
+                    self.selected_items.remove(&result.url);
+                    opened.push(result.url.clone());
+                }
+                Err(_) => unavailable += 1,
+            }
+        }
+        self.mark_read(&opened);
+
+        let mut notes = Vec::new();
+        if loading > 0 {
+            notes.push(format!("{} of {} still loading", loading, total));
+        }
+        if unavailable > 0 {
+            notes.push(format!(
+                "{} of {} can't be opened in {}",
+                unavailable,
+                total,
+                self.config.editor.name()
+            ));
+        }
+        let marker = if loading > 0 { "⏳" } else { "⚠" };
+        let note = (!notes.is_empty()).then(|| format!("{} {}", marker, notes.join(", ")));
+        (paths, note)
+    }
+
+    /// Write the quickfix list of the selected results' fetched pages
+    ///
+    /// Takes every result when none are selected. Pages are activated like
+    /// opened ones, and opened results are unmarked. Also returns a status
+    /// message like `2 of 10 not fetched yet, skipped`.
+    pub async fn prepare_quickfix(&mut self) -> Option<(PathBuf, Option<String>)> {
+        if !self.config.editor.is_vim() {
+            let editor = self.config.editor.name();
+            self.set_status(format!("⚠ The quickfix list needs Vim or Neovim, not {}", editor));
+            return None;
+        }
+        let indices = if self.selected_items.is_empty(&self.results) {
+            (0..self.results.len()).collect()
+        } else {
+            self.marked_indices()
+        };
+        let total = indices.len();
+
+        let mut pages = Vec::new();
+        let mut opened = Vec::new();
+        for index in indices {
+            let Some(result) = self.results.get(index) else {
+                continue;
+            };
+            let fetched = matches!(
+                self.prefetch_statuses.get(&result.url),
+                Some(
+                    PrefetchStatus::Ready(_)
+                        | PrefetchStatus::ReadyArchived(_)
+                        | PrefetchStatus::Cached(_)
+                )
+            );
+            if !fetched {
+                continue;
+            }
+            if let Ok(path) = self.prefetch_manager.activate_page(&result.url).await {
+                pages.push((path, result.title.clone()));
+                opened.push(result.url.clone());
+            }
+        }
+        if pages.is_empty() {
+            self.set_status(format!("⏳ None of the {} pages are fetched yet", total));
+            return None;
+        }
+
+        let (file, count) = match quickfix::write(&pages) {
+            Ok(written) => written,
+            Err(e) => {
+                self.set_status(format!("⚠ {}", e));
+                return None;
+            }
+        };
+        for url in &opened {
+            self.selected_items.remove(url);
+        }
+        self.mark_read(&opened);
+        let skipped = total - count;
+        let note = (skipped > 0)
+            .then(|| format!("⚠ {} of {} not fetched yet, skipped", skipped, total));
+        Some((file, note))
+    }
+
+    /// Start waiting for the selected page if it is still loading
+    ///
+    /// Returns false when the page can be opened (or has failed) right away.
+    pub fn wait_for_selected_page(&mut self) -> bool {
+        let Some(url) = self.selected_url() else {
+            return false;
+        };
+
+        let loading = matches!(
+            self.selected_status(),
+            None | Some(PrefetchStatus::Pending | PrefetchStatus::InProgress)
+        );
+        if loading {
+            self.prefetch_manager.boost(url);
+            self.open_wait_since = Some(Instant::now());
+        }
+        loading
+    }
+
+    /// Check on the page Enter is waiting for
+    ///
+    /// Returns true once it is ready to open. A failed page or the wait
+    /// timing out ends the wait with a status message instead.
+    pub fn poll_open_wait(&mut self) -> bool {
+        let Some(since) = self.open_wait_since else {
+            return false;
+        };
+
+        let status = self.selected_status();
+        let browser = self.config.keymap.label(Action::OpenBrowser);
+        let outcome = match status {
+            Some(
+                PrefetchStatus::Ready(_)
+                | PrefetchStatus::ReadyArchived(_)
+                | PrefetchStatus::Cached(_),
+            ) => Ok(()),
+            Some(PrefetchStatus::Binary(_)) => {
+                self.open_wait_since = None;
+                self.open_selected_document();
+                return false;
+            }
+            Some(status @ PrefetchStatus::Blocked(_)) => {
+                Err(format!("🚧 {}", self.unavailable_reason(status).unwrap_or_default()))
+            }
+            Some(status @ (PrefetchStatus::Failed(_) | PrefetchStatus::Timeout)) => {
+                Err(format!("⚠ {}", self.unavailable_reason(status).unwrap_or_default()))
+            }
+            _ if since.elapsed() >= OPEN_WAIT_TIMEOUT => Err(format!(
+                "⚠ Page still loading after {}s, press {} to open in browser",
+                OPEN_WAIT_TIMEOUT.as_secs(),
+                browser
+            )),
+            _ => return false,
+        };
+
+        self.open_wait_since = None;
+        match outcome {
+            Ok(()) => true,
+            Err(message) => {
+                self.set_status(message);
+                false
+            }
+        }
+    }
+
+    /// Why a page with `status` can't be opened, naming the configured key
+    /// that helps
+    fn unavailable_reason(&self, status: &PrefetchStatus) -> Option<String> {
+        let retry = self.config.keymap.label(Action::Retry);
+        let browser = self.config.keymap.label(Action::OpenBrowser);
+        Some(match status {
+            PrefetchStatus::Failed(failure) if !failure.is_retryable() => {
+                format!("Page is gone ({}), press {} to open in browser", failure, browser)
+            }
+            PrefetchStatus::Failed(failure) => {
+                format!("Prefetch failed: {}, press {} to retry", failure, retry)
+            }
+            PrefetchStatus::Blocked(reason) => {
+                format!("Page is behind a {}, press {} to open in browser", reason, browser)
+            }
+            PrefetchStatus::Timeout => format!("Page timed out, press {} to retry", retry),
+            _ => return None,
+        })
+    }
+
+    /// Open the selected result with the system viewer if it was saved as a PDF
+    ///
+    /// Returns false when the result isn't a document.
+    pub fn open_selected_document(&mut self) -> bool {
+        let Some(PrefetchStatus::Binary(path)) = self.selected_status() else {
+            return false;
+        };
+
+        match open_url(&system_opener(&path.to_string_lossy())) {
+            Ok(()) => {
+                let url = self.selected_url().map(str::to_string);
+                self.mark_read(&Vec::from_iter(url));
+                self.set_status("Opened PDF in the system viewer");
+            }
+            Err(e) => self.set_status(format!("⚠ {}", e)),
+        }
+        true
+    }
+
+    /// Record `urls` in the read log, unless `ui.track_read` is off
+    fn mark_read(&mut self, urls: &[String]) {
+        if !self.config.ui.track_read || urls.is_empty() {
+            return;
+        }
+        let now = unix_now();
+        for url in urls {
+            self.read_log.mark(url, now);
+        }
+        if let Err(e) = self.read_log.save() {
+            tracing::warn!("Failed to save read log: {:#}", e);
+        }
+    }
+
+    /// Mark the selected result read, or unread if it was
+    pub fn toggle_read(&mut self) {
+        if !self.config.ui.track_read {
+            self.set_status("Read tracking is off (ui.track_read)");
+            return;
+        }
+        let Some(url) = self.results.get(self.selected_index).map(|r| r.url.clone()) else {
+            return;
+        };
+        let done = if self.read_log.unmark(&url) {
+            "Marked unread"
+        } else {
+            self.read_log.mark(&url, unix_now());
+            "Marked read"
+        };
+        match self.read_log.save() {
+            Ok(()) => self.set_status(done),
+            Err(e) => self.set_status(format!("⚠ {}, but not saved: {:#}", done, e)),
+        }
+    }
+
+    /// URL of the selected result, or bookmark in the Bookmarks view
+    fn selected_url(&self) -> Option<&str> {
+        match self.state {
+            AppState::Bookmarks => self
+                .bookmarks
+                .get(self.bookmark_selected)
+                .map(|b| b.url.as_str()),
+            _ => self.results.get(self.selected_index).map(|r| r.url.as_str()),
+        }
+    }
+
+    /// Prefetch status of `selected_url`, if known
+    fn selected_status(&self) -> Option<&PrefetchStatus> {
+        let url = self.selected_url()?;
+        match self.state {
+            AppState::Bookmarks => self.bookmark_statuses.get(url),
+            _ => self.prefetch_statuses.get(url),
+        }
+    }
+
+    /// Stop waiting for a page and stay in the results list
+    pub fn cancel_open_wait(&mut self) {
+        self.open_wait_since = None;
+        self.set_status("Stopped waiting for page");
+    }
+
     /// Go back to input mode
     pub fn back_to_input(&mut self) {
+        self.key_sequence.reset();
         self.state = AppState::Input;
     }
 
-    /// Get prefetch progress
-    pub async fn get_prefetch_progress(&self) -> (usize, usize) {
-        self.prefetch_manager.get_progress().await
+    /// Show the Library of cached pages
+    pub fn open_library(&mut self) {
+        self.library = load_library(&self.prefetch_manager.cache_dirs());
+        self.library_selected = 0;
+        self.library_filter.clear();
+        self.library_filtering = false;
+        self.state = AppState::Library;
     }
 
-    /// Get all prefetch statuses (for UI rendering)
-    pub async fn get_all_statuses(&self) -> HashMap<String, PrefetchStatus> {
-        self.prefetch_manager.get_all_statuses().await
+    /// Leave the Library for the search box
+    pub fn close_library(&mut self) {
+        self.library.clear();
+        self.state = AppState::Input;
     }
-}
 
-/// Open URL in default browser
-fn open_url(url: &str) -> Result<()> {
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg(url)
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .context("Failed to open browser")?;
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("xdg-open")
-            .arg(url)
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .context("Failed to open browser")?;
-    }
-
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("cmd")
-            .args(&["/C", "start", "", url])
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .context("Failed to open browser")?;
+    /// Indices into `library` of the entries matching the filter
+    pub fn library_matches(&self) -> Vec<usize> {
+        let filter = self.library_filter.text();
+        self.library
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| filter.is_empty() || entry.matches(filter))
+            .map(|(i, _)| i)
+            .collect()
     }
 
-    Ok(())
-}
+    /// Selected Library entry, if any matches the filter
+    pub fn selected_library_entry(&self) -> Option<&LibraryEntry> {
+        let index = *self.library_matches().get(self.library_selected)?;
+        self.library.get(index)
+    }
 
-/// Open file in neovim (blocking)
-pub fn open_in_neovim(filepath: &PathBuf) -> Result<()> {
-    let status = Command::new("nvim")
-        .arg(filepath)
-        .status()
-        .context("Failed to launch neovim")?;
+    /// Move the Library selection down (wraps)
+    pub fn library_next(&mut self) {
+        let count = self.library_matches().len();
+        if count > 0 {
+            self.library_selected = (self.library_selected + 1) % count;
+        }
+    }
 
-    if !status.success() {
-        anyhow::bail!("Neovim exited with error");
+    /// Move the Library selection up (wraps)
+    pub fn library_previous(&mut self) {
+        let count = self.library_matches().len();
+        if count > 0 {
+            self.library_selected = (self.library_selected + count - 1) % count;
+        }
     }
 
-    Ok(())
-}
\ No newline at end of file
+    /// Keep the Library selection inside the filtered list
+    pub fn clamp_library_selection(&mut self) {
+        let count = self.library_matches().len();
+        self.library_selected = self.library_selected.min(count.saturating_sub(1));
+    }
+
+    /// Pin or unpin the selected Library page
+    pub fn toggle_library_pin(&mut self) {
+        let Some(&index) = self.library_matches().get(self.library_selected) else {
+            return;
+        };
+
+        let entry = &mut self.library[index];
+        match self.prefetch_manager.toggle_pin_file(&entry.path) {
+            Ok((path, pinned)) => {
+                entry.path = path;
+                self.set_status(if pinned { "📌 Pinned page" } else { "Unpinned page" });
+            }
+            Err(e) => self.set_status(format!("⚠ {}", e)),
+        }
+    }
+
+    /// Delete the selected Library page from disk
+    pub fn delete_library_entry(&mut self) {
+        let Some(&index) = self.library_matches().get(self.library_selected) else {
+            return;
+        };
+
+        let entry = self.library.remove(index);
+        match remove_page(&entry.path) {
+            Ok(()) => self.set_status(format!("Deleted {}", entry.title())),
+            Err(e) => {
+                self.set_status(format!("⚠ Failed to delete {}: {}", entry.path.display(), e))
+            }
+        }
+        self.clamp_library_selection();
+    }
+
+    /// Start typing a note for the selected result
+    pub fn start_note(&mut self) {
+        if let Some(result) = self.results.get(self.selected_index) {
+            self.note_input = Some((result.url.clone(), InputLine::default()));
+        }
+    }
+
+    /// Add the typed note to its page, or queue it until the page is fetched
+    pub fn submit_note(&mut self) {
+        let Some((url, input)) = self.note_input.take() else {
+            return;
+        };
+        if input.text().trim().is_empty() {
+            return;
+        }
+        let note = Note::new(input.text());
+
+        let status = self.prefetch_statuses.get(&url);
+        if matches!(status, Some(PrefetchStatus::Binary(_))) {
+            self.set_status("⚠ Notes can't be added to PDFs");
+            return;
+        }
+        match status.and_then(PrefetchStatus::path).map(Path::to_path_buf) {
+            Some(path) => self.write_note(&url, &path, note),
+            None => {
+                self.pending_notes.entry(url).or_default().push(note);
+                self.set_status("📝 Note saved once the page is fetched");
+            }
+        }
+    }
+
+    /// Append `note` to the page and mirror it in the index and bookmarks
+    fn write_note(&mut self, url: &str, path: &Path, note: Note) {
+        if let Err(e) = notes::append_to_page(path, &note) {
+            self.set_status(format!("⚠ {:#}", e));
+            return;
+        }
+        self.prefetch_manager.add_note(url, note.clone());
+        if self.bookmarks.add_note(url, note)
+            && let Err(e) = self.bookmarks.save()
+        {
+            self.set_status(format!("⚠ Note added, but bookmarks weren't saved: {:#}", e));
+            return;
+        }
+        self.set_status(format!("📝 Note added to {}", path.display()));
+    }
+
+    /// Write the queued notes of `url` once `status` says its page is saved
+    fn flush_pending_notes(&mut self, url: &str, status: &PrefetchStatus) {
+        if matches!(status, PrefetchStatus::Binary(_)) {
+            self.pending_notes.remove(url);
+            self.set_status("⚠ The page is a PDF, its queued notes were dropped");
+            return;
+        }
+        let Some(path) = status.path().map(Path::to_path_buf) else {
+            return;
+        };
+        for note in self.pending_notes.remove(url).unwrap_or_default() {
+            self.write_note(url, &path, note);
+        }
+    }
+
+    /// Show the headings of the selected page to pick one to open it at
+    pub fn open_outline(&mut self) {
+        let Some(url) = self.selected_url() else {
+            return;
+        };
+        let path = match self.prefetch_statuses.get(url) {
+            Some(PrefetchStatus::Binary(_)) => {
+                self.set_status("PDFs have no headings to jump to");
+                return;
+            }
+            status => status.and_then(PrefetchStatus::path).map(Path::to_path_buf),
+        };
+        let Some(path) = path else {
+            self.set_status("⏳ Page not ready yet");
+            return;
+        };
+
+        match read_headings(&path) {
+            Ok(headings) if headings.is_empty() => self.set_status("No headings in this page"),
+            Ok(headings) => {
+                self.outline = Some(Outline {
+                    path,
+                    headings,
+                    selected: 0,
+                })
+            }
+            Err(e) => self.set_status(format!("⚠ Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Search the query as typed after a correction, or pick a suggestion
+    pub async fn use_suggestions(&mut self, tx: &UnboundedSender<AppMessage>) {
+        let (Some(meta), Some((query, engine))) = (&self.search_meta, self.last_search.clone())
+        else {
+            self.set_status("The engine didn't correct or suggest anything for this search");
+            return;
+        };
+        if meta.altered.is_some() {
+            self.input.clear();
+            self.input.insert_str(&query);
+            self.spawn_search_with(tx, engine, true).await;
+        } else {
+            self.suggestion_menu = Some(SuggestionMenu::new(meta.suggestions.clone()));
+        }
+    }
+
+    /// Search the suggestion selected in the picker with the same engine
+    pub async fn choose_suggestion(&mut self, tx: &UnboundedSender<AppMessage>) {
+        let Some(menu) = self.suggestion_menu.take() else {
+            return;
+        };
+        let (Some(suggestion), Some((query, engine))) =
+            (menu.selected_query(), self.last_search.clone())
+        else {
+            return;
+        };
+        let text = with_operators(suggestion, &query);
+        self.input.clear();
+        self.input.insert_str(&text);
+        self.spawn_search(tx, engine).await;
+    }
+
+    /// Show the goggle picker
+    pub fn open_goggle_menu(&mut self) {
+        let goggles = goggles::configured(&self.config.search);
+        if goggles.is_empty() {
+            self.set_status("⚠ Add goggles to [search.goggles] in config.toml to pick one");
+            return;
+        }
+        self.goggle_menu = Some(GoggleMenu::new(goggles, self.providers.goggle().as_ref()));
+    }
+
+    /// Use the goggle selected in the picker for Brave searches
+    pub fn choose_goggle(&mut self) {
+        if let Some(menu) = self.goggle_menu.take() {
+            self.set_goggle(menu.selected_goggle().cloned());
+        }
+    }
+
+    /// Turn the goggle off, or back on
+    pub fn toggle_goggle(&mut self) {
+        if self.providers.goggle().is_some() {
+            self.set_goggle(None);
+            return;
+        }
+        let goggle = self
+            .goggle_choice
+            .clone()
+            .or_else(|| goggles::configured(&self.config.search).into_iter().next());
+        match goggle {
+            Some(goggle) => self.set_goggle(Some(goggle)),
+            None => {
+                self.set_status("⚠ Add goggles to [search.goggles] in config.toml to use one");
+            }
+        }
+    }
+
+    fn set_goggle(&mut self, goggle: Option<Goggle>) {
+        match &goggle {
+            Some(goggle) => {
+                self.set_status(format!("Goggle {} on for Brave searches", goggle.name));
+                self.goggle_choice = Some(goggle.clone());
+            }
+            None => self.set_status("Goggle off"),
+        }
+        self.providers.set_goggle(goggle);
+    }
+
+    /// Show the `[open_with]` commands for the selected result
+    pub fn open_with_menu(&mut self) {
+        if self.config.open_with.is_empty() {
+            self.set_status("⚠ Add commands to [open_with] in config.toml to use this menu");
+            return;
+        }
+        let Some(url) = self.selected_url() else {
+            return;
+        };
+        let path = self
+            .prefetch_statuses
+            .get(url)
+            .and_then(PrefetchStatus::path)
+            .map(Path::to_path_buf);
+        self.open_with = Some(OpenWithMenu::new(url, path, &self.config.open_with));
+    }
+
+    /// Run the command chosen in the "open with" menu and close it
+    ///
+    /// The command runs detached; a failure shows up in the status line
+    /// when it exits.
+    pub fn run_open_with(&mut self, tx: &UnboundedSender<AppMessage>) {
+        let Some(menu) = &self.open_with else {
+            return;
+        };
+        let Some((label, line)) = menu.selected_command() else {
+            self.set_status("⏳ Page not ready yet");
+            return;
+        };
+        let label = label.to_string();
+        self.open_with = None;
+
+        match hooks::spawn_shell(&line) {
+            Ok(child) => {
+                self.set_status(format!("Opened with {}", label));
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = hooks::wait(child, None).await {
+                        let error = format!("{:#}", e);
+                        let _ = tx.send(AppMessage::OpenWithFailed { label, error });
+                    }
+                });
+            }
+            Err(e) => self.show_error(
+                Severity::Recoverable,
+                &format!("Failed to run {}: {}", label, e),
+            ),
+        }
+    }
+
+    /// Close the headings popup, returning the editor argument for the chosen heading
+    ///
+    /// Vim and Neovim search for the heading, so the cursor lands on it even
+    /// if the page was re-fetched since; other editors get its line number.
+    pub fn take_outline_jump(&mut self) -> Option<String> {
+        let outline = self.outline.take()?;
+        let heading = outline.selected_heading()?;
+        if self.config.editor.is_vim() {
+            Some(heading.vim_search())
+        } else {
+            Some(format!("+{}", heading.line))
+        }
+    }
+
+    /// Search the cached pages for the words in the search box
+    ///
+    /// The files are read on a blocking thread, which reports its progress
+    /// and the matches as messages.
+    pub fn start_local_search(&mut self, tx: &UnboundedSender<AppMessage>) {
+        let query = self.input.text().trim().to_string();
+        if query.is_empty() {
+            self.set_status("Type the words to look for in the cached pages");
+            return;
+        }
+
+        let paths: Vec<PathBuf> = load_library(&self.prefetch_manager.cache_dirs())
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        self.local_generation += 1;
+        self.local_query = query.clone();
+        self.local_matches.clear();
+        self.local_selected = 0;
+        self.local_progress = Some((0, paths.len()));
+        self.key_sequence.reset();
+        self.state = AppState::LocalSearch;
+
+        let generation = self.local_generation;
+        let tx = tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let total = paths.len();
+            let terms = local_search::terms(&query);
+            let matches = local_search::search_files(&paths, &terms, |scanned| {
+                if scanned % LOCAL_PROGRESS_EVERY == 0 {
+                    let _ = tx.send(AppMessage::LocalSearchProgress {
+                        generation,
+                        scanned,
+                        total,
+                    });
+                }
+            });
+            let _ = tx.send(AppMessage::LocalSearchDone {
+                generation,
+                matches,
+            });
+        });
+    }
+
+    fn finish_local_search(&mut self, matches: Vec<LocalMatch>) {
+        self.local_progress = None;
+        self.local_matches = matches;
+        self.local_selected = 0;
+        match self.local_matches.len() {
+            0 => self.set_status(format!("No cached page contains \"{}\"", self.local_query)),
+            1 => self.set_status("Found 1 cached page"),
+            count => self.set_status(format!("Found {} cached pages", count)),
+        }
+    }
+
+    /// Leave the cached page matches for the search box
+    pub fn close_local_search(&mut self) {
+        // A scan still running is ignored from now on
+        self.local_generation += 1;
+        self.local_progress = None;
+        self.local_matches.clear();
+        self.back_to_input();
+    }
+
+    /// Selected cached page match, if any
+    pub fn selected_local_match(&self) -> Option<&LocalMatch> {
+        self.local_matches.get(self.local_selected)
+    }
+
+    /// Move the cached page selection down (wraps)
+    pub fn local_next(&mut self) {
+        let count = self.local_matches.len();
+        if count > 0 {
+            self.local_selected = (self.local_selected + 1) % count;
+        }
+    }
+
+    /// Move the cached page selection up (wraps)
+    pub fn local_previous(&mut self) {
+        let count = self.local_matches.len();
+        if count > 0 {
+            self.local_selected = (self.local_selected + count - 1) % count;
+        }
+    }
+
+    /// Bookmark the selected result, or refresh its bookmark
+    pub fn bookmark_selected(&mut self) {
+        let Some(result) = self.results.get(self.selected_index) else {
+            return;
+        };
+        let query = self
+            .last_search
+            .as_ref()
+            .map(|(query, _)| query.as_str())
+            .unwrap_or_default();
+
+        let added = self.bookmarks.add(Bookmark::new(result, query));
+        match self.bookmarks.save() {
+            Ok(()) if added => self.set_status("✓ Bookmarked page"),
+            Ok(()) => self.set_status("✓ Updated bookmark"),
+            Err(e) => self.show_error(
+                Severity::Recoverable,
+                &format!("Failed to save bookmarks: {:#}", e),
+            ),
+        }
+    }
+
+    /// Copy the selected page into the Obsidian vault
+    pub fn export_to_obsidian(&mut self) {
+        let Some(vault) = self.config.paths.obsidian_vault.clone() else {
+            self.set_status("⚠ Set paths.obsidian_vault in config.toml to use Obsidian");
+            return;
+        };
+        let Some(url) = self.selected_url() else {
+            return;
+        };
+        let path = match self.prefetch_statuses.get(url) {
+            Some(PrefetchStatus::Binary(_)) => {
+                self.set_status("⚠ PDFs can't be copied to Obsidian");
+                return;
+            }
+            status => status.and_then(PrefetchStatus::path).map(Path::to_path_buf),
+        };
+        let Some(path) = path else {
+            self.set_status("⏳ Page not ready yet");
+            return;
+        };
+
+        match obsidian::export_page(&path, &vault) {
+            Ok(note) => self.set_status(format!("✓ Copied to {}", note.display())),
+            Err(e) => self.show_error(
+                Severity::Recoverable,
+                &format!("Failed to copy to Obsidian: {:#}", e),
+            ),
+        }
+    }
+
+    /// Cite the selected result, to the clipboard or the references file
+    ///
+    /// Pages that aren't fetched yet are cited from the result alone.
+    pub fn cite_selected(&mut self) {
+        let Some(result) = self.results.get(self.selected_index) else {
+            return;
+        };
+        let metadata = self
+            .prefetch_statuses
+            .get(&result.url)
+            .and_then(PrefetchStatus::path)
+            .and_then(PageMetadata::read)
+            .unwrap_or_default();
+        let (today, _) = format_utc(unix_now());
+        let cite = Citation::new(&result.url, &result.title, metadata, &today);
+        let CitationConfig {
+            format,
+            destination,
+        } = self.config.citation;
+
+        match destination {
+            CitationDestination::Clipboard => match copy_to_clipboard(&cite.format(format)) {
+                Ok(_) => self.set_status(format!("Copied citation {}", cite.key)),
+                Err(e) => self.set_status(format!("⚠ {}", e)),
+            },
+            CitationDestination::File => {
+                let file = paths::data_dir().join(format.file_name());
+                match citation::append_to_file(&file, &cite, format) {
+                    Ok(Some(key)) => {
+                        self.set_status(format!("✓ Added {} to {}", key, format.file_name()))
+                    }
+                    Ok(None) => self.set_status(format!("Already in {}", format.file_name())),
+                    Err(e) => self.show_error(
+                        Severity::Recoverable,
+                        &format!("Failed to save the citation: {:#}", e),
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Write the results (or the Tab-selected ones) to an export file
+    pub fn export_results(&mut self, format: ExportFormat) {
+        let Some((query, engine)) = &self.last_search else {
+            return;
+        };
+        let indices: Vec<usize> = if self.selected_items.is_empty(&self.results) {
+            (0..self.results.len()).collect()
+        } else {
+            self.marked_indices()
+        };
+
+        let entries = indices
+            .iter()
+            .filter_map(|&idx| self.results.get(idx))
+            .map(|result| (result, self.prefetch_statuses.get(&result.url)))
+            .collect();
+        let digest = Digest {
+            query,
+            engine: *engine,
+            entries,
+        };
+
+        match digest.write(&self.config.paths.export_dir(), format) {
+            Ok(path) => self.set_status(format!("✓ Exported to {}", path.display())),
+            Err(e) => self.show_error(Severity::Recoverable, &format!("Export failed: {:#}", e)),
+        }
+    }
+
+    /// Show the saved bookmarks
+    ///
+    /// Pages already on disk are marked as cached; nothing is downloaded
+    /// until a bookmark is opened.
+    pub fn open_bookmarks(&mut self) {
+        self.bookmark_selected = 0;
+        self.bookmark_statuses = self
+            .bookmarks
+            .entries()
+            .iter()
+            .filter_map(|b| Some((b.url.clone(), self.prefetch_manager.find_cached(&b.url)?)))
+            .collect();
+        self.key_sequence.reset();
+        self.state = AppState::Bookmarks;
+    }
+
+    /// Leave the bookmarks for the search box
+    pub fn close_bookmarks(&mut self) {
+        self.bookmark_statuses.clear();
+        self.state = AppState::Input;
+    }
+
+    /// Move the bookmark selection down (wraps)
+    pub fn bookmark_next(&mut self) {
+        let count = self.bookmarks.entries().len();
+        if count > 0 {
+            self.bookmark_selected = (self.bookmark_selected + 1) % count;
+        }
+    }
+
+    /// Move the bookmark selection up (wraps)
+    pub fn bookmark_previous(&mut self) {
+        let count = self.bookmarks.entries().len();
+        if count > 0 {
+            self.bookmark_selected = (self.bookmark_selected + count - 1) % count;
+        }
+    }
+
+    /// Delete the selected bookmark
+    pub fn delete_bookmark(&mut self) {
+        let Some(bookmark) = self.bookmarks.remove(self.bookmark_selected) else {
+            return;
+        };
+        let count = self.bookmarks.entries().len();
+        self.bookmark_selected = self.bookmark_selected.min(count.saturating_sub(1));
+
+        match self.bookmarks.save() {
+            Ok(()) => self.set_status(format!("Deleted bookmark {}", bookmark.title)),
+            Err(e) => self.show_error(
+                Severity::Recoverable,
+                &format!("Failed to save bookmarks: {:#}", e),
+            ),
+        }
+    }
+
+    /// Queue the selected bookmark's page unless it is cached or loading
+    ///
+    /// Cached pages go through the prefetcher too, so it knows them when
+    /// the page is activated.
+    pub async fn fetch_selected_bookmark(&mut self) {
+        let Some(bookmark) = self.bookmarks.get(self.bookmark_selected) else {
+            return;
+        };
+        if matches!(
+            self.bookmark_statuses.get(&bookmark.url),
+            Some(PrefetchStatus::Pending | PrefetchStatus::InProgress)
+        ) {
+            return;
+        }
+        let result = bookmark.result();
+        self.prefetch_manager.prefetch_all(std::slice::from_ref(&result)).await;
+        let status = self.prefetch_manager.get_status(&result.url).await;
+        self.bookmark_statuses.insert(result.url, status);
+    }
+
+    /// Apply a prefetch status change pushed by the PrefetchManager
+    pub fn update_prefetch_status(&mut self, url: String, status: PrefetchStatus) {
+        if self.pending_notes.contains_key(&url) {
+            self.flush_pending_notes(&url, &status);
+        }
+        if self.bookmarks.contains(&url) {
+            self.bookmark_statuses.insert(url.clone(), status.clone());
+        }
+        // Commands using {path} become available once the page is fetched
+        if let Some(menu) = &mut self.open_with
+            && menu.url == url
+        {
+            menu.path = status.path().map(Path::to_path_buf);
+        }
+        // Late updates from a previous search's tasks are dropped
+        if self.results.iter().any(|r| r.url == url) {
+            self.prefetch_statuses.insert(url, status);
+        }
+    }
+
+    /// Report the end of a prefetch batch in the status line
+    pub fn finish_prefetch(&mut self, evicted: usize) {
+        let (completed, total) = self.prefetch_progress();
+        let ready = self.ready_count();
+
+        let eviction_note = if evicted > 0 {
+            format!("🧹 Evicted {} cached page(s) over the cache size cap", evicted)
+        } else {
+            String::new()
+        };
+
+        if total > 0 && completed == total {
+            let summary = if ready == total {
+                format!("✓ All {} pages ready!", total)
+            } else {
+                format!("Prefetch finished: {}/{} pages ready", ready, total)
+            };
+            if eviction_note.is_empty() {
+                self.set_status(summary);
+            } else {
+                self.set_status(format!("{} │ {}", summary, eviction_note));
+            }
+        } else if !eviction_note.is_empty() {
+            self.set_status(eviction_note);
+        }
+    }
+
+    /// Pages of the results that were saved or found in the cache
+    fn ready_count(&self) -> usize {
+        self.prefetch_statuses
+            .values()
+            .filter(|s| {
+                matches!(
+                    s,
+                    PrefetchStatus::Ready(_)
+                        | PrefetchStatus::ReadyArchived(_)
+                        | PrefetchStatus::Cached(_)
+                        | PrefetchStatus::Binary(_)
+                )
+            })
+            .count()
+    }
+
+    /// Start the timing of the search just shown, `elapsed` after it started
+    fn time_search(&mut self, elapsed: Duration) {
+        let Some((query, engine)) = &self.last_search else {
+            return;
+        };
+        self.search_timing = Some(SearchTiming {
+            finished_at: unix_now(),
+            engine: *engine,
+            query_length: query.chars().count(),
+            results: self.results.len(),
+            elapsed,
+            prefetch: None,
+        });
+    }
+
+    /// Add the finished prefetch batch to the timing of the search
+    ///
+    /// Only the first batch counts; retries don't change it. The complete
+    /// timing goes to `metrics.csv`.
+    fn time_prefetch(&mut self) {
+        let (_, total) = self.prefetch_progress();
+        let ready = self.ready_count();
+        let elapsed = self.prefetch_manager.batch_elapsed();
+        let Some(timing) = &mut self.search_timing else {
+            return;
+        };
+        if timing.prefetch.is_some() {
+            return;
+        }
+        timing.prefetch = Some(PrefetchTiming {
+            ready,
+            total,
+            elapsed,
+        });
+        let timing = timing.clone();
+        self.log_timing(&timing);
+    }
+
+    /// Drop the timing of the search leaving the screen
+    ///
+    /// A search whose prefetching never finished is logged without it.
+    fn end_timing(&mut self) {
+        if let Some(timing) = self.search_timing.take()
+            && timing.prefetch.is_none()
+        {
+            self.log_timing(&timing);
+        }
+    }
+
+    fn log_timing(&self, timing: &SearchTiming) {
+        if !self.config.search.metrics {
+            return;
+        }
+        if let Err(e) = metrics::append(timing) {
+            tracing::warn!("Failed to write metrics: {:#}", e);
+        }
+    }
+
+    /// Get prefetch progress as (completed, total)
+    pub fn prefetch_progress(&self) -> (usize, usize) {
+        let completed = self
+            .prefetch_statuses
+            .values()
+            .filter(|s| !matches!(s, PrefetchStatus::Pending | PrefetchStatus::InProgress))
+            .count();
+        (completed, self.prefetch_statuses.len())
+    }
+
+    /// Earliest moment the UI needs a redraw without any input
+    ///
+    /// Used by the event loop to expire the status line and the `g` chord,
+    /// to animate the spinner while waiting for a page and to count down
+    /// resting engines.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let timeout = self.config.ui.status_timeout();
+        let status = self.status_set_at.map(|t| t + timeout);
+        let popup = self.error_popup.as_ref().map(|(_, t)| *t + timeout);
+        let chord = self.key_sequence.deadline();
+        let spinner = self.open_wait_since.map(|_| Instant::now() + SPINNER_INTERVAL);
+        let cooldown = self.cooldowns.next_tick(Instant::now());
+        [status, popup, chord, spinner, cooldown].into_iter().flatten().min()
+    }
+}
+
+/// Results below the selection that get prefetched ahead of the rest
+const BOOST_AHEAD: usize = 2;
+
+/// Results moved per mouse wheel notch
+const SCROLL_STEP: usize = 3;
+
+/// How long `nvim --server` may take to hand files to a running Neovim
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Longest gap between the two clicks of a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Remove `results[index]`, remembering where it was for undo
+///
+/// Only the latest [`MAX_UNDO`] removals are kept.
+fn remove_result(
+    results: &mut Vec<SearchResult>,
+    deleted: &mut Vec<(usize, SearchResult)>,
+    index: usize,
+) -> SearchResult {
+    let result = results.remove(index);
+    deleted.push((index, result.clone()));
+    if deleted.len() > MAX_UNDO {
+        deleted.remove(0);
+    }
+    result
+}
+
+/// Put the latest removed result back where it was; returns its index
+fn restore_result(
+    results: &mut Vec<SearchResult>,
+    deleted: &mut Vec<(usize, SearchResult)>,
+) -> Option<usize> {
+    let (index, result) = deleted.pop()?;
+    let index = index.min(results.len());
+    results.insert(index, result);
+    Some(index)
+}
+
+/// Whole results of `item_height` lines that fit in a bordered list
+/// `height` lines tall
+pub fn visible_item_count(height: u16, item_height: usize) -> usize {
+    height.saturating_sub(2) as usize / item_height.max(1)
+}
+
+/// Result drawn at screen `row` of a bordered list in `area`
+///
+/// Mirrors how the list is drawn: items of `item_height` lines (see
+/// [`Density::item_height`]) starting at `offset`, inside a one-line border.
+pub fn result_at_row(
+    area: Rect,
+    row: u16,
+    offset: usize,
+    len: usize,
+    item_height: usize,
+) -> Option<usize> {
+    if row <= area.y || row + 1 >= area.bottom() {
+        return None;
+    }
+    let index = offset + (row - area.y - 1) as usize / item_height.max(1);
+    (index < len).then_some(index)
+}
+
+/// Compute the list scroll offset for a given selection
+///
+/// Keeps the previous offset when the selection is still visible, so the
+/// list scrolls one item at a time, and never leaves blank space after the
+/// last item.
+pub fn compute_scroll_offset(
+    offset: usize,
+    selected: usize,
+    len: usize,
+    visible_items: usize,
+) -> usize {
+    let visible = visible_items.max(1);
+    let mut offset = offset;
+
+    if selected < offset {
+        offset = selected;
+    } else if selected >= offset + visible {
+        offset = selected + 1 - visible;
+    }
+
+    offset.min(len.saturating_sub(visible))
+}
+
+/// Start a browser or viewer from `argv` without waiting for it
+///
+/// See [`crate::config::BrowserConfig::argv`].
+fn open_url(argv: &[String]) -> Result<()> {
+    let (program, args) = argv.split_first().context("No browser configured")?;
+    Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", shell_words::join(argv)))?;
+    Ok(())
+}
+
+/// Fail unless the editor's program can be found
+///
+/// Checked before the terminal is handed over, so a missing editor doesn't
+/// leave the screen torn down.
+pub fn check_editor(editor: &EditorConfig) -> Result<()> {
+    let argv = editor.argv();
+    let program = argv.first().context("No editor configured")?;
+    find_program(program, std::env::var_os("PATH"))
+        .map(|_| ())
+        .with_context(|| format!("Editor {} not found on PATH", program))
+}
+
+/// Open files in the running Neovim at `server`
+///
+/// Waits only for `nvim --server` to hand the files over, not for the
+/// editor. Fails when the server can't be reached.
+pub async fn send_to_nvim_server(
+    server: &str,
+    paths: &[PathBuf],
+    tab: bool,
+    jump: Option<&str>,
+) -> Result<()> {
+    run_remote(server, remote_command(server, paths, tab, jump)).await
+}
+
+/// Load `file` as the quickfix list of the running Neovim at `server`
+pub async fn send_quickfix_to_nvim_server(server: &str, file: &Path) -> Result<()> {
+    let mut command = tokio::process::Command::new("nvim");
+    command
+        .arg("--server")
+        .arg(server)
+        .arg("--remote-send")
+        .arg(quickfix::cfile_keys(file))
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .kill_on_drop(true);
+    run_remote(server, command).await
+}
+
+/// Run an `nvim --server` command, failing when the server can't be reached
+async fn run_remote(server: &str, mut command: tokio::process::Command) -> Result<()> {
+    let output = tokio::time::timeout(REMOTE_TIMEOUT, command.output())
+        .await
+        .context("Neovim server did not respond")?
+        .context("Failed to launch nvim")?;
+
+    // Older Neovim reports connection errors on stderr with status 0
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !stderr.trim().is_empty() {
+        anyhow::bail!("Neovim server {} unreachable: {}", server, stderr.trim());
+    }
+    Ok(())
+}
+
+fn remote_command(
+    server: &str,
+    paths: &[PathBuf],
+    tab: bool,
+    jump: Option<&str>,
+) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("nvim");
+    command
+        .arg("--server")
+        .arg(server)
+        .arg(if tab { "--remote-tab" } else { "--remote" })
+        .args(jump)
+        .args(paths)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .kill_on_drop(true);
+    command
+}
+
+/// Open files in the configured editor (blocking)
+///
+/// Several files are laid out according to `editor.split` when the editor
+/// is Vim or Neovim. `jump` is a `+{command}` argument put before the
+/// files, like `+42` to start on line 42; Vim, Neovim, Nano, Emacs and
+/// most other terminal editors take `+{line}`.
+pub fn open_in_editor(editor: &EditorConfig, paths: &[PathBuf], jump: Option<&str>) -> Result<()> {
+    let mut editor = editor_command(editor, paths, jump)?;
+    let program = editor.get_program().to_string_lossy().into_owned();
+    let status = editor
+        .status()
+        .with_context(|| format!("Failed to launch {}", program))?;
+
+    if !status.success() {
+        anyhow::bail!("{} exited with error", program);
+    }
+
+    Ok(())
+}
+
+/// Open files in the editor in a new tmux window or pane
+///
+/// Returns where they were opened, like `tmux window`. Only meaningful
+/// inside tmux with a tmux [`OpenMode`].
+pub fn open_in_tmux(
+    editor: &EditorConfig,
+    paths: &[PathBuf],
+    jump: Option<&str>,
+) -> Result<&'static str> {
+    let (mut tmux, place) = tmux_command(editor, paths, jump)?;
+    let output = tmux.output().context("Failed to launch tmux")?;
+    if !output.status.success() {
+        anyhow::bail!("tmux: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(place)
+}
+
+fn tmux_command(
+    editor: &EditorConfig,
+    paths: &[PathBuf],
+    jump: Option<&str>,
+) -> Result<(Command, &'static str)> {
+    let (subcommand, place): (&[&str], _) = match editor.open_mode {
+        OpenMode::TmuxWindow => (&["new-window"], "tmux window"),
+        OpenMode::TmuxPane => (&["split-window", "-h"], "tmux pane"),
+        OpenMode::Replace => anyhow::bail!("Not a tmux open mode"),
+    };
+    // tmux runs the command through a shell, so quote every word
+    let shell_command = shell_words::join(editor_argv(editor, paths, jump)?);
+    let mut tmux = Command::new("tmux");
+    tmux.args(subcommand).arg(shell_command);
+    Ok((tmux, place))
+}
+
+fn editor_command(editor: &EditorConfig, paths: &[PathBuf], jump: Option<&str>) -> Result<Command> {
+    let argv = editor_argv(editor, paths, jump)?;
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..]);
+    Ok(command)
+}
+
+/// Editor program, its arguments and the files to open
+fn editor_argv(
+    editor: &EditorConfig,
+    paths: &[PathBuf],
+    jump: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut argv = editor.argv();
+    anyhow::ensure!(!argv.is_empty(), "No editor configured");
+    if paths.len() > 1 && editor.is_vim() {
+        argv.push(editor.split.flag().to_string());
+    }
+    argv.extend(jump.map(str::to_string));
+    argv.extend(paths.iter().map(|path| path.to_string_lossy().into_owned()));
+    Ok(argv)
+}
+
+/// `program` itself when it is a path, else its first match in `path`
+fn find_program(program: &str, path: Option<OsString>) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return is_executable(program).then(|| program.to_path_buf());
+    }
+    std::env::split_paths(&path?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file() || path.with_extension("exe").is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Density, EditorSplit};
+    use crossterm::event::KeyModifiers;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn feed_str(seq: &mut KeySequence, keys: &str) -> KeyAction {
+        let mut action = KeyAction::Unhandled;
+        for c in keys.chars() {
+            action = seq.feed(press(KeyCode::Char(c)));
+        }
+        action
+    }
+
+    #[test]
+    fn test_key_sequence_count_prefix() {
+        let mut seq = KeySequence::default();
+        assert_eq!(feed_str(&mut seq, "5"), KeyAction::Pending);
+        assert_eq!(seq.pending().as_deref(), Some("5"));
+        assert_eq!(feed_str(&mut seq, "j"), KeyAction::Motion(Motion::Down, 5));
+        assert_eq!(seq.pending(), None);
+
+        assert_eq!(feed_str(&mut seq, "12k"), KeyAction::Motion(Motion::Up, 12));
+        assert_eq!(feed_str(&mut seq, "j"), KeyAction::Motion(Motion::Down, 1));
+    }
+
+    #[test]
+    fn test_key_sequence_zero_is_not_a_count_start() {
+        let mut seq = KeySequence::default();
+        assert_eq!(feed_str(&mut seq, "0"), KeyAction::Unhandled);
+        assert_eq!(feed_str(&mut seq, "10j"), KeyAction::Motion(Motion::Down, 10));
+    }
+
+    #[test]
+    fn test_key_sequence_gg_and_g() {
+        let mut seq = KeySequence::default();
+        assert_eq!(feed_str(&mut seq, "g"), KeyAction::Pending);
+        assert_eq!(seq.pending().as_deref(), Some("g"));
+        assert_eq!(feed_str(&mut seq, "g"), KeyAction::Motion(Motion::Top, 0));
+        assert_eq!(feed_str(&mut seq, "G"), KeyAction::Motion(Motion::Bottom, 0));
+        assert_eq!(feed_str(&mut seq, "3gg"), KeyAction::Motion(Motion::Top, 3));
+        assert_eq!(feed_str(&mut seq, "7G"), KeyAction::Motion(Motion::Bottom, 7));
+        // Another key between the two g presses breaks the chord
+        assert_eq!(feed_str(&mut seq, "gjg"), KeyAction::Pending);
+    }
+
+    #[test]
+    fn test_key_sequence_cleared_by_esc_and_other_keys() {
+        let mut seq = KeySequence::default();
+        feed_str(&mut seq, "4");
+        assert_eq!(seq.feed(press(KeyCode::Esc)), KeyAction::Cancelled);
+        assert_eq!(seq.pending(), None);
+        // Esc with nothing pending goes back as usual
+        assert_eq!(seq.feed(press(KeyCode::Esc)), KeyAction::Command(Action::Back));
+
+        feed_str(&mut seq, "4");
+        assert_eq!(seq.feed(press(KeyCode::Tab)), KeyAction::Command(Action::ToggleSelect));
+        assert_eq!(feed_str(&mut seq, "j"), KeyAction::Motion(Motion::Down, 1));
+    }
+
+    #[test]
+    fn test_key_sequence_digit_jumps() {
+        let mut seq = KeySequence::new(Keymap::default(), DigitKeys::Jump);
+        let jump = |number, open| KeyAction::Jump { number, open };
+        assert_eq!(feed_str(&mut seq, "3"), jump(3, false));
+        assert_eq!(seq.pending(), None);
+        assert_eq!(feed_str(&mut seq, "3"), jump(3, true));
+        // A third press starts over
+        assert_eq!(feed_str(&mut seq, "3"), jump(3, false));
+        assert_eq!(feed_str(&mut seq, "0"), jump(10, false));
+        assert_eq!(feed_str(&mut seq, "j0"), jump(10, false));
+
+        // Too slow for a double press
+        seq.jump = seq.jump.map(|(number, at)| (number, at - DOUBLE_PRESS));
+        assert_eq!(feed_str(&mut seq, "0"), jump(10, false));
+        // No counts in this mode, but motions still work
+        assert_eq!(feed_str(&mut seq, "5j"), KeyAction::Motion(Motion::Down, 1));
+        assert_eq!(feed_str(&mut seq, "gg"), KeyAction::Motion(Motion::Top, 0));
+    }
+
+    #[test]
+    fn test_key_sequence_half_page_and_window_keys() {
+        let mut seq = KeySequence::default();
+        let ctrl_d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        let ctrl_u = KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(seq.feed(ctrl_d), KeyAction::Motion(Motion::HalfPageDown, 1));
+        assert_eq!(seq.feed(ctrl_u), KeyAction::Motion(Motion::HalfPageUp, 1));
+        assert_eq!(feed_str(&mut seq, "H"), KeyAction::Motion(Motion::WindowTop, 1));
+        assert_eq!(feed_str(&mut seq, "M"), KeyAction::Motion(Motion::WindowMiddle, 1));
+        assert_eq!(feed_str(&mut seq, "2L"), KeyAction::Motion(Motion::WindowBottom, 2));
+    }
+
+    #[test]
+    fn test_scroll_offset_keeps_selection_visible() {
+        // Terminal heights translated to whole items (list area minus borders)
+        let densities = [Density::Full, Density::Compact, Density::Minimal];
+        let sizes = [6u16, 10, 14, 22, 42].into_iter().flat_map(|h| densities.map(|d| (h, d)));
+        for (height, density) in sizes {
+            let visible = visible_item_count(height, density.item_height());
+            let mut offset = 0;
+            for selected in 0..10 {
+                offset = compute_scroll_offset(offset, selected, 10, visible);
+                assert!(selected >= offset, "height {height}, {density:?}, selected {selected}");
+                assert!(
+                    selected < offset + visible.max(1),
+                    "height {height}, {density:?}, selected {selected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_editor_command_splits_several_files() {
+        let args = |command: &str, paths: &[PathBuf], split| {
+            let editor = EditorConfig {
+                command: Some(command.to_string()),
+                split,
+                ..Default::default()
+            };
+            editor_command(&editor, paths, None)
+                .unwrap()
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        let (a, b) = (PathBuf::from("a.md"), PathBuf::from("b.md"));
+        let both = [a.clone(), b.clone()];
+
+        assert_eq!(args("nvim -R", &[a], EditorSplit::Tabs), ["-R", "a.md"]);
+        assert_eq!(args("nvim -R", &both, EditorSplit::Tabs), ["-R", "-p", "a.md", "b.md"]);
+        assert_eq!(args("nvim -R", &both, EditorSplit::Vertical), ["-R", "-O", "a.md", "b.md"]);
+        // Only Vim understands the split flags
+        assert_eq!(args("hx", &both, EditorSplit::Tabs), ["a.md", "b.md"]);
+    }
+
+    #[test]
+    fn test_tmux_command_quotes_the_editor_command() {
+        let editor = EditorConfig {
+            command: Some("nvim -R".to_string()),
+            open_mode: OpenMode::TmuxPane,
+            ..Default::default()
+        };
+        let paths = [PathBuf::from("/cache/it's here.md")];
+        let (tmux, place) = tmux_command(&editor, &paths, None).unwrap();
+        let args: Vec<_> = tmux.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        assert_eq!(place, "tmux pane");
+        assert_eq!(args, ["split-window", "-h", "nvim -R '/cache/it'\\''s here.md'"]);
+
+        // The jump goes right before the file
+        let (tmux, _) = tmux_command(&editor, &paths, Some("+42")).unwrap();
+        let shell_command = tmux.get_args().last().unwrap().to_string_lossy().into_owned();
+        assert_eq!(shell_command, "nvim -R +42 '/cache/it'\\''s here.md'");
+
+        let replace = EditorConfig::default();
+        assert!(tmux_command(&replace, &paths, None).is_err());
+    }
+
+    #[test]
+    fn test_remote_command() {
+        let args = |tab, jump| {
+            remote_command("/tmp/nvim.sock", &[PathBuf::from("a.md")], tab, jump)
+                .as_std()
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(args(false, None), ["--server", "/tmp/nvim.sock", "--remote", "a.md"]);
+        assert_eq!(args(true, None), ["--server", "/tmp/nvim.sock", "--remote-tab", "a.md"]);
+        assert_eq!(
+            args(false, Some("+7")),
+            ["--server", "/tmp/nvim.sock", "--remote", "+7", "a.md"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_program() {
+        let path = Some(OsString::from("/nonexistent:/bin:/usr/bin"));
+        assert!(find_program("sh", path.clone()).is_some());
+        assert!(find_program("/bin/sh", None).is_some());
+        assert_eq!(find_program("websearch-no-such-editor", path), None);
+        assert_eq!(find_program("sh", None), None);
+    }
+
+    #[test]
+    fn test_result_at_row() {
+        let full = Density::Full.item_height();
+        let area = Rect::new(0, 4, 80, 2 + 3 * full as u16);
+        // Border rows map to nothing
+        assert_eq!(result_at_row(area, 4, 0, 10, full), None);
+        assert_eq!(result_at_row(area, area.bottom() - 1, 0, 10, full), None);
+        // Every line of an item maps to it, shifted by the scroll offset
+        assert_eq!(result_at_row(area, 5, 0, 10, full), Some(0));
+        assert_eq!(result_at_row(area, 8, 0, 10, full), Some(0));
+        assert_eq!(result_at_row(area, 9, 0, 10, full), Some(1));
+        assert_eq!(result_at_row(area, 9, 6, 10, full), Some(7));
+        // Blank space below the last result
+        assert_eq!(result_at_row(area, 13, 0, 2, full), None);
+        assert_eq!(visible_item_count(area.height, full), 3);
+
+        // Denser lists fit more results in the same area
+        let compact = Density::Compact.item_height();
+        assert_eq!(result_at_row(area, 8, 0, 10, compact), Some(1));
+        assert_eq!(visible_item_count(area.height, compact), 6);
+        let minimal = Density::Minimal.item_height();
+        assert_eq!(result_at_row(area, 8, 0, 10, minimal), Some(3));
+        assert_eq!(visible_item_count(area.height, minimal), 12);
+    }
+
+    #[test]
+    fn test_scroll_offset_scrolls_one_item_at_a_time() {
+        assert_eq!(compute_scroll_offset(0, 2, 10, 3), 0);
+        assert_eq!(compute_scroll_offset(0, 3, 10, 3), 1);
+        assert_eq!(compute_scroll_offset(1, 4, 10, 3), 2);
+        // Moving up inside the window keeps the offset
+        assert_eq!(compute_scroll_offset(2, 3, 10, 3), 2);
+    }
+
+    fn titled(titles: &[&str]) -> Vec<SearchResult> {
+        titles
+            .iter()
+            .map(|title| SearchResult {
+                title: title.to_string(),
+                url: format!("https://example.com/{}", title),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn titles(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_undo_puts_results_back_in_place() {
+        let mut results = titled(&["a", "b", "c", "d"]);
+        let mut deleted = Vec::new();
+
+        // Last, then middle, then first
+        assert_eq!(remove_result(&mut results, &mut deleted, 3).title, "d");
+        assert_eq!(remove_result(&mut results, &mut deleted, 1).title, "b");
+        assert_eq!(remove_result(&mut results, &mut deleted, 0).title, "a");
+        assert_eq!(titles(&results), ["c"]);
+
+        assert_eq!(restore_result(&mut results, &mut deleted), Some(0));
+        assert_eq!(restore_result(&mut results, &mut deleted), Some(1));
+        assert_eq!(restore_result(&mut results, &mut deleted), Some(3));
+        assert_eq!(titles(&results), ["a", "b", "c", "d"]);
+        assert_eq!(restore_result(&mut results, &mut deleted), None);
+    }
+
+    #[test]
+    fn test_undo_keeps_the_latest_removals() {
+        let names: Vec<String> = (0..MAX_UNDO + 5).map(|i| i.to_string()).collect();
+        let mut results = titled(&names.iter().map(String::as_str).collect::<Vec<_>>());
+        let mut deleted = Vec::new();
+        for _ in 0..MAX_UNDO + 2 {
+            remove_result(&mut results, &mut deleted, 0);
+        }
+        assert_eq!(deleted.len(), MAX_UNDO);
+
+        while restore_result(&mut results, &mut deleted).is_some() {}
+        // The two oldest removals are gone for good
+        let kept: Vec<String> = (2..MAX_UNDO + 5).map(|i| i.to_string()).collect();
+        assert_eq!(titles(&results), kept);
+    }
+
+    #[test]
+    fn test_scroll_offset_jump_to_last_and_wrap() {
+        // G: last item ends flush with the bottom edge
+        assert_eq!(compute_scroll_offset(0, 9, 10, 4), 6);
+        // Wrap-around from last to first scrolls back to the top
+        assert_eq!(compute_scroll_offset(6, 0, 10, 4), 0);
+        // Window larger than the list never scrolls
+        assert_eq!(compute_scroll_offset(0, 9, 10, 20), 0);
+        // Zero-height area still shows the selected item
+        assert_eq!(compute_scroll_offset(0, 5, 10, 0), 5);
+    }
+}
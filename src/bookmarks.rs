@@ -0,0 +1,239 @@
+//! Results saved for later
+//!
+//! `websearch/bookmarks.json` keeps the title, URL, description and query of
+//! results bookmarked with `m`, newest first. Only the reference is kept;
+//! the page is fetched again when a bookmark is opened and isn't cached. A
+//! file that can't be parsed is moved aside to `bookmarks.json.bak` and the
+//! list starts out empty.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache_index::normalize_url;
+use crate::notes::Note;
+use crate::paths;
+use crate::search::SearchResult;
+
+/// Bookmarks file name inside the data directory
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+/// A saved search result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+    pub description: String,
+    /// Unix timestamp (seconds) of the last save
+    pub saved_at: u64,
+    /// Query the result was found with
+    pub query: String,
+    /// Notes added with `a`, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+}
+
+impl Bookmark {
+    pub fn new(result: &SearchResult, query: &str) -> Self {
+        Self {
+            title: result.title.clone(),
+            url: result.url.clone(),
+            description: result.description.clone(),
+            saved_at: unix_now(),
+            query: query.to_string(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// The bookmark as a result, for prefetching
+    pub fn result(&self) -> SearchResult {
+        SearchResult {
+            title: self.title.clone(),
+            url: self.url.clone(),
+            description: self.description.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Bookmarks persisted as JSON
+#[derive(Debug)]
+pub struct Bookmarks {
+    path: PathBuf,
+    entries: Vec<Bookmark>,
+    /// Where an unreadable file was moved on load
+    pub backup: Option<PathBuf>,
+}
+
+impl Bookmarks {
+    /// Where the bookmarks are kept
+    pub fn path() -> PathBuf {
+        paths::data_dir().join(BOOKMARKS_FILE)
+    }
+
+    /// Load the bookmarks from `path`
+    ///
+    /// A missing file starts out empty; a corrupt one is backed up first.
+    pub fn load(path: PathBuf) -> Self {
+        let mut bookmarks = Self {
+            path,
+            entries: Vec::new(),
+            backup: None,
+        };
+        let Ok(json) = std::fs::read_to_string(&bookmarks.path) else {
+            return bookmarks;
+        };
+        match serde_json::from_str(&json) {
+            Ok(entries) => bookmarks.entries = entries,
+            Err(e) => {
+                let backup = bookmarks.path.with_extension("json.bak");
+                tracing::warn!(error = %e, backup = %backup.display(), "corrupt bookmarks file");
+                if std::fs::rename(&bookmarks.path, &backup).is_ok() {
+                    bookmarks.backup = Some(backup);
+                }
+            }
+        }
+        bookmarks
+    }
+
+    /// Bookmarks, newest first
+    pub fn entries(&self) -> &[Bookmark] {
+        &self.entries
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Bookmark> {
+        self.entries.get(index)
+    }
+
+    /// Whether a bookmark points at `url`
+    pub fn contains(&self, url: &str) -> bool {
+        self.position(url).is_some()
+    }
+
+    /// Add a bookmark, or refresh the one with the same URL
+    ///
+    /// Either way it moves to the top, keeping its notes. Returns whether
+    /// it is new.
+    pub fn add(&mut self, mut bookmark: Bookmark) -> bool {
+        let existing = self.position(&bookmark.url);
+        if let Some(index) = existing {
+            let old = self.entries.remove(index);
+            bookmark.notes.splice(0..0, old.notes);
+        }
+        self.entries.insert(0, bookmark);
+        existing.is_none()
+    }
+
+    /// Add a note to the bookmark of `url`; false when there is none
+    pub fn add_note(&mut self, url: &str, note: Note) -> bool {
+        let Some(index) = self.position(url) else {
+            return false;
+        };
+        self.entries[index].notes.push(note);
+        true
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Bookmark> {
+        (index < self.entries.len()).then(|| self.entries.remove(index))
+    }
+
+    /// Write the bookmarks atomically (temp file + rename)
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.entries)
+            .context("Failed to serialize bookmarks")?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create data directory")?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, json).context("Failed to write bookmarks")?;
+        std::fs::rename(&tmp_path, &self.path).context("Failed to replace bookmarks")?;
+        Ok(())
+    }
+
+    fn position(&self, url: &str) -> Option<usize> {
+        let url = normalize_url(url);
+        self.entries
+            .iter()
+            .position(|bookmark| normalize_url(&bookmark.url) == url)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str, title: &str) -> SearchResult {
+        SearchResult {
+            title: title.to_string(),
+            url: url.to_string(),
+            description: "A page".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("websearch-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_bookmarks_round_trip_and_duplicates() {
+        let dir = temp_dir("bookmarks");
+        let path = dir.join(BOOKMARKS_FILE);
+
+        let mut bookmarks = Bookmarks::load(path.clone());
+        assert!(bookmarks.entries().is_empty());
+        assert!(bookmarks.add(Bookmark::new(&result("https://a.com/x", "A"), "first")));
+        assert!(bookmarks.add(Bookmark::new(&result("https://b.com/", "B"), "second")));
+        let note = Note {
+            text: "read later".to_string(),
+            written_at: 1,
+        };
+        assert!(bookmarks.add_note("https://a.com/x/", note.clone()));
+        assert!(!bookmarks.add_note("https://c.com/", note.clone()));
+        // Same page with a fragment: updated and moved to the top, notes kept
+        assert!(!bookmarks.add(Bookmark::new(&result("https://a.com/x#intro", "A2"), "third")));
+        bookmarks.save().unwrap();
+
+        let loaded = Bookmarks::load(path);
+        assert_eq!(loaded.entries(), bookmarks.entries());
+        assert_eq!(loaded.entries().len(), 2);
+        assert_eq!(loaded.entries()[0].title, "A2");
+        assert_eq!(loaded.entries()[0].query, "third");
+        assert_eq!(loaded.entries()[0].notes, [note]);
+        assert!(loaded.contains("https://b.com"));
+        assert_eq!(loaded.backup, None);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_corrupt_file_is_backed_up() {
+        let dir = temp_dir("bookmarks-corrupt");
+        let path = dir.join(BOOKMARKS_FILE);
+        std::fs::write(&path, "[{\"title\": ").unwrap();
+
+        let mut bookmarks = Bookmarks::load(path.clone());
+        assert!(bookmarks.entries().is_empty());
+        let backup = bookmarks.backup.clone().unwrap();
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "[{\"title\": ");
+
+        // Starts fresh without touching the backup
+        bookmarks.add(Bookmark::new(&result("https://a.com/", "A"), "q"));
+        bookmarks.save().unwrap();
+        assert_eq!(Bookmarks::load(path).entries().len(), 1);
+        assert!(backup.exists());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
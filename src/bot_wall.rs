@@ -0,0 +1,134 @@
+//! Detection of bot walls, consent interstitials and paywalls
+//!
+//! Pages like Cloudflare's "Just a moment…" extract into valid-looking but
+//! useless markdown. These heuristics look at the raw HTML and the extracted
+//! text and name the kind of wall when they find one.
+
+/// Extracted text shorter than this (in chars) counts as "no real content"
+const SHORT_TEXT_CHARS: usize = 200;
+
+/// Title fragments of bot-check interstitials (lowercase)
+const CHALLENGE_TITLES: &[&str] = &[
+    "just a moment",
+    "attention required",
+    "checking your browser",
+    "ddos-guard",
+];
+
+/// HTML fragments of bot-check interstitials (lowercase)
+const CHALLENGE_MARKERS: &[&str] = &[
+    "cf-challenge",
+    "cf_chl_opt",
+    "challenge-platform",
+    "captcha-delivery.com",
+];
+
+/// HTML fragments of cookie/consent interstitials (lowercase)
+const CONSENT_MARKERS: &[&str] = &[
+    "consent.google.com",
+    "consent.yahoo.com",
+    "before you continue",
+    "qc-cmp2-container",
+];
+
+/// HTML or text fragments of paywalls (lowercase)
+const PAYWALL_MARKERS: &[&str] = &[
+    "paywall",
+    "subscribe to continue",
+    "subscribe to read",
+    "already a subscriber",
+    "\"isaccessibleforfree\":false",
+    "\"isaccessibleforfree\": false",
+];
+
+/// Name the wall a page is stuck behind, if any
+///
+/// Bot checks are recognized by their title or challenge scripts alone.
+/// Consent pages and paywalls also need the extracted text to be short,
+/// so a full article that merely mentions a subscription passes.
+pub fn detect_wall(html: &str, title: &str, text: &str) -> Option<&'static str> {
+    let html = html.to_lowercase();
+    let title = title.to_lowercase();
+
+    if CHALLENGE_TITLES.iter().any(|t| title.contains(t))
+        || CHALLENGE_MARKERS.iter().any(|m| html.contains(m))
+    {
+        return Some("bot check");
+    }
+
+    if text.trim().chars().count() >= SHORT_TEXT_CHARS {
+        return None;
+    }
+
+    if CONSENT_MARKERS.iter().any(|m| html.contains(m)) {
+        return Some("consent wall");
+    }
+
+    let text = text.to_lowercase();
+    if PAYWALL_MARKERS
+        .iter()
+        .any(|m| html.contains(m) || text.contains(m))
+    {
+        return Some("paywall");
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLOUDFLARE: &str = r#"<!DOCTYPE html><html lang="en-US"><head><title>Just a moment...</title>
+<meta http-equiv="refresh" content="390"></head><body><div class="main-wrapper" role="main">
+<div class="main-content"><noscript><div class="h2"><span id="challenge-error-text">Enable JavaScript and cookies to continue</span></div></noscript></div></div>
+<script>(function(){window._cf_chl_opt={cvId: '3',cZone: "example.com",cType: 'managed'};}());</script></body></html>"#;
+
+    const CLOUDFLARE_BLOCK: &str = r#"<html><head><title>Attention Required! | Cloudflare</title></head>
+<body><div id="cf-wrapper"><h1 data-translate="block_headline">Sorry, you have been blocked</h1></div></body></html>"#;
+
+    const GOOGLE_CONSENT: &str = r#"<html><head><title>Before you continue to YouTube</title></head>
+<body><form action="https://consent.google.com/save" method="POST"><button>Accept all</button></form></body></html>"#;
+
+    const PAYWALL: &str = r#"<html><head><title>Markets rally on rate hopes</title>
+<script type="application/ld+json">{"@type":"NewsArticle","isAccessibleForFree":false}</script></head>
+<body><article><h1>Markets rally on rate hopes</h1><p>Stocks climbed on Tuesday as investors</p>
+<div class="paywall-prompt">Subscribe to continue reading</div></article></body></html>"#;
+
+    #[test]
+    fn test_detects_bot_checks() {
+        assert_eq!(
+            detect_wall(CLOUDFLARE, "Just a moment...", "Enable JavaScript and cookies to continue"),
+            Some("bot check")
+        );
+        assert_eq!(
+            detect_wall(CLOUDFLARE_BLOCK, "Attention Required! | Cloudflare", "Sorry, you have been blocked"),
+            Some("bot check")
+        );
+    }
+
+    #[test]
+    fn test_detects_consent_walls_and_paywalls() {
+        assert_eq!(
+            detect_wall(GOOGLE_CONSENT, "Before you continue to YouTube", "Accept all"),
+            Some("consent wall")
+        );
+        assert_eq!(
+            detect_wall(
+                PAYWALL,
+                "Markets rally on rate hopes",
+                "Stocks climbed on Tuesday as investors\n\nSubscribe to continue reading"
+            ),
+            Some("paywall")
+        );
+    }
+
+    #[test]
+    fn test_full_articles_pass() {
+        let text = "Stocks climbed on Tuesday as investors bet on lower rates. ".repeat(10);
+        assert_eq!(detect_wall(PAYWALL, "Markets rally on rate hopes", &text), None);
+
+        let short = "<html><head><title>Home</title></head><body><p>Welcome!</p></body></html>";
+        assert_eq!(detect_wall(short, "Home", "Welcome!"), None);
+    }
+}
@@ -0,0 +1,364 @@
+//! Persistent index of cached pages
+//!
+//! `websearch/index.json` maps a normalized URL to the file holding its
+//! prefetched page, so cache lookups don't depend on recomputing filenames.
+//! Filenames use a stable FNV-1a hash that doesn't change between Rust
+//! releases (unlike `DefaultHasher`).
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+use crate::notes::Note;
+
+/// Bytes read from the start of a file when looking for frontmatter
+const FRONTMATTER_READ_LIMIT: u64 = 8 * 1024;
+
+/// Metadata about one cached page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// File name inside `pinned/`, `active_tabs/` or a search directory
+    pub filename: String,
+    pub title: String,
+    /// Unix timestamp (seconds) of the download
+    pub fetched_at: u64,
+    /// File size in bytes
+    pub size: u64,
+    pub etag: Option<String>,
+    /// Unix timestamp (seconds) of the last open in Neovim
+    #[serde(default)]
+    pub last_opened: Option<u64>,
+    /// Notes added with `a`, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+}
+
+/// Normalized URL → cache entry, persisted as JSON
+#[derive(Debug)]
+pub struct CacheIndex {
+    path: PathBuf,
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl CacheIndex {
+    /// Load the index from `path`
+    ///
+    /// A missing or unreadable index starts out empty.
+    pub fn load(path: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    /// Look up the entry for a URL
+    pub fn get(&self, url: &str) -> Option<&CacheEntry> {
+        self.entries.get(&normalize_url(url))
+    }
+
+    /// Add or replace the entry for a URL
+    pub fn insert(&mut self, url: &str, entry: CacheEntry) {
+        self.entries.insert(normalize_url(url), entry);
+    }
+
+    /// Add a note to the entry for a URL; false when there is none
+    pub fn add_note(&mut self, url: &str, note: Note) -> bool {
+        let Some(entry) = self.entries.get_mut(&normalize_url(url)) else {
+            return false;
+        };
+        entry.notes.push(note);
+        true
+    }
+
+    /// Record that a URL's page was just opened
+    pub fn touch(&mut self, url: &str) {
+        if let Some(entry) = self.entries.get_mut(&normalize_url(url)) {
+            entry.last_opened = Some(unix_now());
+        }
+    }
+
+    /// File name → Unix timestamp of the last open, or of the download
+    /// when the page was never opened
+    pub fn last_used_by_filename(&self) -> HashMap<&str, u64> {
+        self.entries
+            .values()
+            .map(|e| (e.filename.as_str(), e.last_opened.unwrap_or(e.fetched_at)))
+            .collect()
+    }
+
+    /// Drop entries whose file no longer exists in any of `dirs`
+    pub fn retain_existing(&mut self, dirs: &[impl AsRef<Path>]) {
+        self.entries
+            .retain(|_, e| dirs.iter().any(|dir| dir.as_ref().join(&e.filename).exists()));
+    }
+
+    /// Write the index atomically (temp file + rename)
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.entries)
+            .context("Failed to serialize cache index")?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, json).context("Failed to write cache index")?;
+        std::fs::rename(&tmp_path, &self.path).context("Failed to replace cache index")?;
+        Ok(())
+    }
+}
+
+/// Normalize a URL for use as an index key
+///
+/// Drops the fragment and a trailing slash; scheme and host are already
+/// lowercased by the parser. Unparsable URLs are used as-is.
+pub fn normalize_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    parsed.set_fragment(None);
+
+    let mut normalized = parsed.to_string();
+    if parsed.query().is_none() && parsed.path().len() > 1 && normalized.ends_with('/') {
+        normalized.pop();
+    }
+    normalized
+}
+
+/// Stable 8-hex-char hash of a URL (low 32 bits of 64-bit FNV-1a)
+pub fn stable_hash(url: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = url.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+    format!("{:08x}", hash & 0xFFFF_FFFF)
+}
+
+/// Current time as a Unix timestamp in seconds
+pub fn unix_now() -> u64 {
+    unix_seconds(SystemTime::now())
+}
+
+/// Convert a system time to a Unix timestamp in seconds
+pub fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Fields of a page's YAML frontmatter
+#[derive(Debug, Default, PartialEq)]
+pub struct Frontmatter {
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Site name
+    pub source: Option<String>,
+    pub excerpt: Option<String>,
+    /// Publish date, RFC 3339 or a shorter date
+    pub published: Option<String>,
+    pub lang: Option<String>,
+    /// The page was cut at `extraction.max_markdown_kb`
+    pub truncated: bool,
+}
+
+/// Parse the leading `---` frontmatter block of a markdown file
+///
+/// The `#+` keyword lines heading a page saved as Org are read too.
+/// Missing or malformed frontmatter yields empty fields.
+pub fn parse_frontmatter(markdown: &str) -> Frontmatter {
+    let mut frontmatter = Frontmatter::default();
+    if markdown.starts_with("#+") {
+        return parse_org_keywords(markdown);
+    }
+    let mut lines = markdown.lines();
+    if lines.next() != Some("---") {
+        return frontmatter;
+    }
+
+    for line in lines.take_while(|line| *line != "---") {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "url" => frontmatter.url = Some(unquote(value)),
+            "title" => frontmatter.title = Some(unquote(value)),
+            "author" => frontmatter.author = Some(unquote(value)),
+            "source" => frontmatter.source = Some(unquote(value)),
+            "excerpt" => frontmatter.excerpt = Some(unquote(value)),
+            "published" => frontmatter.published = Some(unquote(value)),
+            "lang" => frontmatter.lang = Some(unquote(value)),
+            "truncated" => frontmatter.truncated = value == "true",
+            _ => {}
+        }
+    }
+    frontmatter
+}
+
+/// Fields of the `#+TITLE:` and `#+PROPERTY:` lines of an Org page
+fn parse_org_keywords(org: &str) -> Frontmatter {
+    let mut frontmatter = Frontmatter::default();
+    for line in org.lines().take_while(|line| line.starts_with("#+")) {
+        let Some((keyword, value)) = line[2..].split_once(':') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match keyword.to_ascii_uppercase().as_str() {
+            "TITLE" => frontmatter.title = Some(value),
+            "AUTHOR" => frontmatter.author = Some(value),
+            "DATE" => frontmatter.published = Some(value),
+            "LANGUAGE" => frontmatter.lang = Some(value),
+            "PROPERTY" => {
+                let (key, value) = value.split_once(' ').unwrap_or((&value, ""));
+                let value = Some(value.trim().to_string());
+                match key.to_ascii_uppercase().as_str() {
+                    "URL" => frontmatter.url = value,
+                    "SOURCE" => frontmatter.source = value,
+                    "EXCERPT" => frontmatter.excerpt = value,
+                    "TRUNCATED" => frontmatter.truncated = value.as_deref() == Some("true"),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    frontmatter
+}
+
+/// Read the frontmatter at the start of a markdown file
+///
+/// Only the first 8 KB are read. Returns `None` when the file can't be read.
+pub fn read_frontmatter(path: &Path) -> Option<Frontmatter> {
+    let mut head = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(FRONTMATTER_READ_LIMIT)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(parse_frontmatter(&String::from_utf8_lossy(&head)))
+}
+
+/// Strip surrounding quotes and unescape the value
+///
+/// Pages saved before values were fully escaped only escaped `\"`.
+fn unquote(value: &str) -> String {
+    if let Ok(unquoted) = serde_json::from_str::<String>(value) {
+        return unquoted;
+    }
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .replace("\\\"", "\"")
+}
+
+/// `value` as a double-quoted YAML scalar
+///
+/// JSON's string escapes are all valid in YAML, and newlines and other
+/// control characters come out escaped, so the value stays on one line.
+pub fn yaml_quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+/// `value` unquoted when YAML would read it back as the same string, like
+/// most URLs and dates
+pub fn yaml_scalar(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value.chars().all(|c| c.is_ascii_graphic())
+        && !matches!(value, "null" | "true" | "false")
+        && value.parse::<f64>().is_err()
+        && !value.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        && !value.ends_with(':');
+    if plain {
+        value.to_string()
+    } else {
+        yaml_quote(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_hash_is_fnv1a() {
+        // Known FNV-1a 64 values: "" = cbf29ce484222325, "a" = af63dc4c8601ec8c
+        assert_eq!(stable_hash(""), "84222325");
+        assert_eq!(stable_hash("a"), "8601ec8c");
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url("HTTPS://Example.COM/Docs/#intro"),
+            "https://example.com/Docs"
+        );
+        assert_eq!(normalize_url("https://example.com/"), "https://example.com/");
+        assert_eq!(
+            normalize_url("https://example.com/a/?q=1"),
+            "https://example.com/a/?q=1"
+        );
+        assert_eq!(normalize_url("not a url"), "not a url");
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("websearch-index-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("index.json");
+
+        let mut index = CacheIndex::load(path.clone());
+        let entry = CacheEntry {
+            filename: "example_com_1234abcd_Title.md".to_string(),
+            title: "Title".to_string(),
+            fetched_at: 1_700_000_000,
+            size: 42,
+            etag: Some("\"abc\"".to_string()),
+            last_opened: None,
+            notes: Vec::new(),
+        };
+        index.insert("https://example.com/page#top", entry.clone());
+        index.save().unwrap();
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let mut loaded = CacheIndex::load(path);
+        assert_eq!(loaded.get("https://example.com/page"), Some(&entry));
+
+        loaded.touch("https://example.com/page");
+        let opened = loaded.last_used_by_filename()["example_com_1234abcd_Title.md"];
+        assert!(opened > entry.fetched_at);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_frontmatter() {
+        let md = "---\ntitle: \"Say \\\"hi\\\"\"\nurl: https://example.com/a\nauthor: \"X\"\n---\n\n# Body\nurl: ignored";
+        assert_eq!(
+            parse_frontmatter(md),
+            Frontmatter {
+                url: Some("https://example.com/a".to_string()),
+                title: Some("Say \"hi\"".to_string()),
+                author: Some("X".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(parse_frontmatter("# No frontmatter"), Frontmatter::default());
+
+        let org = "#+TITLE: Say \"hi\"\n#+DATE: 2019-07\n#+PROPERTY: URL https://example.com/a\n\
+                   #+PROPERTY: SOURCE Example Blog\n\n#+PROPERTY: URL ignored";
+        assert_eq!(
+            parse_frontmatter(org),
+            Frontmatter {
+                url: Some("https://example.com/a".to_string()),
+                title: Some("Say \"hi\"".to_string()),
+                source: Some("Example Blog".to_string()),
+                published: Some("2019-07".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! Canonical versions of AMP and mobile result URLs
+//!
+//! Engines often return `amp.` hosts, `/amp` paths, `en.m.wikipedia.org`
+//! or `mobile.twitter.com`, which extract worse than the regular page.
+//! With `prefetch.normalize_urls` pages are downloaded from the URL
+//! [`rewrite`] gives; Reddit goes to `old.reddit.com`, which renders its
+//! comments server-side. The result keeps its original URL everywhere else.
+//!
+//! A page's `<link rel="canonical">` is recorded as its `canonical_url`.
+//! With `prefetch.follow_canonical`, a page whose canonical URL is a
+//! different page, as [`differs`] tells, is downloaded again from there.
+
+use dom_query::Document;
+use url::Url;
+
+/// Hosts downloaded from another host
+const HOSTS: &[(&str, &str)] = &[
+    ("m.wikipedia.org", "en.wikipedia.org"),
+    ("mobile.twitter.com", "twitter.com"),
+    ("m.twitter.com", "twitter.com"),
+    ("mobile.x.com", "x.com"),
+    ("m.youtube.com", "www.youtube.com"),
+    ("reddit.com", "old.reddit.com"),
+    ("www.reddit.com", "old.reddit.com"),
+    ("new.reddit.com", "old.reddit.com"),
+    ("m.reddit.com", "old.reddit.com"),
+    ("np.reddit.com", "old.reddit.com"),
+];
+
+/// Wikimedia projects whose `xx.m.` mobile hosts drop the `m.`
+const WIKIMEDIA: &[&str] = &[
+    "wikipedia",
+    "wiktionary",
+    "wikibooks",
+    "wikiquote",
+    "wikisource",
+    "wikivoyage",
+    "wikinews",
+    "wikiversity",
+    "wikimedia",
+];
+
+/// `url` with AMP and mobile parts rewritten to the regular page
+///
+/// Returns `url` unchanged when no rule applies or it doesn't parse.
+pub fn rewrite(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let Some(host) = parsed.host_str().map(str::to_ascii_lowercase) else {
+        return url.to_string();
+    };
+    if host.ends_with(".cdn.ampproject.org")
+        && let Some(original) = amp_cache_original(&parsed)
+    {
+        return rewrite(&original);
+    }
+
+    let mut changed = false;
+    if let Some(canonical) = rewrite_host(&host) {
+        changed |= parsed.set_host(Some(&canonical)).is_ok();
+    }
+    if let Some(path) = rewrite_path(parsed.path()) {
+        parsed.set_path(&path);
+        changed = true;
+    }
+    if parsed.query().is_some() {
+        let pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+        let kept: Vec<&(String, String)> =
+            pairs.iter().filter(|(key, value)| !is_amp_param(key, value)).collect();
+        if kept.len() < pairs.len() {
+            changed = true;
+            if kept.is_empty() {
+                parsed.set_query(None);
+            } else {
+                parsed.query_pairs_mut().clear().extend_pairs(kept);
+            }
+        }
+    }
+    if changed { parsed.to_string() } else { url.to_string() }
+}
+
+/// Regular host for a mobile or AMP `host`
+fn rewrite_host(host: &str) -> Option<String> {
+    if let Some((_, canonical)) = HOSTS.iter().find(|(mobile, _)| *mobile == host) {
+        return Some(canonical.to_string());
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    match labels.as_slice() {
+        [lang, "m", project, "org"] if WIKIMEDIA.contains(project) => {
+            Some(format!("{}.{}.org", lang, project))
+        }
+        // Not `amp.dev` itself
+        ["amp", rest @ ..] if rest.len() >= 2 => Some(rest.join(".")),
+        _ => None,
+    }
+}
+
+/// `path` without its `amp` segment or `.amp` extension
+fn rewrite_path(path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = path.split('/').collect();
+    // A trailing slash leaves an empty last segment
+    let last = segments.iter().rposition(|s| !s.is_empty())?;
+    let segment = segments[last];
+    if segment == "amp" {
+        segments.remove(last);
+    } else if let Some(stem) = segment.strip_suffix(".amp") {
+        return Some(replace_segment(&segments, last, stem));
+    } else if let Some(stem) = segment.strip_suffix(".amp.html") {
+        return Some(replace_segment(&segments, last, &format!("{}.html", stem)));
+    } else if segments.get(1) == Some(&"amp") && last > 1 {
+        segments.remove(1);
+    } else {
+        return None;
+    }
+    let path = segments.join("/");
+    Some(if path.is_empty() { "/".to_string() } else { path })
+}
+
+fn replace_segment(segments: &[&str], index: usize, segment: &str) -> String {
+    let mut segments = segments.to_vec();
+    segments[index] = segment;
+    segments.join("/")
+}
+
+/// Query parameters that ask for the AMP version
+fn is_amp_param(key: &str, value: &str) -> bool {
+    key == "amp" || (key == "outputType" && value == "amp")
+}
+
+/// Original URL of a Google AMP cache URL
+///
+/// `https://example-com.cdn.ampproject.org/c/s/example.com/post` is
+/// `https://example.com/post`; without the `s/` it was plain HTTP.
+fn amp_cache_original(url: &Url) -> Option<String> {
+    let path = url.path().strip_prefix("/")?;
+    let (kind, rest) = path.split_once('/')?;
+    if !matches!(kind, "c" | "v" | "i") {
+        return None;
+    }
+    let (scheme, rest) = match rest.strip_prefix("s/") {
+        Some(rest) => ("https", rest),
+        None => ("http", rest),
+    };
+    let mut original = format!("{}://{}", scheme, rest);
+    if let Some(query) = url.query() {
+        original.push('?');
+        original.push_str(query);
+    }
+    Url::parse(&original).ok().map(String::from)
+}
+
+/// Absolute URL of the page's `<link rel="canonical">`
+///
+/// Must run before Readability, which removes the `<head>`.
+pub fn declared(document: &Document, final_url: &str) -> Option<String> {
+    let href = document.select("link[rel='canonical']").attr("href")?;
+    let href = href.trim();
+    if href.is_empty() {
+        return None;
+    }
+    let url = Url::parse(final_url).ok()?.join(href).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+/// Whether `a` and `b` are different pages, not two spellings of one
+///
+/// Scheme, `www.`, trailing slashes, fragments and the rewrites of
+/// [`rewrite`] don't count.
+pub fn differs(a: &str, b: &str) -> bool {
+    page_key(&rewrite(a)) != page_key(&rewrite(b))
+}
+
+fn page_key(url: &str) -> String {
+    let Ok(url) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let path = url.path().trim_end_matches('/');
+    match url.query() {
+        Some(query) => format!("{}{}?{}", host, path, query),
+        None => format!("{}{}", host, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_table() {
+        let cases = [
+            // AMP hosts, paths and parameters
+            ("https://amp.theguardian.com/world/2024/story", "https://theguardian.com/world/2024/story"),
+            ("https://amp.dev/documentation/", "https://amp.dev/documentation/"),
+            ("https://example.com/news/story/amp", "https://example.com/news/story"),
+            ("https://example.com/news/story/amp/", "https://example.com/news/story/"),
+            ("https://example.com/amp/news/story", "https://example.com/news/story"),
+            ("https://www.bbc.co.uk/news/world-123.amp", "https://www.bbc.co.uk/news/world-123"),
+            ("https://example.com/post.amp.html", "https://example.com/post.html"),
+            ("https://example.com/amp", "https://example.com/"),
+            ("https://example.com/post?amp=1", "https://example.com/post"),
+            ("https://example.com/post?id=7&amp", "https://example.com/post?id=7"),
+            ("https://example.com/post?outputType=amp&id=7", "https://example.com/post?id=7"),
+            ("https://example.com/post?outputType=print", "https://example.com/post?outputType=print"),
+            ("https://example.com/ampere/amplifier", "https://example.com/ampere/amplifier"),
+            (
+                "https://example-com.cdn.ampproject.org/c/s/example.com/news/story/amp?x=1",
+                "https://example.com/news/story?x=1",
+            ),
+            ("https://example-com.cdn.ampproject.org/c/example.com/a", "http://example.com/a"),
+            // Mobile hosts
+            ("https://en.m.wikipedia.org/wiki/Rust", "https://en.wikipedia.org/wiki/Rust"),
+            ("https://de.m.wiktionary.org/wiki/Haus", "https://de.wiktionary.org/wiki/Haus"),
+            ("https://m.wikipedia.org/wiki/Rust", "https://en.wikipedia.org/wiki/Rust"),
+            ("https://en.m.example.org/wiki/Rust", "https://en.m.example.org/wiki/Rust"),
+            ("https://mobile.twitter.com/rustlang/status/1", "https://twitter.com/rustlang/status/1"),
+            ("https://mobile.x.com/rustlang", "https://x.com/rustlang"),
+            ("https://m.youtube.com/watch?v=abc", "https://www.youtube.com/watch?v=abc"),
+            // Reddit prefers the old, server-rendered site
+            ("https://www.reddit.com/r/rust/comments/x/y/", "https://old.reddit.com/r/rust/comments/x/y/"),
+            ("https://reddit.com/r/rust", "https://old.reddit.com/r/rust"),
+            ("https://old.reddit.com/r/rust", "https://old.reddit.com/r/rust"),
+            // Untouched
+            ("https://example.com/docs/", "https://example.com/docs/"),
+            ("https://Example.com/a%20b?q=x y", "https://Example.com/a%20b?q=x y"),
+            ("not a url", "not a url"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(rewrite(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_declared_canonical_and_differs() {
+        let html = r#"<html><head>
+            <link rel="canonical" href="/news/story">
+            </head><body><p>Story</p></body></html>"#;
+        let document = Document::from(html);
+        assert_eq!(
+            declared(&document, "https://amp.example.com/news/story/amp").as_deref(),
+            Some("https://amp.example.com/news/story")
+        );
+        let none = Document::from("<html><head><link rel='canonical' href=' '></head></html>");
+        assert_eq!(declared(&none, "https://example.com/"), None);
+
+        // Spellings of the same page
+        assert!(!differs("https://www.example.com/post/", "http://example.com/post#top"));
+        assert!(!differs("https://example.com/post/amp", "https://example.com/post"));
+        assert!(!differs("https://www.reddit.com/r/rust", "https://old.reddit.com/r/rust/"));
+        // Different pages
+        assert!(differs("https://example.com/post?page=2", "https://example.com/post"));
+        assert!(differs("https://mirror.example.net/post", "https://example.com/post"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Citations of search results, made with `c`
+//!
+//! A citation is built from the saved page's frontmatter (title, author,
+//! site name) when the page has been fetched, else from the result alone,
+//! with today as the access date. The BibTeX entry's key is made from the
+//! site and the access year, like `rustlang2026`. Depending on
+//! `citation.destination` it is copied to the clipboard or appended to a
+//! references file in the data directory.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+
+use crate::config::CitationFormat;
+use crate::extract_clean_md::PageMetadata;
+use crate::search::site_host;
+
+/// What is known about a cited page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Citation {
+    /// BibTeX key and footnote label
+    pub key: String,
+    pub title: String,
+    pub author: Option<String>,
+    /// Site name, like "The Rust Programming Language Blog"
+    pub site: Option<String>,
+    pub url: String,
+    /// Access date, `YYYY-MM-DD`
+    pub accessed: String,
+}
+
+impl Citation {
+    /// Citation of `url`, preferring the saved page's metadata over the
+    /// result's `title`
+    pub fn new(url: &str, title: &str, metadata: PageMetadata, accessed: &str) -> Self {
+        let year = accessed.get(..4).unwrap_or(accessed);
+        let clean = |text: Option<String>| {
+            text.map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|t| !t.is_empty())
+        };
+        // Bylines often read "By Jane Doe"
+        let author = clean(metadata.byline).map(|byline| {
+            match byline.get(..3).filter(|by| by.eq_ignore_ascii_case("by ")) {
+                Some(_) => byline[3..].to_string(),
+                None => byline,
+            }
+        });
+
+        Self {
+            key: format!("{}{}", key_stem(url), year),
+            title: clean(metadata.title)
+                .or_else(|| clean(Some(title.to_string())))
+                .unwrap_or_else(|| url.to_string()),
+            author,
+            site: clean(metadata.site_name),
+            url: url.to_string(),
+            accessed: accessed.to_string(),
+        }
+    }
+
+    /// The citation written out in `format`
+    pub fn format(&self, format: CitationFormat) -> String {
+        match format {
+            CitationFormat::Bibtex => self.bibtex(),
+            CitationFormat::Plain => self.plain(),
+            CitationFormat::Footnote => self.footnote(),
+        }
+    }
+
+    /// `@online` entry, for biblatex
+    fn bibtex(&self) -> String {
+        let mut entry = format!("@online{{{},\n", self.key);
+        // Double braces keep the title's capitalization
+        entry.push_str(&format!("  title = {{{{{}}}}},\n", escape_bibtex(&self.title)));
+        if let Some(author) = &self.author {
+            // A comma would make BibTeX read "Last, First"; braces make the
+            // byline a single name
+            let author = escape_bibtex(author);
+            if author.contains(',') {
+                entry.push_str(&format!("  author = {{{{{}}}}},\n", author));
+            } else {
+                entry.push_str(&format!("  author = {{{}}},\n", author));
+            }
+        }
+        if let Some(site) = &self.site {
+            entry.push_str(&format!("  organization = {{{}}},\n", escape_bibtex(site)));
+        }
+        let year = self.accessed.get(..4).unwrap_or(&self.accessed);
+        entry.push_str(&format!("  year = {{{}}},\n", year));
+        entry.push_str(&format!("  url = {{{}}},\n", bibtex_url(&self.url)));
+        entry.push_str(&format!("  urldate = {{{}}},\n", self.accessed));
+        entry.push_str("}\n");
+        entry
+    }
+
+    /// One line like `Jane Doe. "Title." Site. URL (accessed 2026-10-16).`
+    fn plain(&self) -> String {
+        let mut line = String::new();
+        if let Some(author) = &self.author {
+            line.push_str(&with_period(author));
+            line.push(' ');
+        }
+        line.push_str(&format!("\"{}\" ", with_period(&self.title)));
+        if let Some(site) = &self.site {
+            line.push_str(&with_period(site));
+            line.push(' ');
+        }
+        line.push_str(&format!("{} (accessed {}).\n", self.url, self.accessed));
+        line
+    }
+
+    /// Markdown footnote definition like `[^rustlang2026]: ...`
+    fn footnote(&self) -> String {
+        let mut line = format!("[^{}]: ", self.key);
+        if let Some(author) = &self.author {
+            line.push_str(&format!("{}, ", author));
+        }
+        let title = self.title.replace('[', "\\[").replace(']', "\\]");
+        line.push_str(&format!("\"[{}]({})\", ", title, self.url));
+        if let Some(site) = &self.site {
+            line.push_str(&format!("*{}*, ", site.replace('*', "\\*")));
+        }
+        line.push_str(&format!("accessed {}.\n", self.accessed));
+        line
+    }
+}
+
+/// Append `citation` to the references file at `path`
+///
+/// Returns the key it was written under, or `None` when the URL is already
+/// in the file. A key already used for another page gets a letter, like
+/// `rustlang2026b`.
+pub fn append_to_file(
+    path: &Path,
+    citation: &Citation,
+    format: CitationFormat,
+) -> Result<Option<String>> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let url = match format {
+        CitationFormat::Bibtex => bibtex_url(&citation.url),
+        _ => citation.url.clone(),
+    };
+    if existing.contains(&url) {
+        return Ok(None);
+    }
+
+    let mut citation = citation.clone();
+    citation.key = unique_key(&existing, &citation.key);
+    let mut text = String::new();
+    // Blank line between entries
+    if !existing.is_empty() && !existing.ends_with("\n\n") {
+        text.push_str(if existing.ends_with('\n') { "\n" } else { "\n\n" });
+    }
+    text.push_str(&citation.format(format));
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(text.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(Some(citation.key))
+}
+
+/// `key`, or `key` plus the first free letter from `b` on
+fn unique_key(existing: &str, key: &str) -> String {
+    let taken = |key: &str| {
+        existing.contains(&format!("{{{},", key)) || existing.contains(&format!("[^{}]:", key))
+    };
+    if !taken(key) {
+        return key.to_string();
+    }
+    ('b'..='z')
+        .map(|letter| format!("{}{}", key, letter))
+        .find(|key| !taken(key))
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Key prefix for a URL's site: the name under the top-level domain,
+/// `rustlang` for `blog.rust-lang.org`
+fn key_stem(url: &str) -> String {
+    let host = site_host(url).unwrap_or_default();
+    let mut labels: Vec<&str> = host.split('.').collect();
+    if labels.len() > 1 {
+        labels.pop();
+    }
+    // Second-level registries, like the `co` of `bbc.co.uk`
+    let registry = matches!(labels.last(), Some(&("co" | "com" | "org" | "net" | "ac" | "gov")));
+    if labels.len() > 1 && registry {
+        labels.pop();
+    }
+    let stem: String = labels
+        .last()
+        .unwrap_or(&"")
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect();
+    if stem.is_empty() {
+        "web".to_string()
+    } else {
+        stem
+    }
+}
+
+/// `text` with BibTeX's special characters escaped
+pub fn escape_bibtex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '{' | '}' | '%' | '&' | '$' | '#' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `url` for biblatex's verbatim `url` field, where only unbalanced braces
+/// would break the entry
+fn bibtex_url(url: &str) -> String {
+    url.replace('{', "%7B").replace('}', "%7D")
+}
+
+/// `text` ending in a full stop, unless it already ends in punctuation
+fn with_period(text: &str) -> String {
+    if text.ends_with(['.', '?', '!']) {
+        text.to_string()
+    } else {
+        format!("{}.", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn citation(title: &str, byline: Option<&str>) -> Citation {
+        let metadata = PageMetadata {
+            title: Some(title.to_string()),
+            byline: byline.map(str::to_string),
+            excerpt: None,
+            site_name: Some("Rust Blog".to_string()),
+        };
+        let url = "https://blog.rust-lang.org/2026/a?q=50%25";
+        Citation::new(url, "Result title", metadata, "2026-10-16")
+    }
+
+    #[test]
+    fn test_bibtex_escapes_awkward_titles() {
+        let title = "{Async} Rust: 100% safe & fast_ish #1 $5 ~ ^ C:\\dir";
+        let cite = citation(title, Some("By Ann Lee, Bo Ma"));
+        assert_eq!(cite.key, "rustlang2026");
+        assert_eq!(
+            cite.format(CitationFormat::Bibtex),
+            "@online{rustlang2026,\n  \
+             title = {{\\{Async\\} Rust: 100\\% safe \\& fast\\_ish \\#1 \\$5 \\textasciitilde{} \
+             \\textasciicircum{} C:\\textbackslash{}dir}},\n  \
+             author = {{Ann Lee, Bo Ma}},\n  organization = {Rust Blog},\n  year = {2026},\n  \
+             url = {https://blog.rust-lang.org/2026/a?q=50%25},\n  urldate = {2026-10-16},\n}\n"
+        );
+
+        let cite = citation("Why [not] Rust?", Some("Ann Lee"));
+        assert_eq!(
+            cite.format(CitationFormat::Plain),
+            "Ann Lee. \"Why [not] Rust?\" Rust Blog. https://blog.rust-lang.org/2026/a?q=50%25 \
+             (accessed 2026-10-16).\n"
+        );
+        assert_eq!(
+            cite.format(CitationFormat::Footnote),
+            "[^rustlang2026]: Ann Lee, \"[Why \\[not\\] Rust?]\
+             (https://blog.rust-lang.org/2026/a?q=50%25)\", *Rust Blog*, accessed 2026-10-16.\n"
+        );
+
+        // Without metadata the result's title is used
+        let url = "https://www.bbc.co.uk/news";
+        let bare = Citation::new(url, "News", PageMetadata::default(), "2025-01-02");
+        assert_eq!(bare.key, "bbc2025");
+        assert_eq!((bare.title.as_str(), bare.author), ("News", None));
+    }
+
+    #[test]
+    fn test_append_to_file_skips_known_urls_and_suffixes_keys() {
+        let name = format!("websearch-references-{}.bib", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&path);
+
+        let first = citation("First", None);
+        let mut second = citation("Second", None);
+        second.url = "https://blog.rust-lang.org/2026/b".to_string();
+
+        let append = |cite: &Citation| append_to_file(&path, cite, CitationFormat::Bibtex).unwrap();
+        assert_eq!(append(&first).as_deref(), Some("rustlang2026"));
+        assert_eq!(append(&first), None);
+        assert_eq!(append(&second).as_deref(), Some("rustlang2026b"));
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written.matches("@online{").count(), 2);
+        assert!(written.contains("}\n\n@online{rustlang2026b,\n  title = {{Second}},\n"));
+        assert!(written.contains("  year = {2026},\n  url = {https://blog.rust-lang.org/2026/b}"));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
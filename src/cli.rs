@@ -0,0 +1,502 @@
+//! Command line arguments and the non-interactive search
+//!
+//! Without `--search` or `--fetch` the TUI starts, searching right away for
+//! a query given as plain arguments. With `--search`, the query runs on the
+//! chosen engine and the results are printed, as a table or with `--json`
+//! in the export schema of [`crate::export::Digest::to_json`]; `--fetch`
+//! also prefetches every page and prints where it was saved. `--fetch URL`
+//! on its own turns pages into markdown in `active_tabs/` without a search.
+//! `--re-extract` rebuilds cached pages from their kept HTML, offline.
+//! The terminal is never switched to raw mode or the alternate screen on
+//! these paths, so the output can be piped.
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use std::fmt::Write;
+use std::io::BufRead;
+use std::path::PathBuf;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use url::Url;
+
+use crate::app::{open_in_editor, AppMessage};
+use crate::config::{Config, Engine, OutputFormat};
+use crate::export::{self, Digest};
+use crate::paths;
+use crate::prefetch::{PrefetchManager, PrefetchStatus};
+use crate::query_filters::QueryFilters;
+use crate::search::{SearchProviders, SearchResult};
+
+/// Widest title column in the results table
+const TITLE_WIDTH: usize = 50;
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Args {
+    /// Log requests and prefetch results to debug.log
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Forget saved cookies before starting
+    #[arg(long)]
+    pub clear_cookies: bool,
+
+    /// Browse cached pages only: no searching, no downloads
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Confirm the Brave API key with a one-result search at startup
+    #[arg(long)]
+    pub check_keys: bool,
+
+    /// Reopen the last search and its selection
+    #[arg(long, conflicts_with = "search")]
+    pub resume: bool,
+
+    /// Search for QUERY and print the results instead of starting the TUI
+    #[arg(long, value_name = "QUERY")]
+    pub search: Option<String>,
+
+    /// Engine for the search, the configured default engine if not given
+    #[arg(long, value_parser = parse_engine)]
+    pub engine: Option<Engine>,
+
+    /// Print the results as JSON (same as --output json)
+    #[arg(long, requires = "search")]
+    pub json: bool,
+
+    /// Output format of --search
+    #[arg(long, value_enum, default_value_t = Output::Table, requires = "search")]
+    pub output: Output,
+
+    /// Save each URL as markdown in active_tabs/ and print its path (- reads
+    /// URLs from stdin); with --search, prefetch every result instead
+    #[arg(long, value_name = "URL", num_args = 0.., conflicts_with = "resume")]
+    pub fetch: Option<Vec<String>>,
+
+    /// Open the pages saved by --fetch in the editor
+    #[arg(long, requires = "fetch", conflicts_with = "search")]
+    pub open: bool,
+
+    /// Extract cached pages again from their kept HTML, without downloading
+    /// them; every page with kept HTML if no URL is given
+    #[arg(
+        long,
+        value_name = "URL",
+        num_args = 0..,
+        conflicts_with_all = ["search", "resume", "fetch"]
+    )]
+    pub re_extract: Option<Vec<String>>,
+
+    /// Start the TUI with the results for QUERY
+    #[arg(
+        value_name = "QUERY",
+        conflicts_with_all = ["search", "resume", "fetch", "re_extract"]
+    )]
+    pub query: Vec<String>,
+}
+
+/// How `--search` prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    Table,
+    Json,
+}
+
+impl Args {
+    /// Query to search for when the TUI starts, the words joined by spaces
+    pub fn start_query(&self) -> Option<String> {
+        let query = self.query.join(" ");
+        let query = query.trim();
+        (!query.is_empty()).then(|| query.to_string())
+    }
+
+    /// URLs to fetch without searching
+    pub fn fetch_urls(&self) -> Option<&[String]> {
+        match (&self.search, &self.fetch) {
+            (None, Some(urls)) => Some(urls),
+            _ => None,
+        }
+    }
+
+    fn output(&self) -> Output {
+        if self.json { Output::Json } else { self.output }
+    }
+}
+
+/// Engine names accepted by `--engine`
+fn parse_engine(name: &str) -> Result<Engine, String> {
+    match name.to_lowercase().as_str() {
+        "brave" => Ok(Engine::Brave),
+        "duckduckgo" | "ddg" => Ok(Engine::DuckDuckGo),
+        "searxng" | "searx" => Ok(Engine::SearXNG),
+        "startpage" | "sp" => Ok(Engine::Startpage),
+        _ => Err("expected brave, duckduckgo (ddg), searxng or startpage".to_string()),
+    }
+}
+
+/// Run `--search` and print the results to stdout
+///
+/// Fails when the search fails or finds nothing, so the exit code is
+/// non-zero.
+pub async fn run_search(args: &Args, query: &str, config: &Config) -> Result<()> {
+    anyhow::ensure!(
+        args.fetch.as_ref().is_none_or(|urls| urls.is_empty()),
+        "--fetch takes no URLs together with --search"
+    );
+    anyhow::ensure!(!config.network.offline, "Can't search in offline mode");
+    let engine = args.engine.unwrap_or(config.search.default_engine);
+    let providers = SearchProviders::new(&config.search);
+    let filters = QueryFilters::parse(query);
+    anyhow::ensure!(!filters.query.is_empty(), "Add search terms to the site operators");
+    let mut results = providers.get(engine).search(&filters.query).await?;
+    filters.apply(&mut results);
+    results.truncate(config.search.max_results);
+    if results.is_empty() {
+        anyhow::bail!("No results found");
+    }
+
+    let statuses = if args.fetch.is_some() {
+        let (manager, mut rx) = start_prefetch(config)?;
+        manager.set_engine(Some(engine));
+        fetch_pages(&manager, &mut rx, &results).await
+    } else {
+        Vec::new()
+    };
+
+    let output = match args.output() {
+        Output::Json => {
+            let digest = Digest {
+                query,
+                engine,
+                entries: results
+                    .iter()
+                    .enumerate()
+                    .map(|(i, result)| (result, statuses.get(i)))
+                    .collect(),
+            };
+            digest.to_json()? + "\n"
+        }
+        Output::Table => format_table(&results, &statuses),
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+/// Run `--fetch` on `urls` and print the path of every saved page
+///
+/// Pages are fetched like prefetched results, then moved to
+/// `active_tabs/`. Failures are reported on stderr. Returns the exit code:
+/// 0 when every page was saved, 1 when some failed and 2 when all did.
+pub async fn run_fetch(urls: &[String], open: bool, config: &Config) -> Result<i32> {
+    let urls = if urls.iter().any(|url| url == "-") {
+        let stdin = read_urls(std::io::stdin().lock())?;
+        expand_stdin(urls, &stdin)
+    } else {
+        urls.to_vec()
+    };
+    anyhow::ensure!(!urls.is_empty(), "No URLs to fetch");
+
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for url in &urls {
+        match Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {
+                results.push(SearchResult {
+                    title: url_title(&parsed),
+                    url: url.clone(),
+                    description: String::new(),
+                    ..Default::default()
+                });
+            }
+            _ => {
+                eprintln!("{}: not an http(s) URL", url);
+                failed += 1;
+            }
+        }
+    }
+
+    let mut saved = Vec::new();
+    if !results.is_empty() {
+        let (manager, mut rx) = start_prefetch(config)?;
+        let statuses = fetch_pages(&manager, &mut rx, &results).await;
+        for (result, status) in results.iter().zip(statuses) {
+            let path = match &status {
+                // Not markdown, so it stays where it was saved
+                PrefetchStatus::Binary(path) => Ok(path.clone()),
+                PrefetchStatus::Ready(_)
+                | PrefetchStatus::ReadyArchived(_)
+                | PrefetchStatus::Cached(_) => manager
+                    .activate_page(&result.url)
+                    .await
+                    .map_err(|e| format!("{:#}", e)),
+                PrefetchStatus::Failed(failure) => {
+                    Err(format!("{}: {}", export::status_name(&status), failure))
+                }
+                PrefetchStatus::Blocked(reason) => {
+                    Err(format!("{}: {}", export::status_name(&status), reason))
+                }
+                _ => Err(export::status_name(&status).to_string()),
+            };
+            match path {
+                Ok(path) => {
+                    println!("{}", path.display());
+                    saved.push(path);
+                }
+                Err(error) => {
+                    eprintln!("{}: {}", result.url, error);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    let mut code = exit_code(failed, urls.len());
+    let pages: Vec<PathBuf> = saved
+        .into_iter()
+        .filter(|path| OutputFormat::of_path(path).is_some())
+        .collect();
+    if open
+        && !pages.is_empty()
+        && let Err(e) = open_in_editor(&config.editor, &pages, None)
+    {
+        eprintln!("Error: {:#}", e);
+        code = code.max(1);
+    }
+    Ok(code)
+}
+
+/// Run `--re-extract` and print the path of every page extracted again
+///
+/// Pages whose HTML wasn't kept are reported on stderr. Returns the exit
+/// code like [`run_fetch`].
+pub async fn run_re_extract(urls: &[String], config: &Config) -> Result<i32> {
+    let (manager, _rx) = start_prefetch(config)?;
+    let urls = if urls.is_empty() { manager.kept_html_urls() } else { urls.to_vec() };
+    anyhow::ensure!(!urls.is_empty(), "No cached pages with kept HTML (extraction.keep_html)");
+
+    let mut failed = 0;
+    for url in &urls {
+        match manager.re_extract(url).await {
+            Ok(path) => println!("{}", path.display()),
+            Err(e) => {
+                eprintln!("{}: {:#}", url, e);
+                failed += 1;
+            }
+        }
+    }
+    Ok(exit_code(failed, urls.len()))
+}
+
+/// Exit code of `--fetch`: 0 all saved, 1 some failed, 2 all failed
+fn exit_code(failed: usize, total: usize) -> i32 {
+    match failed {
+        0 => 0,
+        _ if failed < total => 1,
+        _ => 2,
+    }
+}
+
+/// Non-empty lines of `reader` that aren't `#` comments
+fn read_urls(reader: impl BufRead) -> Result<Vec<String>> {
+    let mut urls = Vec::new();
+    for line in reader.lines() {
+        let line = line.context("Failed to read URLs from stdin")?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            urls.push(line.to_string());
+        }
+    }
+    Ok(urls)
+}
+
+/// `urls` with each `-` replaced by the URLs read from stdin
+fn expand_stdin(urls: &[String], stdin: &[String]) -> Vec<String> {
+    urls.iter()
+        .flat_map(|url| {
+            if url == "-" {
+                stdin.to_vec()
+            } else {
+                vec![url.clone()]
+            }
+        })
+        .collect()
+}
+
+/// Stand-in title for the file name of a page fetched by URL
+///
+/// The last path segment, like `post` for `https://example.com/blog/post`.
+fn url_title(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Prefetch manager reporting to a channel of its own
+fn start_prefetch(
+    config: &Config,
+) -> Result<(PrefetchManager, UnboundedReceiver<AppMessage>)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let manager = PrefetchManager::new(paths::data_dir().to_path_buf(), config, tx)
+        .context("Failed to start prefetching")?;
+    Ok((manager, rx))
+}
+
+/// Prefetch every result and wait until each has finished or failed
+///
+/// Returns the final statuses in the order of `results`.
+async fn fetch_pages(
+    manager: &PrefetchManager,
+    rx: &mut UnboundedReceiver<AppMessage>,
+    results: &[SearchResult],
+) -> Vec<PrefetchStatus> {
+    manager.prefetch_all(results).await;
+
+    loop {
+        let mut statuses = Vec::with_capacity(results.len());
+        for result in results {
+            statuses.push(manager.get_status(&result.url).await);
+        }
+        let loading = statuses
+            .iter()
+            .any(|s| matches!(s, PrefetchStatus::Pending | PrefetchStatus::InProgress));
+        // Every status change is reported on the channel
+        if !loading || rx.recv().await.is_none() {
+            return statuses;
+        }
+    }
+}
+
+/// Numbered table of titles and URLs, with the saved page under each
+/// result when `statuses` is given
+fn format_table(results: &[SearchResult], statuses: &[PrefetchStatus]) -> String {
+    let mut table = String::new();
+    for (i, result) in results.iter().enumerate() {
+        let title: String = result.title.chars().take(TITLE_WIDTH).collect();
+        let _ = writeln!(
+            table,
+            "{:>2}. {:<width$}  {}",
+            i + 1,
+            title,
+            result.url,
+            width = TITLE_WIDTH
+        );
+        let Some(status) = statuses.get(i) else {
+            continue;
+        };
+        let _ = match (status.path(), status) {
+            (Some(path), _) => writeln!(table, "    {}", path.display()),
+            (None, PrefetchStatus::Failed(failure)) => {
+                writeln!(table, "    {}: {}", export::status_name(status), failure)
+            }
+            (None, PrefetchStatus::Blocked(reason)) => {
+                writeln!(table, "    {}: {}", export::status_name(status), reason)
+            }
+            (None, _) => writeln!(table, "    {}", export::status_name(status)),
+        };
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use std::path::PathBuf;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::iter::once("websearch-tui").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_arguments() {
+        Args::command().debug_assert();
+
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.search, None);
+        assert_eq!(args.start_query(), None);
+        assert!(!args.resume);
+
+        let args = parse(&["rust", "pin projection", "--engine", "searxng"]).unwrap();
+        assert_eq!(args.start_query().as_deref(), Some("rust pin projection"));
+        assert_eq!(args.engine, Some(Engine::SearXNG));
+        assert_eq!(parse(&["  "]).unwrap().start_query(), None);
+
+        let args = parse(&["--search", "tokio cancellation", "--engine", "ddg", "--json"]).unwrap();
+        assert_eq!(args.search.as_deref(), Some("tokio cancellation"));
+        assert_eq!(args.engine, Some(Engine::DuckDuckGo));
+        assert_eq!(args.output(), Output::Json);
+        assert_eq!(parse(&["--search", "q", "--output", "json"]).unwrap().output(), Output::Json);
+        assert_eq!(parse(&["--search", "q"]).unwrap().output(), Output::Table);
+
+        // --fetch prefetches the results of --search, or fetches its URLs
+        let args = parse(&["--search", "q", "--fetch"]).unwrap();
+        assert_eq!(args.fetch, Some(Vec::new()));
+        assert_eq!(args.fetch_urls(), None);
+        let args = parse(&["--fetch", "https://a.com/", "-", "--open"]).unwrap();
+        assert_eq!(args.fetch_urls(), Some(&["https://a.com/".to_string(), "-".to_string()][..]));
+        assert!(args.open);
+        assert!(parse(&["--open"]).is_err());
+        assert_eq!(parse(&["--re-extract"]).unwrap().re_extract, Some(Vec::new()));
+        assert!(parse(&["--re-extract", "https://a.com/", "--fetch"]).is_err());
+
+        // Search options need a query, and --resume makes no sense with one
+        assert!(parse(&["--json"]).is_err());
+        assert!(parse(&["--search", "q", "--engine", "bing"]).is_err());
+        assert!(parse(&["--search", "q", "--resume"]).is_err());
+        assert!(parse(&["--resume", "rust"]).is_err());
+    }
+
+    #[test]
+    fn test_fetch_helpers() {
+        let stdin = read_urls("https://a.com/\n\n  # comment\n https://b.com/x \n".as_bytes());
+        assert_eq!(stdin.unwrap(), ["https://a.com/", "https://b.com/x"]);
+        let urls = ["https://c.com/".to_string(), "-".to_string()];
+        let stdin = ["https://a.com/".to_string()];
+        assert_eq!(expand_stdin(&urls, &stdin), ["https://c.com/", "https://a.com/"]);
+
+        let title = |url: &str| url_title(&Url::parse(url).unwrap());
+        assert_eq!(title("https://example.com/blog/post/"), "post");
+        assert_eq!(title("https://example.com/"), "");
+
+        assert_eq!(exit_code(0, 3), 0);
+        assert_eq!(exit_code(1, 3), 1);
+        assert_eq!(exit_code(3, 3), 2);
+    }
+
+    #[test]
+    fn test_format_table() {
+        let results = vec![
+            SearchResult {
+                title: "Tokio".to_string(),
+                url: "https://tokio.rs/".to_string(),
+                description: String::new(),
+                ..Default::default()
+            },
+            SearchResult {
+                title: "Slow".to_string(),
+                url: "https://slow.example/".to_string(),
+                description: String::new(),
+                ..Default::default()
+            },
+        ];
+        let title = |t: &str| format!("{:<width$}", t, width = TITLE_WIDTH);
+
+        assert_eq!(
+            format_table(&results, &[]),
+            format!(
+                " 1. {}  https://tokio.rs/\n 2. {}  https://slow.example/\n",
+                title("Tokio"),
+                title("Slow")
+            )
+        );
+
+        let statuses = [
+            PrefetchStatus::Ready(PathBuf::from("/data/tokio.md")),
+            PrefetchStatus::Timeout,
+        ];
+        let table = format_table(&results, &statuses);
+        assert!(table.contains("https://tokio.rs/\n    /data/tokio.md\n"));
+        assert!(table.ends_with("https://slow.example/\n    timeout\n"));
+    }
+}
@@ -0,0 +1,125 @@
+//! System clipboard access
+//!
+//! Locally a native clipboard tool (`pbcopy`, `wl-copy`, `xclip`, `xsel`)
+//! is tried first, since it reports whether the copy worked. Over SSH, or
+//! without any tool, the OSC 52 terminal escape sequence is used instead,
+//! which reaches the local clipboard through SSH and tmux.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Native clipboard commands, tried in order
+const NATIVE_TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Copy text to the system clipboard
+///
+/// Returns a short name of the mechanism that was used.
+pub fn copy_to_clipboard(text: &str) -> Result<&'static str> {
+    // Over SSH a native tool would fill the remote machine's clipboard
+    if !over_ssh() {
+        match copy_with_native_tool(text) {
+            Ok(tool) => return Ok(tool),
+            Err(e) if !osc52_supported() => return Err(e),
+            Err(_) => {}
+        }
+    }
+
+    if osc52_supported() {
+        let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some());
+        let mut stdout = std::io::stdout();
+        stdout
+            .write_all(sequence.as_bytes())
+            .and_then(|_| stdout.flush())
+            .context("Failed to write OSC 52 sequence")?;
+        return Ok("OSC 52");
+    }
+
+    copy_with_native_tool(text)
+}
+
+/// Whether the app runs in an SSH session
+fn over_ssh() -> bool {
+    std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some()
+}
+
+/// Whether the terminal is expected to understand OSC 52
+///
+/// The Linux console and dumb terminals silently ignore it.
+fn osc52_supported() -> bool {
+    match std::env::var("TERM") {
+        Ok(term) => !matches!(term.as_str(), "" | "linux" | "dumb"),
+        Err(_) => false,
+    }
+}
+
+/// Build the OSC 52 "set clipboard" sequence
+///
+/// Inside tmux the sequence is wrapped in a DCS passthrough so it reaches
+/// the outer terminal.
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let osc = format!("\x1b]52;c;{}\x07", encoded);
+
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))
+    } else {
+        osc
+    }
+}
+
+/// Pipe text into the first available native clipboard tool
+fn copy_with_native_tool(text: &str) -> Result<&'static str> {
+    for (tool, args) in NATIVE_TOOLS {
+        let child = Command::new(tool)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let Ok(mut child) = child else {
+            continue;
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .with_context(|| format!("Failed to write to {}", tool))?;
+        }
+
+        let status = child
+            .wait()
+            .with_context(|| format!("Failed to wait for {}", tool))?;
+        if status.success() {
+            return Ok(tool);
+        }
+    }
+
+    anyhow::bail!("No clipboard available (terminal lacks OSC 52 and no pbcopy/wl-copy/xclip/xsel found)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(
+            osc52_sequence("https://example.com", false),
+            "\x1b]52;c;aHR0cHM6Ly9leGFtcGxlLmNvbQ==\x07"
+        );
+    }
+
+    #[test]
+    fn test_osc52_sequence_tmux_passthrough() {
+        let seq = osc52_sequence("hi", true);
+        assert_eq!(seq, "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+}
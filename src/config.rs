@@ -0,0 +1,1200 @@
+//! User configuration from `config.toml`
+//!
+//! Loaded once at startup from `config.toml` in the config directory
+//! (`~/.config/websearch-tui/config.toml` on Linux). A missing file means
+//! defaults. Environment variables override file values, so an existing
+//! `.env` keeps working.
+//!
+//! ```toml
+//! [search]
+//! default_engine = "duckduckgo"   # brave, duckduckgo, searxng, startpage
+//! max_results = 10
+//! brave_api_key = "..."
+//! region = "us-en"                # DuckDuckGo region
+//! remember_searxng_health = true  # keep instance health between sessions
+//! brave_goggle = "docs"           # name from [search.goggles], or a goggle URL
+//! metrics = false                 # append search timings to metrics.csv
+//!
+//! [search.goggles]
+//! docs = "https://example.com/docs.goggle"
+//!
+//! [prefetch]
+//! concurrency = 12
+//! timeout_secs = 8
+//! max_page_mb = 5
+//! wayback = true
+//! respect_robots = false
+//! normalize_urls = true
+//! follow_canonical = false
+//!
+//! [extraction]
+//! max_table_width = 120           # wider tables become lists
+//! images = "links"                # strip, links, download
+//! table_of_contents = true         # list the sections of long pages
+//! format = "markdown"              # markdown, org, text
+//! wrap_width = 80                  # line width of the text format
+//! max_markdown_kb = 500            # longer pages are truncated, 0 = no limit
+//! keep_html = false                # save the HTML too, for re-extraction
+//!
+//! [cache]
+//! max_age_days = 5
+//! max_size_mb = 200
+//!
+//! [network]
+//! proxy = "socks5h://127.0.0.1:9050"
+//! user_agent = "Mozilla/5.0 ..."
+//! persist_cookies = false
+//! offline = false
+//!
+//! [editor]
+//! command = "nvim -R"             # else $VISUAL, $EDITOR, then nvim
+//! split = "tabs"                  # tabs, horizontal, vertical
+//! nvim_server = "/tmp/nvim.sock"  # else $NVIM
+//! open_mode = "tmux-window"       # replace, tmux-window, tmux-pane
+//! remote_tab = false
+//!
+//! [browser]
+//! command = "firefox -P research %s"  # else $BROWSER, then the system opener
+//!
+//! [ui]
+//! status_timeout_secs = 5
+//! digit_keys = "count"            # or "jump": 3 selects result 3
+//! density = "full"                # or "compact", "minimal"; z cycles it
+//! track_read = true               # dim results whose page was opened before
+//!
+//! [citation]
+//! format = "bibtex"               # bibtex, plain, footnote
+//! destination = "clipboard"       # clipboard, file
+//!
+//! [hooks]
+//! post_fetch_hook = "summarize {path} {url}"
+//! on_open = "notes-sync {path}"
+//! timeout_secs = 30
+//!
+//! [open_with]
+//! mpv = "mpv {url}"
+//! glow = "glow -p {path}"
+//!
+//! [paths]
+//! data_dir = "~/websearch-data"
+//! export_dir = "~/notes/searches"
+//! obsidian_vault = "~/vault/Clippings"
+//!
+//! [keys]
+//! open_browser = "ctrl+o"
+//!
+//! [theme]
+//! preset = "light"
+//! ascii_icons = true
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::keymap::{KeyList, Keymap};
+use crate::paths;
+use crate::theme::Theme;
+
+/// Config file name inside the config directory
+const CONFIG_FILE: &str = "config.toml";
+
+/// Editor used when neither the config nor the environment names one
+const DEFAULT_EDITOR: &str = "nvim";
+
+/// Most results any engine returns for one query
+pub const MAX_RESULTS_LIMIT: usize = 10;
+
+/// Search engines selectable as the default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Brave,
+    DuckDuckGo,
+    SearXNG,
+    Startpage,
+}
+
+impl Engine {
+    /// Every engine, in the order they are listed
+    pub const ALL: [Engine; 4] = [
+        Engine::Brave,
+        Engine::DuckDuckGo,
+        Engine::SearXNG,
+        Engine::Startpage,
+    ];
+
+    /// Display name for the help bar
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Brave => "Brave",
+            Engine::DuckDuckGo => "DuckDuckGo",
+            Engine::SearXNG => "SearXNG",
+            Engine::Startpage => "Startpage",
+        }
+    }
+
+    /// Short name for the results header, like `ddg`
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Engine::Brave => "brave",
+            Engine::DuckDuckGo => "ddg",
+            Engine::SearXNG => "searxng",
+            Engine::Startpage => "startpage",
+        }
+    }
+}
+
+/// All settings, with defaults for anything the file leaves out
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub search: SearchConfig,
+    pub prefetch: PrefetchConfig,
+    pub extraction: ExtractionConfig,
+    pub cache: CacheConfig,
+    pub network: NetworkConfig,
+    pub editor: EditorConfig,
+    pub browser: BrowserConfig,
+    pub ui: UiConfig,
+    pub citation: CitationConfig,
+    pub hooks: HooksConfig,
+    /// Menu label → command for `o`, see [`crate::open_with`]
+    pub open_with: BTreeMap<String, String>,
+    pub paths: PathsConfig,
+    /// Action name → key descriptors, see [`crate::keymap`]
+    pub keys: HashMap<String, KeyList>,
+    /// Bindings built from `keys`
+    #[serde(skip)]
+    pub keymap: Keymap,
+    pub theme: ThemeConfig,
+    /// Colors and icons built from `theme`
+    #[serde(skip)]
+    pub style: Theme,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    /// Engine used by Enter in the search box
+    pub default_engine: Engine,
+    /// Results kept per search
+    pub max_results: usize,
+    /// Brave Search API key (`BRAVE_SEARCH_API_KEY`)
+    pub brave_api_key: Option<String>,
+    /// Region of DuckDuckGo results, like `de-de` or `us-en`
+    pub region: Option<String>,
+    /// Keep SearXNG instance health in `searxng_health.json` between sessions
+    pub remember_searxng_health: bool,
+    /// Goggle active at startup: a name from `goggles`, or a URL or ID
+    pub brave_goggle: Option<String>,
+    /// Goggle name → URL or ID, see [`crate::goggles`]
+    pub goggles: BTreeMap<String, String>,
+    /// Append search and prefetch timings to `metrics.csv`
+    pub metrics: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            default_engine: Engine::Brave,
+            max_results: MAX_RESULTS_LIMIT,
+            brave_api_key: None,
+            region: None,
+            remember_searxng_health: true,
+            brave_goggle: None,
+            goggles: BTreeMap::new(),
+            metrics: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrefetchConfig {
+    /// Pages downloaded at once
+    pub concurrency: usize,
+    /// Per-page timeout in seconds
+    pub timeout_secs: u64,
+    /// Pages larger than this are skipped (`WEBSEARCH_MAX_PAGE_MB`)
+    pub max_page_mb: u64,
+    /// Fall back to the Wayback Machine (`WEBSEARCH_WAYBACK`)
+    pub wayback: bool,
+    /// Skip pages the host's robots.txt disallows
+    pub respect_robots: bool,
+    /// Download AMP and mobile results from the regular page
+    pub normalize_urls: bool,
+    /// Download a page again from the canonical URL it declares
+    pub follow_canonical: bool,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 12,
+            timeout_secs: 8,
+            max_page_mb: 5,
+            wayback: true,
+            respect_robots: false,
+            normalize_urls: true,
+            follow_canonical: false,
+        }
+    }
+}
+
+impl PrefetchConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn max_page_bytes(&self) -> u64 {
+        self.max_page_mb * 1024 * 1024
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtractionConfig {
+    /// Tables wider than this many characters are written as lists
+    pub max_table_width: usize,
+    /// What happens to the images of a page, see [`crate::images`]
+    pub images: ImageMode,
+    /// Long pages start with a list of their sections, see [`crate::toc`]
+    pub table_of_contents: bool,
+    /// Format pages are saved in, see [`crate::formats`]
+    pub format: OutputFormat,
+    /// Column paragraphs are wrapped at in the `text` format
+    pub wrap_width: usize,
+    /// Longer pages are cut at a heading, `0` for no limit
+    pub max_markdown_kb: usize,
+    /// Keep the downloaded HTML beside each page, see [`crate::raw_html`]
+    pub keep_html: bool,
+}
+
+impl Default for ExtractionConfig {
+    fn default() -> Self {
+        Self {
+            max_table_width: 120,
+            images: ImageMode::default(),
+            table_of_contents: true,
+            format: OutputFormat::default(),
+            wrap_width: 80,
+            max_markdown_kb: 500,
+            keep_html: false,
+        }
+    }
+}
+
+impl ExtractionConfig {
+    pub fn max_markdown_bytes(&self) -> usize {
+        self.max_markdown_kb * 1024
+    }
+}
+
+/// How images are kept in saved pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageMode {
+    /// Only the alt text is kept, in italics
+    Strip,
+    /// Images stay as links to the web
+    #[default]
+    Links,
+    /// Images are saved next to the page and linked locally
+    Download,
+}
+
+/// Format of saved pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Markdown with YAML frontmatter
+    #[default]
+    Markdown,
+    /// Org-mode with `#+TITLE:` and `#+PROPERTY:` lines
+    Org,
+    /// Plain text wrapped at `wrap_width`
+    Text,
+}
+
+impl OutputFormat {
+    /// File extension of saved pages
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "md",
+            OutputFormat::Org => "org",
+            OutputFormat::Text => "txt",
+        }
+    }
+
+    /// Format of a saved page, from its extension
+    pub fn of_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "md" => Some(OutputFormat::Markdown),
+            "org" => Some(OutputFormat::Org),
+            "txt" => Some(OutputFormat::Text),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Unpinned pages older than this are deleted at startup
+    pub max_age_days: u64,
+    /// Least-recently-opened pages are evicted above this (`WEBSEARCH_CACHE_MB`)
+    pub max_size_mb: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: 5,
+            max_size_mb: 200,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_days * 24 * 60 * 60)
+    }
+
+    pub fn max_size_bytes(&self) -> u64 {
+        self.max_size_mb * 1024 * 1024
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// HTTP or SOCKS5 proxy URL (`WEBSEARCH_PROXY`)
+    pub proxy: Option<String>,
+    /// User-Agent of the scrape client (`WEBSEARCH_USER_AGENT`)
+    pub user_agent: Option<String>,
+    /// Keep cookies between runs (`WEBSEARCH_PERSIST_COOKIES`)
+    pub persist_cookies: bool,
+    /// Only show cached pages, never touching the network
+    /// (`WEBSEARCH_OFFLINE`, `--offline`)
+    pub offline: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorConfig {
+    /// Program and arguments used to open pages (`$VISUAL`, `$EDITOR`)
+    pub command: Option<String>,
+    /// How several selected pages are laid out
+    pub split: EditorSplit,
+    /// Address of a running Neovim to send pages to (`$NVIM`)
+    pub nvim_server: Option<String>,
+    /// Open sent pages in new tabs (`--remote-tab`) instead of `--remote`
+    pub remote_tab: bool,
+    /// Where the editor runs
+    pub open_mode: OpenMode,
+}
+
+impl EditorConfig {
+    /// Program and arguments, split with shell quoting rules
+    pub fn argv(&self) -> Vec<String> {
+        shell_words::split(self.command.as_deref().unwrap_or(DEFAULT_EDITOR)).unwrap_or_default()
+    }
+
+    /// Program name for the help bar, like `nvim` or `hx`
+    pub fn name(&self) -> String {
+        self.argv()
+            .first()
+            .and_then(|program| Path::new(program).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Whether the editor takes Vim's `-p`/`-o`/`-O` for several files
+    pub fn is_vim(&self) -> bool {
+        matches!(self.name().as_str(), "nvim" | "vim" | "vi" | "gvim" | "mvim")
+    }
+}
+
+/// Where the editor runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OpenMode {
+    /// In this terminal, with the TUI suspended until it exits
+    #[default]
+    Replace,
+    /// In a new tmux window, when running inside tmux
+    TmuxWindow,
+    /// In a new tmux pane beside the TUI, when running inside tmux
+    TmuxPane,
+}
+
+/// Layout for opening several pages at once (Vim's `-p`, `-o`, `-O`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorSplit {
+    #[default]
+    Tabs,
+    Horizontal,
+    Vertical,
+}
+
+impl EditorSplit {
+    /// Editor flag selecting the layout
+    pub fn flag(self) -> &'static str {
+        match self {
+            EditorSplit::Tabs => "-p",
+            EditorSplit::Horizontal => "-o",
+            EditorSplit::Vertical => "-O",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrowserConfig {
+    /// Program and arguments used to open URLs (`$BROWSER`); `%s` marks
+    /// where the URL goes
+    pub command: Option<String>,
+}
+
+impl BrowserConfig {
+    /// Program and arguments that open `url`
+    ///
+    /// The URL replaces every `%s`, or is appended when there is none.
+    /// Without a command it is the system opener, like `xdg-open`.
+    pub fn argv(&self, url: &str) -> Vec<String> {
+        let Some(command) = &self.command else {
+            return system_opener(url);
+        };
+        let mut argv = shell_words::split(command).unwrap_or_default();
+        if argv.iter().any(|arg| arg.contains("%s")) {
+            for arg in &mut argv {
+                *arg = arg.replace("%s", url);
+            }
+        } else {
+            argv.push(url.to_string());
+        }
+        argv
+    }
+}
+
+/// The platform's default handler for a URL or file
+pub fn system_opener(target: &str) -> Vec<String> {
+    let program: &[&str] = if cfg!(target_os = "macos") {
+        &["open"]
+    } else if cfg!(target_os = "windows") {
+        &["cmd", "/C", "start", ""]
+    } else {
+        &["xdg-open"]
+    };
+    program
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain([target.to_string()])
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    /// How long status messages stay visible
+    pub status_timeout_secs: u64,
+    /// What digits do in the results list
+    pub digit_keys: DigitKeys,
+    /// Lines per result, changed with `z` and saved back to the file
+    pub density: Density,
+    /// Remember opened pages in `read.json` and mark them in results
+    pub track_read: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            status_timeout_secs: 5,
+            digit_keys: DigitKeys::Count,
+            density: Density::Full,
+            track_read: true,
+        }
+    }
+}
+
+/// How much of each result the list shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    /// Title, URL and description
+    #[default]
+    Full,
+    /// Title and domain on one line
+    Compact,
+    /// Title only, one line per result
+    Minimal,
+}
+
+impl Density {
+    /// Value in `config.toml`
+    pub fn name(self) -> &'static str {
+        match self {
+            Density::Full => "full",
+            Density::Compact => "compact",
+            Density::Minimal => "minimal",
+        }
+    }
+
+    /// The density `z` switches to
+    pub fn next(self) -> Self {
+        match self {
+            Density::Full => Density::Compact,
+            Density::Compact => Density::Minimal,
+            Density::Minimal => Density::Full,
+        }
+    }
+
+    /// Terminal lines of one result, the blank line after it included
+    pub fn item_height(self) -> usize {
+        match self {
+            Density::Full => 4,
+            Density::Compact => 2,
+            Density::Minimal => 1,
+        }
+    }
+}
+
+/// Meaning of digit keys in the results list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigitKeys {
+    /// Vim counts: `5j`, `12G`
+    #[default]
+    Count,
+    /// `3` selects result 3 and opens it when pressed again; `0` is 10
+    Jump,
+}
+
+impl UiConfig {
+    pub fn status_timeout(&self) -> Duration {
+        Duration::from_secs(self.status_timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CitationConfig {
+    /// How citations made with `c` are written
+    pub format: CitationFormat,
+    /// Where they go
+    pub destination: CitationDestination,
+}
+
+/// Style of a citation, see [`crate::citation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CitationFormat {
+    /// biblatex `@online` entry
+    #[default]
+    Bibtex,
+    /// One line of text
+    Plain,
+    /// Markdown footnote definition
+    Footnote,
+}
+
+impl CitationFormat {
+    /// References file in the data directory that citations are appended to
+    pub fn file_name(self) -> &'static str {
+        match self {
+            CitationFormat::Bibtex => "references.bib",
+            CitationFormat::Plain => "references.txt",
+            CitationFormat::Footnote => "references.md",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CitationDestination {
+    #[default]
+    Clipboard,
+    /// Appended to the format's references file
+    File,
+}
+
+/// Commands run on saved pages, see [`crate::hooks`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Run after a page is fetched and converted to markdown
+    pub post_fetch_hook: Option<String>,
+    /// Run when a page is opened in the editor
+    pub on_open: Option<String>,
+    /// Hooks running longer than this are killed
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            post_fetch_hook: None,
+            on_open: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl HooksConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    /// Where cached pages live (`WEBSEARCH_DATA_DIR`)
+    pub data_dir: Option<PathBuf>,
+    /// Where digests are exported, `exports/` in the data directory by default
+    pub export_dir: Option<PathBuf>,
+    /// Obsidian folder pages are copied to with `O`
+    pub obsidian_vault: Option<PathBuf>,
+}
+
+impl PathsConfig {
+    /// Directory Markdown digests are written to
+    pub fn export_dir(&self) -> PathBuf {
+        self.export_dir
+            .clone()
+            .unwrap_or_else(|| paths::data_dir().join("exports"))
+    }
+}
+
+/// Preset plus per-element colors, see [`crate::theme`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// `dark`, `light` or `mono`
+    pub preset: String,
+    /// Plain characters instead of emoji
+    pub ascii_icons: bool,
+    /// Element name → color
+    #[serde(flatten)]
+    pub colors: HashMap<String, String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            preset: "dark".to_string(),
+            ascii_icons: false,
+            colors: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file and apply environment overrides
+    pub fn load() -> Result<Self> {
+        let path = paths::config_dir().map(|dir| dir.join(CONFIG_FILE));
+        let source = match &path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(source) => Some(source),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+                }
+            },
+            None => None,
+        };
+
+        let file = match (&path, &source) {
+            (Some(path), Some(source)) => Some((path.as_path(), source.as_str())),
+            _ => None,
+        };
+        Self::from_sources(file, |var| std::env::var(var).ok())
+    }
+
+    /// Write `key` (like `ui.density`) into the config file as `value`
+    ///
+    /// Only that line changes, or is added, so comments and the rest of the
+    /// file stay as they are. `value` is a TOML value, quoted if a string.
+    pub fn save_value(key: &str, value: &str) -> Result<PathBuf> {
+        let dir = paths::config_dir().context("No config directory")?;
+        let path = dir.join(CONFIG_FILE);
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        std::fs::write(&path, set_value(&source, key, value))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Parse `file` (path and contents), apply overrides from `lookup`, validate
+    fn from_sources(
+        file: Option<(&Path, &str)>,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let mut config: Config = match file {
+            Some((path, source)) => toml::from_str(source)
+                .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?,
+            None => Config::default(),
+        };
+
+        let overridden = config.apply_env(&lookup)?;
+
+        let built = Keymap::from_config(&config.keys)
+            .and_then(|keymap| Ok((keymap, Theme::from_config(&config.theme)?)));
+        let problem = match built {
+            Ok((keymap, style)) => {
+                config.keymap = keymap;
+                config.style = style;
+                config.problems().into_iter().next()
+            }
+            Err(problem) => Some(problem),
+        };
+        if let Some((key, problem)) = problem {
+            if let Some((_, var)) = overridden.iter().find(|(k, _)| *k == key) {
+                anyhow::bail!("Invalid {}: {}", var, problem);
+            }
+            match file {
+                Some((path, source)) => match line_of(source, &key) {
+                    Some(line) => {
+                        anyhow::bail!("{}:{}: {} {}", path.display(), line, key, problem)
+                    }
+                    None => anyhow::bail!("{}: {} {}", path.display(), key, problem),
+                },
+                None => anyhow::bail!("{} {}", key, problem),
+            }
+        }
+
+        if let Some(dir) = &config.paths.data_dir {
+            config.paths.data_dir = Some(paths::expand_home(dir));
+        }
+        if let Some(dir) = &config.paths.export_dir {
+            config.paths.export_dir = Some(paths::expand_home(dir));
+        }
+        if let Some(dir) = &config.paths.obsidian_vault {
+            config.paths.obsidian_vault = Some(paths::expand_home(dir));
+        }
+        Ok(config)
+    }
+
+    /// Override settings from environment variables
+    ///
+    /// Returns the overridden keys with the variable that set them.
+    fn apply_env(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Vec<(&'static str, &'static str)>> {
+        let get = |var: &str| {
+            lookup(var)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let mut overridden = Vec::new();
+
+        if let Some(key) = get("BRAVE_SEARCH_API_KEY") {
+            self.search.brave_api_key = Some(key);
+            overridden.push(("search.brave_api_key", "BRAVE_SEARCH_API_KEY"));
+        }
+        if let Some(value) = get("WEBSEARCH_MAX_PAGE_MB") {
+            self.prefetch.max_page_mb = parse_number("WEBSEARCH_MAX_PAGE_MB", &value)?;
+            overridden.push(("prefetch.max_page_mb", "WEBSEARCH_MAX_PAGE_MB"));
+        }
+        if let Some(value) = get("WEBSEARCH_WAYBACK") {
+            self.prefetch.wayback = parse_bool("WEBSEARCH_WAYBACK", &value)?;
+            overridden.push(("prefetch.wayback", "WEBSEARCH_WAYBACK"));
+        }
+        if let Some(value) = get("WEBSEARCH_CACHE_MB") {
+            self.cache.max_size_mb = parse_number("WEBSEARCH_CACHE_MB", &value)?;
+            overridden.push(("cache.max_size_mb", "WEBSEARCH_CACHE_MB"));
+        }
+        if let Some(proxy) = get("WEBSEARCH_PROXY") {
+            self.network.proxy = Some(proxy);
+            overridden.push(("network.proxy", "WEBSEARCH_PROXY"));
+        }
+        if let Some(user_agent) = get("WEBSEARCH_USER_AGENT") {
+            self.network.user_agent = Some(user_agent);
+            overridden.push(("network.user_agent", "WEBSEARCH_USER_AGENT"));
+        }
+        if let Some(value) = get("WEBSEARCH_PERSIST_COOKIES") {
+            self.network.persist_cookies = parse_bool("WEBSEARCH_PERSIST_COOKIES", &value)?;
+            overridden.push(("network.persist_cookies", "WEBSEARCH_PERSIST_COOKIES"));
+        }
+        if let Some(value) = get("WEBSEARCH_OFFLINE") {
+            self.network.offline = parse_bool("WEBSEARCH_OFFLINE", &value)?;
+            overridden.push(("network.offline", "WEBSEARCH_OFFLINE"));
+        }
+        if let Some(dir) = get("WEBSEARCH_DATA_DIR") {
+            self.paths.data_dir = Some(PathBuf::from(dir));
+            overridden.push(("paths.data_dir", "WEBSEARCH_DATA_DIR"));
+        }
+        // Unlike the others these only fill in what the file leaves out
+        if self.editor.command.is_none()
+            && let Some((var, command)) = ["VISUAL", "EDITOR"]
+                .into_iter()
+                .find_map(|var| get(var).map(|command| (var, command)))
+        {
+            self.editor.command = Some(command);
+            overridden.push(("editor.command", var));
+        }
+        if self.editor.nvim_server.is_none()
+            && let Some(server) = get("NVIM")
+        {
+            self.editor.nvim_server = Some(server);
+            overridden.push(("editor.nvim_server", "NVIM"));
+        }
+        if self.browser.command.is_none()
+            && let Some(command) = get("BROWSER")
+        {
+            self.browser.command = Some(command);
+            overridden.push(("browser.command", "BROWSER"));
+        }
+
+        Ok(overridden)
+    }
+
+    /// Out-of-range settings as `(key, problem)`
+    fn problems(&self) -> Vec<(String, String)> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, key: &str, problem: String| {
+            if !ok {
+                problems.push((key.to_string(), problem));
+            }
+        };
+
+        check(
+            (1..=MAX_RESULTS_LIMIT).contains(&self.search.max_results),
+            "search.max_results",
+            format!("must be between 1 and {}", MAX_RESULTS_LIMIT),
+        );
+        check(
+            (1..=64).contains(&self.prefetch.concurrency),
+            "prefetch.concurrency",
+            "must be between 1 and 64".to_string(),
+        );
+        check(
+            (1..=120).contains(&self.prefetch.timeout_secs),
+            "prefetch.timeout_secs",
+            "must be between 1 and 120".to_string(),
+        );
+        check(
+            self.prefetch.max_page_mb > 0,
+            "prefetch.max_page_mb",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.cache.max_age_days > 0,
+            "cache.max_age_days",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.cache.max_size_mb > 0,
+            "cache.max_size_mb",
+            "must be at least 1".to_string(),
+        );
+        if let Some(proxy) = &self.network.proxy {
+            check(
+                url::Url::parse(proxy).is_ok(),
+                "network.proxy",
+                format!("is not a valid URL: {}", proxy),
+            );
+        }
+        for (key, command) in [
+            ("editor.command", &self.editor.command),
+            ("browser.command", &self.browser.command),
+            ("hooks.post_fetch_hook", &self.hooks.post_fetch_hook),
+            ("hooks.on_open", &self.hooks.on_open),
+        ] {
+            if let Some(command) = command {
+                match shell_words::split(command) {
+                    Ok(argv) => check(!argv.is_empty(), key, "must not be empty".to_string()),
+                    Err(e) => check(false, key, format!("is not a valid command: {}", e)),
+                }
+            }
+        }
+        check(
+            self.ui.status_timeout_secs > 0,
+            "ui.status_timeout_secs",
+            "must be at least 1".to_string(),
+        );
+        for (name, id) in &self.search.goggles {
+            check(
+                !id.trim().is_empty(),
+                &format!("search.goggles.{}", name),
+                "must not be empty".to_string(),
+            );
+        }
+        for (label, command) in &self.open_with {
+            check(
+                !command.trim().is_empty(),
+                &format!("open_with.{}", label),
+                "must not be empty".to_string(),
+            );
+        }
+        check(
+            self.hooks.timeout_secs > 0,
+            "hooks.timeout_secs",
+            "must be at least 1".to_string(),
+        );
+
+        problems
+    }
+}
+
+fn parse_number(var: &str, value: &str) -> Result<u64> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid {}: expected a number, got {:?}", var, value))
+}
+
+/// `1`/`true`/`yes`/`on` or `0`/`false`/`no`/`off`, case-insensitive
+fn parse_bool(var: &str, value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => anyhow::bail!("Invalid {}: expected true or false, got {:?}", var, value),
+    }
+}
+
+/// 1-based line of `table.field = ...` in a TOML source
+fn line_of(source: &str, key: &str) -> Option<usize> {
+    let (table, field) = key.split_once('.')?;
+    let mut current = "";
+
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = header.trim();
+        } else if current == table
+            && line
+                .strip_prefix(field)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+        {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// `source` with `key` (`table.field`) set to `value`, see [`Config::save_value`]
+fn set_value(source: &str, key: &str, value: &str) -> String {
+    let (table, field) = key.split_once('.').unwrap_or(("", key));
+    let assignment = format!("{} = {}", field, value);
+    let mut lines: Vec<String> = source.lines().map(String::from).collect();
+
+    match line_of(source, key) {
+        Some(line) => {
+            // Keep a comment after the old value
+            let old = &lines[line - 1];
+            let comment = old
+                .find(" #")
+                .map(|at| old[old[..at].trim_end().len()..].to_string())
+                .unwrap_or_default();
+            lines[line - 1] = format!("{}{}", assignment, comment);
+        }
+        None => {
+            let header = format!("[{}]", table);
+            match lines.iter().position(|line| line.trim() == header) {
+                Some(at) => lines.insert(at + 1, assignment),
+                None => {
+                    if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                        lines.push(String::new());
+                    }
+                    lines.push(header);
+                    lines.push(assignment);
+                }
+            }
+        }
+    }
+    let mut source = lines.join("\n");
+    source.push('\n');
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        let vars = vars.to_vec();
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    fn load(source: &str, vars: &[(&'static str, &'static str)]) -> Result<Config> {
+        Config::from_sources(Some((Path::new("config.toml"), source)), env(vars))
+    }
+
+    #[test]
+    fn test_missing_file_means_defaults() {
+        let config = Config::from_sources(None, env(&[])).unwrap();
+        assert_eq!(config.search.default_engine, Engine::Brave);
+        assert_eq!(config.search.max_results, 10);
+        assert_eq!(config.prefetch.concurrency, 12);
+        assert_eq!(config.prefetch.timeout(), Duration::from_secs(8));
+        assert_eq!(config.cache.max_size_bytes(), 200 * 1024 * 1024);
+        assert_eq!(config.editor.argv(), ["nvim"]);
+        assert!(config.prefetch.wayback);
+        assert!(!config.network.persist_cookies);
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let source = "[search]\ndefault_engine = \"startpage\"\nbrave_api_key = \"from-file\"\n\n[cache]\nmax_size_mb = 50\n\n[prefetch]\nwayback = true\n";
+
+        let config = load(source, &[]).unwrap();
+        assert_eq!(config.search.default_engine, Engine::Startpage);
+        assert_eq!(config.search.brave_api_key.as_deref(), Some("from-file"));
+        assert_eq!(config.cache.max_size_mb, 50);
+
+        let config = load(
+            source,
+            &[
+                ("BRAVE_SEARCH_API_KEY", "from-env"),
+                ("WEBSEARCH_CACHE_MB", " 300 "),
+                ("WEBSEARCH_WAYBACK", "Off"),
+                ("WEBSEARCH_PROXY", ""),
+                ("WEBSEARCH_OFFLINE", "yes"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.search.default_engine, Engine::Startpage);
+        assert_eq!(config.search.brave_api_key.as_deref(), Some("from-env"));
+        assert_eq!(config.cache.max_size_mb, 300);
+        assert!(!config.prefetch.wayback);
+        assert_eq!(config.network.proxy, None);
+        assert!(config.network.offline);
+    }
+
+    #[test]
+    fn test_validation_points_at_the_line() {
+        let error = load("[search]\nmax_results = 5\n\n[prefetch]\nconcurrency = 0\n", &[])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "config.toml:5: prefetch.concurrency must be between 1 and 64");
+
+        let error = load("[ui]\nstatus_timeout_secs = \"soon\"\n", &[])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("line 2"), "{}", error);
+
+        let error = load("[search]\nengine = \"brave\"\n", &[]).unwrap_err().to_string();
+        assert!(error.contains("line 2") && error.contains("engine"), "{}", error);
+
+        let error = load("[search]\ndefault_engine = \"bing\"\n", &[])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("bing"), "{}", error);
+
+        let error = load("[ui]\nstatus_timeout_secs = 3\n\n[keys]\nquit = \"ctrl+c\"\nretry = \"y\"\n", &[])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "config.toml:6: keys.retry y conflicts with copy_url (y)");
+
+        let error = load("[theme]\npreset = \"light\"\nurl = \"bleu\"\n", &[])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "config.toml:3: theme.url is not a color: \"bleu\"");
+    }
+
+    #[test]
+    fn test_editor_from_config_visual_or_editor() {
+        let vars = [("VISUAL", "code --wait"), ("EDITOR", "vi")];
+        let editor = |source, vars| load(source, vars).unwrap().editor;
+
+        assert_eq!(editor("", &vars[1..]).argv(), ["vi"]);
+        assert_eq!(editor("", &vars).argv(), ["code", "--wait"]);
+        let configured = editor("[editor]\ncommand = \"hx '-c' 'my config.toml'\"\n", &vars);
+        assert_eq!(configured.argv(), ["hx", "-c", "my config.toml"]);
+        assert_eq!(configured.name(), "hx");
+        assert!(!configured.is_vim());
+        assert!(editor("", &[("EDITOR", "/usr/bin/nvim -R")]).is_vim());
+
+        let server = |source, vars| editor(source, vars).nvim_server;
+        assert_eq!(server("", &[]), None);
+        assert_eq!(server("", &[("NVIM", "/run/nvim.1")]).as_deref(), Some("/run/nvim.1"));
+        let configured = "[editor]\nnvim_server = \"/tmp/nvim.sock\"\n";
+        assert_eq!(
+            server(configured, &[("NVIM", "/run/nvim.1")]).as_deref(),
+            Some("/tmp/nvim.sock")
+        );
+
+        let error = load("", &[("EDITOR", "vim 'unclosed")]).unwrap_err().to_string();
+        assert!(error.starts_with("Invalid EDITOR: is not a valid command"), "{}", error);
+    }
+
+    #[test]
+    fn test_browser_from_config_or_browser_var() {
+        let browser = |source, vars| load(source, vars).unwrap().browser;
+        let url = "https://example.com/?q=a b";
+
+        assert_eq!(browser("", &[]).argv(url), system_opener(url));
+        assert_eq!(browser("", &[("BROWSER", "w3m")]).argv(url), ["w3m", url]);
+        let configured = "[browser]\ncommand = \"firefox -P research\"\n";
+        assert_eq!(
+            browser(configured, &[("BROWSER", "w3m")]).argv(url),
+            ["firefox", "-P", "research", url]
+        );
+        let placeholder = "[browser]\ncommand = \"chromium '--app=%s' --new-window\"\n";
+        assert_eq!(
+            browser(placeholder, &[]).argv(url),
+            ["chromium", &format!("--app={}", url), "--new-window"]
+        );
+
+        let error = load("[browser]\ncommand = \"\"\n", &[]).unwrap_err().to_string();
+        assert_eq!(error, "config.toml:2: browser.command must not be empty");
+    }
+
+    #[test]
+    fn test_invalid_env_values_name_the_variable() {
+        let error = load("", &[("WEBSEARCH_CACHE_MB", "lots")]).unwrap_err().to_string();
+        assert_eq!(error, "Invalid WEBSEARCH_CACHE_MB: expected a number, got \"lots\"");
+
+        let error = load("[cache]\nmax_size_mb = 10\n", &[("WEBSEARCH_CACHE_MB", "0")])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Invalid WEBSEARCH_CACHE_MB: must be at least 1");
+
+        assert!(load("", &[("WEBSEARCH_PERSIST_COOKIES", "maybe")]).is_err());
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        assert!(parse_bool("X", "1").unwrap());
+        assert!(parse_bool("X", "YES").unwrap());
+        assert!(!parse_bool("X", "0").unwrap());
+        assert!(!parse_bool("X", "off").unwrap());
+        assert!(!parse_bool("X", "false").unwrap());
+        assert!(parse_bool("X", "2").is_err());
+    }
+
+    #[test]
+    fn test_set_value_keeps_the_rest_of_the_file() {
+        let source = "# My settings\n[ui]\nstatus_timeout_secs = 3\ndensity = \"full\"  # z cycles\n\n\
+                      [cache]\nmax_age_days = 2\n";
+        let saved = set_value(source, "ui.density", "\"compact\"");
+        assert_eq!(saved, source.replace("\"full\"", "\"compact\""));
+        assert_eq!(load(&saved, &[]).unwrap().ui.density, Density::Compact);
+
+        // Added to an existing table, or in a new one at the end
+        let saved = set_value("[ui]\nstatus_timeout_secs = 3\n", "ui.density", "\"minimal\"");
+        assert_eq!(saved, "[ui]\ndensity = \"minimal\"\nstatus_timeout_secs = 3\n");
+        let saved = set_value("[cache]\nmax_age_days = 2", "ui.density", "\"minimal\"");
+        assert_eq!(saved, "[cache]\nmax_age_days = 2\n\n[ui]\ndensity = \"minimal\"\n");
+        assert_eq!(set_value("", "ui.density", "\"full\""), "[ui]\ndensity = \"full\"\n");
+
+        let cycle = std::iter::successors(Some(Density::default()), |d| Some(d.next()));
+        let names: Vec<_> = cycle.skip(1).take(3).map(Density::name).collect();
+        assert_eq!(names, ["compact", "minimal", "full"]);
+    }
+}
@@ -0,0 +1,144 @@
+//! Engines resting after rate limiting
+//!
+//! When an engine rate-limits a search ([`RateLimited`]) it rests for the
+//! wait it asked for in `Retry-After`, or else for a backoff that starts at
+//! 30 seconds and doubles with each limit in a row, up to 10 minutes.
+//! Searches with a resting engine are refused, and its key counts down in
+//! the help bar. A successful search ends the backoff.
+//!
+//! [`RateLimited`]: crate::search::RateLimited
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::Engine;
+
+/// First wait without a `Retry-After`
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Longest wait without a `Retry-After`
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+/// Longest `Retry-After` honoured
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Resting engines and their backoff
+#[derive(Debug, Default)]
+pub struct Cooldowns {
+    engines: HashMap<Engine, Cooldown>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cooldown {
+    until: Instant,
+    /// Rate limits since the last successful search
+    strikes: u32,
+}
+
+impl Cooldowns {
+    /// Rest `engine` after a rate limit at `now`; returns the wait
+    pub fn limit(
+        &mut self,
+        engine: Engine,
+        retry_after: Option<Duration>,
+        now: Instant,
+    ) -> Duration {
+        let strikes = self.engines.get(&engine).map_or(0, |c| c.strikes) + 1;
+        let wait = match retry_after {
+            Some(wait) => wait.min(MAX_RETRY_AFTER),
+            None => BASE_BACKOFF
+                .saturating_mul(1 << (strikes - 1).min(10))
+                .min(MAX_BACKOFF),
+        };
+        self.engines.insert(
+            engine,
+            Cooldown {
+                until: now + wait,
+                strikes,
+            },
+        );
+        wait
+    }
+
+    /// End the backoff of `engine` after a successful search
+    pub fn succeed(&mut self, engine: Engine) {
+        self.engines.remove(&engine);
+    }
+
+    /// Time before `engine` may search again, `None` when it may now
+    pub fn remaining(&self, engine: Engine, now: Instant) -> Option<Duration> {
+        let left = self.engines.get(&engine)?.until.checked_duration_since(now)?;
+        (!left.is_zero()).then_some(left)
+    }
+
+    /// Resting engines with their time left, in [`Engine::ALL`] order
+    pub fn resting(&self, now: Instant) -> Vec<(Engine, Duration)> {
+        Engine::ALL
+            .into_iter()
+            .filter_map(|engine| Some((engine, self.remaining(engine, now)?)))
+            .collect()
+    }
+
+    /// When a countdown next shows another second, for redrawing
+    pub fn next_tick(&self, now: Instant) -> Option<Instant> {
+        self.engines
+            .values()
+            .filter(|cooldown| cooldown.until > now)
+            .map(|cooldown| cooldown.until - Duration::from_secs((cooldown.until - now).as_secs()))
+            .min()
+    }
+}
+
+/// Seconds shown for `left`, rounded up
+pub fn countdown_secs(left: Duration) -> u64 {
+    left.as_secs() + u64::from(left.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_until_success() {
+        let start = Instant::now();
+        let secs = Duration::from_secs;
+        let mut cooldowns = Cooldowns::default();
+        assert_eq!(cooldowns.remaining(Engine::Brave, start), None);
+
+        // Retry-After wins over the backoff, within reason
+        assert_eq!(cooldowns.limit(Engine::Brave, Some(secs(42)), start), secs(42));
+        assert_eq!(cooldowns.remaining(Engine::Brave, start + secs(2)), Some(secs(40)));
+        assert_eq!(cooldowns.remaining(Engine::Brave, start + secs(42)), None);
+        assert_eq!(cooldowns.resting(start), [(Engine::Brave, secs(42))]);
+        assert_eq!(cooldowns.limit(Engine::Brave, Some(secs(86_400)), start), MAX_RETRY_AFTER);
+
+        // Without one, each limit in a row waits twice as long
+        let waits: Vec<Duration> = (0..7)
+            .map(|_| cooldowns.limit(Engine::DuckDuckGo, None, start))
+            .collect();
+        assert_eq!(
+            waits,
+            [secs(30), secs(60), secs(120), secs(240), secs(480), MAX_BACKOFF, MAX_BACKOFF]
+        );
+        cooldowns.succeed(Engine::DuckDuckGo);
+        assert_eq!(cooldowns.remaining(Engine::DuckDuckGo, start), None);
+        assert_eq!(cooldowns.limit(Engine::DuckDuckGo, None, start), secs(30));
+        assert_eq!(cooldowns.remaining(Engine::Startpage, start), None);
+    }
+
+    #[test]
+    fn test_countdown_ticks_each_second() {
+        let start = Instant::now();
+        let mut cooldowns = Cooldowns::default();
+        cooldowns.limit(Engine::Brave, Some(Duration::from_millis(2500)), start);
+
+        let now = start + Duration::from_millis(300);
+        let left = cooldowns.remaining(Engine::Brave, now).unwrap();
+        assert_eq!(countdown_secs(left), 3);
+        // 2.2s left shows "3s" until 2s are left
+        let tick = cooldowns.next_tick(now).unwrap();
+        assert_eq!(tick - now, Duration::from_millis(200));
+        assert_eq!(countdown_secs(cooldowns.remaining(Engine::Brave, tick).unwrap()), 2);
+        assert_eq!(cooldowns.next_tick(start + Duration::from_secs(3)), None);
+    }
+}
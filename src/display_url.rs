@@ -0,0 +1,185 @@
+//! Readable URLs for the result list
+//!
+//! Engines hand out internationalized hosts as punycode (`xn--…`) and
+//! non-ASCII paths percent-encoded, which is unreadable in the list.
+//! [`display_url`] decodes both for showing only; searching, fetching,
+//! the cache and copied URLs keep the URL as the engine sent it. A host
+//! label mixing scripts, like Cyrillic letters among Latin ones, could pass
+//! for another site, so its punycode form is shown beside it.
+
+use std::borrow::Cow;
+use url::{Position, Url};
+
+/// `url` with its host in Unicode and its path percent-decoded
+///
+/// URLs with nothing to decode come back unchanged.
+pub fn display_url(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let Some(host) = parsed.host_str() else {
+        return url.to_string();
+    };
+    if !is_punycode(host) && !parsed.path().contains('%') {
+        return url.to_string();
+    }
+    format!(
+        "{}{}{}{}{}",
+        &parsed[..Position::BeforeHost],
+        display_host(host),
+        &parsed[Position::AfterHost..Position::BeforePath],
+        display_path(parsed.path()),
+        &parsed[Position::AfterPath..]
+    )
+}
+
+/// `host` in Unicode, followed by its punycode when a label mixes scripts
+pub fn display_host(host: &str) -> String {
+    if !is_punycode(host) {
+        return host.to_string();
+    }
+    let (unicode, decoded) = idna::domain_to_unicode(host);
+    if decoded.is_err() {
+        return host.to_string();
+    }
+    if unicode.split('.').any(mixes_scripts) {
+        format!("{} ({})", unicode, host)
+    } else {
+        unicode
+    }
+}
+
+fn is_punycode(host: &str) -> bool {
+    host.split('.').any(|label| label.to_ascii_lowercase().starts_with("xn--"))
+}
+
+/// Each path segment percent-decoded, unless that would make it ambiguous
+///
+/// Segments that aren't UTF-8 once decoded, or would gain a `/`, a control
+/// character or an invisible format character stay encoded.
+fn display_path(path: &str) -> String {
+    let hidden = |c: char| c == '/' || c.is_control() || is_format(c);
+    path.split('/')
+        .map(|segment| match urlencoding::decode(segment) {
+            Ok(decoded) if !decoded.contains(hidden) => decoded,
+            _ => Cow::Borrowed(segment),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether `c` is a format character (Unicode category Cf)
+///
+/// These are invisible or reorder the text around them, like the
+/// right-to-left override U+202E or the zero-width space U+200B.
+fn is_format(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{0600}'..='\u{0605}'
+            | '\u{061C}'
+            | '\u{06DD}'
+            | '\u{070F}'
+            | '\u{0890}'..='\u{0891}'
+            | '\u{08E2}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{206F}'
+            | '\u{FEFF}'
+            | '\u{FFF9}'..='\u{FFFB}'
+            | '\u{110BD}'
+            | '\u{110CD}'
+            | '\u{13430}'..='\u{1343F}'
+            | '\u{1BCA0}'..='\u{1BCA3}'
+            | '\u{1D173}'..='\u{1D17A}'
+            | '\u{E0001}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// Writing systems told apart in host labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Hangul,
+    /// Han and kana, which Japanese writes together
+    Cjk,
+    Other,
+}
+
+/// Script of a letter; digits and `-` belong to every script
+fn script(c: char) -> Option<Script> {
+    Some(match c {
+        '0'..='9' | '-' => return None,
+        'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' => Script::Latin,
+        '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+        '\u{0400}'..='\u{052F}' => Script::Cyrillic,
+        '\u{0530}'..='\u{058F}' => Script::Armenian,
+        '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+        '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}' => Script::Arabic,
+        '\u{0900}'..='\u{097F}' => Script::Devanagari,
+        '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+        '\u{1100}'..='\u{11FF}' | '\u{AC00}'..='\u{D7AF}' => Script::Hangul,
+        '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' => Script::Cjk,
+        _ => Script::Other,
+    })
+}
+
+/// Whether a host label has letters of more than one script
+fn mixes_scripts(label: &str) -> bool {
+    let mut scripts = label.chars().filter_map(script);
+    let Some(first) = scripts.next() else {
+        return false;
+    };
+    scripts.any(|script| script != first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_url_decodes_hosts_and_paths() {
+        let cases = [
+            // Plain URLs are left exactly as they were
+            ("https://example.com", "https://example.com"),
+            ("https://docs.rs/tokio/latest/tokio/?search=a%20b", "https://docs.rs/tokio/latest/tokio/?search=a%20b"),
+            ("https://xn--mnchen-3ya.de/stadt", "https://münchen.de/stadt"),
+            ("https://xn--e1afmkfd.xn--p1ai/", "https://пример.рф/"),
+            ("https://xn--r8jz45g.jp:8443/", "https://例え.jp:8443/"),
+            (
+                "https://ru.wikipedia.org/wiki/%D0%9C%D0%BE%D1%81%D0%BA%D0%B2%D0%B0#%D0%98",
+                "https://ru.wikipedia.org/wiki/Москва#%D0%98",
+            ),
+            // Encoded slashes, control characters and broken UTF-8 stay encoded
+            ("https://example.com/a%2Fb/%0A/%FF/caf%C3%A9", "https://example.com/a%2Fb/%0A/%FF/café"),
+            // So do a right-to-left override and a zero-width space
+            ("https://example.com/%E2%80%AEfdp.exe/a%E2%80%8Bb", "https://example.com/%E2%80%AEfdp.exe/a%E2%80%8Bb"),
+            // Cyrillic "а" and "е" among Latin letters: shown with the punycode
+            ("https://xn--pple-43d.com/login", "https://аpple.com (xn--pple-43d.com)/login"),
+        ];
+        for (url, shown) in cases {
+            assert_eq!(display_url(url), shown, "{}", url);
+        }
+        assert_eq!(display_url("not a url"), "not a url");
+    }
+
+    #[test]
+    fn test_mixed_scripts_per_label() {
+        assert!(!mixes_scripts("münchen"));
+        assert!(!mixes_scripts("пример-2024"));
+        assert!(!mixes_scripts("例え"));
+        assert!(mixes_scripts("pаypal"));
+        assert_eq!(display_host("xn--e1afmkfd.com"), "пример.com");
+        assert_eq!(display_host("example.com"), "example.com");
+    }
+}
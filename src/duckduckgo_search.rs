@@ -6,39 +6,91 @@
 //! - Privacy-focused (no tracking, no personalization)
 //!
 //! This approach uses the existing HTTP client for optimal performance.
+//! The first page is a GET; later pages are requested the way the "Next"
+//! button does, by POSTing the form with the result offset. The region in
+//! `search.region` goes along as `kl`.
+//! Clients sending too many queries get an anomaly page with a CAPTCHA
+//! instead of results, which is reported as rate limiting.
 
 use anyhow::{Context, Result};
-use scraper::{Html, Selector};
+use futures::future::BoxFuture;
+use html_escape::decode_html_entities;
+use scraper::{ElementRef, Html, Selector};
 
-use crate::globals::get_http_client;
-use crate::search::SearchResult;
+use crate::config::Engine;
+use crate::globals::get_scrape_client;
+use crate::search::{RateLimited, SearchProvider, SearchResult};
 
 /// Maximum number of search results to fetch
 pub const MAX_RESULTS: usize = 10;
 
+/// Error for DuckDuckGo's bot challenge
+const RATE_LIMITED: &str =
+    "DuckDuckGo is rate-limiting this client — try again in a minute or use another engine";
+
+/// Address of the HTML interface
+const SEARCH_URL: &str = "https://html.duckduckgo.com/html/";
+
+/// DuckDuckGo's HTML interface as a [`SearchProvider`]
+pub struct DuckDuckGo {
+    /// Region sent as `kl`, like `de-de`; DuckDuckGo guesses one when `None`
+    pub region: Option<String>,
+}
+
+impl SearchProvider for DuckDuckGo {
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+        Box::pin(duckduckgo_search(query, 0, self.region.as_deref()))
+    }
+
+    fn search_page<'a>(
+        &'a self,
+        query: &'a str,
+        page: usize,
+    ) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+        Box::pin(duckduckgo_search(query, page, self.region.as_deref()))
+    }
+
+    fn name(&self) -> &'static str {
+        Engine::DuckDuckGo.name()
+    }
+}
+
 /// Perform search using DuckDuckGo HTML interface
 ///
 /// Uses the html.duckduckgo.com static interface which is:
 /// - Fast and lightweight (no JavaScript)
 /// - Scraping-friendly
-/// - Returns up to MAX_RESULTS results
-pub async fn duckduckgo_search(query: &str) -> Result<Vec<SearchResult>> {
-    let client = get_http_client();
+/// - Returns up to MAX_RESULTS results per page, `page` counting from 0
+pub async fn duckduckgo_search(
+    query: &str,
+    page: usize,
+    region: Option<&str>,
+) -> Result<Vec<SearchResult>> {
+    let client = get_scrape_client()?;
 
     // Use DuckDuckGo's HTML-only interface
-    let url = format!(
-        "https://html.duckduckgo.com/html/?q={}",
-        urlencoding::encode(query)
-    );
-
-    let response = client
-        .get(&url)
+    let request = if page == 0 {
+        let mut url = format!("{}?q={}", SEARCH_URL, urlencoding::encode(query));
+        if let Some(region) = region {
+            url.push_str(&format!("&kl={}", urlencoding::encode(region)));
+        }
+        tracing::debug!(engine = "duckduckgo", %url, "GET");
+        client.get(url)
+    } else {
+        tracing::debug!(engine = "duckduckgo", page, "POST");
+        client.post(SEARCH_URL).form(&page_form(query, page, region))
+    };
+    let response = request
         .header("Accept", "text/html")
         .header("Accept-Language", "en-US,en;q=0.9")
         .send()
         .await
         .context("Failed to send search request to DuckDuckGo")?;
 
+    tracing::debug!(engine = "duckduckgo", status = %response.status(), "response");
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        anyhow::bail!(RateLimited::from_response(RATE_LIMITED, &response));
+    }
     if !response.status().is_success() {
         anyhow::bail!("DuckDuckGo returned status: {}", response.status());
     }
@@ -47,17 +99,36 @@ pub async fn duckduckgo_search(query: &str) -> Result<Vec<SearchResult>> {
         .text()
         .await
         .context("Failed to read DuckDuckGo response")?;
+    tracing::debug!(engine = "duckduckgo", bytes = html.len(), "body read");
 
     parse_duckduckgo_html(&html)
 }
 
+/// Fields of the form behind the "Next" button, for results from `page` on
+fn page_form(query: &str, page: usize, region: Option<&str>) -> Vec<(&'static str, String)> {
+    let offset = page * MAX_RESULTS;
+    let mut form = vec![
+        ("q", query.to_string()),
+        ("s", offset.to_string()),
+        ("dc", (offset + 1).to_string()),
+        ("v", "l".to_string()),
+        ("o", "json".to_string()),
+        ("api", "d.js".to_string()),
+    ];
+    if let Some(region) = region {
+        form.push(("kl", region.to_string()));
+    }
+    form
+}
+
 /// Parse DuckDuckGo HTML results page
 ///
 /// Extracts title, URL, and description from search results.
 /// DuckDuckGo's HTML structure uses:
-/// - Results are in <div class="result">
+/// - Results are in <div class="result">, ads also have `result--ad`
 /// - Title and URL are in <a class="result__a">
-/// - Description is in <a class="result__snippet">
+/// - Description is in <a class="result__snippet">, or a `div` on later
+///   pages, whose links can also be absolute `https://duckduckgo.com/l/`
 fn parse_duckduckgo_html(html: &str) -> Result<Vec<SearchResult>> {
     let document = Html::parse_document(html);
 
@@ -73,7 +144,13 @@ fn parse_duckduckgo_html(html: &str) -> Result<Vec<SearchResult>> {
 
     let mut results = Vec::new();
 
-    for result_elem in document.select(&result_selector).take(MAX_RESULTS) {
+    let organic = document
+        .select(&result_selector)
+        .filter(|elem| !elem.value().classes().any(|class| class == "result--ad"));
+    for result_elem in organic {
+        if results.len() == MAX_RESULTS {
+            break;
+        }
         // Extract title and URL from the title link
         let title_elem = match result_elem.select(&title_selector).next() {
             Some(elem) => elem,
@@ -81,12 +158,7 @@ fn parse_duckduckgo_html(html: &str) -> Result<Vec<SearchResult>> {
         };
 
         // Get title text
-        let title = title_elem
-            .text()
-            .collect::<Vec<_>>()
-            .join(" ")
-            .trim()
-            .to_string();
+        let title = element_text(title_elem);
 
         if title.is_empty() {
             continue;
@@ -99,6 +171,10 @@ fn parse_duckduckgo_html(html: &str) -> Result<Vec<SearchResult>> {
         };
 
         // DuckDuckGo uses redirect URLs like //duckduckgo.com/l/?uddg=<encoded_url>&rut=...
+        let href = href
+            .strip_prefix("https:")
+            .filter(|rest| rest.starts_with("//duckduckgo.com/l/?"))
+            .unwrap_or(href);
         let url = if href.starts_with("//duckduckgo.com/l/?") || href.starts_with("/l/?") {
             // Extract the uddg parameter
             if let Some(uddg_start) = href.find("uddg=") {
@@ -130,29 +206,50 @@ fn parse_duckduckgo_html(html: &str) -> Result<Vec<SearchResult>> {
         let description = result_elem
             .select(&snippet_selector)
             .next()
-            .map(|elem| {
-                elem.text()
-                    .collect::<Vec<_>>()
-                    .join(" ")
-                    .trim()
-                    .to_string()
-            })
+            .map(element_text)
             .unwrap_or_else(|| String::from("No description"));
 
         results.push(SearchResult {
             title,
             url,
             description,
+            ..Default::default()
         });
     }
 
     if results.is_empty() {
+        if is_bot_challenge(&document) {
+            anyhow::bail!(RateLimited::new(RATE_LIMITED));
+        }
         anyhow::bail!("No results found or failed to parse DuckDuckGo HTML. The page structure may have changed.");
     }
 
     Ok(results)
 }
 
+/// Text of a result link or snippet, on one line
+///
+/// DuckDuckGo escapes some titles and snippets twice, so entities like
+/// `&amp;#x27;` are still there after parsing and get decoded again.
+fn element_text(element: ElementRef) -> String {
+    let text = element.text().collect::<String>();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    decode_html_entities(&text).into_owned()
+}
+
+/// Whether a page without results is DuckDuckGo's anomaly page
+///
+/// It shows a CAPTCHA in an `anomaly-modal` and posts the answer back to
+/// `/html/`; a real "no results" page has its `no-results` notice instead.
+fn is_bot_challenge(document: &Html) -> bool {
+    let matches = |selector: &str| {
+        Selector::parse(selector)
+            .is_ok_and(|selector| document.select(&selector).next().is_some())
+    };
+    matches("[class*='anomaly-modal'], #anomaly-modal")
+        || (matches("form[action*='/html/']") && !matches(".no-results"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,6 +274,87 @@ mod tests {
         assert_eq!(results[1].url, "https://test.com");
     }
 
+    #[test]
+    fn test_parse_continuation_page() {
+        let page_two = r#"
+            <div class="serp__results"><div id="links" class="results">
+                <div class="result results_links results_links_deep result--ad">
+                    <a class="result__a" href="https://duckduckgo.com/y.js?ad_domain=shop.example">Buy now</a>
+                </div>
+                <div class="result results_links results_links_deep web-result ">
+                    <h2 class="result__title">
+                        <a rel="nofollow" class="result__a" href="https://duckduckgo.com/l/?uddg=https%3A%2F%2Fdocs.rs%2Ftokio&amp;rut=abc">tokio - Rust</a>
+                    </h2>
+                    <div class="result__snippet">An event-driven, <b>non-blocking</b> I/O platform.</div>
+                </div>
+                <div class="result results_links results_links_deep web-result ">
+                    <a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Ftokio.rs%2F">Tokio</a>
+                </div>
+                <div class="nav-link">
+                    <form action="/html/" method="post">
+                        <input type="submit" class="btn btn--alt" value="Next">
+                        <input type="hidden" name="s" value="20">
+                    </form>
+                </div>
+            </div></div>
+        "#;
+
+        let results = parse_duckduckgo_html(page_two).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "tokio - Rust");
+        assert_eq!(results[0].url, "https://docs.rs/tokio");
+        assert_eq!(results[0].description, "An event-driven, non-blocking I/O platform.");
+        assert_eq!(results[1].url, "https://tokio.rs/");
+        assert_eq!(results[1].description, "No description");
+
+        let form = page_form("tokio", 2, Some("de-de"));
+        assert!(form.contains(&("s", "20".to_string())));
+        assert!(form.contains(&("dc", "21".to_string())));
+        assert!(form.contains(&("kl", "de-de".to_string())));
+        assert!(!page_form("tokio", 1, None).iter().any(|(name, _)| *name == "kl"));
+    }
+
+    #[test]
+    fn test_entities_are_decoded() {
+        let sample_html = r#"
+            <div class="result">
+                <a class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fexample.com%2Fq%3Fa%3D1%26b%3D2&rut=1">Tom &amp; Jerry&#x27;s &quot;Best&quot; Episodes</a>
+                <a class="result__snippet">Rock &amp;amp; roll &amp;#x27;n&amp;#x27; &lt;b&gt;blues&lt;/b&gt;</a>
+            </div>
+        "#;
+
+        let results = parse_duckduckgo_html(sample_html).unwrap();
+        assert_eq!(results[0].title, "Tom & Jerry's \"Best\" Episodes");
+        assert_eq!(results[0].url, "https://example.com/q?a=1&b=2");
+        assert_eq!(results[0].description, "Rock & roll 'n' <b>blues</b>");
+    }
+
+    #[test]
+    fn test_bot_challenge_page() {
+        let challenge = r#"
+            <html><body>
+                <form id="challenge-form" action="//duckduckgo.com/anomaly.js?sv=html&cc=botnet" method="POST">
+                    <div class="anomaly-modal__mask">
+                        <div class="anomaly-modal__title">Unfortunately, bots use DuckDuckGo too.</div>
+                        <div class="anomaly-modal__description">Please complete the following challenge.</div>
+                    </div>
+                </form>
+            </body></html>
+        "#;
+        let err = parse_duckduckgo_html(challenge).unwrap_err();
+        assert_eq!(err.to_string(), RATE_LIMITED);
+        assert!(crate::search::rate_limited(&err).is_some());
+
+        let no_results = r#"
+            <html><body>
+                <form id="search_form" action="/html/" method="post"><input name="q"></form>
+                <div class="no-results">No results.</div>
+            </body></html>
+        "#;
+        let err = parse_duckduckgo_html(no_results).unwrap_err();
+        assert!(err.to_string().starts_with("No results found"));
+    }
+
     #[tokio::test]
     async fn test_max_results_constant() {
         assert_eq!(MAX_RESULTS, 10);
@@ -0,0 +1,96 @@
+//! How each search engine fared this session, for the engine status panel
+//!
+//! Every search and every further page of results reports its engine, how
+//! long it took and its outcome. `Ctrl+S` shows the latest of each next to
+//! whether the engine has the API key it needs, which tells a dead network
+//! from a missing key or a blocking engine.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::Engine;
+
+/// The latest use of one engine
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EngineStats {
+    /// Unix timestamp (seconds) of the latest search
+    pub last_used: Option<u64>,
+    /// Results of the latest search, `None` when it failed
+    pub last_results: Option<usize>,
+    /// Message of the latest failure, kept after later successes
+    pub last_error: Option<String>,
+    /// How long the latest search took
+    pub latency: Option<Duration>,
+    pub searches: u32,
+    pub failures: u32,
+}
+
+impl EngineStats {
+    /// Whether the latest search failed
+    pub fn failed(&self) -> bool {
+        self.last_used.is_some() && self.last_results.is_none()
+    }
+}
+
+/// [`EngineStats`] of every engine used so far
+#[derive(Debug, Default)]
+pub struct EngineStatsTable {
+    engines: HashMap<Engine, EngineStats>,
+}
+
+impl EngineStatsTable {
+    /// Record a search with `engine` at `now`, with its result count or error
+    pub fn record(
+        &mut self,
+        engine: Engine,
+        latency: Duration,
+        outcome: Result<usize, String>,
+        now: u64,
+    ) {
+        let stats = self.engines.entry(engine).or_default();
+        stats.last_used = Some(now);
+        stats.latency = Some(latency);
+        stats.searches += 1;
+        match outcome {
+            Ok(count) => stats.last_results = Some(count),
+            Err(error) => {
+                stats.last_results = None;
+                stats.last_error = Some(error);
+                stats.failures += 1;
+            }
+        }
+    }
+
+    /// Stats of `engine`, `None` when it hasn't been used
+    pub fn get(&self, engine: Engine) -> Option<&EngineStats> {
+        self.engines.get(&engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_outcomes() {
+        let mut table = EngineStatsTable::default();
+        assert_eq!(table.get(Engine::Brave), None);
+
+        let ms = Duration::from_millis;
+        table.record(Engine::DuckDuckGo, ms(800), Err("rate limited".to_string()), 100);
+        let stats = table.get(Engine::DuckDuckGo).unwrap();
+        assert!(stats.failed());
+        assert_eq!(stats.last_error.as_deref(), Some("rate limited"));
+
+        table.record(Engine::DuckDuckGo, ms(350), Ok(10), 160);
+        let stats = table.get(Engine::DuckDuckGo).unwrap();
+        assert!(!stats.failed());
+        assert_eq!(stats.last_used, Some(160));
+        assert_eq!(stats.last_results, Some(10));
+        assert_eq!(stats.latency, Some(ms(350)));
+        // The error stays visible after the engine recovers
+        assert_eq!(stats.last_error.as_deref(), Some("rate limited"));
+        assert_eq!((stats.searches, stats.failures), (2, 1));
+        assert_eq!(table.get(Engine::Brave), None);
+    }
+}
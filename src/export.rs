@@ -0,0 +1,420 @@
+//! Exports of a result set
+//!
+//! `e` in the results list writes a Markdown digest to
+//! `exports/<query>-<date>.md` in the data directory (or
+//! `paths.export_dir`): the query, when and where it was searched, and a
+//! numbered list of results. Pages on disk get a link to their local copy,
+//! relative to the digest.
+//!
+//! `E` writes the same results as `<query>-<date>.json` for scripts, with
+//! the metadata of each prefetched page; see [`Digest::to_json`] for the
+//! schema.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Engine;
+use crate::extract_clean_md::PageMetadata;
+use crate::prefetch::{sanitize_filename, PrefetchStatus};
+use crate::search::SearchResult;
+
+/// Longest query slug in an export file name
+const MAX_SLUG_LEN: usize = 50;
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Results to export, with the prefetch status of each page if known
+pub struct Digest<'a> {
+    pub query: &'a str,
+    pub engine: Engine,
+    pub entries: Vec<(&'a SearchResult, Option<&'a PrefetchStatus>)>,
+}
+
+/// The JSON export, see [`Digest::to_json`]
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    query: &'a str,
+    engine: Engine,
+    results: Vec<JsonResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonResult<'a> {
+    #[serde(flatten)]
+    result: &'a SearchResult,
+    status: &'static str,
+    path: Option<&'a Path>,
+    page: Option<PageMetadata>,
+}
+
+impl Digest<'_> {
+    /// Write the export to a new file in `dir`
+    ///
+    /// Returns the path written; an existing export is never overwritten.
+    pub fn write(&self, dir: &Path, format: ExportFormat) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let now = unix_now();
+        let (date, _) = format_utc(now);
+        let stem = format!("{}-{}", slug(self.query), date);
+        let path = unique_path(dir, &stem, format.extension());
+        let contents = match format {
+            ExportFormat::Markdown => self.to_markdown(dir, now),
+            ExportFormat::Json => self.to_json()?,
+        };
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// The results as pretty-printed JSON
+    ///
+    /// The schema is stable; fields are only ever added:
+    ///
+    /// ```json
+    /// {
+    ///   "query": "rust async",
+    ///   "engine": "duckduckgo",
+    ///   "results": [
+    ///     {
+    ///       "title": "Async Rust",
+    ///       "url": "https://example.com/async",
+    ///       "description": "Futures explained",
+    ///       "status": "ready",
+    ///       "path": "/home/me/.local/share/websearch-tui/current_search/3f9a1c20/example_com_1234abcd_Async_Rust.md",
+    ///       "page": {
+    ///         "title": "Async Rust",
+    ///         "byline": "Jane Doe",
+    ///         "excerpt": "A tour of futures",
+    ///         "site_name": "Example"
+    ///       }
+    ///     }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// `status` is `ready`, `archived`, `cached`, `pdf`, `pending`,
+    /// `loading`, `failed`, `blocked` or `timeout`. `path` is the local copy
+    /// and null until the page is on disk; `page` is null unless `path` is a
+    /// Markdown page, and its fields are null when the page had no such
+    /// metadata.
+    pub fn to_json(&self) -> Result<String> {
+        let results = self
+            .entries
+            .iter()
+            .map(|&(result, status)| {
+                let path = status.and_then(PrefetchStatus::path);
+                let page = match status {
+                    Some(PrefetchStatus::Binary(_)) => None,
+                    _ => path.and_then(PageMetadata::read),
+                };
+                JsonResult {
+                    result,
+                    status: status.map_or("pending", status_name),
+                    path,
+                    page,
+                }
+            })
+            .collect();
+        let export = JsonExport {
+            query: self.query,
+            engine: self.engine,
+            results,
+        };
+        serde_json::to_string_pretty(&export).context("Failed to serialize results")
+    }
+
+    /// The digest as Markdown, with local links relative to `dir`
+    fn to_markdown(&self, dir: &Path, now: u64) -> String {
+        let (date, time) = format_utc(now);
+        let mut markdown = format!(
+            "# {}\n\n- Searched: {} {} UTC\n- Engine: {}\n- Results: {}\n",
+            self.query,
+            date,
+            time,
+            self.engine.name(),
+            self.entries.len()
+        );
+
+        for (number, &(result, status)) in self.entries.iter().enumerate() {
+            let _ = write!(
+                markdown,
+                "\n{}. [{}](<{}>)\n",
+                number + 1,
+                escape_link_text(&result.title),
+                result.url
+            );
+            if !result.description.is_empty() {
+                let _ = writeln!(markdown, "   {}", result.description);
+            }
+            if let Some(local) = status.and_then(PrefetchStatus::path)
+                && !matches!(status, Some(PrefetchStatus::Binary(_)))
+            {
+                let link = relative_to(dir, local);
+                let name = local.file_name().unwrap_or_default().to_string_lossy();
+                let _ = writeln!(
+                    markdown,
+                    "   Local copy: [{}](<{}>)",
+                    escape_link_text(&name),
+                    link.display()
+                );
+            }
+        }
+        markdown
+    }
+}
+
+/// `status` value in the JSON export
+pub fn status_name(status: &PrefetchStatus) -> &'static str {
+    match status {
+        PrefetchStatus::Ready(_) => "ready",
+        PrefetchStatus::ReadyArchived(_) => "archived",
+        PrefetchStatus::Cached(_) => "cached",
+        PrefetchStatus::Binary(_) => "pdf",
+        PrefetchStatus::Pending => "pending",
+        PrefetchStatus::InProgress => "loading",
+        PrefetchStatus::Failed(_) => "failed",
+        PrefetchStatus::Blocked(_) => "blocked",
+        PrefetchStatus::Timeout => "timeout",
+    }
+}
+
+/// File name stem for a query, like `rust_async_runtime`
+fn slug(query: &str) -> String {
+    let slug: String = sanitize_filename(query).chars().take(MAX_SLUG_LEN).collect();
+    let slug = slug.trim_matches('_');
+    if slug.is_empty() {
+        "search".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// `dir/stem.ext`, or `dir/stem-2.ext` and so on if that is taken
+fn unique_path(dir: &Path, stem: &str, extension: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.{}", stem, extension));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.{}", stem, n, extension));
+        n += 1;
+    }
+    path
+}
+
+/// `path` relative to `dir`, or as given when they share no root
+pub fn relative_to(dir: &Path, path: &Path) -> PathBuf {
+    let dir: Vec<Component> = dir.components().collect();
+    let target: Vec<Component> = path.components().collect();
+    let common = dir.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return path.to_path_buf();
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in common..dir.len() {
+        relative.push("..");
+    }
+    relative.extend(&target[common..]);
+    relative
+}
+
+fn escape_link_text(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
+
+/// `YYYY-MM-DD` and `hh:mm` of a Unix timestamp, in UTC
+pub fn format_utc(secs: u64) -> (String, String) {
+    let days = (secs / 86_400) as i64;
+    let minutes = secs % 86_400 / 60;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!("{:02}:{:02}", minutes / 60, minutes % 60),
+    )
+}
+
+/// RFC 3339 timestamp of Unix time `secs`, like `2026-10-16T09:30:05Z`
+pub fn rfc3339(secs: u64) -> String {
+    let (date, time) = format_utc(secs);
+    format!("{}T{}:{:02}Z", date, time, secs % 60)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prefetch::Failure;
+
+    fn result(title: &str, url: &str, description: &str) -> SearchResult {
+        SearchResult {
+            title: title.to_string(),
+            url: url.to_string(),
+            description: description.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_digest() {
+        let results = [
+            result("Async [book]", "https://example.com/async", "Futures explained"),
+            result("Tokio", "https://tokio.rs/", ""),
+        ];
+        let local = PrefetchStatus::Cached(PathBuf::from(
+            "/data/websearch/active_tabs/example_com_1234abcd_Async.md",
+        ));
+        let digest = Digest {
+            query: "rust async",
+            engine: Engine::DuckDuckGo,
+            entries: vec![(&results[0], Some(&local)), (&results[1], None)],
+        };
+
+        assert_eq!(
+            digest.to_markdown(Path::new("/data/websearch/exports"), 1_760_623_380),
+            "# rust async\n\n\
+             - Searched: 2025-10-16 14:03 UTC\n\
+             - Engine: DuckDuckGo\n\
+             - Results: 2\n\
+             \n\
+             1. [Async \\[book\\]](<https://example.com/async>)\n   \
+             Futures explained\n   \
+             Local copy: [example_com_1234abcd_Async.md](<../active_tabs/example_com_1234abcd_Async.md>)\n\
+             \n\
+             2. [Tokio](<https://tokio.rs/>)\n"
+        );
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let dir = std::env::temp_dir().join(format!("websearch-json-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let page = dir.join("example_com_1234abcd_Async.md");
+        std::fs::write(
+            &page,
+            "---\ntitle: \"Async Rust\"\nurl: https://example.com/async\nauthor: \"Jane\"\n---\n\nBody",
+        )
+        .unwrap();
+
+        let results = [
+            result("Async", "https://example.com/async", "Futures explained"),
+            result("Slow", "https://slow.example/", ""),
+            result("Gone", "https://gone.example/", ""),
+        ];
+        let ready = PrefetchStatus::Ready(page.clone());
+        let failed = PrefetchStatus::Failed(Failure::Http(404));
+        let digest = Digest {
+            query: "rust async",
+            engine: Engine::SearXNG,
+            entries: vec![
+                (&results[0], Some(&ready)),
+                (&results[1], None),
+                (&results[2], Some(&failed)),
+            ],
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&digest.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "query": "rust async",
+                "engine": "searxng",
+                "results": [
+                    {
+                        "title": "Async",
+                        "url": "https://example.com/async",
+                        "description": "Futures explained",
+                        "status": "ready",
+                        "path": page,
+                        "page": {
+                            "title": "Async Rust",
+                            "byline": "Jane",
+                            "excerpt": null,
+                            "site_name": null
+                        }
+                    },
+                    {
+                        "title": "Slow",
+                        "url": "https://slow.example/",
+                        "description": "",
+                        "status": "pending",
+                        "path": null,
+                        "page": null
+                    },
+                    {
+                        "title": "Gone",
+                        "url": "https://gone.example/",
+                        "description": "",
+                        "status": "failed",
+                        "path": null,
+                        "page": null
+                    }
+                ]
+            })
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_file_names() {
+        assert_eq!(slug("rust async: runtimes?"), "rust_async_runtimes");
+        assert_eq!(slug("???"), "search");
+
+        let dir = std::env::temp_dir().join(format!("websearch-export-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = unique_path(&dir, "query-2025-10-16", "md");
+        std::fs::write(&first, "").unwrap();
+        assert_eq!(first, dir.join("query-2025-10-16.md"));
+        assert_eq!(
+            unique_path(&dir, "query-2025-10-16", "md"),
+            dir.join("query-2025-10-16-2.md")
+        );
+        assert_eq!(
+            unique_path(&dir, "query-2025-10-16", "json"),
+            dir.join("query-2025-10-16.json")
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), ("1970-01-01".to_string(), "00:00".to_string()));
+        assert_eq!(format_utc(951_825_600), ("2000-02-29".to_string(), "12:00".to_string()));
+    }
+}
@@ -1,7 +1,67 @@
 use anyhow::{Context, Result};
+use dom_query::{Document, NodeRef};
 use dom_smoothie::{
     CandidateSelectMode, Config, ParsePolicy, Readability, TextMode,
 };
+use serde::Serialize;
+use std::path::Path;
+use url::Url;
+
+use crate::cache_index::{read_frontmatter, unix_now, yaml_quote, yaml_scalar};
+use crate::canonical;
+use crate::config::{ExtractionConfig, ImageMode, OutputFormat};
+use crate::export::rfc3339;
+use crate::formats;
+use crate::images;
+use crate::outline::parse_heading;
+use crate::page_info;
+use crate::toc;
+
+/// Reading speed behind `reading_time_min`
+const WORDS_PER_MINUTE: usize = 220;
+
+/// Readability articles shorter than this, in characters, are retried on
+/// the whole page
+const MIN_ARTICLE_CHARS: usize = 300;
+
+/// Elements left out when the whole page is converted
+const FALLBACK_SKIP_TAGS: [&str; 12] = [
+    "head", "script", "style", "noscript", "template", "svg", "iframe", "nav", "aside", "footer",
+    "form", "button",
+];
+
+/// Classes of highlighted code that don't name the language, as in
+/// `hljs rust` or `sourceCode python`
+const HIGHLIGHT_CLASSES: [&str; 10] = [
+    "hljs", "sourceCode", "highlight", "prettyprint", "notranslate", "chroma", "shiki",
+    "line-numbers", "code", "syntax",
+];
+
+/// Most cells a `colspan` cell is spread over
+const MAX_COLSPAN: usize = 20;
+
+/// How the text of a page was found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Extraction {
+    /// Readability's article, or a plain-text page as it is
+    #[default]
+    Article,
+    /// The whole `<body>`, without navigation and scripts
+    Fallback,
+    /// Only the page's visible text
+    Text,
+}
+
+impl Extraction {
+    /// Value of the `extraction` frontmatter field
+    pub fn name(self) -> &'static str {
+        match self {
+            Extraction::Article => "article",
+            Extraction::Fallback => "fallback",
+            Extraction::Text => "text",
+        }
+    }
+}
 
 /// Struct for extracted content
 #[derive(Debug, Clone)]
@@ -11,11 +71,119 @@ pub struct ExtractedContent {
     pub excerpt: Option<String>,
     pub site_name: Option<String>,
     pub markdown: String,
+    /// URL the page was requested with (the search result URL)
     pub url: String,
+    /// URL of the page's `<link rel="canonical">`, else the one it was
+    /// served from after redirects
+    pub canonical_url: String,
+    /// Wayback Machine snapshot the content was taken from
+    pub archived_snapshot: Option<String>,
+    /// Capture date of the snapshot (`YYYY-MM-DD`)
+    pub archived_date: Option<String>,
+    /// Language of the text, like `en-US` as the page declares it or `de`
+    /// as detected, see [`crate::page_info`]
+    pub lang: Option<String>,
+    /// When the page was published, RFC 3339 or a shorter date
+    pub published: Option<String>,
+    /// Search engine the result came from
+    pub engine: Option<String>,
+    /// When the page was downloaded, as a Unix timestamp
+    pub fetched_at: u64,
+    /// Whether Readability found the text or a fallback did
+    pub extraction: Extraction,
+    /// Section list written before the text, see [`crate::toc`]
+    pub contents: Option<String>,
+    /// The text was cut at `extraction.max_markdown_kb`
+    pub truncated: bool,
+}
+
+/// Metadata of [`ExtractedContent`], as kept in a saved page's frontmatter
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub byline: Option<String>,
+    pub excerpt: Option<String>,
+    pub site_name: Option<String>,
+}
+
+impl PageMetadata {
+    /// Read the metadata of a saved page, `None` if it can't be read
+    pub fn read(path: &Path) -> Option<Self> {
+        let frontmatter = read_frontmatter(path)?;
+        Some(Self {
+            title: frontmatter.title,
+            byline: frontmatter.author,
+            excerpt: frontmatter.excerpt,
+            site_name: frontmatter.source,
+        })
+    }
 }
 
 /// Extract clean content from html and convert to Markdown
-pub fn extract_clean_markdown(html: &str, url: &str) -> Result<ExtractedContent> {
+///
+/// `final_url` is where the page ended up after redirects and is used to
+/// resolve relative links; `url` is the original result URL. When
+/// Readability fails or finds almost nothing, as on documentation sites and
+/// bare HTML, the whole page is converted instead, see [`Extraction`].
+pub fn extract_clean_markdown(
+    html: &str,
+    url: &str,
+    final_url: &str,
+    settings: &ExtractionConfig,
+) -> Result<ExtractedContent> {
+    let article = match readability_article(html, url, final_url, settings) {
+        Ok(article) => Some(article),
+        Err(e) => {
+            tracing::debug!(url, "Readability failed, converting the whole page: {:#}", e);
+            None
+        }
+    };
+
+    let mut content = match article {
+        Some(article) if article.markdown.trim().chars().count() >= MIN_ARTICLE_CHARS => article,
+        article => {
+            let fallback = fallback_content(html, url, final_url, settings);
+            match article {
+                // Readability's few lines beat an empty page
+                Some(article) if fallback.markdown.trim().is_empty() => article,
+                Some(article) => ExtractedContent {
+                    // Readability's metadata is still better than the page's
+                    title: if article.title.trim().is_empty() {
+                        fallback.title
+                    } else {
+                        article.title
+                    },
+                    byline: article.byline,
+                    excerpt: article.excerpt,
+                    site_name: article.site_name,
+                    lang: article.lang.or(fallback.lang),
+                    published: article.published.or(fallback.published),
+                    ..fallback
+                },
+                None => fallback,
+            }
+        }
+    };
+
+    content.markdown = resolve_links(&content.markdown, final_url);
+    content.lang = page_info::detect_language(&content.markdown, content.lang.take());
+    if settings.images == ImageMode::Strip {
+        content.markdown = images::strip(&content.markdown);
+    }
+    content.truncate(settings.max_markdown_bytes());
+    if settings.table_of_contents {
+        content.contents = toc::table_of_contents(&content.title, &content.markdown);
+    }
+    Ok(content)
+}
+
+/// The article Readability picks out of the page
+fn readability_article(
+    html: &str,
+    url: &str,
+    final_url: &str,
+    settings: &ExtractionConfig,
+) -> Result<ExtractedContent> {
     // corrected config
     let config = Config {
         text_mode: TextMode::Markdown,
@@ -24,8 +192,10 @@ pub fn extract_clean_markdown(html: &str, url: &str) -> Result<ExtractedContent>
         ..Default::default()
     };
 
-    // Readability::new returns Result<Readability, ReadabilityError>
-    let mut readability = Readability::new(html, Some(url), Some(config))
+    let document = prepare_document(html, settings);
+    let published = page_info::published_date(&document, final_url);
+    let canonical_url = canonical::declared(&document, final_url);
+    let mut readability = Readability::with_document(document, Some(final_url), Some(config))
         .context("Failed to create Readability instance")?;
 
     let article = readability
@@ -41,30 +211,507 @@ pub fn extract_clean_markdown(html: &str, url: &str) -> Result<ExtractedContent>
         site_name: article.site_name,
         markdown: article.text_content.to_string(),
         url: url.to_string(),
+        canonical_url: canonical_url.unwrap_or_else(|| final_url.to_string()),
+        archived_snapshot: None,
+        archived_date: None,
+        lang: article.lang,
+        published,
+        engine: None,
+        fetched_at: unix_now(),
+        extraction: Extraction::Article,
+        contents: None,
+        truncated: false,
+    })
+}
+
+/// The whole page converted to markdown, or at least its visible text
+fn fallback_content(
+    html: &str,
+    url: &str,
+    final_url: &str,
+    settings: &ExtractionConfig,
+) -> ExtractedContent {
+    let document = prepare_document(html, settings);
+    let title = document.select("title").text().trim().to_string();
+    let title = if title.is_empty() {
+        document.select("h1").first().text().trim().to_string()
+    } else {
+        title
+    };
+    let lang = document
+        .select("html")
+        .attr("lang")
+        .map(|lang| lang.to_string())
+        .filter(|lang| !lang.trim().is_empty());
+
+    let body = document.select("body");
+    let markdown = body
+        .nodes()
+        .first()
+        .map(|node| node.md(Some(&FALLBACK_SKIP_TAGS)).to_string())
+        .unwrap_or_default();
+    let (markdown, extraction) = if markdown.trim().is_empty() {
+        (body.formatted_text().trim().to_string(), Extraction::Text)
+    } else {
+        (markdown.trim().to_string(), Extraction::Fallback)
+    };
+
+    ExtractedContent {
+        title: if title.is_empty() { url.to_string() } else { title },
+        lang,
+        published: page_info::published_date(&document, final_url),
+        canonical_url: canonical::declared(&document, final_url)
+            .unwrap_or_else(|| final_url.to_string()),
+        extraction,
+        ..ExtractedContent::from_plain_text(&markdown, url, final_url, "")
+    }
+}
+
+/// Parse `html`, with code blocks and tables in the shape the markdown
+/// converter handles
+fn prepare_document(html: &str, settings: &ExtractionConfig) -> Document {
+    let document = Document::from(html);
+    mark_code_languages(&document);
+    normalize_tables(&document, settings.max_table_width);
+    document
+}
+
+/// Put the language of each code block where the markdown converter looks
+///
+/// Highlighters name the language in classes, which Readability strips:
+/// `language-rust` (Prism), `hljs rust`, `lang-rust`, `brush: js` (MDN)
+/// or `highlight-source-rust` on the surrounding `<div>` (GitHub). The
+/// first one found on the `<pre>`, its `<code>` or its parent becomes
+/// `data-lang`, which gives the fence its language.
+fn mark_code_languages(document: &Document) {
+    for pre in document.select("pre").iter() {
+        if pre.has_attr("data-lang") || pre.has_attr("data-language") {
+            continue;
+        }
+        let classes = [
+            pre.attr("class"),
+            pre.select("code").first().attr("class"),
+            pre.parent().attr("class"),
+        ];
+        if let Some(language) = classes.iter().flatten().find_map(|class| class_language(class)) {
+            pre.set_attr("data-lang", &language);
+        }
+    }
+}
+
+/// Reshape data tables so they convert to pipe tables
+///
+/// The converter only writes a table whose rows have the same number of
+/// `<td>`s, and takes every `<th>` as a heading. So a first row of `<th>`s
+/// (or the `<thead>` row) becomes the header, other `<th>`s become cells,
+/// `colspan` cells are followed by blank ones and short rows are padded.
+/// Tables wider than `max_width` characters become a list per row. Tables
+/// holding tables are layout and left alone.
+fn normalize_tables(document: &Document, max_width: usize) {
+    for table in document.select("table").nodes() {
+        if table.is("table:has(table)") || table.is("table table") {
+            continue;
+        }
+        let rows: Vec<NodeRef> = table.find(&["tr"]);
+        let header = rows.first().is_some_and(|row| {
+            let cells = table_cells(row);
+            row.parent().is_some_and(|parent| parent.has_name("thead"))
+                || (!cells.is_empty() && cells.iter().all(|cell| cell.has_name("th")))
+        });
+
+        for (i, row) in rows.iter().enumerate() {
+            let name = if header && i == 0 { "th" } else { "td" };
+            for cell in table_cells(row) {
+                cell.rename(name);
+                let span = cell
+                    .attr("colspan")
+                    .and_then(|span| span.trim().parse::<usize>().ok())
+                    .unwrap_or(1)
+                    .clamp(1, MAX_COLSPAN);
+                cell.remove_attr("colspan");
+                for _ in 1..span {
+                    add_cell_after(&cell, name);
+                }
+            }
+        }
+
+        let rows: Vec<Vec<NodeRef>> = rows.iter().map(table_cells).collect();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        for cells in &rows {
+            if let Some(last) = cells.last() {
+                let name = if last.has_name("th") { "th" } else { "td" };
+                for _ in cells.len()..columns {
+                    add_cell_after(last, name);
+                }
+            }
+        }
+        let rows: Vec<Vec<NodeRef>> = table.find(&["tr"]).iter().map(table_cells).collect();
+
+        // Every column as wide as its widest cell, plus the `| ` separators
+        let width: usize = (0..columns)
+            .map(|column| {
+                let cells = rows.iter().filter_map(|cells| cells.get(column));
+                cells.map(cell_width).max().unwrap_or(0) + 3
+            })
+            .sum::<usize>()
+            + 1;
+        if width > max_width {
+            table.replace_with_html(table_as_lists(&rows, header));
+        }
+    }
+}
+
+/// The `<td>` and `<th>` cells of a row
+fn table_cells<'a>(row: &NodeRef<'a>) -> Vec<NodeRef<'a>> {
+    row.element_children()
+        .into_iter()
+        .filter(|cell| cell.has_name("td") || cell.has_name("th"))
+        .collect()
+}
+
+/// Insert an empty `<td>` or `<th>` after `cell`
+fn add_cell_after(cell: &NodeRef, name: &str) {
+    // Table cells can't be parsed on their own, so the new cell is renamed
+    cell.after_html("<span></span>");
+    if let Some(blank) = cell.next_element_sibling() {
+        blank.rename(name);
+    }
+}
+
+/// Rough width of a cell in the markdown, counting link targets
+fn cell_width(cell: &NodeRef) -> usize {
+    let text = cell.text();
+    let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let links: usize = cell
+        .find(&["a"])
+        .iter()
+        .filter_map(|link| link.attr("href"))
+        .map(|href| href.chars().count() + 4)
+        .sum();
+    words.chars().count() + links
+}
+
+/// HTML for a table too wide for a pipe table, like a definition list
+///
+/// Each row becomes its first cell in bold and a list of the other cells,
+/// named after their column heading when there is one.
+fn table_as_lists(rows: &[Vec<NodeRef>], header: bool) -> String {
+    let headings: Vec<String> = match (header, rows.first()) {
+        (true, Some(cells)) => {
+            cells.iter().map(|cell| cell.inner_html().trim().to_string()).collect()
+        }
+        _ => Vec::new(),
+    };
+    let mut html = String::new();
+    for cells in rows.iter().skip(usize::from(header)) {
+        let Some((first, rest)) = cells.split_first() else {
+            continue;
+        };
+        html.push_str(&format!("<p><strong>{}</strong></p><ul>", first.inner_html().trim()));
+        for (i, cell) in rest.iter().enumerate() {
+            if cell.text().trim().is_empty() {
+                continue;
+            }
+            match headings.get(i + 1).filter(|heading| !heading.is_empty()) {
+                Some(heading) => {
+                    html.push_str(&format!("<li><em>{}</em>: {}</li>", heading, cell.inner_html()))
+                }
+                None => html.push_str(&format!("<li>{}</li>", cell.inner_html())),
+            }
+        }
+        html.push_str("</ul>");
+    }
+    html
+}
+
+/// Language named by a code block's `class` attribute
+fn class_language(class: &str) -> Option<String> {
+    let mut tokens = class.split_whitespace();
+    let mut bare = None;
+    let mut highlighted = false;
+    while let Some(token) = tokens.next() {
+        let named = ["language-", "lang-", "highlight-source-", "brush:"]
+            .iter()
+            .find_map(|prefix| token.strip_prefix(prefix));
+        match named {
+            // `brush: js` puts the language in the next class
+            Some("") if token == "brush:" => return tokens.next().and_then(clean_language),
+            Some(language) => return clean_language(language),
+            None if HIGHLIGHT_CLASSES.contains(&token) => highlighted = true,
+            None => {
+                bare.get_or_insert(token);
+            }
+        }
+    }
+    bare.filter(|_| highlighted).and_then(clean_language)
+}
+
+/// Lowercase fence language, `None` for the names of plain text
+fn clean_language(language: &str) -> Option<String> {
+    let language: String = language
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '.' | '#'))
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match language.as_str() {
+        "" | "none" | "text" | "txt" | "plain" | "plaintext" | "nohighlight" => None,
+        _ => Some(language),
+    }
+}
+
+/// `markdown` with relative links and images made absolute against `base`
+///
+/// Protocol-relative (`//cdn...`) and fragment-only (`#section`) targets
+/// resolve too, so they keep working outside the page. `javascript:` links
+/// are replaced by their text; other schemes like `mailto:` are kept.
+pub fn resolve_links(markdown: &str, base: &str) -> String {
+    let Ok(base) = Url::parse(base) else {
+        return markdown.to_string();
+    };
+    rewrite_links(markdown, |text, target, image| {
+        if target.starts_with('<') {
+            return None;
+        }
+        let prefix = if image { "!" } else { "" };
+        match Url::parse(target) {
+            Ok(url) if url.scheme() == "javascript" => Some(text.to_string()),
+            Ok(_) => None,
+            Err(url::ParseError::RelativeUrlWithoutBase) => {
+                let absolute = base.join(target).ok()?;
+                Some(format!("{}[{}]({})", prefix, text, absolute))
+            }
+            Err(_) => None,
+        }
     })
 }
 
+/// Replace the inline links `[text](target)` and images `![alt](src)`
+///
+/// `rewrite` gets the text, the target and whether it is an image, and
+/// returns the replacement or `None` to keep the link. Links spanning
+/// lines and targets with titles are left alone; balanced parentheses in
+/// targets, like `javascript:void(0)`, are part of the target.
+pub fn rewrite_links(
+    markdown: &str,
+    mut rewrite: impl FnMut(&str, &str, bool) -> Option<String>,
+) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+
+    while let Some(middle) = rest.find("](") {
+        let after = middle + 2;
+        let open = rest[..middle].rfind('[');
+        let (Some(open), Some(close)) = (open, closing_paren(&rest[after..])) else {
+            out.push_str(&rest[..after]);
+            rest = &rest[after..];
+            continue;
+        };
+        let close = after + close;
+        let text = &rest[open + 1..middle];
+        let target = &rest[after..close];
+        let image = rest[..open].ends_with('!');
+        let start = if image { open - 1 } else { open };
+
+        let replacement = if text.contains('\n') || target.contains(char::is_whitespace) {
+            None
+        } else {
+            rewrite(text, target, image)
+        };
+        match replacement {
+            Some(link) => {
+                out.push_str(&rest[..start]);
+                out.push_str(&link);
+            }
+            None => out.push_str(&rest[..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Byte offset of the `)` closing a link target that starts `text`
+pub fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Byte offset to cut `markdown` at to keep it under `max_bytes`
+///
+/// The start of the last heading before the limit, unless that loses more
+/// than half the limit, or else the last blank line outside a code block;
+/// a page with neither is cut at a line break.
+fn truncation_point(markdown: &str, max_bytes: usize) -> usize {
+    let mut heading = None;
+    let mut blank = None;
+    let mut line_end = None;
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+
+    for line in markdown.split_inclusive('\n') {
+        if offset + line.len() > max_bytes {
+            break;
+        }
+        let trimmed = line.trim();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if trimmed.is_empty() {
+            blank = Some(offset);
+        } else if offset >= max_bytes / 2 && parse_heading(line.trim_end()).is_some() {
+            heading = Some(offset);
+        }
+        offset += line.len();
+        if fence.is_none() {
+            line_end = Some(offset);
+        }
+    }
+
+    heading
+        .or(blank)
+        .or(line_end)
+        .unwrap_or_else(|| markdown.floor_char_boundary(max_bytes))
+}
+
+/// Minutes to read `words` words, at least one
+pub fn reading_minutes(words: usize) -> usize {
+    words.div_ceil(WORDS_PER_MINUTE).max(1)
+}
+
 impl ExtractedContent {
+    /// Words of extracted text
+    pub fn word_count(&self) -> usize {
+        self.markdown.split_whitespace().count()
+    }
+
+    /// Wrap a plain-text page without running Readability
+    pub fn from_plain_text(text: &str, url: &str, final_url: &str, title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            byline: None,
+            excerpt: None,
+            site_name: None,
+            markdown: text.to_string(),
+            url: url.to_string(),
+            canonical_url: final_url.to_string(),
+            archived_snapshot: None,
+            archived_date: None,
+            lang: None,
+            published: None,
+            engine: None,
+            fetched_at: unix_now(),
+            extraction: Extraction::Article,
+            contents: None,
+            truncated: false,
+        }
+    }
+
+    /// Cut the text of a page longer than `max_bytes`, `0` for no limit
+    ///
+    /// The cut is made at the last heading that fits, else at the last
+    /// paragraph break, and never inside a code block. A section pointing
+    /// at the original URL replaces the rest.
+    pub fn truncate(&mut self, max_bytes: usize) {
+        if max_bytes == 0 || self.markdown.len() <= max_bytes {
+            return;
+        }
+        let kept = truncation_point(&self.markdown, max_bytes);
+        self.markdown.truncate(kept);
+        let kept = self.markdown.trim_end().len();
+        self.markdown.truncate(kept);
+        self.markdown.push_str(&format!(
+            "\n\n## ⚠ Document truncated at {} KB — open the original URL for the rest\n\n\
+             [{}]({})\n",
+            max_bytes / 1024,
+            self.url,
+            self.url
+        ));
+        self.truncated = true;
+    }
+
+    /// The page in the configured `extraction.format`
+    pub fn to_formatted(&self, settings: &ExtractionConfig) -> String {
+        match settings.format {
+            OutputFormat::Markdown => self.to_formatted_markdown(),
+            OutputFormat::Org => formats::org_document(self),
+            OutputFormat::Text => formats::text_document(self, settings.wrap_width),
+        }
+    }
+
     /// Format Markdown with YAML frontmatter
+    ///
+    /// Text fields are always double-quoted and escaped, so every value
+    /// stays on its line whatever the page put in its title.
     pub fn to_formatted_markdown(&self) -> String {
         let mut result = String::new();
 
         // YAML frontmatter
         result.push_str("---\n");
-        result.push_str(&format!("title: \"{}\"\n", self.title.replace('"', "\\\"")));
-        result.push_str(&format!("url: {}\n", self.url));
+        result.push_str(&format!("title: {}\n", yaml_quote(&self.title)));
+        result.push_str(&format!("url: {}\n", yaml_scalar(&self.url)));
+        result.push_str(&format!("canonical_url: {}\n", yaml_scalar(&self.canonical_url)));
 
         if let Some(ref byline) = self.byline {
-            result.push_str(&format!("author: \"{}\"\n", byline.replace('"', "\\\"")));
+            result.push_str(&format!("author: {}\n", yaml_quote(byline)));
         }
 
         if let Some(ref site_name) = self.site_name {
-            result.push_str(&format!("source: \"{}\"\n", site_name.replace('"', "\\\"")));
+            result.push_str(&format!("source: {}\n", yaml_quote(site_name)));
+        }
+
+        if let Some(ref excerpt) = self.excerpt
+            && !excerpt.trim().is_empty()
+        {
+            let excerpt = excerpt.split_whitespace().collect::<Vec<_>>().join(" ");
+            result.push_str(&format!("excerpt: {}\n", yaml_quote(&excerpt)));
+        }
+
+        if let Some(ref published) = self.published {
+            result.push_str(&format!("published: {}\n", published));
+        }
+        if let Some(ref lang) = self.lang
+            && !lang.trim().is_empty()
+        {
+            result.push_str(&format!("lang: {}\n", yaml_quote(lang.trim())));
+        }
+        if let Some(ref engine) = self.engine {
+            result.push_str(&format!("engine: {}\n", yaml_quote(engine)));
+        }
+        result.push_str(&format!("fetched_at: {}\n", rfc3339(self.fetched_at)));
+        if self.extraction != Extraction::Article {
+            result.push_str(&format!("extraction: {}\n", self.extraction.name()));
+        }
+        if self.truncated {
+            result.push_str("truncated: true\n");
+        }
+
+        let words = self.word_count();
+        result.push_str(&format!("word_count: {}\n", words));
+        result.push_str(&format!("reading_time_min: {}\n", reading_minutes(words)));
+
+        if let Some(ref snapshot) = self.archived_snapshot {
+            result.push_str(&format!("archived_snapshot: {}\n", yaml_scalar(snapshot)));
+        }
+        if let Some(ref date) = self.archived_date {
+            result.push_str(&format!("archived_date: {}\n", yaml_scalar(date)));
         }
         result.push_str("---\n\n");
 
-        // Header
-        result.push_str(&format!("# {}\n\n", self.title));
+        // Header, on one line even if the title isn't
+        let title = self.title.split_whitespace().collect::<Vec<_>>().join(" ");
+        result.push_str(&format!("# {}\n\n", title));
 
         // Meta data
         if let Some(ref byline) = self.byline {
@@ -73,16 +720,23 @@ impl ExtractedContent {
         if let Some(ref site_name) = self.site_name {
             result.push_str(&format!("**Source**: {}\n", site_name));
         }
-        result.push_str(&format!("**URL**: [{}]({})\n\n", self.title, self.url));
+        if let Some(ref snapshot) = self.archived_snapshot {
+            let date = self.archived_date.as_deref().unwrap_or("unknown date");
+            result.push_str(&format!("**Archived**: [snapshot from {}]({})\n", date, snapshot));
+        }
+        result.push_str(&format!("**URL**: [{}]({})\n\n", title, self.url));
 
         // Excerpt
-        if let Some(ref excerpt) = self.excerpt {
-            if !excerpt.is_empty() {
-                result.push_str(&format!("> {}\n\n", excerpt.trim()));
-            }
+        if let Some(ref excerpt) = self.excerpt
+            && !excerpt.is_empty()
+        {
+            result.push_str(&format!("> {}\n\n", excerpt.trim()));
         }
 
         result.push_str("---\n\n");
+        if let Some(ref contents) = self.contents {
+            result.push_str(contents);
+        }
         result.push_str(&self.markdown);
         result
     }
@@ -92,6 +746,10 @@ impl ExtractedContent {
 mod tests {
     use super::*;
 
+    fn extract(html: &str, url: &str, final_url: &str) -> Result<ExtractedContent> {
+        extract_clean_markdown(html, url, final_url, &ExtractionConfig::default())
+    }
+
     #[test]
     fn test_extract_simple_article() {
         let html = r#"
@@ -106,7 +764,7 @@ mod tests {
             </html>
         "#;
 
-        let result = extract_clean_markdown(html, "https://test.com");
+        let result = extract(html, "https://test.com", "https://test.com");
         assert!(result.is_ok());
         
         let content = result.unwrap();
@@ -114,4 +772,303 @@ mod tests {
         assert!(content.markdown.contains("bold"));
         assert!(!content.markdown.is_empty());
     }
+
+    #[test]
+    fn test_docs_pages_fall_back_to_the_whole_body() {
+        let html = r#"
+            <html lang="en">
+            <head><title>Config reference</title><script>track()</script></head>
+            <body>
+                <nav><a href="/">Home</a> <a href="/guide">Guide</a></nav>
+                <h1>Config reference</h1>
+                <h2>timeout</h2>
+                <pre><code>timeout = 30</code></pre>
+                <p>See <a href="options#retry">retry</a>.</p>
+                <footer>© Example</footer>
+            </body>
+            </html>
+        "#;
+        let content = extract(html, "https://bit.ly/x", "https://docs.example/ref/")
+            .unwrap();
+
+        assert_eq!(content.extraction, Extraction::Fallback);
+        assert_eq!(content.title, "Config reference");
+        assert_eq!(content.lang.as_deref(), Some("en"));
+        assert!(content.markdown.contains("timeout = 30"), "{}", content.markdown);
+        assert!(content.markdown.contains("(https://docs.example/ref/options#retry)"));
+        assert!(!content.markdown.contains("Guide") && !content.markdown.contains("track()"));
+        assert!(content.to_formatted_markdown().contains("\nextraction: fallback\n"));
+    }
+
+    #[test]
+    fn test_bare_text_pages_keep_their_text() {
+        let text = "Just a line of text.\nAnd another one.";
+        let content = extract(text, "u", "https://a.example/").unwrap();
+        assert_ne!(content.extraction, Extraction::Article);
+        assert!(content.markdown.contains("And another one"), "{}", content.markdown);
+        assert_eq!(content.title, "u");
+
+        // Only navigation: the visible text is kept rather than nothing
+        let html = "<html><head><title>Menu</title></head>\
+                    <body><nav>Start here</nav></body></html>";
+        let content = extract(html, "u", "https://a.example/").unwrap();
+        assert_eq!(content.extraction, Extraction::Text);
+        assert_eq!((content.title.as_str(), content.markdown.as_str()), ("Menu", "Start here"));
+    }
+
+    #[test]
+    fn test_plain_text_is_wrapped_unchanged() {
+        let content = ExtractedContent::from_plain_text(
+            "line one\n  indented <b>not html</b>\n",
+            "https://example.com/notes.txt",
+            "https://example.com/notes.txt",
+            "Notes",
+        );
+        let markdown = content.to_formatted_markdown();
+        assert!(markdown.starts_with("---\ntitle: \"Notes\"\nurl: https://example.com/notes.txt\n"));
+        assert!(markdown.contains("\nword_count: 5\nreading_time_min: 1\n---\n"));
+        assert!(markdown.ends_with("line one\n  indented <b>not html</b>\n"));
+    }
+
+    #[test]
+    fn test_links_resolve_against_final_url() {
+        let html = r#"
+            <html>
+            <head><title>Redirected</title></head>
+            <body>
+                <article>
+                    <h1>Redirected</h1>
+                    <p>The article body links to <a href="/docs/guide">the guide</a> for more
+                    details, with enough text around it to count as content.</p>
+                </article>
+            </body>
+            </html>
+        "#;
+
+        let content =
+            extract(html, "https://bit.ly/abc", "https://real.example/blog/post")
+                .unwrap();
+        assert!(content.markdown.contains("https://real.example/docs/guide"));
+
+        let markdown = content.to_formatted_markdown();
+        assert!(markdown.contains("url: https://bit.ly/abc\n"));
+        assert!(markdown.contains("canonical_url: https://real.example/blog/post\n"));
+    }
+
+    #[test]
+    fn test_resolve_links_handles_every_flavor() {
+        let markdown = "[install](/docs/installation) [intro](../guide/intro.html) \
+                        [zip](//cdn.example.com/file.zip) [below](#section-2) [next](?page=2)\n\
+                        [mail](mailto:a@b.c) [js](javascript:void(0)) \
+                        [abs](https://other.example/x) [titled](/a \"Title\")\n\
+                        ![fig](../img/figure.png) ![cdn](//cdn.example.com/a.png)\n";
+        assert_eq!(
+            resolve_links(markdown, "https://example.com/blog/post/"),
+            "[install](https://example.com/docs/installation) \
+             [intro](https://example.com/blog/guide/intro.html) \
+             [zip](https://cdn.example.com/file.zip) \
+             [below](https://example.com/blog/post/#section-2) \
+             [next](https://example.com/blog/post/?page=2)\n\
+             [mail](mailto:a@b.c) js [abs](https://other.example/x) [titled](/a \"Title\")\n\
+             ![fig](https://example.com/blog/img/figure.png) \
+             ![cdn](https://cdn.example.com/a.png)\n"
+        );
+        assert_eq!(resolve_links("[a](/b)", "not a url"), "[a](/b)");
+    }
+
+    #[test]
+    fn test_code_blocks_keep_their_language() {
+        let prose = "<p>Some explanation of the code below, long enough for Readability to treat \
+                     the page as an article worth keeping rather than as boilerplate.</p>";
+        let fixtures = [
+            // GitHub README
+            (
+                r#"<div class="highlight highlight-source-rust notranslate position-relative">
+                   <pre><span class="pl-k">fn</span> main() {}</pre></div>"#,
+                "```rust\n",
+            ),
+            // MDN
+            (
+                r#"<div class="code-example"><div class="example-header">
+                   <span class="language-name">js</span></div>
+                   <pre class="brush: js notranslate"><code>const a = 1;</code></pre></div>"#,
+                "```js\n",
+            ),
+            // Prism blog
+            (
+                r#"<pre class="line-numbers">
+                   <code class="language-TypeScript">let a: number;</code></pre>"#,
+                "```typescript\n",
+            ),
+            // highlight.js
+            (r#"<pre><code class="hljs python">print(1)</code></pre>"#, "```python\n"),
+        ];
+        for (code, fence) in fixtures {
+            let html = format!(
+                "<html><head><title>Post</title></head><body><article><h1>Post</h1>\
+                 {prose}{prose}{code}{prose}</article></body></html>"
+            );
+            let content = extract(&html, "https://a.example/", "https://a.example/")
+                .unwrap();
+            assert!(content.markdown.contains(fence), "{}\n{}", fence, content.markdown);
+        }
+
+        assert_eq!(class_language("language-none"), None);
+        assert_eq!(class_language("hljs"), None);
+        assert_eq!(class_language("prettyprint lang-go"), Some("go".to_string()));
+    }
+
+    /// Markdown of an article holding `body`
+    fn article_markdown(body: &str, settings: &ExtractionConfig) -> String {
+        let prose = "<p>Some explanation around the table, long enough for Readability to \
+                     treat the page as an article worth keeping rather than as boilerplate.</p>";
+        let html = format!(
+            "<html><head><title>Post</title></head><body><article><h1>Post</h1>\
+             {prose}{prose}{body}{prose}</article></body></html>"
+        );
+        extract_clean_markdown(&html, "https://a.example/", "https://a.example/post", settings)
+            .unwrap()
+            .markdown
+    }
+
+    #[test]
+    fn test_tables_become_pipe_tables() {
+        let settings = ExtractionConfig::default();
+        let simple = "<table><thead><tr><th>Name</th><th>Type</th></tr></thead><tbody>\
+                      <tr><td>timeout</td><td>u64 | none</td></tr>\
+                      <tr><td>retry</td><td>bool</td></tr></tbody></table>";
+        assert!(
+            article_markdown(simple, &settings).contains(
+                "| Name | Type |\n| ---- | ---- |\n| timeout | u64 \\| none |\n| retry | bool |\n"
+            )
+        );
+
+        let links = "<table><tr><th>Crate</th><th>Docs</th></tr>\
+                     <tr><td><a href=\"/tokio\">tokio</a></td>\
+                     <td>See <a href=\"https://docs.rs/tokio\">docs</a></td></tr></table>";
+        assert!(article_markdown(links, &settings).contains(
+            "| [tokio](https://a.example/tokio) | See [docs](https://docs.rs/tokio) |"
+        ));
+
+        // Row headings and a colspan, which the converter used to run together
+        let spans = "<table><tr><th>Feature</th><th>A</th><th>B</th></tr>\
+                     <tr><th>Speed</th><td colspan=\"2\">same</td></tr>\
+                     <tr><th>Size</th><td>1</td></tr></table>";
+        let markdown = article_markdown(spans, &settings);
+        assert!(
+            markdown.contains("| Feature | A | B |\n")
+                && markdown.contains("| Speed | same |  |\n")
+                && markdown.contains("| Size | 1 |  |\n"),
+            "{}",
+            markdown
+        );
+    }
+
+    #[test]
+    fn test_wide_tables_become_lists() {
+        let table = "<table><tr><th>Option</th><th>Meaning</th><th>Default</th></tr>\
+                     <tr><td><code>timeout</code></td><td>How long to wait for a page before \
+                     giving up and trying the archive</td><td>8</td></tr>\
+                     <tr><td>wayback</td><td>Use the <a href=\"/archive\">archive</a></td>\
+                     <td></td></tr></table>";
+        let narrow = ExtractionConfig { max_table_width: 60, ..Default::default() };
+        let markdown = article_markdown(table, &narrow);
+        assert!(!markdown.contains("| Option"), "{}", markdown);
+        assert!(markdown.contains("**`timeout`**"), "{}", markdown);
+        assert!(markdown.contains("*Default*: 8"), "{}", markdown);
+        assert!(markdown.contains("*Meaning*: Use the [archive](https://a.example/archive)"));
+
+        let wide = ExtractionConfig { max_table_width: 200, ..Default::default() };
+        assert!(article_markdown(table, &wide).contains("| Option | Meaning | Default |"));
+    }
+
+    #[test]
+    fn test_hostile_titles_keep_frontmatter_valid() {
+        let titles = [
+            "Say \"hi\" to C:\\dir\\",
+            "Line one\n---\nline: two",
+            "---",
+            "Rust: a guide # not a comment",
+            "- [ ] 'quoted' & *starred* 🦀",
+            "\ttabbed\r\u{7}bell",
+        ];
+        for title in titles {
+            let mut content = ExtractedContent::from_plain_text(
+                "Some text",
+                "https://example.com/a?b=1#c",
+                "https://example.com/a?b=1#c",
+                title,
+            );
+            content.byline = Some(format!("By {}", title));
+            content.site_name = Some(title.to_string());
+            content.lang = Some("en-GB".to_string());
+            content.engine = Some("DuckDuckGo".to_string());
+            content.fetched_at = 1_760_607_005;
+
+            let markdown = content.to_formatted_markdown();
+            let yaml = markdown
+                .strip_prefix("---\n")
+                .and_then(|rest| rest.split_once("\n---\n\n"))
+                .map(|(yaml, _)| yaml)
+                .unwrap();
+            let parsed: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+            assert_eq!(parsed["title"].as_str(), Some(title), "{}", yaml);
+            assert_eq!(parsed["author"].as_str(), Some(format!("By {}", title).as_str()));
+            assert_eq!(parsed["url"].as_str(), Some("https://example.com/a?b=1#c"));
+            assert_eq!(parsed["word_count"].as_u64(), Some(2));
+            assert_eq!(parsed["lang"].as_str(), Some("en-GB"));
+            assert_eq!(parsed["engine"].as_str(), Some("DuckDuckGo"));
+            assert_eq!(parsed["fetched_at"].as_str(), Some("2025-10-16T09:30:05Z"));
+
+            // The cache index reads the same values back
+            let frontmatter = crate::cache_index::parse_frontmatter(&markdown);
+            assert_eq!(frontmatter.title.as_deref(), Some(title));
+            assert_eq!(frontmatter.source.as_deref(), Some(title));
+        }
+    }
+
+    #[test]
+    fn test_archived_snapshot_in_frontmatter() {
+        let mut content = ExtractedContent::from_plain_text(
+            "Recovered text",
+            "https://example.com/old-post",
+            "https://example.com/old-post",
+            "Old post",
+        );
+        content.archived_snapshot =
+            Some("http://web.archive.org/web/20190305121530/https://example.com/old-post".to_string());
+        content.archived_date = Some("2019-03-05".to_string());
+
+        let markdown = content.to_formatted_markdown();
+        let frontmatter = markdown.split("---\n\n").next().unwrap();
+        assert!(frontmatter.contains(
+            "archived_snapshot: http://web.archive.org/web/20190305121530/https://example.com/old-post\n"
+        ));
+        assert!(frontmatter.contains("archived_date: 2019-03-05\n"));
+    }
+
+    #[test]
+    fn test_truncation_cuts_at_heading_outside_code() {
+        let first = format!("## One\n\n{}\n\n", "text ".repeat(120));
+        let code = format!("```rust\n{}```\n", "// code\n## not a heading\n".repeat(40));
+        let text = format!("{}## Two\n\n{}\n## Three\n\nEnd.\n", first, code);
+        let url = "https://example.com/huge";
+        let mut content = ExtractedContent::from_plain_text(&text, url, url, "Huge");
+
+        content.truncate(0);
+        assert!(!content.truncated);
+        content.truncate(1024);
+        assert!(content.truncated);
+        assert_eq!(
+            content.markdown,
+            format!(
+                "{}\n\n## ⚠ Document truncated at 1 KB — open the original URL for the rest\n\n\
+                 [{}]({})\n",
+                first.trim_end(),
+                url,
+                url
+            )
+        );
+        assert!(content.to_formatted_markdown().contains("truncated: true\n"));
+    }
 }
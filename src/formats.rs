@@ -0,0 +1,488 @@
+//! Org-mode and plain-text versions of extracted pages
+//!
+//! `extraction.format` picks how pages are saved. Markdown is what the
+//! extractor produces; the other formats are converted from it line by
+//! line. Org turns headings, lists, quotes, links and code blocks into
+//! their Org syntax, with `#+TITLE:` and `#+PROPERTY:` lines instead of
+//! the YAML frontmatter. Text drops all markup, puts link targets in
+//! parentheses and wraps paragraphs at `extraction.wrap_width` columns;
+//! code blocks and tables are indented and never wrapped.
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::export::rfc3339;
+use crate::extract_clean_md::{closing_paren, reading_minutes, ExtractedContent};
+
+/// How inline markup is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Org,
+    Text,
+}
+
+/// The page as an Org document
+pub fn org_document(content: &ExtractedContent) -> String {
+    let one_line = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut org = format!("#+TITLE: {}\n", one_line(&content.title));
+    if let Some(author) = &content.byline {
+        org.push_str(&format!("#+AUTHOR: {}\n", one_line(author)));
+    }
+    if let Some(published) = &content.published {
+        org.push_str(&format!("#+DATE: {}\n", published));
+    }
+    if let Some(lang) = &content.lang {
+        org.push_str(&format!("#+LANGUAGE: {}\n", one_line(lang)));
+    }
+
+    let words = content.word_count();
+    let mut properties = vec![
+        ("URL", content.url.clone()),
+        ("CANONICAL_URL", content.canonical_url.clone()),
+    ];
+    if let Some(site_name) = &content.site_name {
+        properties.push(("SOURCE", one_line(site_name)));
+    }
+    if let Some(excerpt) = content.excerpt.as_deref().filter(|e| !e.trim().is_empty()) {
+        properties.push(("EXCERPT", one_line(excerpt)));
+    }
+    if let Some(engine) = &content.engine {
+        properties.push(("ENGINE", engine.clone()));
+    }
+    properties.push(("FETCHED_AT", rfc3339(content.fetched_at)));
+    properties.push(("WORD_COUNT", words.to_string()));
+    properties.push(("READING_TIME_MIN", reading_minutes(words).to_string()));
+    if let Some(snapshot) = &content.archived_snapshot {
+        properties.push(("ARCHIVED_SNAPSHOT", snapshot.clone()));
+    }
+    if content.truncated {
+        properties.push(("TRUNCATED", "true".to_string()));
+    }
+    for (key, value) in properties {
+        org.push_str(&format!("#+PROPERTY: {} {}\n", key, value));
+    }
+    org.push('\n');
+
+    if let Some(contents) = &content.contents {
+        org.push_str(&markdown_to_org(contents));
+        org.push('\n');
+    }
+    org.push_str(&markdown_to_org(&content.markdown));
+    org
+}
+
+/// The page as plain text, wrapped at `width` columns
+pub fn text_document(content: &ExtractedContent, width: usize) -> String {
+    let title = content.title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut text = format!("{}\n{}\n\n", title, "=".repeat(title.width().clamp(1, width)));
+    if let Some(author) = &content.byline {
+        text.push_str(&format!("Author: {}\n", author.trim()));
+    }
+    if let Some(site_name) = &content.site_name {
+        text.push_str(&format!("Source: {}\n", site_name.trim()));
+    }
+    if let Some(published) = &content.published {
+        text.push_str(&format!("Published: {}\n", published));
+    }
+    text.push_str(&format!("URL: {}\n", content.url));
+    let words = content.word_count();
+    text.push_str(&format!("Length: {} words, {} min\n\n", words, reading_minutes(words)));
+
+    if let Some(contents) = &content.contents {
+        text.push_str(&markdown_to_text(contents, width));
+        text.push('\n');
+    }
+    text.push_str(&markdown_to_text(&content.markdown, width));
+    text
+}
+
+/// `markdown` in Org syntax
+pub fn markdown_to_org(markdown: &str) -> String {
+    let mut org = String::with_capacity(markdown.len());
+    let mut fence: Option<&str> = None;
+    let mut in_quote = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+                org.push_str("#+END_SRC\n");
+            } else if trimmed.starts_with('*') || trimmed.starts_with("#+") {
+                // Org would read these as headings or keywords
+                org.push_str(&format!(",{}\n", line));
+            } else {
+                org.push_str(line);
+                org.push('\n');
+            }
+            continue;
+        }
+
+        let quoted = trimmed.starts_with('>');
+        if in_quote && !quoted {
+            org.push_str("#+END_QUOTE\n");
+            in_quote = false;
+        }
+        if quoted {
+            if !in_quote {
+                org.push_str("#+BEGIN_QUOTE\n");
+                in_quote = true;
+            }
+            let text = trimmed.trim_start_matches('>').trim_start();
+            org.push_str(&inline(text, Style::Org));
+            org.push('\n');
+            continue;
+        }
+
+        if let Some((open, language)) = fence_start(trimmed) {
+            fence = Some(open);
+            org.push_str(format!("#+BEGIN_SRC {}", language).trim_end());
+            org.push('\n');
+        } else if let Some((level, text)) = heading(line) {
+            org.push_str(&format!("{} {}\n", "*".repeat(level), inline(text, Style::Org)));
+        } else if is_rule(trimmed) {
+            org.push_str("-----\n");
+        } else if is_table_separator(trimmed) {
+            // `|:---|---:|` becomes `|----+----|`
+            let cells: Vec<String> = trimmed
+                .trim_matches('|')
+                .split('|')
+                .map(|cell| "-".repeat(cell.len()))
+                .collect();
+            org.push_str(&format!("|{}|\n", cells.join("+")));
+        } else if let Some((indent, marker, text)) = list_item(line) {
+            let marker = if marker.ends_with('.') { marker } else { "-" };
+            org.push_str(&format!("{}{} {}\n", indent, marker, inline(text, Style::Org)));
+        } else {
+            org.push_str(&inline(line, Style::Org));
+            org.push('\n');
+        }
+    }
+    if fence.is_some() {
+        org.push_str("#+END_SRC\n");
+    }
+    if in_quote {
+        org.push_str("#+END_QUOTE\n");
+    }
+    org
+}
+
+/// `markdown` as plain text, with paragraphs wrapped at `width` columns
+pub fn markdown_to_text(markdown: &str, width: usize) -> String {
+    let mut text = String::with_capacity(markdown.len());
+    let mut fence: Option<&str> = None;
+    let mut paragraph = String::new();
+
+    let flush = |paragraph: &mut String, text: &mut String| {
+        if !paragraph.is_empty() {
+            text.push_str(&wrap(paragraph, width, "", ""));
+            paragraph.clear();
+        }
+    };
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            } else {
+                text.push_str(&format!("    {}\n", line));
+            }
+            continue;
+        }
+
+        if let Some((open, _)) = fence_start(trimmed) {
+            flush(&mut paragraph, &mut text);
+            fence = Some(open);
+        } else if trimmed.is_empty() {
+            flush(&mut paragraph, &mut text);
+            text.push('\n');
+        } else if let Some((level, heading)) = heading(line) {
+            flush(&mut paragraph, &mut text);
+            let heading = inline(heading, Style::Text);
+            let underline = if level <= 2 { "=" } else { "-" };
+            let rule = underline.repeat(heading.width().clamp(1, width));
+            text.push_str(&format!("{}\n{}\n", heading, rule));
+        } else if is_rule(trimmed) || is_table_separator(trimmed) {
+            flush(&mut paragraph, &mut text);
+        } else if trimmed.starts_with('|') {
+            flush(&mut paragraph, &mut text);
+            text.push_str(&format!("    {}\n", inline(trimmed, Style::Text)));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            flush(&mut paragraph, &mut text);
+            let quote = inline(quote.trim_start_matches('>').trim_start(), Style::Text);
+            text.push_str(&wrap(&quote, width, "    ", "    "));
+        } else if let Some((indent, marker, item)) = list_item(line) {
+            flush(&mut paragraph, &mut text);
+            let marker = if marker.ends_with('.') { marker } else { "-" };
+            let first = format!("{}{} ", indent, marker);
+            let rest = " ".repeat(first.width());
+            text.push_str(&wrap(&inline(item, Style::Text), width, &first, &rest));
+        } else {
+            if !paragraph.is_empty() {
+                paragraph.push(' ');
+            }
+            paragraph.push_str(&inline(trimmed, Style::Text));
+        }
+    }
+    flush(&mut paragraph, &mut text);
+
+    // Collapse the blank lines left by dropped markup
+    let mut collapsed = String::with_capacity(text.len());
+    for line in text.lines() {
+        if line.is_empty() && (collapsed.is_empty() || collapsed.ends_with("\n\n")) {
+            continue;
+        }
+        collapsed.push_str(line);
+        collapsed.push('\n');
+    }
+    collapsed
+}
+
+/// Words of `text` on lines of at most `width` columns
+///
+/// The first line starts with `first`, the others with `rest`. A word
+/// longer than a line gets a line of its own.
+fn wrap(text: &str, width: usize, first: &str, rest: &str) -> String {
+    let mut wrapped = String::with_capacity(text.len() + 16);
+    let mut line = first.to_string();
+    let mut line_has_words = false;
+
+    for word in text.split_whitespace() {
+        if line_has_words && line.width() + 1 + word.width() > width {
+            wrapped.push_str(&line);
+            wrapped.push('\n');
+            line = rest.to_string();
+            line_has_words = false;
+        }
+        if line_has_words {
+            line.push(' ');
+        }
+        line.push_str(word);
+        line_has_words = true;
+    }
+    if line_has_words {
+        wrapped.push_str(&line);
+        wrapped.push('\n');
+    }
+    wrapped
+}
+
+/// The fence characters and language of a line opening a code block
+fn fence_start(trimmed: &str) -> Option<(&str, &str)> {
+    let open = trimmed.get(..3).filter(|open| *open == "```" || *open == "~~~")?;
+    let language = trimmed.trim_start_matches(['`', '~']).trim();
+    Some((open, language.split_whitespace().next().unwrap_or("")))
+}
+
+/// Level and text of an ATX heading
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then(|| (level, text.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Whether the line is a thematic break like `---` or `* * *`
+fn is_rule(trimmed: &str) -> bool {
+    let marks: String = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&mark| marks.chars().all(|c| c == mark))
+}
+
+/// Whether the line is the `|---|:---:|` row under a table's header
+fn is_table_separator(trimmed: &str) -> bool {
+    trimmed.starts_with('|')
+        && trimmed.contains('-')
+        && trimmed.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+/// Indentation, marker and text of a list item like `  - text` or `2. text`
+fn list_item(line: &str) -> Option<(&str, &str, &str)> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let (marker, text) = trimmed.split_once(' ')?;
+    let bullet = matches!(marker, "-" | "*" | "+");
+    let numbered = marker
+        .strip_suffix(['.', ')'])
+        .is_some_and(|n| !n.is_empty() && n.len() <= 9 && n.bytes().all(|b| b.is_ascii_digit()));
+    (bullet || numbered).then_some((indent, marker, text))
+}
+
+/// Inline markup of `text` in `style`
+///
+/// Handles code spans, links, images, `**strong**`, `*emphasis*` and
+/// backslash escapes. Underscores inside words are left alone.
+fn inline(text: &str, style: Style) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < text.len() {
+        let rest = &text[i..];
+        let c = rest.chars().next().unwrap_or_default();
+        let prev = text[..i].chars().next_back();
+
+        if c == '\\'
+            && let Some(escaped) = rest[1..].chars().next().filter(char::is_ascii_punctuation)
+        {
+            out.push(escaped);
+            i += 1 + escaped.len_utf8();
+        } else if c == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            let code = &rest[1..1 + end];
+            match style {
+                Style::Org if code.contains('~') => out.push_str(&format!("={}=", code)),
+                Style::Org => out.push_str(&format!("~{}~", code)),
+                Style::Text => out.push_str(code),
+            }
+            i += end + 2;
+        } else if let Some((image, label, target, len)) = link(rest) {
+            out.push_str(&link_text(image, label, target, style));
+            i += len;
+        } else if let Some(inner) = rest.strip_prefix("**")
+            && let Some(end) = inner.find("**").filter(|&end| end > 0)
+        {
+            let strong = inline(&inner[..end], style);
+            match style {
+                Style::Org => out.push_str(&format!("*{}*", strong)),
+                Style::Text => out.push_str(&strong),
+            }
+            i += end + 4;
+        } else if let Some(len) = emphasis(rest, prev) {
+            let emphasized = inline(&rest[1..len - 1], style);
+            match style {
+                Style::Org => out.push_str(&format!("/{}/", emphasized)),
+                Style::Text => out.push_str(&emphasized),
+            }
+            i += len;
+        } else {
+            out.push(c);
+            i += c.len_utf8();
+        }
+    }
+    out
+}
+
+/// A link or image starting `text`: whether it is an image, its text and
+/// target, and its length
+fn link(text: &str) -> Option<(bool, &str, &str, usize)> {
+    let image = text.starts_with("![");
+    let open = if image { 2 } else { text.strip_prefix('[').map(|_| 1)? };
+
+    // Link text may hold an image, so brackets are matched
+    let mut depth = 0;
+    let mut close = None;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 0 => {
+                close = Some(open + i);
+                break;
+            }
+            ']' => depth -= 1,
+            '\n' => return None,
+            _ => {}
+        }
+    }
+    let close = close?;
+    let target_start = close + 2;
+    text.get(close + 1..target_start).filter(|paren| *paren == "(")?;
+    let target_end = target_start + closing_paren(&text[target_start..])?;
+    let target = &text[target_start..target_end];
+    if target.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((image, &text[open..close], target, target_end + 1))
+}
+
+/// A link in `style`
+fn link_text(image: bool, label: &str, target: &str, style: Style) -> String {
+    let label_text = inline(label, style);
+    match style {
+        Style::Org if image => format!("[[{}]]", target),
+        Style::Org => match target.strip_prefix('#') {
+            // Table of contents entries point at headings by their text
+            Some(_) => format!("[[*{}][{}]]", inline(label, Style::Text), label_text),
+            None if label_text.is_empty() => format!("[[{}]]", target),
+            None => format!("[[{}][{}]]", target, label_text),
+        },
+        Style::Text if image => match label_text.trim() {
+            "" => String::new(),
+            alt => format!("[{}]", alt),
+        },
+        Style::Text if target.starts_with('#') || target == label_text => label_text,
+        Style::Text if label_text.is_empty() => target.to_string(),
+        Style::Text => format!("{} ({})", label_text, target),
+    }
+}
+
+/// Length of `*emphasis*` or `_emphasis_` starting `text`
+///
+/// `prev` is the character before it: an underscore inside a word, as in
+/// `snake_case`, doesn't open emphasis.
+fn emphasis(text: &str, prev: Option<char>) -> Option<usize> {
+    let mark = text.chars().next().filter(|&c| c == '*' || c == '_')?;
+    if mark == '_' && prev.is_some_and(char::is_alphanumeric) {
+        return None;
+    }
+    let inner = &text[1..];
+    if inner.starts_with(char::is_whitespace) || inner.starts_with(mark) {
+        return None;
+    }
+    let end = inner.char_indices().find_map(|(i, c)| {
+        let before = inner[..i].chars().next_back()?;
+        let after = inner[i + 1..].chars().next();
+        let closes = c == mark
+            && !before.is_whitespace()
+            && (mark == '*' || !after.is_some_and(char::is_alphanumeric));
+        closes.then_some(i)
+    })?;
+    Some(end + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "## Install `cargo-watch`\n\n\
+                        Run it with **care**, see [the *book*](https://doc.rust-lang.org/book/) \
+                        and keep snake_case_names as they are.\n\n\
+                        - one\n  * nested with ![logo](https://a.example/l.png)\n2. two\n\n\
+                        > Quoted\n> text\n\n\
+                        ```rust\n* not a heading\nfn main() {}\n```\n\n\
+                        | Key | Value |\n|:---|---:|\n| `a` | 1 |\n\n---\n";
+
+    #[test]
+    fn test_markdown_to_org() {
+        assert_eq!(
+            markdown_to_org(PAGE),
+            "** Install ~cargo-watch~\n\n\
+             Run it with *care*, see [[https://doc.rust-lang.org/book/][the /book/]] \
+             and keep snake_case_names as they are.\n\n\
+             - one\n  - nested with [[https://a.example/l.png]]\n2. two\n\n\
+             #+BEGIN_QUOTE\nQuoted\ntext\n#+END_QUOTE\n\n\
+             #+BEGIN_SRC rust\n,* not a heading\nfn main() {}\n#+END_SRC\n\n\
+             | Key | Value |\n|----+----|\n| ~a~ | 1 |\n\n-----\n"
+        );
+        assert_eq!(
+            markdown_to_org("- [Getting **Started**](#getting-started)"),
+            "- [[*Getting Started][Getting *Started*]]\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_to_text_wraps_paragraphs_only() {
+        assert_eq!(
+            markdown_to_text(PAGE, 40),
+            "Install cargo-watch\n===================\n\n\
+             Run it with care, see the book\n\
+             (https://doc.rust-lang.org/book/) and\n\
+             keep snake_case_names as they are.\n\n\
+             - one\n  - nested with [logo]\n2. two\n\n\
+             \x20   Quoted\n    text\n\n\
+             \x20   * not a heading\n    fn main() {}\n\n\
+             \x20   | Key | Value |\n    | a | 1 |\n\n"
+        );
+    }
+}
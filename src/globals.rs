@@ -1,59 +1,299 @@
 //! Global shared resources for optimal performance
 //!
 //! This module provides singleton instances of expensive-to-create resources:
-//! - HTTP client with optimized connection pooling and compression
+//! - An API client for JSON APIs (Brave, SearXNG, Wayback Machine) that
+//!   identifies itself as websearch-tui
+//! - A scrape client for HTML search pages and page prefetching that looks
+//!   like a desktop Chrome, since an honest UA gets CAPTCHAs
+//!
+//! Both use optimized connection pooling and compression. All requests go
+//! through the configured proxy (`network.proxy` or `WEBSEARCH_PROXY`), or
+//! else `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` (HTTP or SOCKS5), skipping
+//! hosts listed in `NO_PROXY`.
+//!
+//! In offline mode (`network.offline`) neither client is built and asking
+//! for one fails, so no request can slip out.
+//!
+//! The scrape client keeps cookies for the session, so consent and CAPTCHA
+//! cookies stick. With `network.persist_cookies` they are saved to
+//! `cookies.json` in the data directory between runs.
 
-use anyhow::Result;
-use reqwest::Client;
-use std::sync::OnceLock;
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, USER_AGENT};
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy};
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
-/// Global HTTP client - reuses connections across requests
-static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+use crate::config::NetworkConfig;
+use crate::paths;
+
+/// Longest redirect chain the HTTP client follows
+pub const MAX_REDIRECTS: usize = 10;
+
+/// Proxy environment variables, highest precedence first
+const PROXY_ENV_VARS: &[&str] = &[
+    "WEBSEARCH_PROXY",
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
+/// User-Agent of the API client
+const API_USER_AGENT: &str = concat!(
+    "websearch-tui/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/user/websearch-tui)"
+);
+
+/// Default User-Agent of the scrape client
+const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36";
+
+/// Client hints Chrome sends alongside `BROWSER_USER_AGENT`
+const BROWSER_CLIENT_HINTS: &[(&str, &str)] = &[
+    (
+        "sec-ch-ua",
+        "\"Google Chrome\";v=\"131\", \"Chromium\";v=\"131\", \"Not_A Brand\";v=\"24\"",
+    ),
+    ("sec-ch-ua-mobile", "?0"),
+    ("sec-ch-ua-platform", "\"Linux\""),
+];
+
+/// Persisted cookies file inside the data directory
+const COOKIES_FILE: &str = "cookies.json";
+
+/// Network settings from the config, set by `init_globals`
+static NETWORK: OnceLock<NetworkConfig> = OnceLock::new();
+
+/// Global API client - reuses connections across requests
+static API_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Global scrape client - reuses connections across requests
+static SCRAPE_CLIENT: OnceLock<Client> = OnceLock::new();
 
-/// Get or create the global HTTP client
+/// Cookies of the scrape client
+static COOKIE_JAR: OnceLock<Arc<CookieStoreMutex>> = OnceLock::new();
+
+/// Get or create the client for JSON APIs
+///
+/// Sends an honest `websearch-tui/x.y` User-Agent. Fails in offline mode.
+pub fn get_api_client() -> Result<&'static Client> {
+    ensure_online()?;
+    Ok(API_CLIENT.get_or_init(|| build_api_client().expect("Failed to create HTTP client")))
+}
+
+/// Get or create the client for HTML scraping and page prefetching
+///
+/// Sends a desktop Chrome User-Agent with matching client hints, or the
+/// configured User-Agent. Fails in offline mode.
+pub fn get_scrape_client() -> Result<&'static Client> {
+    ensure_online()?;
+    Ok(SCRAPE_CLIENT.get_or_init(|| {
+        build_scrape_client(cookie_jar().clone()).expect("Failed to create HTTP client")
+    }))
+}
+
+fn ensure_online() -> Result<()> {
+    anyhow::ensure!(!network().offline, "No network requests in offline mode");
+    Ok(())
+}
+
+fn build_api_client() -> Result<Client> {
+    base_client_builder()?
+        .user_agent(API_USER_AGENT)
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+fn build_scrape_client(jar: Arc<CookieStoreMutex>) -> Result<Client> {
+    let headers = scrape_headers(network().user_agent.clone())?;
+    base_client_builder()?
+        .default_headers(headers)
+        .cookie_provider(jar)
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+fn network() -> &'static NetworkConfig {
+    NETWORK.get_or_init(NetworkConfig::default)
+}
+
+/// The scrape client's cookie jar, loaded from disk when persistence is on
+fn cookie_jar() -> &'static Arc<CookieStoreMutex> {
+    COOKIE_JAR.get_or_init(|| {
+        let store = if persist_cookies() {
+            load_cookies(&cookies_path())
+        } else {
+            CookieStore::default()
+        };
+        Arc::new(CookieStoreMutex::new(store))
+    })
+}
+
+/// Whether cookies are saved between runs
+fn persist_cookies() -> bool {
+    network().persist_cookies
+}
+
+fn cookies_path() -> PathBuf {
+    paths::data_dir().join(COOKIES_FILE)
+}
+
+/// Read a saved cookie jar; a missing or corrupt file starts empty
+fn load_cookies(path: &Path) -> CookieStore {
+    std::fs::File::open(path)
+        .ok()
+        .and_then(|file| cookie_store::serde::json::load(BufReader::new(file)).ok())
+        .unwrap_or_default()
+}
+
+/// Save the cookie jar when persistence is enabled
+///
+/// Only persistent, unexpired cookies are written.
+pub fn save_cookies() -> Result<()> {
+    if !persist_cookies() {
+        return Ok(());
+    }
+
+    let store = cookie_jar()
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Cookie jar lock poisoned"))?;
+    let mut json = Vec::new();
+    cookie_store::serde::json::save(&store, &mut json)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize cookies: {}", e))?;
+    std::fs::write(cookies_path(), json).context("Failed to save cookies")
+}
+
+/// Forget all cookies, in memory and on disk
+pub fn clear_cookies() -> Result<()> {
+    if let Ok(mut store) = cookie_jar().lock() {
+        store.clear();
+    }
+    match std::fs::remove_file(cookies_path()) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).context("Failed to delete saved cookies")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Default headers of the scrape client
+///
+/// Client hints are only sent with the built-in Chrome User-Agent, so a
+/// custom one doesn't contradict them.
+fn scrape_headers(custom_user_agent: Option<String>) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        ACCEPT,
+        HeaderValue::from_static(
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        ),
+    );
+    headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"));
+
+    match custom_user_agent.filter(|ua| !ua.trim().is_empty()) {
+        Some(ua) => {
+            let value = HeaderValue::from_str(ua.trim())
+                .context("Invalid User-Agent")?;
+            headers.insert(USER_AGENT, value);
+        }
+        None => {
+            headers.insert(USER_AGENT, HeaderValue::from_static(BROWSER_USER_AGENT));
+            for (name, value) in BROWSER_CLIENT_HINTS {
+                headers.insert(*name, HeaderValue::from_static(value));
+            }
+        }
+    }
+    Ok(headers)
+}
+
+/// Builder with the settings both clients share
 ///
 /// Features:
 /// - Connection pooling (reuses TCP connections)
 /// - Gzip/Brotli decompression (reduces bandwidth ~4x)
 /// - TCP and HTTP/2 keepalive
 /// - Reasonable timeouts
-/// - Proper User-Agent
-pub fn get_http_client() -> &'static Client {
-    HTTP_CLIENT.get_or_init(|| {
-        Client::builder()
-            // Timeouts
-            .connect_timeout(Duration::from_secs(5))
-            .timeout(Duration::from_secs(20))
-            .read_timeout(Duration::from_secs(15))
-            // Connection pooling - OPTIMIZED
-            .pool_max_idle_per_host(15) // Up from 10
-            .pool_idle_timeout(Duration::from_secs(120)) // Up from 90
-            // TCP/HTTP keepalive - NEW
-            .tcp_keepalive(Some(Duration::from_secs(60)))
-            .http2_keep_alive_interval(Some(Duration::from_secs(30)))
-            .http2_keep_alive_timeout(Duration::from_secs(10))
-            // Compression - reduces traffic ~4x
-            .gzip(true)
-            .brotli(true)
-            // User agent (some sites block requests without it)
-            // .user_agent(concat!(
-            //     "Mozilla/5.0 (compatible; websearch-tui/",
-            //     env!("CARGO_PKG_VERSION"),
-            //     "; +https://github.com/user/websearch-tui)"
-            // ))
-            .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/115.0")
-            .build()
-            .expect("Failed to create HTTP client")
+/// - Proxy from the config or environment (fails on an unparsable proxy URL)
+fn base_client_builder() -> Result<ClientBuilder> {
+    let mut builder = Client::builder();
+
+    if let Some((source, url)) = select_proxy(network().proxy.as_deref(), |name| std::env::var(name).ok()) {
+        let proxy = Proxy::all(&url)
+            .with_context(|| format!("Invalid proxy URL in {}: {}", source, url))?
+            .no_proxy(NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder
+        // Timeouts
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(20))
+        .read_timeout(Duration::from_secs(15))
+        // Connection pooling - OPTIMIZED
+        .pool_max_idle_per_host(15) // Up from 10
+        .pool_idle_timeout(Duration::from_secs(120)) // Up from 90
+        // TCP/HTTP keepalive - NEW
+        .tcp_keepalive(Some(Duration::from_secs(60)))
+        .http2_keep_alive_interval(Some(Duration::from_secs(30)))
+        .http2_keep_alive_timeout(Duration::from_secs(10))
+        // Shorteners and resolvers redirect, but never endlessly
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        // Compression - reduces traffic ~4x
+        .gzip(true)
+        .brotli(true))
+}
+
+/// First set, non-empty proxy variable as `(name, url)`
+///
+/// `lookup` reads an environment variable.
+fn proxy_from_env(lookup: impl Fn(&str) -> Option<String>) -> Option<(&'static str, String)> {
+    PROXY_ENV_VARS.iter().find_map(|&var| {
+        lookup(var)
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .map(|url| (var, url))
+    })
+}
+
+/// The configured proxy, or else the first proxy environment variable
+///
+/// Returns `(source, url)`.
+fn select_proxy(
+    configured: Option<&str>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Option<(&'static str, String)> {
+    configured
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .map(|url| ("network.proxy", url))
+        .or_else(|| proxy_from_env(lookup))
+}
+
+/// Whether requests go through a proxy
+pub fn proxy_active() -> bool {
+    static ACTIVE: OnceLock<bool> = OnceLock::new();
+    *ACTIVE.get_or_init(|| {
+        select_proxy(network().proxy.as_deref(), |name| std::env::var(name).ok()).is_some()
     })
 }
 
 /// Initialize all global resources upfront
 ///
 /// Call this at startup to avoid initialization delays during first use.
-pub fn init_globals() -> Result<()> {
-    // Force initialization of HTTP client
-    let _ = get_http_client();
+/// Fails with a clear message when the proxy URL or User-Agent is invalid.
+pub fn init_globals(network: &NetworkConfig) -> Result<()> {
+    let _ = NETWORK.set(network.clone());
+    if network.offline {
+        return Ok(());
+    }
+    let _ = API_CLIENT.set(build_api_client()?);
+    let _ = SCRAPE_CLIENT.set(build_scrape_client(cookie_jar().clone())?);
     Ok(())
 }
 
@@ -62,9 +302,134 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_http_client_singleton() {
-        let client1 = get_http_client();
-        let client2 = get_http_client();
-        assert!(std::ptr::eq(client1, client2));
+    fn test_http_client_singletons() {
+        let api = get_api_client().unwrap();
+        let scrape = get_scrape_client().unwrap();
+        assert!(std::ptr::eq(api, get_api_client().unwrap()));
+        assert!(std::ptr::eq(scrape, get_scrape_client().unwrap()));
+        assert!(!std::ptr::eq(api, scrape));
+    }
+
+    #[tokio::test]
+    async fn test_cookies_are_replayed() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Sets a cookie on the first request and echoes the Cookie header after
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for first in [true, false] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let cookie = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("cookie: "))
+                    .unwrap_or("")
+                    .trim()
+                    .to_string();
+                let set_cookie = if first {
+                    "Set-Cookie: consent=yes; Path=/\r\n"
+                } else {
+                    ""
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    set_cookie,
+                    cookie.len(),
+                    cookie
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let client = build_scrape_client(Arc::default()).unwrap();
+        let url = format!("http://{}/", addr);
+        let first = client.get(&url).send().await.unwrap().text().await.unwrap();
+        assert_eq!(first, "");
+        let second = client.get(&url).send().await.unwrap().text().await.unwrap();
+        assert_eq!(second, "consent=yes");
+    }
+
+    #[test]
+    fn test_load_cookies_tolerates_missing_and_corrupt_files() {
+        let dir = std::env::temp_dir().join(format!("websearch-cookies-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cookies.json");
+
+        assert_eq!(load_cookies(&path).iter_any().count(), 0);
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(load_cookies(&path).iter_any().count(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_scrape_headers() {
+        let browser = scrape_headers(None).unwrap();
+        assert_eq!(browser[USER_AGENT], BROWSER_USER_AGENT);
+        assert_eq!(browser["sec-ch-ua-platform"], "\"Linux\"");
+        assert!(browser[ACCEPT].to_str().unwrap().starts_with("text/html"));
+
+        let custom = scrape_headers(Some("MyBrowser/1.0".to_string())).unwrap();
+        assert_eq!(custom[USER_AGENT], "MyBrowser/1.0");
+        assert!(!custom.contains_key("sec-ch-ua"));
+
+        assert_eq!(scrape_headers(Some(" ".to_string())).unwrap()[USER_AGENT], BROWSER_USER_AGENT);
+        assert!(scrape_headers(Some("bad\nagent".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_proxy_env_precedence() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        let all = env(&[
+            ("ALL_PROXY", "socks5h://127.0.0.1:9050"),
+            ("HTTPS_PROXY", "http://corp:3128"),
+            ("WEBSEARCH_PROXY", "socks5://127.0.0.1:1080"),
+        ]);
+        assert_eq!(
+            proxy_from_env(all),
+            Some(("WEBSEARCH_PROXY", "socks5://127.0.0.1:1080".to_string()))
+        );
+
+        let standard = env(&[("all_proxy", "socks5h://127.0.0.1:9050"), ("http_proxy", "http://corp:3128")]);
+        assert_eq!(
+            proxy_from_env(standard),
+            Some(("http_proxy", "http://corp:3128".to_string()))
+        );
+
+        // Empty values are treated as unset
+        let empty = env(&[("WEBSEARCH_PROXY", " "), ("ALL_PROXY", "socks5h://127.0.0.1:9050")]);
+        assert_eq!(
+            proxy_from_env(empty),
+            Some(("ALL_PROXY", "socks5h://127.0.0.1:9050".to_string()))
+        );
+
+        assert_eq!(proxy_from_env(env(&[])), None);
+
+        // The config file beats the generic variables
+        let corp = || env(&[("HTTPS_PROXY", "http://corp:3128")]);
+        assert_eq!(
+            select_proxy(Some("socks5h://127.0.0.1:9050"), corp()),
+            Some(("network.proxy", "socks5h://127.0.0.1:9050".to_string()))
+        );
+        assert_eq!(
+            select_proxy(Some(""), corp()),
+            Some(("HTTPS_PROXY", "http://corp:3128".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_socks_proxy_urls_are_accepted() {
+        assert!(Proxy::all("socks5h://127.0.0.1:9050").is_ok());
+        assert!(Proxy::all("http://[::1").is_err());
     }
 }
\ No newline at end of file
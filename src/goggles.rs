@@ -0,0 +1,176 @@
+//! Brave Goggles: custom ranking rules for Brave searches
+//!
+//! A goggle is a published rules file, like one that boosts documentation
+//! sites and buries SEO farms, and Brave re-ranks results by it when its
+//! URL goes along as `goggles_id`. Goggles are named in `[search.goggles]`;
+//! `search.brave_goggle` picks the one active at startup, by name or as a
+//! URL. `Ctrl+G` picks another and `Alt+G` turns the goggle off and on.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::config::SearchConfig;
+
+/// A goggle and the name it is shown with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Goggle {
+    pub name: String,
+    /// URL or ID sent as `goggles_id`
+    pub id: String,
+}
+
+impl Goggle {
+    /// The goggle `value` names in `named`, else `value` as a URL or ID
+    ///
+    /// An unnamed goggle is called by its file name, `tech_blogs` for
+    /// `https://example.com/goggles/tech_blogs.goggle`.
+    pub fn resolve(value: &str, named: &BTreeMap<String, String>) -> Self {
+        if let Some(id) = named.get(value) {
+            return Goggle {
+                name: value.to_string(),
+                id: id.clone(),
+            };
+        }
+        let file = value.trim_end_matches('/').rsplit('/').next().unwrap_or(value);
+        let name = file.strip_suffix(".goggle").unwrap_or(file);
+        Goggle {
+            name: if name.is_empty() { value } else { name }.to_string(),
+            id: value.to_string(),
+        }
+    }
+}
+
+/// Every configured goggle: the named ones, then `brave_goggle` if unnamed
+pub fn configured(config: &SearchConfig) -> Vec<Goggle> {
+    let mut goggles: Vec<Goggle> = config
+        .goggles
+        .iter()
+        .map(|(name, id)| Goggle {
+            name: name.clone(),
+            id: id.clone(),
+        })
+        .collect();
+    if let Some(value) = &config.brave_goggle
+        && !config.goggles.contains_key(value)
+    {
+        goggles.push(Goggle::resolve(value, &config.goggles));
+    }
+    goggles
+}
+
+/// Goggle active at startup
+pub fn initial(config: &SearchConfig) -> Option<Goggle> {
+    let value = config.brave_goggle.as_deref()?;
+    Some(Goggle::resolve(value, &config.goggles))
+}
+
+/// Error for a goggle Brave refused (HTTP 422), with Brave's reason if given
+pub fn rejection_message(goggle: &Goggle, body: &str) -> String {
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        error: ErrorDetail,
+    }
+    #[derive(Deserialize)]
+    struct ErrorDetail {
+        detail: String,
+    }
+
+    let mut message = format!(
+        "Brave rejected the goggle {} ({}); check that it is a published goggle URL",
+        goggle.name, goggle.id
+    );
+    if let Ok(response) = serde_json::from_str::<ErrorResponse>(body) {
+        message.push_str(&format!(": {}", response.error.detail));
+    }
+    message
+}
+
+/// Picker popup: no goggle, then the configured ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoggleMenu {
+    pub goggles: Vec<Goggle>,
+    /// 0 is "no goggle", `i + 1` is `goggles[i]`
+    pub selected: usize,
+}
+
+impl GoggleMenu {
+    /// Menu over `goggles` with `active` selected
+    pub fn new(goggles: Vec<Goggle>, active: Option<&Goggle>) -> Self {
+        let selected = active
+            .and_then(|active| goggles.iter().position(|g| g == active))
+            .map_or(0, |i| i + 1);
+        Self { goggles, selected }
+    }
+
+    /// Move the selection down (wraps)
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % (self.goggles.len() + 1);
+    }
+
+    /// Move the selection up (wraps)
+    pub fn previous(&mut self) {
+        let count = self.goggles.len() + 1;
+        self.selected = (self.selected + count - 1) % count;
+    }
+
+    /// The selected goggle, `None` for "no goggle"
+    pub fn selected_goggle(&self) -> Option<&Goggle> {
+        self.selected.checked_sub(1).and_then(|i| self.goggles.get(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SearchConfig {
+        SearchConfig {
+            brave_goggle: Some("https://example.com/goggles/tech_blogs.goggle".to_string()),
+            goggles: BTreeMap::from([
+                ("docs".to_string(), "https://example.com/docs.goggle".to_string()),
+                ("no_seo".to_string(), "https://example.com/no-seo.goggle".to_string()),
+            ]),
+            ..SearchConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_names_and_urls() {
+        let config = config();
+        let docs = Goggle::resolve("docs", &config.goggles);
+        assert_eq!(docs.id, "https://example.com/docs.goggle");
+
+        let startup = initial(&config).unwrap();
+        assert_eq!(startup.name, "tech_blogs");
+        assert_eq!(startup.id, "https://example.com/goggles/tech_blogs.goggle");
+        assert_eq!(Goggle::resolve("a1b2c3", &BTreeMap::new()).name, "a1b2c3");
+
+        let names: Vec<_> = configured(&config).into_iter().map(|g| g.name).collect();
+        assert_eq!(names, ["docs", "no_seo", "tech_blogs"]);
+        assert_eq!(initial(&SearchConfig::default()), None);
+
+        let body = r#"{"type":"ErrorResponse","error":{"id":"x","status":422,
+            "code":"VALIDATION","detail":"Unable to validate request parameter(s)."}}"#;
+        assert_eq!(
+            rejection_message(&docs, body),
+            "Brave rejected the goggle docs (https://example.com/docs.goggle); check that it is \
+             a published goggle URL: Unable to validate request parameter(s)."
+        );
+        assert!(rejection_message(&docs, "Unprocessable").ends_with("goggle URL"));
+    }
+
+    #[test]
+    fn test_menu_starts_at_active_goggle() {
+        let goggles = configured(&config());
+        let mut menu = GoggleMenu::new(goggles.clone(), Some(&goggles[1]));
+        assert_eq!(menu.selected_goggle(), Some(&goggles[1]));
+        menu.next();
+        menu.next();
+        assert_eq!(menu.selected_goggle(), None);
+        menu.previous();
+        assert_eq!(menu.selected_goggle().map(|g| g.name.as_str()), Some("tech_blogs"));
+
+        let menu = GoggleMenu::new(Vec::new(), None);
+        assert_eq!((menu.selected, menu.selected_goggle()), (0, None));
+    }
+}
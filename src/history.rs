@@ -0,0 +1,125 @@
+//! Earlier result sets, for moving back and forward between searches
+//!
+//! Each new search pushes the one it replaces onto the back stack, like a
+//! browser. Only a few are kept; the directories in `current_search/`
+//! holding their pages stay until they drop off the end.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::config::Engine;
+use crate::prefetch::PrefetchStatus;
+use crate::search::SearchResult;
+
+/// Most result sets kept behind the current one
+const MAX_ENTRIES: usize = 10;
+
+/// A result set as it was when the user left it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSnapshot {
+    pub query: String,
+    pub engine: Engine,
+    pub results: Vec<SearchResult>,
+    pub selected: usize,
+    pub statuses: HashMap<String, PrefetchStatus>,
+}
+
+/// Back and forward stacks of result sets
+#[derive(Debug, Default)]
+pub struct SearchHistory {
+    back: VecDeque<SearchSnapshot>,
+    forward: Vec<SearchSnapshot>,
+}
+
+impl SearchHistory {
+    /// Remember the result set a new search replaces
+    ///
+    /// Drops the forward stack and, past the limit, the oldest entry.
+    pub fn push(&mut self, current: SearchSnapshot) {
+        self.forward.clear();
+        self.back.push_back(current);
+        if self.back.len() > MAX_ENTRIES {
+            self.back.pop_front();
+        }
+    }
+
+    /// Step back from `current` to the previous result set
+    pub fn back(&mut self, current: SearchSnapshot) -> Option<SearchSnapshot> {
+        let previous = self.back.pop_back()?;
+        self.forward.push(current);
+        Some(previous)
+    }
+
+    /// Step forward from `current` to the result set left with `back`
+    pub fn forward(&mut self, current: SearchSnapshot) -> Option<SearchSnapshot> {
+        let next = self.forward.pop()?;
+        self.back.push_back(current);
+        Some(next)
+    }
+
+    /// URLs of every remembered result, whose pages must stay on disk
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        self.back
+            .iter()
+            .chain(&self.forward)
+            .flat_map(|snapshot| &snapshot.results)
+            .map(|result| result.url.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(query: &str) -> SearchSnapshot {
+        SearchSnapshot {
+            query: query.to_string(),
+            engine: Engine::Brave,
+            results: vec![SearchResult {
+                title: query.to_string(),
+                url: format!("https://example.com/{}", query),
+                description: String::new(),
+                ..Default::default()
+            }],
+            selected: 0,
+            statuses: HashMap::new(),
+        }
+    }
+
+    fn query(snapshot: Option<SearchSnapshot>) -> Option<String> {
+        snapshot.map(|s| s.query)
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut history = SearchHistory::default();
+        history.push(snapshot("a"));
+        history.push(snapshot("b"));
+
+        // Showing "c": back to b, back to a, then forward again
+        assert_eq!(query(history.back(snapshot("c"))), Some("b".to_string()));
+        assert_eq!(query(history.back(snapshot("b"))), Some("a".to_string()));
+        assert_eq!(query(history.back(snapshot("a"))), None);
+        assert_eq!(query(history.forward(snapshot("a"))), Some("b".to_string()));
+        assert_eq!(query(history.forward(snapshot("b"))), Some("c".to_string()));
+        assert_eq!(query(history.forward(snapshot("c"))), None);
+
+        // A new search drops the forward stack
+        history.back(snapshot("c"));
+        history.push(snapshot("b"));
+        assert_eq!(query(history.forward(snapshot("d"))), None);
+        assert_eq!(
+            history.urls().collect::<Vec<_>>(),
+            ["https://example.com/a", "https://example.com/b"]
+        );
+    }
+
+    #[test]
+    fn test_oldest_entries_are_dropped() {
+        let mut history = SearchHistory::default();
+        for i in 0..MAX_ENTRIES + 2 {
+            history.push(snapshot(&i.to_string()));
+        }
+        assert_eq!(history.urls().count(), MAX_ENTRIES);
+        assert_eq!(history.urls().next(), Some("https://example.com/2"));
+    }
+}
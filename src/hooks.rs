@@ -0,0 +1,174 @@
+//! User commands run on saved pages
+//!
+//! `hooks.post_fetch_hook` runs after a page has been fetched and converted
+//! to markdown, `hooks.on_open` when a page is opened in the editor.
+//! `{path}` and `{url}` in the command are replaced with shell-quoted
+//! values, so they must not be quoted again. Hooks run through the shell in
+//! the background: nothing waits for them, one running longer than
+//! `hooks.timeout_secs` is killed, and failures only go to the log.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+/// Longest stderr excerpt kept in the log when a hook fails
+const MAX_STDERR_CHARS: usize = 500;
+
+/// `template` with `{path}` and `{url}` replaced by quoted values
+///
+/// Without a path `{path}` is left as it is. The values aren't searched
+/// again, so a URL containing `{path}` stays as it is.
+pub fn command_line(template: &str, path: Option<&Path>, url: &str) -> String {
+    let path = path.map(Path::to_string_lossy);
+    let mut line = String::with_capacity(template.len() + url.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        line.push_str(&rest[..open]);
+        rest = &rest[open..];
+        if let (Some(after), Some(path)) = (rest.strip_prefix("{path}"), &path) {
+            line.push_str(&shell_words::quote(path));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{url}") {
+            line.push_str(&shell_words::quote(url));
+            rest = after;
+        } else {
+            line.push('{');
+            rest = &rest[1..];
+        }
+    }
+
+    line.push_str(rest);
+    line
+}
+
+/// Run the hook `name` for a page in the background
+pub fn spawn(name: &'static str, template: &str, path: &Path, url: &str, limit: Duration) {
+    let line = command_line(template, Some(path), url);
+    let url = url.to_string();
+    tokio::spawn(async move {
+        match run(&line, limit).await {
+            Ok(()) => tracing::debug!(hook = name, url = url.as_str(), "hook finished"),
+            Err(e) => tracing::warn!(
+                hook = name,
+                url = url.as_str(),
+                command = line.as_str(),
+                "hook failed: {:#}",
+                e
+            ),
+        }
+    });
+}
+
+/// Run a shell command line, killing it after `limit`
+async fn run(line: &str, limit: Duration) -> Result<()> {
+    let child = spawn_shell(line).context("Failed to start the hook")?;
+    wait(child, Some(limit)).await
+}
+
+/// Start a shell command line detached from the terminal
+///
+/// Only stderr is kept, for [`wait`] to report failures with.
+pub fn spawn_shell(line: &str) -> std::io::Result<Child> {
+    shell(line)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+}
+
+/// Wait for a command started by [`spawn_shell`], killing it after `limit`
+///
+/// Fails with the exit status and the start of stderr unless it succeeds.
+pub async fn wait(mut child: Child, limit: Option<Duration>) -> Result<()> {
+    // Read stderr while waiting so a chatty command can't fill the pipe
+    let mut stderr = child.stderr.take();
+    let read_stderr = async {
+        let mut buf = Vec::new();
+        if let Some(stderr) = &mut stderr {
+            let _ = tokio::io::AsyncReadExt::read_to_end(stderr, &mut buf).await;
+        }
+        buf
+    };
+    let finished = async { tokio::join!(child.wait(), read_stderr) };
+    let finished = match limit {
+        Some(limit) => tokio::time::timeout(limit, finished)
+            .await
+            .map_err(|_| limit),
+        None => Ok(finished.await),
+    };
+    let (status, stderr) = match finished {
+        Ok((status, stderr)) => (status.context("Failed to wait for the command")?, stderr),
+        Err(limit) => {
+            let _ = child.kill().await;
+            anyhow::bail!("timed out after {}s", limit.as_secs_f32());
+        }
+    };
+
+    if status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&stderr);
+    let excerpt: String = stderr.trim().chars().take(MAX_STDERR_CHARS).collect();
+    anyhow::bail!("{}: {}", status, excerpt)
+}
+
+/// `sh -c line`, or `cmd /C line` on Windows
+fn shell(line: &str) -> Command {
+    let (program, flag) = if cfg!(target_os = "windows") {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut command = Command::new(program);
+    command.arg(flag).arg(line);
+    command
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hook_gets_quoted_path_and_url() {
+        let dir = std::env::temp_dir().join(format!("websearch hooks {}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("record args.sh");
+        let record = dir.join("args.txt");
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\nfor arg in \"$@\"; do printf '%s\\n' \"$arg\"; done > {}\n",
+                shell_words::quote(&record.to_string_lossy())
+            ),
+        )
+        .unwrap();
+
+        let script = shell_words::quote(&script.to_string_lossy()).into_owned();
+        let template = format!("sh {} {{path}} --url={{url}} {{other}}", script);
+        let page = dir.join("it's a page.md");
+        let url = "https://example.com/a?b=1&c='2'#{path}";
+        run(&command_line(&template, Some(&page), url), Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        let args = std::fs::read_to_string(&record).unwrap();
+        assert_eq!(args, format!("{}\n--url={}\n{{other}}\n", page.display(), url));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_hook_failures_and_timeouts_are_errors() {
+        let failed = run("echo broken >&2; exit 3", Duration::from_secs(10)).await;
+        let message = format!("{:#}", failed.unwrap_err());
+        assert!(message.contains("3") && message.ends_with("broken"), "{}", message);
+
+        let started = std::time::Instant::now();
+        let slow = run("sleep 5", Duration::from_millis(100)).await;
+        assert!(format!("{:#}", slow.unwrap_err()).starts_with("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,220 @@
+//! Images of saved pages
+//!
+//! `extraction.images` decides what happens to the images of an article.
+//! `links` keeps them pointing at the web and `strip` replaces each with
+//! its alt text in italics. `download` saves up to ten images of 2 MB at
+//! most into `assets/<page-hash>/` while the page is prefetched, and points
+//! the markdown at the local copies. `assets/` sits next to the cache
+//! directories, so the relative links keep working when a page is opened
+//! or pinned. Asset directories whose page is gone are removed with the
+//! cache cleanup.
+
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::{timeout_at, Instant};
+
+use crate::extract_clean_md::rewrite_links;
+use crate::globals::get_scrape_client;
+
+/// Images downloaded per page
+const MAX_IMAGES: usize = 10;
+
+/// Larger images are left as links
+const MAX_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+
+/// `markdown` with each image replaced by its alt text in italics
+pub fn strip(markdown: &str) -> String {
+    rewrite_links(markdown, |alt, _, image| {
+        if !image {
+            return None;
+        }
+        let alt = alt.trim();
+        Some(if alt.is_empty() {
+            String::new()
+        } else {
+            format!("*{}*", alt)
+        })
+    })
+}
+
+/// Download the web images of `markdown` into `dir`
+///
+/// Returns the markdown with the images saved pointing at
+/// `link_prefix` plus their file name. Images that fail, are too large or
+/// don't finish within `budget` keep their web address.
+pub async fn download(markdown: &str, dir: &Path, link_prefix: &str, budget: Duration) -> String {
+    let mut urls = Vec::new();
+    rewrite_links(markdown, |_, target, image| {
+        if image
+            && (target.starts_with("http://") || target.starts_with("https://"))
+            && !urls.iter().any(|url| url == target)
+        {
+            urls.push(target.to_string());
+        }
+        None
+    });
+    urls.truncate(MAX_IMAGES);
+    if urls.is_empty() {
+        return markdown.to_string();
+    }
+
+    let deadline = Instant::now() + budget;
+    let fetched = join_all(urls.iter().map(|url| timeout_at(deadline, fetch_image(url)))).await;
+
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        tracing::warn!(dir = %dir.display(), "Failed to create the assets directory: {}", e);
+        return markdown.to_string();
+    }
+    let mut saved = HashMap::new();
+    for (i, (url, fetched)) in urls.iter().zip(fetched).enumerate() {
+        let (body, content_type) = match fetched {
+            Ok(Ok(image)) => image,
+            Ok(Err(e)) => {
+                tracing::debug!(url = url.as_str(), "image skipped: {:#}", e);
+                continue;
+            }
+            Err(_) => {
+                tracing::debug!(url = url.as_str(), "image skipped: out of time");
+                continue;
+            }
+        };
+        let name = file_name(i + 1, url, content_type.as_deref());
+        match std::fs::write(dir.join(&name), body) {
+            Ok(()) => {
+                saved.insert(url.as_str(), format!("{}{}", link_prefix, name));
+            }
+            Err(e) => tracing::warn!(url = url.as_str(), "Failed to save image: {}", e),
+        }
+    }
+
+    rewrite_links(markdown, |alt, target, image| {
+        let local = saved.get(target).filter(|_| image)?;
+        Some(format!("![{}]({})", alt, local))
+    })
+}
+
+/// Download one image, with its content type
+async fn fetch_image(url: &str) -> Result<(Vec<u8>, Option<String>)> {
+    let mut response = get_scrape_client()?
+        .get(url)
+        .header("Accept", "image/*")
+        .send()
+        .await
+        .context("Failed to download image")?
+        .error_for_status()?;
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    if let Some(content_type) = &content_type {
+        anyhow::ensure!(content_type.starts_with("image/"), "not an image: {}", content_type);
+    }
+    let too_large = || anyhow::anyhow!("image too large (>{}MB)", MAX_IMAGE_BYTES / (1024 * 1024));
+    if response.content_length().is_some_and(|len| len > MAX_IMAGE_BYTES as u64) {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.context("Failed to read image")? {
+        if body.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, content_type))
+}
+
+/// File name of the `n`th image, like `03.png`
+///
+/// The extension comes from the URL, else from the content type.
+fn file_name(n: usize, url: &str, content_type: Option<&str>) -> String {
+    let from_url = url::Url::parse(url).ok().and_then(|url| {
+        let extension = url.path().rsplit_once('.')?.1.to_ascii_lowercase();
+        let known = ["png", "jpg", "jpeg", "gif", "webp", "svg", "avif", "bmp", "ico"];
+        known.contains(&extension.as_str()).then_some(extension)
+    });
+    let extension = from_url.unwrap_or_else(|| {
+        let subtype = content_type
+            .and_then(|t| t.split(';').next())
+            .and_then(|t| t.trim().strip_prefix("image/"))
+            .unwrap_or("");
+        match subtype {
+            "jpeg" => "jpg".to_string(),
+            "svg+xml" => "svg".to_string(),
+            "png" | "gif" | "webp" | "avif" | "bmp" => subtype.to_string(),
+            _ => "img".to_string(),
+        }
+    });
+    format!("{:02}.{}", n, extension)
+}
+
+/// Remove the directories in `assets` whose page isn't in any of `dirs`
+///
+/// Pages are matched by the URL hash in their file name. Returns the
+/// number of directories removed.
+pub fn remove_orphans(assets: &Path, dirs: &[impl AsRef<Path>]) -> usize {
+    let Ok(entries) = std::fs::read_dir(assets) else {
+        return 0;
+    };
+    let pages: HashSet<String> = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .flat_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.split(['_', '.'])
+                .filter(|part| part.len() == 8 && part.chars().all(|c| c.is_ascii_hexdigit()))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    entries
+        .flatten()
+        .filter(|entry| !pages.contains(entry.file_name().to_string_lossy().as_ref()))
+        .filter(|entry| std::fs::remove_dir_all(entry.path()).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_keeps_alt_text() {
+        let markdown = "Intro ![A diagram](https://a.example/d.png) and ![](x.png).\n\
+                        [![Logo](https://a.example/l.svg)](https://a.example/) \
+                        [link](https://b.example/)";
+        assert_eq!(
+            strip(markdown),
+            "Intro *A diagram* and .\n[*Logo*](https://a.example/) [link](https://b.example/)"
+        );
+        assert_eq!(file_name(3, "https://a.example/img/Photo.JPG?w=200", None), "03.jpg");
+        assert_eq!(file_name(12, "https://a.example/render", Some("image/svg+xml")), "12.svg");
+    }
+
+    #[test]
+    fn test_remove_orphans_keeps_assets_of_cached_pages() {
+        let root = std::env::temp_dir().join(format!("websearch-assets-{}", std::process::id()));
+        let pages = root.join("pinned");
+        let assets = root.join("assets");
+        for dir in ["0badf00d", "a3f8d912", "deadbeef"] {
+            std::fs::create_dir_all(assets.join(dir)).unwrap();
+            std::fs::write(assets.join(dir).join("01.png"), "").unwrap();
+        }
+        std::fs::create_dir_all(&pages).unwrap();
+        std::fs::write(pages.join("github_com_a3f8d912_Rust_Guide.md"), "").unwrap();
+        std::fs::write(pages.join("example_org_deadbeef_.md"), "").unwrap();
+
+        assert_eq!(remove_orphans(&assets, &[&pages, &root.join("missing")]), 1);
+        assert!(!assets.join("0badf00d").exists());
+        assert!(assets.join("a3f8d912/01.png").exists() && assets.join("deadbeef").exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
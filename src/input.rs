@@ -0,0 +1,405 @@
+//! Single-line text input with Unicode-aware editing
+//!
+//! The cursor is tracked as a char index (never a byte offset), so editing
+//! can't split a multi-byte character. Screen columns are computed
+//! separately with unicode-width, which handles double-width CJK and
+//! zero-width combining characters.
+
+use unicode_width::UnicodeWidthChar;
+
+/// Editable line of text with a cursor
+#[derive(Debug, Clone, Default)]
+pub struct InputLine {
+    text: String,
+    /// Cursor position in chars, in `0..=char_count`
+    cursor: usize,
+}
+
+impl InputLine {
+    /// Current text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Number of chars in the text
+    pub fn char_count(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Insert a char at the cursor
+    pub fn insert_char(&mut self, c: char) {
+        let byte_pos = self.byte_pos(self.cursor);
+        self.text.insert(byte_pos, c);
+        self.cursor += 1;
+    }
+
+    /// Insert a string at the cursor, dropping control characters
+    pub fn insert_str(&mut self, text: &str) {
+        let filtered: String = text.chars().filter(|c| !c.is_control()).collect();
+        let byte_pos = self.byte_pos(self.cursor);
+        self.text.insert_str(byte_pos, &filtered);
+        self.cursor += filtered.chars().count();
+    }
+
+    /// Delete the char before the cursor (Backspace)
+    pub fn delete_before(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.remove_chars(self.cursor, self.cursor + 1);
+        }
+    }
+
+    /// Delete the char under the cursor (Delete)
+    pub fn delete_after(&mut self) {
+        if self.cursor < self.char_count() {
+            self.remove_chars(self.cursor, self.cursor + 1);
+        }
+    }
+
+    /// Move cursor one char left
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    /// Move cursor one char right
+    pub fn move_right(&mut self) {
+        if self.cursor < self.char_count() {
+            self.cursor += 1;
+        }
+    }
+
+    /// Move cursor to start of line
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Move cursor to end of line
+    pub fn move_end(&mut self) {
+        self.cursor = self.char_count();
+    }
+
+    /// Move cursor to the start of the previous word (Alt+B)
+    pub fn move_word_left(&mut self) {
+        self.cursor = self.word_start_before(self.cursor);
+    }
+
+    /// Move cursor to the end of the next word (Alt+F)
+    pub fn move_word_right(&mut self) {
+        self.cursor = self.word_end_after(self.cursor);
+    }
+
+    /// Delete from the start of the previous word to the cursor (Ctrl+W)
+    pub fn delete_word_before(&mut self) {
+        let start = self.word_start_before(self.cursor);
+        self.remove_chars(start, self.cursor);
+        self.cursor = start;
+    }
+
+    /// Delete from the start of the line to the cursor (Ctrl+U)
+    pub fn kill_to_start(&mut self) {
+        self.remove_chars(0, self.cursor);
+        self.cursor = 0;
+    }
+
+    /// Delete from the cursor to the end of the line (Ctrl+K)
+    pub fn kill_to_end(&mut self) {
+        self.remove_chars(self.cursor, self.char_count());
+    }
+
+    /// Clear text and cursor
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    /// Visible slice and cursor column for a box `width` columns wide
+    ///
+    /// Scrolls horizontally so the cursor always stays inside the box.
+    /// Returns the visible text and the cursor's screen column within it.
+    pub fn visible_window(&self, width: usize) -> (&str, usize) {
+        let width = width.max(1);
+        let widths: Vec<usize> = self.text.chars().map(char_width).collect();
+        let cursor_col: usize = widths[..self.cursor].iter().sum();
+        let (start, start_col) = window_start(&widths, self.cursor, width);
+
+        // Take as many chars as fit from `start`
+        let mut end = start;
+        let mut used = 0;
+        while end < widths.len() && used + widths[end] <= width {
+            used += widths[end];
+            end += 1;
+        }
+
+        let (start_byte, end_byte) = (self.byte_pos(start), self.byte_pos(end));
+        (&self.text[start_byte..end_byte], cursor_col - start_col)
+    }
+
+    /// Move the cursor to screen `column` of a box `width` columns wide
+    ///
+    /// Uses the same horizontal scroll as [`Self::visible_window`], so a
+    /// click lands on the char drawn under it (or the end of the text).
+    pub fn move_to_column(&mut self, column: usize, width: usize) {
+        let widths: Vec<usize> = self.text.chars().map(char_width).collect();
+        let (mut pos, _) = window_start(&widths, self.cursor, width.max(1));
+        let mut col = 0;
+        // A click on the right half of a wide char goes after it
+        while pos < widths.len() && col + widths[pos].div_ceil(2) <= column {
+            col += widths[pos];
+            pos += 1;
+        }
+        self.cursor = pos;
+    }
+
+    /// Char index where the word before `pos` starts
+    ///
+    /// Skips separators first, then word chars, so `docs.rs/tokio|` stops
+    /// at `docs.rs/|tokio`.
+    fn word_start_before(&self, pos: usize) -> usize {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut i = pos;
+        while i > 0 && !is_word_char(chars[i - 1]) {
+            i -= 1;
+        }
+        while i > 0 && is_word_char(chars[i - 1]) {
+            i -= 1;
+        }
+        i
+    }
+
+    /// Char index where the word after `pos` ends
+    fn word_end_after(&self, pos: usize) -> usize {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut i = pos;
+        while i < chars.len() && !is_word_char(chars[i]) {
+            i += 1;
+        }
+        while i < chars.len() && is_word_char(chars[i]) {
+            i += 1;
+        }
+        i
+    }
+
+    /// Remove chars in `start..end` (char indices)
+    fn remove_chars(&mut self, start: usize, end: usize) {
+        let (start_byte, end_byte) = (self.byte_pos(start), self.byte_pos(end));
+        self.text.drain(start_byte..end_byte);
+    }
+
+    /// Convert a char index to a byte offset
+    fn byte_pos(&self, char_pos: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_pos)
+            .map(|(byte_pos, _)| byte_pos)
+            .unwrap_or(self.text.len())
+    }
+}
+
+/// First visible char and its column when the cursor is at `cursor`
+///
+/// Leaves room for the cursor cell itself at the right edge.
+fn window_start(widths: &[usize], cursor: usize, width: usize) -> (usize, usize) {
+    let cursor_col: usize = widths[..cursor].iter().sum();
+    let mut start = 0;
+    let mut start_col = 0;
+    while cursor_col - start_col >= width && start < cursor {
+        start_col += widths[start];
+        start += 1;
+    }
+    (start, start_col)
+}
+
+/// Word chars for word motions; punctuation like `/` and `.` separates words
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Display width of a char (control chars count as zero)
+fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> InputLine {
+        let mut input = InputLine::default();
+        text.chars().for_each(|c| input.insert_char(c));
+        input
+    }
+
+    #[test]
+    fn test_insert_and_delete_emoji() {
+        let mut input = line("a🦀b");
+        assert_eq!(input.cursor, 3);
+        input.move_left();
+        input.delete_before();
+        assert_eq!(input.text(), "ab");
+        assert_eq!(input.cursor, 1);
+        input.insert_char('🚀');
+        assert_eq!(input.text(), "a🚀b");
+        input.delete_after();
+        assert_eq!(input.text(), "a🚀");
+    }
+
+    #[test]
+    fn test_combining_characters() {
+        // "é" as e + COMBINING ACUTE ACCENT
+        let mut input = line("cafe\u{301}");
+        assert_eq!(input.char_count(), 5);
+        let (visible, col) = input.visible_window(20);
+        assert_eq!(visible, "cafe\u{301}");
+        // Combining mark takes no column
+        assert_eq!(col, 4);
+
+        input.delete_before();
+        assert_eq!(input.text(), "cafe");
+    }
+
+    #[test]
+    fn test_double_width_cjk_cursor_column() {
+        let mut input = line("日本語");
+        assert_eq!(input.visible_window(20).1, 6);
+        input.move_left();
+        assert_eq!(input.visible_window(20).1, 4);
+        input.move_home();
+        input.delete_after();
+        assert_eq!(input.text(), "本語");
+        assert_eq!(input.visible_window(20).1, 0);
+    }
+
+    #[test]
+    fn test_move_to_column() {
+        let mut input = line("a日本b");
+        input.move_to_column(0, 20);
+        assert_eq!(input.cursor, 0);
+        input.move_to_column(2, 20);
+        assert_eq!(input.cursor, 2);
+        input.move_to_column(99, 20);
+        assert_eq!(input.cursor, 4);
+
+        // Scrolled: "abcdefghij" in a 5-column box shows "ghij" plus the cursor
+        let mut input = line("abcdefghij");
+        input.move_to_column(1, 5);
+        assert_eq!(input.cursor, 7);
+    }
+
+    #[test]
+    fn test_cursor_bounds() {
+        let mut input = line("ab");
+        input.move_right();
+        assert_eq!(input.cursor, 2);
+        input.move_home();
+        input.move_left();
+        assert_eq!(input.cursor, 0);
+        input.delete_before();
+        assert_eq!(input.text(), "ab");
+        input.move_end();
+        input.delete_after();
+        assert_eq!(input.text(), "ab");
+    }
+
+    #[test]
+    fn test_move_word_left_and_right() {
+        let mut input = line("https://docs.rs/tokio now");
+        input.move_word_left();
+        assert_eq!(input.cursor, 22);
+        input.move_word_left();
+        assert_eq!(input.cursor, 16);
+        input.move_word_left();
+        assert_eq!(input.cursor, 13);
+        input.move_word_right();
+        assert_eq!(input.cursor, 15);
+        input.move_word_right();
+        assert_eq!(input.cursor, 21);
+
+        // Boundaries are no-ops
+        input.move_end();
+        input.move_word_right();
+        assert_eq!(input.cursor, input.char_count());
+        input.move_home();
+        input.move_word_left();
+        assert_eq!(input.cursor, 0);
+    }
+
+    #[test]
+    fn test_delete_word_before() {
+        let mut input = line("rust docs.rs/tokio");
+        input.delete_word_before();
+        assert_eq!(input.text(), "rust docs.rs/");
+        input.delete_word_before();
+        assert_eq!(input.text(), "rust docs.");
+        input.delete_word_before();
+        assert_eq!(input.text(), "rust ");
+        input.delete_word_before();
+        assert_eq!(input.text(), "");
+        // At the start of the line nothing happens
+        input.delete_word_before();
+        assert_eq!(input.text(), "");
+        assert_eq!(input.cursor, 0);
+    }
+
+    #[test]
+    fn test_delete_word_before_mid_line_unicode() {
+        let mut input = line("héllo wörld");
+        input.move_word_left();
+        input.delete_word_before();
+        assert_eq!(input.text(), "wörld");
+        assert_eq!(input.cursor, 0);
+    }
+
+    #[test]
+    fn test_kill_to_start_and_end() {
+        let mut input = line("日本語 query");
+        input.move_word_left();
+        input.kill_to_end();
+        assert_eq!(input.text(), "日本語 ");
+        assert_eq!(input.cursor, 4);
+
+        let mut input = line("日本語 query");
+        input.move_word_left();
+        input.kill_to_start();
+        assert_eq!(input.text(), "query");
+        assert_eq!(input.cursor, 0);
+
+        // No-ops at the boundaries
+        input.kill_to_start();
+        assert_eq!(input.text(), "query");
+        input.move_end();
+        input.kill_to_end();
+        assert_eq!(input.text(), "query");
+    }
+
+    #[test]
+    fn test_insert_str_filters_control_chars() {
+        let mut input = line("ab");
+        input.move_left();
+        input.insert_str("x\x1b[1;5Dy\u{7}z");
+        assert_eq!(input.text(), "ax[1;5Dyzb");
+        assert_eq!(input.cursor, 9);
+    }
+
+    #[test]
+    fn test_horizontal_scroll_keeps_cursor_visible() {
+        let input = line("0123456789abcdef");
+        let (visible, col) = input.visible_window(8);
+        assert_eq!(visible, "9abcdef");
+        assert_eq!(col, 7);
+
+        let mut input = input;
+        input.move_home();
+        let (visible, col) = input.visible_window(8);
+        assert_eq!(visible, "01234567");
+        assert_eq!(col, 0);
+    }
+
+    #[test]
+    fn test_horizontal_scroll_with_wide_chars() {
+        let input = line("日本語日本語");
+        let (visible, col) = input.visible_window(5);
+        // Cursor at column 12; needs the cursor cell free at the right
+        assert_eq!(visible, "本語");
+        assert_eq!(col, 4);
+    }
+}
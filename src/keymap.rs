@@ -0,0 +1,799 @@
+//! Configurable keybindings
+//!
+//! Actions are bound to key descriptors like `"ctrl+b"`, `"G"`, `"F5"` or
+//! the sequence `"g g"`. The `[keys]` section of `config.toml` replaces the
+//! default bindings of the actions it names:
+//!
+//! ```toml
+//! [keys]
+//! open_browser = "ctrl+o"
+//! search_ddg = ["alt+d", "F2"]
+//! ```
+//!
+//! Text editing in the search box and the Library filter, and count
+//! prefixes in the results list, are not configurable.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::app::Motion;
+use crate::config::Engine;
+
+/// Screen a key press is handled on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyContext {
+    /// The search box
+    Input,
+    /// The results list
+    Results,
+    /// The Library
+    Library,
+    /// The Bookmarks view
+    Bookmarks,
+    /// Cached pages found with the local search
+    LocalSearch,
+}
+
+/// Something a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Search,
+    SearchDdg,
+    SearchSearxng,
+    SearchStartpage,
+    Library,
+    LocalSearch,
+    Bookmarks,
+    Clear,
+    Next,
+    Prev,
+    First,
+    Last,
+    HalfPageDown,
+    HalfPageUp,
+    WindowTop,
+    WindowMiddle,
+    WindowBottom,
+    ToggleSelect,
+    SelectAll,
+    InvertSelection,
+    OpenEditor,
+    OpenBrowser,
+    CopyUrl,
+    CopyPath,
+    Retry,
+    RetryAll,
+    Refresh,
+    ReExtract,
+    Pin,
+    Bookmark,
+    Export,
+    ExportJson,
+    ExportObsidian,
+    Cite,
+    OpenWith,
+    Back,
+    HistoryBack,
+    HistoryForward,
+    NextPage,
+    Delete,
+    Undo,
+    Sort,
+    SiteSearch,
+    Outline,
+    Annotate,
+    Filter,
+    EngineStatus,
+    Goggles,
+    ToggleGoggle,
+    Density,
+    ToggleRead,
+    Preview,
+    Suggestions,
+    Quickfix,
+}
+
+use KeyContext::{Bookmarks, Input, Library, LocalSearch, Results};
+
+impl Action {
+    /// Every action, in the order bindings are matched
+    const ALL: &[Action] = &[
+        Action::Quit,
+        Action::Search,
+        Action::SearchDdg,
+        Action::SearchSearxng,
+        Action::SearchStartpage,
+        Action::Library,
+        Action::LocalSearch,
+        Action::Bookmarks,
+        Action::Clear,
+        Action::Next,
+        Action::Prev,
+        Action::First,
+        Action::Last,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::WindowTop,
+        Action::WindowMiddle,
+        Action::WindowBottom,
+        Action::ToggleSelect,
+        Action::SelectAll,
+        Action::InvertSelection,
+        Action::OpenEditor,
+        Action::OpenBrowser,
+        Action::CopyUrl,
+        Action::CopyPath,
+        Action::Retry,
+        Action::RetryAll,
+        Action::Refresh,
+        Action::ReExtract,
+        Action::Pin,
+        Action::Bookmark,
+        Action::Export,
+        Action::ExportJson,
+        Action::ExportObsidian,
+        Action::Cite,
+        Action::OpenWith,
+        Action::Back,
+        Action::HistoryBack,
+        Action::HistoryForward,
+        Action::NextPage,
+        Action::Delete,
+        Action::Undo,
+        Action::Sort,
+        Action::SiteSearch,
+        Action::Outline,
+        Action::Annotate,
+        Action::Filter,
+        Action::EngineStatus,
+        Action::Goggles,
+        Action::ToggleGoggle,
+        Action::Density,
+        Action::ToggleRead,
+        Action::Preview,
+        Action::Suggestions,
+        Action::Quickfix,
+    ];
+
+    /// Engine searched by a search action, `None` for the default engine
+    pub fn engine(self) -> Option<Engine> {
+        match self {
+            Action::SearchDdg => Some(Engine::DuckDuckGo),
+            Action::SearchSearxng => Some(Engine::SearXNG),
+            Action::SearchStartpage => Some(Engine::Startpage),
+            _ => None,
+        }
+    }
+
+    /// Name used in the `[keys]` section
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Search => "search",
+            Action::SearchDdg => "search_ddg",
+            Action::SearchSearxng => "search_searxng",
+            Action::SearchStartpage => "search_startpage",
+            Action::Library => "library",
+            Action::LocalSearch => "local_search",
+            Action::Bookmarks => "bookmarks",
+            Action::Clear => "clear",
+            Action::Next => "next",
+            Action::Prev => "prev",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::HalfPageDown => "half_page_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::WindowTop => "window_top",
+            Action::WindowMiddle => "window_middle",
+            Action::WindowBottom => "window_bottom",
+            Action::ToggleSelect => "toggle_select",
+            Action::SelectAll => "select_all",
+            Action::InvertSelection => "invert_selection",
+            Action::OpenEditor => "open_editor",
+            Action::OpenBrowser => "open_browser",
+            Action::CopyUrl => "copy_url",
+            Action::CopyPath => "copy_path",
+            Action::Retry => "retry",
+            Action::RetryAll => "retry_all",
+            Action::Refresh => "refresh",
+            Action::ReExtract => "re_extract",
+            Action::Pin => "pin",
+            Action::Bookmark => "bookmark",
+            Action::Export => "export",
+            Action::ExportJson => "export_json",
+            Action::ExportObsidian => "export_obsidian",
+            Action::Cite => "cite",
+            Action::OpenWith => "open_with",
+            Action::Back => "back",
+            Action::HistoryBack => "history_back",
+            Action::HistoryForward => "history_forward",
+            Action::NextPage => "next_page",
+            Action::Delete => "delete",
+            Action::Undo => "undo",
+            Action::Sort => "sort",
+            Action::SiteSearch => "site_search",
+            Action::Outline => "outline",
+            Action::Annotate => "annotate",
+            Action::Filter => "filter",
+            Action::EngineStatus => "engine_status",
+            Action::Goggles => "goggles",
+            Action::ToggleGoggle => "toggle_goggle",
+            Action::Density => "density",
+            Action::ToggleRead => "toggle_read",
+            Action::Preview => "preview",
+            Action::Suggestions => "suggestions",
+            Action::Quickfix => "quickfix",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name)
+    }
+
+    /// Screens the action is available on
+    fn contexts(self) -> &'static [KeyContext] {
+        match self {
+            Action::Quit => &[Input, Results, Library, Bookmarks, LocalSearch],
+            Action::Search
+            | Action::SearchDdg
+            | Action::SearchSearxng
+            | Action::SearchStartpage
+            | Action::Bookmarks
+            | Action::Clear => &[Input],
+            Action::Library => &[Input, Library],
+            Action::LocalSearch => &[Input, LocalSearch],
+            Action::Next | Action::Prev | Action::OpenEditor | Action::Back => {
+                &[Results, Library, Bookmarks, LocalSearch]
+            }
+            Action::Pin => &[Results, Library],
+            Action::OpenBrowser => &[Results, Bookmarks],
+            Action::Delete => &[Results, Library, Bookmarks],
+            Action::Filter => &[Library],
+            Action::EngineStatus | Action::Goggles | Action::ToggleGoggle => &[Input, Results],
+            _ => &[Results],
+        }
+    }
+
+    fn defaults(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["ctrl+q", "ctrl+c"],
+            Action::Search => &["enter"],
+            Action::SearchDdg => &["ctrl+d"],
+            Action::SearchSearxng => &["ctrl+x"],
+            Action::SearchStartpage => &["ctrl+z"],
+            Action::Library => &["ctrl+l"],
+            Action::LocalSearch => &["ctrl+f"],
+            Action::Bookmarks => &["ctrl+o"],
+            Action::Clear => &["esc"],
+            Action::Next => &["j", "down"],
+            Action::Prev => &["k", "up"],
+            Action::First => &["g g"],
+            Action::Last => &["G"],
+            Action::HalfPageDown => &["ctrl+d"],
+            Action::HalfPageUp => &["ctrl+u"],
+            Action::WindowTop => &["H"],
+            Action::WindowMiddle => &["M"],
+            Action::WindowBottom => &["L"],
+            Action::ToggleSelect => &["tab"],
+            Action::SelectAll => &["ctrl+a"],
+            Action::InvertSelection => &["v"],
+            Action::OpenEditor => &["enter"],
+            Action::OpenBrowser => &["ctrl+b"],
+            Action::CopyUrl => &["y"],
+            Action::CopyPath => &["Y"],
+            Action::Retry => &["r"],
+            Action::RetryAll => &["R"],
+            Action::Refresh => &["ctrl+r"],
+            Action::ReExtract => &["ctrl+e"],
+            Action::Pin => &["*"],
+            Action::Bookmark => &["m"],
+            Action::Export => &["e"],
+            Action::ExportJson => &["E"],
+            Action::ExportObsidian => &["O"],
+            Action::Cite => &["c"],
+            Action::OpenWith => &["o"],
+            Action::Back => &["esc"],
+            Action::HistoryBack => &["[", "alt+left"],
+            Action::HistoryForward => &["]", "alt+right"],
+            Action::NextPage => &["n"],
+            Action::Delete => &["d"],
+            Action::Undo => &["u"],
+            Action::Sort => &["s"],
+            Action::SiteSearch => &["S"],
+            Action::Outline => &[":"],
+            Action::Annotate => &["a"],
+            Action::Filter => &["/"],
+            Action::EngineStatus => &["ctrl+s"],
+            Action::Goggles => &["ctrl+g"],
+            Action::ToggleGoggle => &["alt+g"],
+            Action::Density => &["z"],
+            Action::ToggleRead => &["x"],
+            Action::Preview => &["i"],
+            Action::Suggestions => &["ctrl+y"],
+            Action::Quickfix => &["Q"],
+        }
+    }
+
+    /// Cursor motion of the action in the results list, if it is one
+    pub fn motion(self) -> Option<Motion> {
+        match self {
+            Action::Next => Some(Motion::Down),
+            Action::Prev => Some(Motion::Up),
+            Action::First => Some(Motion::Top),
+            Action::Last => Some(Motion::Bottom),
+            Action::HalfPageDown => Some(Motion::HalfPageDown),
+            Action::HalfPageUp => Some(Motion::HalfPageUp),
+            Action::WindowTop => Some(Motion::WindowTop),
+            Action::WindowMiddle => Some(Motion::WindowMiddle),
+            Action::WindowBottom => Some(Motion::WindowBottom),
+            _ => None,
+        }
+    }
+}
+
+/// A key with its modifiers
+///
+/// Shift is folded into the character (`G`, `*`), and letters pressed with
+/// Ctrl or Alt are lowercase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                if modifiers.is_empty() {
+                    KeyCode::Char(c)
+                } else {
+                    KeyCode::Char(c.to_ascii_lowercase())
+                }
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    /// Parse a descriptor like `ctrl+b`, `G`, `F5` or `enter`
+    fn parse(descriptor: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = descriptor;
+        // A trailing "+" is the plus key itself ("ctrl++")
+        while let Some((modifier, rest)) = key.split_once('+')
+            && !modifier.is_empty()
+            && !rest.is_empty()
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier \"{}\" in \"{}\"", modifier, descriptor)),
+            };
+            key = rest;
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            name => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                        KeyCode::Char(c.to_ascii_uppercase())
+                    }
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n @ 1..=12) => KeyCode::F(n),
+                        _ => return Err(format!("unknown key \"{}\"", descriptor)),
+                    },
+                }
+            }
+        };
+        Ok(Self::new(code, modifiers))
+    }
+
+    /// A key that types a character
+    fn is_text(&self) -> bool {
+        self.modifiers.is_empty() && matches!(self.code, KeyCode::Char(_))
+    }
+
+    /// Digit of a plain number key
+    pub fn digit(&self) -> Option<u32> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.is_empty() => c.to_digit(10),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) if !self.modifiers.is_empty() => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => f.write_str("Enter"),
+            KeyCode::Esc => f.write_str("Esc"),
+            KeyCode::Tab => f.write_str("Tab"),
+            KeyCode::Backspace => f.write_str("Backspace"),
+            KeyCode::Delete => f.write_str("Del"),
+            KeyCode::Insert => f.write_str("Ins"),
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::Home => f.write_str("Home"),
+            KeyCode::End => f.write_str("End"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Display a key sequence: `gg` for plain keys, else `Ctrl+X S`
+pub fn format_keys(keys: &[KeyChord]) -> String {
+    let separator = if keys.iter().all(KeyChord::is_text) { "" } else { " " };
+    keys.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Parse a whitespace-separated key sequence like `g g`
+fn parse_keys(descriptor: &str) -> Result<Vec<KeyChord>, String> {
+    let keys = descriptor
+        .split_whitespace()
+        .map(KeyChord::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err("empty key".to_string());
+    }
+    Ok(keys)
+}
+
+/// One descriptor or a list of them, as written in `[keys]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn descriptors(&self) -> Vec<&str> {
+        match self {
+            KeyList::One(key) => vec![key.as_str()],
+            KeyList::Many(keys) => keys.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Outcome of looking up the keys pressed so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    /// The keys complete a binding
+    Action(Action),
+    /// The keys start a longer binding
+    Prefix,
+    /// Nothing is bound to the keys
+    None,
+}
+
+/// What a key does in a popup list, like the goggle or suggestion picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuKey {
+    Quit,
+    /// Use the selected entry
+    Choose,
+    Close,
+    Next,
+    Previous,
+}
+
+/// Key sequences bound to each action
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<KeyChord>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_config(&HashMap::new()).expect("default keybindings are valid")
+    }
+}
+
+impl Keymap {
+    /// Default bindings, replaced per action by the `[keys]` section
+    ///
+    /// Errors name the offending `keys.<action>` entry.
+    pub fn from_config(overrides: &HashMap<String, KeyList>) -> Result<Self, (String, String)> {
+        let mut names: Vec<&String> = overrides.keys().collect();
+        names.sort();
+
+        let mut configured: HashMap<Action, Vec<Vec<KeyChord>>> = HashMap::new();
+        for name in names {
+            let entry = format!("keys.{}", name);
+            let action = Action::from_name(name)
+                .ok_or_else(|| (entry.clone(), "is not a known action".to_string()))?;
+            let keys = overrides[name]
+                .descriptors()
+                .into_iter()
+                .map(parse_keys)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|problem| (entry.clone(), problem))?;
+            if keys.is_empty() {
+                return Err((entry, "needs at least one key".to_string()));
+            }
+            configured.insert(action, keys);
+        }
+
+        let mut bindings = Vec::new();
+        for &action in Action::ALL {
+            match configured.get(&action) {
+                Some(keys) => bindings.extend(keys.iter().map(|k| (action, k.clone()))),
+                None => {
+                    for descriptor in action.defaults() {
+                        let keys = parse_keys(descriptor).expect("default keybindings are valid");
+                        bindings.push((action, keys));
+                    }
+                }
+            }
+        }
+
+        let keymap = Self { bindings };
+        keymap
+            .check(|action| configured.contains_key(&action))
+            .map_err(|(action, problem)| (format!("keys.{}", action.name()), problem))?;
+        Ok(keymap)
+    }
+
+    /// Reject bindings that can never fire
+    ///
+    /// `configured` tells which actions the user rebound, so a clash with a
+    /// default binding is blamed on the user's entry.
+    fn check(&self, configured: impl Fn(Action) -> bool) -> Result<(), (Action, String)> {
+        for (i, (action, keys)) in self.bindings.iter().enumerate() {
+            let action = *action;
+            let shown = format_keys(keys);
+            if action == Action::Quit && keys.len() > 1 {
+                return Err((action, "must be a single key".to_string()));
+            }
+            if action.contexts().contains(&Input) && keys[0].is_text() {
+                return Err((
+                    action,
+                    format!("{} would type into the search box, add ctrl or alt", shown),
+                ));
+            }
+            if action.contexts().contains(&Results) && keys.iter().any(|k| k.digit().is_some()) {
+                return Err((action, format!("{} is reserved for counts", shown)));
+            }
+
+            for (other, other_keys) in &self.bindings[i + 1..] {
+                let shared = action.contexts().iter().any(|c| other.contexts().contains(c));
+                let clash = other_keys.starts_with(keys) || keys.starts_with(other_keys);
+                if *other != action && shared && clash {
+                    let (blamed, against) = if configured(*other) {
+                        (*other, action)
+                    } else {
+                        (action, *other)
+                    };
+                    return Err((
+                        blamed,
+                        format!(
+                            "{} conflicts with {} ({})",
+                            format_keys(if blamed == action { keys } else { other_keys }),
+                            against.name(),
+                            format_keys(if blamed == action { other_keys } else { keys })
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Match the keys pressed so far on a screen
+    pub fn lookup(&self, context: KeyContext, keys: &[KeyChord]) -> Lookup {
+        let mut prefix = false;
+        for (action, bound) in &self.bindings {
+            if !action.contexts().contains(&context) {
+                continue;
+            }
+            if bound.as_slice() == keys {
+                return Lookup::Action(*action);
+            }
+            prefix |= bound.starts_with(keys);
+        }
+        if prefix { Lookup::Prefix } else { Lookup::None }
+    }
+
+    /// Whether a single key press triggers `action`
+    pub fn is_bound(&self, action: Action, key: &KeyEvent) -> bool {
+        let chord = KeyChord::from_event(key);
+        self.bindings
+            .iter()
+            .any(|(a, keys)| *a == action && keys.as_slice() == [chord])
+    }
+
+    /// What `key` does in a popup list that `toggle` opens and closes
+    ///
+    /// The list is driven by the results keys: next and previous move,
+    /// the editor key chooses and `Back` closes.
+    pub fn menu_key(&self, key: &KeyEvent, toggle: Action) -> Option<MenuKey> {
+        let bound = |action| self.is_bound(action, key);
+        if bound(Action::Quit) {
+            Some(MenuKey::Quit)
+        } else if bound(Action::OpenEditor) {
+            Some(MenuKey::Choose)
+        } else if bound(Action::Back) || bound(toggle) {
+            Some(MenuKey::Close)
+        } else if bound(Action::Next) {
+            Some(MenuKey::Next)
+        } else if bound(Action::Prev) {
+            Some(MenuKey::Previous)
+        } else {
+            None
+        }
+    }
+
+    /// First key of `action`, like `j`
+    pub fn primary(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| format_keys(keys))
+            .unwrap_or_default()
+    }
+
+    /// Keys of `action` for the help bar, like `j/↓`
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|(a, _)| *a == action)
+            .map(|(_, keys)| format_keys(keys))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(descriptor: &str) -> KeyChord {
+        KeyChord::parse(descriptor).unwrap()
+    }
+
+    fn keymap(entries: &[(&str, &[&str])]) -> Result<Keymap, (String, String)> {
+        let overrides = entries
+            .iter()
+            .map(|(name, keys)| {
+                let keys = keys.iter().map(|k| k.to_string()).collect();
+                (name.to_string(), KeyList::Many(keys))
+            })
+            .collect();
+        Keymap::from_config(&overrides)
+    }
+
+    #[test]
+    fn test_parse_descriptors() {
+        let ctrl_b = KeyEvent::new(KeyCode::Char('b'), KeyModifiers::CONTROL);
+        assert_eq!(chord("ctrl+b"), KeyChord::from_event(&ctrl_b));
+        assert_eq!(chord("Ctrl+B"), chord("ctrl+b"));
+        assert_eq!(chord("shift+g"), chord("G"));
+        let shift_g = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(chord("G"), KeyChord::from_event(&shift_g));
+        assert_eq!(chord("F5").code, KeyCode::F(5));
+        assert_eq!(chord("ctrl++").code, KeyCode::Char('+'));
+        assert_eq!(parse_keys("g g").unwrap(), vec![chord("g"), chord("g")]);
+
+        assert!(KeyChord::parse("hyper+x").is_err());
+        assert!(KeyChord::parse("F13").is_err());
+        assert!(KeyChord::parse("foo").is_err());
+        assert!(parse_keys("  ").is_err());
+    }
+
+    #[test]
+    fn test_format_keys() {
+        assert_eq!(format_keys(&parse_keys("g g").unwrap()), "gg");
+        assert_eq!(format_keys(&parse_keys("ctrl+x s").unwrap()), "Ctrl+X s");
+        assert_eq!(Keymap::default().label(Action::Next), "j/↓");
+        assert_eq!(Keymap::default().label(Action::OpenBrowser), "Ctrl+B");
+    }
+
+    #[test]
+    fn test_lookup_sequences_and_contexts() {
+        let keys = Keymap::default();
+        assert_eq!(keys.lookup(Results, &[chord("g")]), Lookup::Prefix);
+        assert_eq!(keys.lookup(Results, &[chord("g"), chord("g")]), Lookup::Action(Action::First));
+        assert_eq!(keys.lookup(Results, &[chord("ctrl+d")]), Lookup::Action(Action::HalfPageDown));
+        assert_eq!(keys.lookup(Input, &[chord("ctrl+d")]), Lookup::Action(Action::SearchDdg));
+        assert_eq!(keys.lookup(Library, &[chord("d")]), Lookup::Action(Action::Delete));
+        assert_eq!(keys.lookup(Results, &[chord("d")]), Lookup::Action(Action::Delete));
+        assert_eq!(keys.lookup(Input, &[chord("d")]), Lookup::None);
+        assert_eq!(keys.lookup(Bookmarks, &[chord("d")]), Lookup::Action(Action::Delete));
+        assert_eq!(keys.lookup(Bookmarks, &[chord("ctrl+b")]), Lookup::Action(Action::OpenBrowser));
+    }
+
+    #[test]
+    fn test_menu_keys() {
+        let keys = Keymap::default();
+        let menu_key = |code, modifiers| {
+            keys.menu_key(&KeyEvent::new(code, modifiers), Action::Goggles)
+        };
+        assert_eq!(menu_key(KeyCode::Char('j'), KeyModifiers::NONE), Some(MenuKey::Next));
+        assert_eq!(menu_key(KeyCode::Up, KeyModifiers::NONE), Some(MenuKey::Previous));
+        assert_eq!(menu_key(KeyCode::Enter, KeyModifiers::NONE), Some(MenuKey::Choose));
+        assert_eq!(menu_key(KeyCode::Esc, KeyModifiers::NONE), Some(MenuKey::Close));
+        assert_eq!(menu_key(KeyCode::Char('g'), KeyModifiers::CONTROL), Some(MenuKey::Close));
+        assert_eq!(menu_key(KeyCode::Char('c'), KeyModifiers::CONTROL), Some(MenuKey::Quit));
+        assert_eq!(menu_key(KeyCode::Char('x'), KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn test_overrides_replace_defaults() {
+        let keys = keymap(&[("open_browser", &["ctrl+o"]), ("search_ddg", &["alt+d", "F2"])]).unwrap();
+        assert_eq!(keys.lookup(Results, &[chord("ctrl+o")]), Lookup::Action(Action::OpenBrowser));
+        assert_eq!(keys.lookup(Results, &[chord("ctrl+b")]), Lookup::None);
+        assert_eq!(keys.lookup(Input, &[chord("F2")]), Lookup::Action(Action::SearchDdg));
+        assert_eq!(keys.label(Action::SearchDdg), "Alt+D/F2");
+    }
+
+    #[test]
+    fn test_invalid_bindings_are_rejected() {
+        let (entry, problem) = keymap(&[("retry", &["y"])]).unwrap_err();
+        assert_eq!(entry, "keys.retry");
+        assert_eq!(problem, "y conflicts with copy_url (y)");
+
+        let (entry, problem) = keymap(&[("open_browser", &["g"])]).unwrap_err();
+        assert_eq!(entry, "keys.open_browser");
+        assert_eq!(problem, "g conflicts with first (gg)");
+
+        let (entry, _) = keymap(&[("search_ddg", &["d"])]).unwrap_err();
+        assert_eq!(entry, "keys.search_ddg");
+        let (_, problem) = keymap(&[("pin", &["5"])]).unwrap_err();
+        assert_eq!(problem, "5 is reserved for counts");
+        assert_eq!(keymap(&[("teleport", &["t"])]).unwrap_err().1, "is not a known action");
+        assert!(keymap(&[("quit", &["ctrl+x q"])]).is_err());
+        assert!(keymap(&[("open_browser", &["ctrl+bb"])]).is_err());
+
+        // Different screens may share a key
+        assert!(keymap(&[("filter", &["y"])]).is_ok());
+    }
+}
@@ -0,0 +1,168 @@
+//! Library of previously fetched pages
+//!
+//! Lists the markdown files in `pinned/`, `active_tabs/` and the searches in
+//! `current_search/`.
+//! Listing only stats the files; each page's frontmatter is read the first
+//! time its title or domain is needed, and malformed files fall back to the
+//! filename.
+
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+use crate::cache_index::read_frontmatter;
+use crate::config::OutputFormat;
+
+/// A cached page on disk
+#[derive(Debug)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    /// File size in bytes
+    pub size: u64,
+    meta: OnceCell<PageMeta>,
+}
+
+/// Title and domain from a page's frontmatter
+#[derive(Debug)]
+struct PageMeta {
+    title: String,
+    domain: String,
+}
+
+impl LibraryEntry {
+    /// Page title, or the file name when the frontmatter has none
+    pub fn title(&self) -> &str {
+        &self.meta().title
+    }
+
+    /// Domain of the page URL (empty when unknown)
+    pub fn domain(&self) -> &str {
+        &self.meta().domain
+    }
+
+    /// Whether the title, domain or file name contains `filter` (case-insensitive)
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        let filename = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        [self.title(), self.domain(), &filename]
+            .iter()
+            .any(|field| field.to_lowercase().contains(&filter))
+    }
+
+    fn meta(&self) -> &PageMeta {
+        self.meta.get_or_init(|| {
+            let frontmatter = read_frontmatter(&self.path).unwrap_or_default();
+
+            let title = frontmatter.title.filter(|t| !t.is_empty()).unwrap_or_else(|| {
+                self.path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            });
+            let domain = frontmatter
+                .url
+                .and_then(|url| Url::parse(&url).ok())
+                .and_then(|url| url.host_str().map(|h| h.trim_start_matches("www.").to_string()))
+                .unwrap_or_default();
+
+            PageMeta { title, domain }
+        })
+    }
+}
+
+/// List saved pages in `dirs`, most recently modified first
+///
+/// Unreadable directories and entries are skipped.
+pub fn load_library(dirs: &[impl AsRef<Path>]) -> Vec<LibraryEntry> {
+    let mut entries: Vec<LibraryEntry> = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|entry| OutputFormat::of_path(&entry.path()).is_some())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(LibraryEntry {
+                path: entry.path(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                size: metadata.len(),
+                meta: OnceCell::new(),
+            })
+        })
+        .collect();
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.modified));
+    entries
+}
+
+/// Compact age like `5m`, `3h` or `2d`
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => "now".to_string(),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86_399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// Human-readable file size like `812 B` or `14.2 KB`
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let bytes_f = bytes as f64;
+    if bytes_f < KB {
+        format!("{} B", bytes)
+    } else if bytes_f < KB * KB {
+        format!("{:.1} KB", bytes_f / KB)
+    } else {
+        format!("{:.1} MB", bytes_f / (KB * KB))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_library_reads_frontmatter_and_tolerates_garbage() {
+        let dir = std::env::temp_dir().join(format!("websearch-library-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("docs_rs_00000000_Tokio.md"),
+            "---\ntitle: \"Tokio docs\"\nurl: https://www.docs.rs/tokio\n---\n\nBody",
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.md"), [0xff, 0xfe, b'-', b'-']).unwrap();
+        std::fs::write(dir.join("ignored.json"), "not a page").unwrap();
+
+        let library = load_library(&[&dir, &dir.join("missing")]);
+        assert_eq!(library.len(), 2);
+
+        let tokio = library.iter().find(|e| e.title() == "Tokio docs").unwrap();
+        assert_eq!(tokio.domain(), "docs.rs");
+        assert!(tokio.matches("TOKIO"));
+        assert!(!tokio.matches("python"));
+
+        let broken = library.iter().find(|e| e.title() == "broken").unwrap();
+        assert_eq!(broken.domain(), "");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_format_age_and_size() {
+        assert_eq!(format_age(Duration::from_secs(30)), "now");
+        assert_eq!(format_age(Duration::from_secs(5 * 60)), "5m");
+        assert_eq!(format_age(Duration::from_secs(3 * 3600)), "3h");
+        assert_eq!(format_age(Duration::from_secs(2 * 86_400 + 10)), "2d");
+
+        assert_eq!(format_size(812), "812 B");
+        assert_eq!(format_size(14_540), "14.2 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
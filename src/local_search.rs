@@ -0,0 +1,208 @@
+//! Full-text search over the cached pages
+//!
+//! Every query reads the markdown files again, a line at a time, so only
+//! one line is ever held in memory and nothing has to be kept up to date.
+//! A page matches when all the query's words appear in its body (the
+//! frontmatter is skipped); its best line is the one containing the most
+//! of them.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::cache_index::read_frontmatter;
+
+/// Longest snippet of the best line shown in the list
+const SNIPPET_CHARS: usize = 160;
+
+/// Characters kept before the first match when the line is cut
+const SNIPPET_LEAD: usize = 40;
+
+/// A cached page containing every word of the query
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalMatch {
+    pub path: PathBuf,
+    /// Page title, or the file name when the frontmatter has none
+    pub title: String,
+    /// 1-based line number of the best line
+    pub line: usize,
+    /// The best line, cut around its first match
+    pub snippet: String,
+    /// Query words on the best line
+    best_hits: usize,
+    /// Body lines containing any query word
+    matching_lines: usize,
+}
+
+/// Lowercased words of `query`
+pub fn terms(query: &str) -> Vec<String> {
+    query.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// Search `paths` for `terms`, best pages first
+///
+/// `progress` is called with the number of files scanned so far after each
+/// one. Files that can't be read are skipped; among equally good pages the
+/// order of `paths` is kept.
+pub fn search_files(
+    paths: &[PathBuf],
+    terms: &[String],
+    mut progress: impl FnMut(usize),
+) -> Vec<LocalMatch> {
+    let mut matches = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        match search_file(path, terms) {
+            Ok(Some(found)) => matches.push(found),
+            Ok(None) => {}
+            Err(e) => tracing::debug!(path = %path.display(), error = %e, "skipping unreadable page"),
+        }
+        progress(i + 1);
+    }
+    matches.sort_by_key(|m| std::cmp::Reverse((m.best_hits, m.matching_lines)));
+    matches
+}
+
+/// Best line of `path` for `terms`, if the page contains all of them
+pub fn search_file(path: &Path, terms: &[String]) -> std::io::Result<Option<LocalMatch>> {
+    if terms.is_empty() {
+        return Ok(None);
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = Vec::new();
+    let mut found = vec![false; terms.len()];
+    let mut best: Option<(usize, usize, String)> = None;
+    let mut matching_lines = 0;
+    let mut in_frontmatter = false;
+    let mut number = 0;
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        number += 1;
+        // Pages are UTF-8, but a stray byte shouldn't hide the rest
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end();
+
+        if number == 1 && line == "---" {
+            in_frontmatter = true;
+            continue;
+        }
+        if in_frontmatter {
+            in_frontmatter = line != "---";
+            continue;
+        }
+
+        let lower = line.to_lowercase();
+        let mut hits = 0;
+        for (term, found) in terms.iter().zip(found.iter_mut()) {
+            if lower.contains(term.as_str()) {
+                *found = true;
+                hits += 1;
+            }
+        }
+        if hits == 0 {
+            continue;
+        }
+        matching_lines += 1;
+        if best.as_ref().is_none_or(|(best_hits, _, _)| hits > *best_hits) {
+            best = Some((hits, number, snippet(line, &lower, terms)));
+        }
+    }
+
+    let Some((best_hits, line, snippet)) = best.filter(|_| found.iter().all(|&f| f)) else {
+        return Ok(None);
+    };
+    let title = read_frontmatter(path)
+        .and_then(|frontmatter| frontmatter.title)
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+
+    Ok(Some(LocalMatch {
+        path: path.to_path_buf(),
+        title,
+        line,
+        snippet,
+        best_hits,
+        matching_lines,
+    }))
+}
+
+/// `line` cut to [`SNIPPET_CHARS`] so that its first match is visible
+fn snippet(line: &str, lower: &str, terms: &[String]) -> String {
+    let line = line.trim_start();
+    let lower = lower.trim_start();
+    let first = terms
+        .iter()
+        .filter_map(|term| lower.find(term.as_str()))
+        .min()
+        .unwrap_or(0);
+    // Lowercasing can change lengths, so count characters and clamp
+    let first_char = lower[..first].chars().count();
+    let start = first_char.saturating_sub(SNIPPET_LEAD);
+
+    let mut text: String = line.chars().skip(start).take(SNIPPET_CHARS).collect();
+    if start > 0 {
+        text.insert(0, '…');
+    }
+    if line.chars().count() > start + SNIPPET_CHARS {
+        text.push('…');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_files_ranks_pages_by_best_line() {
+        let dir = std::env::temp_dir().join(format!("websearch-local-search-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let spread = write(
+            "spread.md",
+            "---\ntitle: \"Spread out\"\n---\n\nTokio is a runtime.\n\nIt has a Select macro.\n",
+        );
+        let together = write("together.md", "# Notes\n\nUse tokio::select! to race futures\n");
+        let only_frontmatter = write("meta.md", "---\ntitle: \"Tokio select\"\n---\n\nNothing here\n");
+        let binary = dir.join("binary.md");
+        std::fs::write(&binary, [b't', b'o', b'k', b'i', b'o', b' ', 0xff, b'\n']).unwrap();
+        let missing = dir.join("missing.md");
+
+        let paths = [spread.clone(), only_frontmatter, missing, binary, together.clone()];
+        let mut scanned = Vec::new();
+        let matches = search_files(&paths, &terms("TOKIO select"), |n| scanned.push(n));
+        assert_eq!(scanned, [1, 2, 3, 4, 5]);
+
+        // Both words on one line beats both words in the page
+        let found: Vec<_> = matches.iter().map(|m| (m.path.clone(), m.line)).collect();
+        assert_eq!(found, [(together, 3), (spread, 5)]);
+        assert_eq!(matches[0].title, "together");
+        assert_eq!(matches[1].title, "Spread out");
+        assert_eq!(matches[1].snippet, "Tokio is a runtime.");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_snippet_keeps_the_first_match_in_view() {
+        let line = format!("  {}needle{}", "a".repeat(100), "b".repeat(300));
+        let cut = snippet(&line, &line.to_lowercase(), &terms("needle"));
+        assert!(cut.starts_with('…') && cut.ends_with('…'));
+        assert_eq!(cut.chars().count(), SNIPPET_CHARS + 2);
+        assert_eq!(cut.find("needle"), Some('…'.len_utf8() + SNIPPET_LEAD));
+
+        assert_eq!(snippet("  Short line", "  short line", &terms("line")), "Short line");
+    }
+}
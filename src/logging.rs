@@ -0,0 +1,92 @@
+//! Debug logging to `debug.log` in the data directory
+//!
+//! Enabled with `--debug` or `WEBSEARCH_DEBUG=1`. Logs only ever go to the
+//! file, never to the terminal, which is in raw mode while the TUI runs.
+//! Without debug logging no subscriber is installed and `tracing` calls
+//! cost next to nothing.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::Level;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+use crate::paths;
+
+/// Log file name inside the data directory
+const LOG_FILE: &str = "debug.log";
+
+/// Environment variable enabling debug logging
+const DEBUG_ENV: &str = "WEBSEARCH_DEBUG";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Start debug logging if requested by the command line or environment
+///
+/// The returned guard flushes the log when dropped, so keep it alive until
+/// exit.
+pub fn init() -> Result<Option<WorkerGuard>> {
+    if !debug_requested(std::env::args().skip(1), std::env::var(DEBUG_ENV).ok()) {
+        return Ok(None);
+    }
+
+    let log_dir = paths::data_dir();
+    std::fs::create_dir_all(log_dir).context("Failed to create log directory")?;
+    let (writer, guard) =
+        tracing_appender::non_blocking(tracing_appender::rolling::never(log_dir, LOG_FILE));
+
+    // Our own events in detail; dependencies only when something is wrong
+    let filter = Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), Level::DEBUG)
+        .with_default(Level::WARN);
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false),
+        )
+        .with(filter)
+        .try_init()
+        .context("Failed to start debug logging")?;
+
+    ENABLED.store(true, Ordering::Relaxed);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "debug logging started");
+    Ok(Some(guard))
+}
+
+/// Where the debug log is written
+pub fn log_path() -> PathBuf {
+    paths::data_dir().join(LOG_FILE)
+}
+
+/// Whether debug logging is on
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// `--debug` among the arguments, or a truthy `WEBSEARCH_DEBUG`
+fn debug_requested(mut args: impl Iterator<Item = String>, env: Option<String>) -> bool {
+    args.any(|arg| arg == "--debug")
+        || env.is_some_and(|v| matches!(v.trim(), "1" | "true" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter().map(|s| s.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_debug_requested() {
+        assert!(debug_requested(args(&["--debug"]), None));
+        assert!(debug_requested(args(&["--clear-cookies", "--debug"]), None));
+        assert!(debug_requested(args(&[]), Some("1".to_string())));
+        assert!(!debug_requested(args(&[]), Some("0".to_string())));
+        assert!(!debug_requested(args(&["--debugger"]), None));
+        assert!(!debug_requested(args(&[]), None));
+    }
+}
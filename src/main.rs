@@ -2,69 +2,202 @@
 //!
 //! This TUI application provides:
 //! - Fast web search via Brave Search API, DuckDuckGo, or SearXNG
-//! - Background prefetching with intelligent caching (12 concurrent, 8s timeout)
+//! - Background prefetching with intelligent caching (concurrency and timeout
+//!   set in `[prefetch]`)
 //! - Clean markdown extraction from web pages (dom_smoothie)
 //! - Seamless Neovim integration for reading
-//! - Auto-cleanup of files older than 5 days
+//! - Auto-cleanup of files older than `cache.max_age_days`
 
 mod app;
+mod bookmarks;
+mod bot_wall;
+mod cache_index;
+mod canonical;
+mod citation;
+mod cli;
+mod clipboard;
+mod config;
+mod cooldown;
+mod display_url;
 mod duckduckgo_search;
+mod engine_stats;
+mod export;
 mod extract_clean_md;
+mod formats;
 mod globals;
+mod goggles;
+mod history;
+mod hooks;
+mod images;
+mod input;
+mod keymap;
+mod library;
+mod local_search;
+mod logging;
+mod metrics;
+mod notes;
+mod obsidian;
+mod open_with;
+mod outline;
+mod page_info;
+mod paths;
 mod prefetch;
+mod preflight;
+mod query_filters;
+mod quickfix;
+mod read_log;
+mod raw_html;
+mod robots;
+mod row_cache;
 mod search;
+mod searxng_health;
 mod searxng_search;
+mod selection;
+mod session;
+mod sort;
 mod startpage_search;
+mod suggestions;
+mod terminal;
+mod theme;
+mod toc;
 mod ui;
+mod wayback;
 
 use anyhow::Result;
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use clap::Parser;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
 use dotenvy::dotenv;
+use futures::StreamExt;
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::Instant;
 use tokio::sync::mpsc;
+use tokio::time::sleep_until;
 
-use app::{App, AppMessage, AppState};
+use app::{App, AppMessage, AppState, KeyAction, Severity};
+use config::OpenMode;
+use export::ExportFormat;
+use keymap::{Action, KeyContext, MenuKey};
+use session::Session;
 use ui::draw_ui;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Load environment variables
+    let args = cli::Args::parse();
+
+    // Load environment variables: ./.env first, then the config directory
     dotenv().ok();
+    if let Some(config_dir) = paths::config_dir() {
+        dotenvy::from_path(config_dir.join(".env")).ok();
+    }
+
+    // config.toml, with environment variables taking precedence
+    let mut config = config::Config::load()?;
+    if args.offline {
+        config.network.offline = true;
+    }
+
+    // Resolve the data directory, moving ./websearch from older versions
+    if let Some(legacy) = paths::init(config.paths.data_dir.as_deref())? {
+        eprintln!(
+            "Moved {} to {}",
+            legacy.display(),
+            paths::data_dir().display()
+        );
+    }
+
+    // Debug log (--debug / WEBSEARCH_DEBUG=1); flushed when the guard drops
+    let _log_guard = logging::init()?;
 
     // Initialize global resources (HTTP client)
     // This happens once at startup, avoiding delays during use
-    globals::init_globals()?;
+    globals::init_globals(&config.network)?;
 
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    if args.clear_cookies {
+        globals::clear_cookies()?;
+    }
 
-    // Create app state
-    let mut app = App::new()?;
+    // --search, --fetch and --re-extract print their results without touching the terminal mode
+    if let Some(query) = &args.search {
+        let res = cli::run_search(&args, query, &config).await;
+        if let Err(err) = globals::save_cookies() {
+            eprintln!("Warning: {:?}", err);
+        }
+        if let Err(err) = res {
+            eprintln!("Error: {:#}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(urls) = args.fetch_urls() {
+        let code = match cli::run_fetch(urls, args.open, &config).await {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("Error: {:#}", err);
+                2
+            }
+        };
+        if let Err(err) = globals::save_cookies() {
+            eprintln!("Warning: {:?}", err);
+        }
+        std::process::exit(code);
+    }
+    if let Some(urls) = &args.re_extract {
+        let code = match cli::run_re_extract(urls, &config).await {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("Error: {:#}", err);
+                2
+            }
+        };
+        std::process::exit(code);
+    }
+
+    // Setup terminal; it is restored when `tui` drops, or by the panic hook
+    terminal::install_panic_hook();
+    let tui = terminal::Tui::enter()?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
 
     // Create channel for background tasks
     let (tx, mut rx) = mpsc::unbounded_channel();
 
+    // Create app state
+    let mut app = App::new(config, tx.clone())?;
+
+    // Configuration problems show as a notice over the first screen
+    let probes = preflight::probe(&app.config, &app.providers, args.check_keys).await;
+    let report = preflight::check(&probes);
+    app.notices = report.notices;
+    app.unusable_engines = report.unusable;
+
+    if args.resume {
+        match Session::load(&Session::path(), app.config.cache.max_age()) {
+            Some(session) => app.restore_session(session).await,
+            None => app.set_status("No recent search to resume"),
+        }
+    } else if let Some(query) = args.start_query() {
+        let engine = args.engine.unwrap_or(app.config.search.default_engine);
+        app.launch_search(&tx, &query, engine).await;
+    } else if app.config.network.offline {
+        // Nothing to search for, so start with what is there
+        app.open_library();
+        app.set_status("Offline: browsing cached pages");
+    }
+
     // Run the app
     let res = run_app(&mut terminal, &mut app, tx, &mut rx).await;
 
+    // Keep the selection for the next --resume
+    app.save_session();
+
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    drop(tui);
+
+    if let Err(err) = globals::save_cookies() {
+        eprintln!("Warning: {:?}", err);
+    }
 
     if let Err(err) = res {
         eprintln!("Error: {:?}", err);
@@ -79,279 +212,577 @@ async fn run_app<B: ratatui::backend::Backend>(
     tx: mpsc::UnboundedSender<AppMessage>,
     rx: &mut mpsc::UnboundedReceiver<AppMessage>,
 ) -> Result<()> {
-    // Track 'g' key for gg command
-    let mut last_g_press: Option<std::time::Instant> = None;
+    let mut events = EventStream::new();
 
     loop {
-        // Check for messages from background tasks
-        while let Ok(msg) = rx.try_recv() {
-            match msg {
-                AppMessage::SearchComplete(results) => {
-                    app.finish_search(results).await;
-                }
-                AppMessage::SearchError(err) => {
-                    app.show_error(&format!("Search failed: {}", err));
+        app.expire_status();
+
+        // Enter on a loading page: open it as soon as it's ready
+        if app.poll_open_wait() {
+            open_selected_in_editor(terminal, app, &mut events, None).await?;
+        }
+
+        // Draw UI
+        terminal.draw(|f| draw_ui(f, app))?;
+
+        // Sleep until a background message, a terminal event, or a UI deadline
+        let deadline = app.next_deadline();
+        let event = tokio::select! {
+            Some(msg) = rx.recv() => {
+                app.handle_message(msg).await;
+                // Apply everything already queued before redrawing
+                while let Ok(msg) = rx.try_recv() {
+                    app.handle_message(msg).await;
                 }
+                continue;
             }
-        }
+            event = events.next() => match event {
+                Some(event) => event?,
+                None => return Ok(()),
+            },
+            _ = sleep_until(deadline.unwrap_or_else(Instant::now).into()), if deadline.is_some() => {
+                continue;
+            }
+            // SIGINT from outside; raw mode turns Ctrl+C into a key press bound to quit
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
 
-        // Get prefetch progress and all statuses for UI
-        let prefetch_progress = app.get_prefetch_progress().await;
-        let statuses = app.get_all_statuses().await;
+        // Pastes arrive as one event; only the search box accepts them
+        if let Event::Paste(text) = &event {
+            if app.state == AppState::Input {
+                app.paste(text);
+            }
+            continue;
+        }
 
-        // Update progress in status
-        if app.state == AppState::Results {
-            let (completed, total) = prefetch_progress;
-            if total > 0 && completed < total {
-                app.update_prefetch_progress(completed, total);
+        if let Event::Mouse(mouse) = event {
+            match app.handle_mouse(mouse) {
+                Some(Action::OpenEditor) => open_selected(terminal, app, &mut events).await?,
+                Some(Action::OpenBrowser) => app.open_in_browser(),
+                _ => {}
             }
+            continue;
         }
 
-        // Draw UI
-        terminal.draw(|f| draw_ui(f, app, prefetch_progress, &statuses))?;
+        if let Event::Key(key) = event {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
 
-        // Handle input with timeout
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind != KeyEventKind::Press {
-                    continue;
+            // Any key press dismisses the previous status message
+            app.clear_status();
+
+            // Startup notices: any key dismisses them
+            if !app.notices.is_empty() {
+                if app.config.keymap.is_bound(Action::Quit, &key) {
+                    return Ok(());
                 }
+                app.notices.clear();
+                continue;
+            }
 
-                match app.state {
-                    AppState::Input => {
-                        match key.code {
-                            KeyCode::Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                return Ok(());
-                            }
-                            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                // Ctrl+D: DuckDuckGo search
-                                if !app.input.trim().is_empty() {
-                                    let query = app.input.clone();
-                                    app.start_search().await;
-
-                                    // Spawn DuckDuckGo search task
-                                    let tx_clone = tx.clone();
-                                    tokio::spawn(async move {
-                                        match duckduckgo_search::duckduckgo_search(&query).await {
-                                            Ok(results) => {
-                                                let _ = tx_clone
-                                                    .send(AppMessage::SearchComplete(results));
-                                            }
-                                            Err(e) => {
-                                                let _ = tx_clone.send(AppMessage::SearchError(
-                                                    e.to_string(),
-                                                ));
-                                            }
-                                        }
-                                    });
-                                }
-                            }
-                            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                // Ctrl+X: SearXNG search
-                                if !app.input.trim().is_empty() {
-                                    let query = app.input.clone();
-                                    app.start_search().await;
-
-                                    // Spawn SearXNG search task
-                                    let tx_clone = tx.clone();
-                                    tokio::spawn(async move {
-                                        match searxng_search::searxng_search(&query).await {
-                                            Ok(results) => {
-                                                let _ = tx_clone
-                                                    .send(AppMessage::SearchComplete(results));
-                                            }
-                                            Err(e) => {
-                                                let _ = tx_clone.send(AppMessage::SearchError(
-                                                    e.to_string(),
-                                                ));
-                                            }
-                                        }
-                                    });
-                                }
-                            }
-                            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                // Ctrl+Z: Startpage search (Google results with privacy)
-                                if !app.input.trim().is_empty() {
-                                    let query = app.input.clone();
-                                    app.start_search().await;
-
-                                    // Spawn Startpage search task
-                                    let tx_clone = tx.clone();
-                                    tokio::spawn(async move {
-                                        match startpage_search::startpage_search(&query).await {
-                                            Ok(results) => {
-                                                let _ = tx_clone
-                                                    .send(AppMessage::SearchComplete(results));
-                                            }
-                                            Err(e) => {
-                                                let _ = tx_clone.send(AppMessage::SearchError(
-                                                    e.to_string(),
-                                                ));
-                                            }
-                                        }
-                                    });
-                                }
-                            }
-                            KeyCode::Char(c) => {
-                                app.insert_char(c);
-                            }
-                            KeyCode::Backspace => {
-                                app.delete_char_before();
-                            }
-                            KeyCode::Delete => {
-                                app.delete_char_after();
-                            }
-                            KeyCode::Left => {
-                                app.cursor_left();
-                            }
-                            KeyCode::Right => {
-                                app.cursor_right();
-                            }
-                            KeyCode::Home => {
-                                app.cursor_home();
-                            }
-                            KeyCode::End => {
-                                app.cursor_end();
-                            }
-                            KeyCode::Enter => {
-                                if !app.input.trim().is_empty() {
-                                    let query = app.input.clone();
-                                    app.start_search().await;
-
-                                    // Spawn search task
-                                    let tx_clone = tx.clone();
-                                    tokio::spawn(async move {
-                                        // Use Brave search (Enter)
-                                        let api_key = std::env::var("BRAVE_SEARCH_API_KEY")
-                                            .unwrap_or_default();
-
-                                        if api_key.is_empty() {
-                                            let _ = tx_clone.send(AppMessage::SearchError(
-                                                "BRAVE_SEARCH_API_KEY not set".to_string(),
-                                            ));
-                                        } else {
-                                            match search::brave_search(&api_key, &query).await {
-                                                Ok(results) => {
-                                                    let _ = tx_clone
-                                                        .send(AppMessage::SearchComplete(results));
-                                                }
-                                                Err(e) => {
-                                                    let _ = tx_clone.send(AppMessage::SearchError(
-                                                        e.to_string(),
-                                                    ));
-                                                }
-                                            }
-                                        }
-                                    });
-                                }
-                            }
-                            KeyCode::Esc => {
-                                app.clear_input();
-                            }
-                            _ => {}
+            match app.state {
+                // Engine status panel: any key closes it
+                AppState::Input | AppState::Results if app.engine_status => {
+                    if app.config.keymap.is_bound(Action::Quit, &key) {
+                        return Ok(());
+                    }
+                    app.engine_status = false;
+                }
+                // Goggle picker: move, use the selected goggle, or close it
+                AppState::Input | AppState::Results if app.goggle_menu.is_some() => {
+                    let menu_key = app.config.keymap.menu_key(&key, Action::Goggles);
+                    match (menu_key, &mut app.goggle_menu) {
+                        (Some(MenuKey::Quit), _) => return Ok(()),
+                        (Some(MenuKey::Choose), _) => app.choose_goggle(),
+                        (Some(MenuKey::Close), _) => app.goggle_menu = None,
+                        (Some(MenuKey::Next), Some(menu)) => menu.next(),
+                        (Some(MenuKey::Previous), Some(menu)) => menu.previous(),
+                        _ => {}
+                    }
+                }
+                AppState::Input => match app.key_sequence.feed_in(KeyContext::Input, key) {
+                    KeyAction::Command(action) => match action {
+                        Action::Quit => return Ok(()),
+                        Action::Search
+                        | Action::SearchDdg
+                        | Action::SearchSearxng
+                        | Action::SearchStartpage => {
+                            let engine =
+                                action.engine().unwrap_or(app.config.search.default_engine);
+                            app.spawn_search(&tx, engine).await;
+                        }
+                        Action::Library => app.open_library(),
+                        Action::LocalSearch => app.start_local_search(&tx),
+                        Action::Bookmarks => app.open_bookmarks(),
+                        Action::Clear => app.clear_input(),
+                        Action::EngineStatus => app.engine_status = true,
+                        Action::Goggles => app.open_goggle_menu(),
+                        Action::ToggleGoggle => app.toggle_goggle(),
+                        _ => {}
+                    },
+                    KeyAction::Unhandled => match key.code {
+                        // Readline-style editing
+                        KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.cursor_home();
+                        }
+                        KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.cursor_end();
+                        }
+                        KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.delete_word_before();
+                        }
+                        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.kill_to_start();
                         }
+                        KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.kill_to_end();
+                        }
+                        KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::ALT) => {
+                            app.move_word_left();
+                        }
+                        KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::ALT) => {
+                            app.move_word_right();
+                        }
+                        KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.move_word_left();
+                        }
+                        KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.move_word_right();
+                        }
+                        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.insert_char(c);
+                        }
+                        KeyCode::Backspace => {
+                            app.delete_char_before();
+                        }
+                        KeyCode::Delete => {
+                            app.delete_char_after();
+                        }
+                        KeyCode::Left => {
+                            app.cursor_left();
+                        }
+                        KeyCode::Right => {
+                            app.cursor_right();
+                        }
+                        KeyCode::Home => {
+                            app.cursor_home();
+                        }
+                        KeyCode::End => {
+                            app.cursor_end();
+                        }
+                        _ => {}
+                    },
+                    KeyAction::Motion(..)
+                    | KeyAction::Jump { .. }
+                    | KeyAction::Pending
+                    | KeyAction::Cancelled => {}
+                },
+                // While Enter waits for a page only Esc (and quit) do anything
+                AppState::Results | AppState::Bookmarks if app.open_wait_since.is_some() => {
+                    if app.config.keymap.is_bound(Action::Quit, &key) {
+                        return Ok(());
                     }
-                    AppState::Results => {
-                        match key.code {
-                            KeyCode::Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                return Ok(());
-                            }
-                            KeyCode::Char('j') | KeyCode::Down => {
-                                app.next_result();
-                                last_g_press = None;
-                            }
-                            KeyCode::Char('k') | KeyCode::Up => {
-                                app.previous_result();
-                                last_g_press = None;
-                            }
-                            KeyCode::Char('g') => {
-                                // Check for gg (go to top)
-                                if let Some(last) = last_g_press {
-                                    if last.elapsed() < Duration::from_millis(500) {
-                                        app.first_result();
-                                        last_g_press = None;
-                                    } else {
-                                        last_g_press = Some(std::time::Instant::now());
-                                    }
-                                } else {
-                                    last_g_press = Some(std::time::Instant::now());
-                                }
-                            }
-                            KeyCode::Char('G') => {
-                                // Go to bottom
-                                app.last_result();
-                                last_g_press = None;
-                            }
-                            KeyCode::Tab => {
-                                app.toggle_selection();
-                                last_g_press = None;
-                            }
-                            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.open_in_browser();
-                                last_g_press = None;
-                            }
-                            KeyCode::Enter => {
-                                last_g_press = None;
-
-                                // Check if multiple items selected - prevent multi-neovim
-                                if !app.selected_items.is_empty() {
-                                    app.status_message = "⚠ Can't open multiple files in Neovim. Unselect with Tab, or use Ctrl+B for browser".to_string();
-                                    continue;
-                                }
-
-                                // Try to open in neovim
-                                match app.prepare_neovim_open().await {
-                                    Ok(filepath) => {
-                                        // Exit TUI mode
-                                        disable_raw_mode()?;
-                                        execute!(
-                                            io::stdout(),
-                                            LeaveAlternateScreen,
-                                            DisableMouseCapture
-                                        )?;
-
-                                        // Open in neovim (blocking)
-                                        let result = app::open_in_neovim(&filepath);
-
-                                        // Re-enter TUI mode
-                                        enable_raw_mode()?;
-                                        execute!(
-                                            io::stdout(),
-                                            EnterAlternateScreen,
-                                            EnableMouseCapture
-                                        )?;
-                                        terminal.clear()?;
-
-                                        if let Err(e) = result {
-                                            app.show_error(&format!("Neovim error: {}", e));
-                                        }
-                                    }
-                                    Err(e) => {
-                                        app.status_message = format!("⏳ {}", e);
-                                    }
-                                }
+                    if key.code == KeyCode::Esc {
+                        app.cancel_open_wait();
+                    }
+                }
+                // "Open N tabs?": y opens them, any other key cancels
+                AppState::Results if app.pending_open.is_some() => {
+                    app.confirm_open(key.code == KeyCode::Char('y'));
+                }
+                // Note prompt: a one-line editor, Enter saves and Esc cancels
+                AppState::Results if app.note_input.is_some() => {
+                    let Some((_, input)) = &mut app.note_input else {
+                        continue;
+                    };
+                    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                    match key.code {
+                        KeyCode::Esc => app.note_input = None,
+                        KeyCode::Enter => app.submit_note(),
+                        KeyCode::Char('w') if ctrl => input.delete_word_before(),
+                        KeyCode::Char('u') if ctrl => input.kill_to_start(),
+                        KeyCode::Char('a') if ctrl => input.move_home(),
+                        KeyCode::Char('e') if ctrl => input.move_end(),
+                        KeyCode::Char(c) if !ctrl => input.insert_char(c),
+                        KeyCode::Backspace => input.delete_before(),
+                        KeyCode::Delete => input.delete_after(),
+                        KeyCode::Left => input.move_left(),
+                        KeyCode::Right => input.move_right(),
+                        KeyCode::Home => input.move_home(),
+                        KeyCode::End => input.move_end(),
+                        _ => {}
+                    }
+                }
+                // Headings popup: move, open the page at one, or close it
+                AppState::Results if app.outline.is_some() => {
+                    let menu_key = app.config.keymap.menu_key(&key, Action::Outline);
+                    match (menu_key, &mut app.outline) {
+                        (Some(MenuKey::Quit), _) => return Ok(()),
+                        (Some(MenuKey::Choose), _) => {
+                            let jump = app.take_outline_jump();
+                            open_selected_in_editor(terminal, app, &mut events, jump.as_deref())
+                                .await?;
+                        }
+                        (Some(MenuKey::Close), _) => app.outline = None,
+                        (Some(MenuKey::Next), Some(outline)) => outline.next(),
+                        (Some(MenuKey::Previous), Some(outline)) => outline.previous(),
+                        _ => {}
+                    }
+                }
+                // Result preview: Esc or the preview key closes it
+                AppState::Results if app.preview => {
+                    let keys = &app.config.keymap;
+                    if keys.is_bound(Action::Quit, &key) {
+                        return Ok(());
+                    }
+                    if keys.is_bound(Action::Back, &key) || keys.is_bound(Action::Preview, &key) {
+                        app.preview = false;
+                    }
+                }
+                // "Open with" menu: move, run a command, or close it
+                AppState::Results if app.open_with.is_some() => {
+                    let menu_key = app.config.keymap.menu_key(&key, Action::OpenWith);
+                    match (menu_key, &mut app.open_with) {
+                        (Some(MenuKey::Quit), _) => return Ok(()),
+                        (Some(MenuKey::Choose), _) => app.run_open_with(&tx),
+                        (Some(MenuKey::Close), _) => app.open_with = None,
+                        (Some(MenuKey::Next), Some(menu)) => menu.next(),
+                        (Some(MenuKey::Previous), Some(menu)) => menu.previous(),
+                        _ => {}
+                    }
+                }
+                // Suggestion picker: move, search the suggestion, or close it
+                AppState::Results if app.suggestion_menu.is_some() => {
+                    let menu_key = app.config.keymap.menu_key(&key, Action::Suggestions);
+                    match (menu_key, &mut app.suggestion_menu) {
+                        (Some(MenuKey::Quit), _) => return Ok(()),
+                        (Some(MenuKey::Choose), _) => app.choose_suggestion(&tx).await,
+                        (Some(MenuKey::Close), _) => app.suggestion_menu = None,
+                        (Some(MenuKey::Next), Some(menu)) => menu.next(),
+                        (Some(MenuKey::Previous), Some(menu)) => menu.previous(),
+                        _ => {}
+                    }
+                }
+                AppState::Results => {
+                    // Counts and multi-key bindings go through the key sequence
+                    let action = match app.key_sequence.feed(key) {
+                        KeyAction::Motion(motion, count) => {
+                            app.apply_motion(motion, count);
+                            continue;
+                        }
+                        KeyAction::Jump { number, open } => {
+                            if app.jump_to_result(number) && open {
+                                open_selected(terminal, app, &mut events).await?;
                             }
-                            KeyCode::Esc => {
-                                app.back_to_input();
-                                last_g_press = None;
+                            continue;
+                        }
+                        KeyAction::Command(action) => action,
+                        KeyAction::Pending | KeyAction::Cancelled | KeyAction::Unhandled => continue,
+                    };
+
+                    match action {
+                        Action::Quit => return Ok(()),
+                        Action::ToggleSelect => app.toggle_selection(),
+                        Action::SelectAll => app.select_all(),
+                        Action::InvertSelection => app.invert_selection(),
+                        Action::OpenBrowser => app.open_in_browser(),
+                        Action::CopyUrl => app.copy_urls(),
+                        Action::CopyPath => app.copy_markdown_path().await,
+                        Action::Refresh => app.refresh_selected().await,
+                        Action::ReExtract => app.re_extract_selected().await,
+                        Action::Retry => app.retry_prefetch(false).await,
+                        Action::RetryAll => app.retry_prefetch(true).await,
+                        Action::Pin => app.toggle_pin().await,
+                        Action::Bookmark => app.bookmark_selected(),
+                        Action::Export => app.export_results(ExportFormat::Markdown),
+                        Action::ExportJson => app.export_results(ExportFormat::Json),
+                        Action::ExportObsidian => app.export_to_obsidian(),
+                        Action::Cite => app.cite_selected(),
+                        Action::OpenWith => app.open_with_menu(),
+                        Action::OpenEditor => open_selected(terminal, app, &mut events).await?,
+                        Action::Delete => app.delete_result().await,
+                        Action::Undo => app.undo_delete().await,
+                        Action::Sort => app.cycle_sort(),
+                        Action::SiteSearch => app.search_selected_site(),
+                        Action::Outline => app.open_outline(),
+                        Action::Annotate => app.start_note(),
+                        Action::Back => app.clear_selection_or_back(),
+                        Action::HistoryBack => app.history_back().await,
+                        Action::HistoryForward => app.history_forward().await,
+                        Action::NextPage => app.load_next_page(&tx),
+                        Action::EngineStatus => app.engine_status = true,
+                        Action::Goggles => app.open_goggle_menu(),
+                        Action::ToggleGoggle => app.toggle_goggle(),
+                        Action::Density => app.cycle_density(),
+                        Action::ToggleRead => app.toggle_read(),
+                        Action::Preview => app.preview = !app.results.is_empty(),
+                        Action::Suggestions => app.use_suggestions(&tx).await,
+                        Action::Quickfix => open_quickfix(terminal, app, &mut events).await?,
+                        _ => {}
+                    }
+                }
+                AppState::Searching => {
+                    if app.config.keymap.is_bound(Action::Quit, &key) {
+                        return Ok(());
+                    }
+                    if key.code == KeyCode::Esc {
+                        app.cancel_search();
+                    }
+                }
+                AppState::Library if app.library_filtering => match key.code {
+                    KeyCode::Esc => {
+                        app.library_filter.clear();
+                        app.library_filtering = false;
+                    }
+                    KeyCode::Enter => {
+                        app.library_filtering = false;
+                    }
+                    KeyCode::Backspace => {
+                        app.library_filter.delete_before();
+                        app.clamp_library_selection();
+                    }
+                    KeyCode::Left => app.library_filter.move_left(),
+                    KeyCode::Right => app.library_filter.move_right(),
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.library_filter.insert_char(c);
+                        app.clamp_library_selection();
+                    }
+                    _ => {}
+                },
+                AppState::Library => {
+                    let KeyAction::Command(action) = app.key_sequence.feed_in(KeyContext::Library, key)
+                    else {
+                        continue;
+                    };
+                    match action {
+                        Action::Quit => return Ok(()),
+                        Action::Library | Action::Back => app.close_library(),
+                        Action::Next => app.library_next(),
+                        Action::Prev => app.library_previous(),
+                        Action::Filter => app.library_filtering = true,
+                        Action::Delete => app.delete_library_entry(),
+                        Action::Pin => app.toggle_library_pin(),
+                        Action::OpenEditor => {
+                            if let Some(path) = app.selected_library_entry().map(|e| e.path.clone()) {
+                                open_files_in_editor(terminal, app, &mut events, &[path], None)
+                                    .await?;
                             }
-                            _ => {
-                                last_g_press = None;
+                        }
+                        _ => {}
+                    }
+                }
+                AppState::LocalSearch => {
+                    let KeyAction::Command(action) =
+                        app.key_sequence.feed_in(KeyContext::LocalSearch, key)
+                    else {
+                        continue;
+                    };
+                    match action {
+                        Action::Quit => return Ok(()),
+                        Action::LocalSearch | Action::Back => app.close_local_search(),
+                        Action::Next => app.local_next(),
+                        Action::Prev => app.local_previous(),
+                        Action::OpenEditor => {
+                            // Start on the best matching line
+                            if let Some(found) = app.selected_local_match() {
+                                let path = found.path.clone();
+                                let jump = format!("+{}", found.line);
+                                open_files_in_editor(
+                                    terminal,
+                                    app,
+                                    &mut events,
+                                    &[path],
+                                    Some(&jump),
+                                )
+                                .await?;
                             }
                         }
+                        _ => {}
                     }
-                    AppState::Searching => {
-                        if key.modifiers.contains(KeyModifiers::CONTROL)
-                            && key.code == KeyCode::Char('q')
-                        {
-                            return Ok(());
+                }
+                AppState::Bookmarks => {
+                    let KeyAction::Command(action) =
+                        app.key_sequence.feed_in(KeyContext::Bookmarks, key)
+                    else {
+                        continue;
+                    };
+                    match action {
+                        Action::Quit => return Ok(()),
+                        Action::Back => app.close_bookmarks(),
+                        Action::Next => app.bookmark_next(),
+                        Action::Prev => app.bookmark_previous(),
+                        Action::Delete => app.delete_bookmark(),
+                        Action::OpenBrowser => app.open_in_browser(),
+                        Action::OpenEditor => {
+                            // Pages that aren't cached are downloaded first
+                            app.fetch_selected_bookmark().await;
+                            if !app.open_selected_document() && !app.wait_for_selected_page() {
+                                open_selected_in_editor(terminal, app, &mut events, None).await?;
+                            }
                         }
+                        _ => {}
                     }
-                    AppState::Error => {
-                        app.dismiss_error();
+                }
+                AppState::Error => {
+                    if app.config.keymap.is_bound(Action::Quit, &key) {
+                        return Ok(());
                     }
+                    app.dismiss_error();
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Open the selected result: PDFs in the system viewer, pages in the editor
+///
+/// Pages still loading are opened once they're ready. With Tab-selected
+/// results, every ready page opens in one editor.
+async fn open_selected<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    events: &mut EventStream,
+) -> Result<()> {
+    // Tab-selected results open together, skipping pages not ready yet
+    if !app.selected_items.is_empty(&app.results) {
+        let (paths, note) = app.prepare_neovim_open_selected().await;
+        if !paths.is_empty() {
+            open_files_in_editor(terminal, app, events, &paths, None).await?;
+        }
+        if let Some(note) = note {
+            app.set_status(note);
+        }
+        return Ok(());
+    }
+
+    if app.open_selected_document() || app.wait_for_selected_page() {
+        return Ok(());
+    }
+
+    open_selected_in_editor(terminal, app, events, None).await
+}
+
+/// Open the selected result in the editor, at `jump` if given
+///
+/// A failure to activate the page is shown in the status line.
+async fn open_selected_in_editor<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    events: &mut EventStream,
+    jump: Option<&str>,
+) -> Result<()> {
+    match app.prepare_neovim_open().await {
+        Ok(filepath) => open_files_in_editor(terminal, app, events, &[filepath], jump).await,
+        Err(e) => {
+            app.set_status(format!("⏳ {}", e));
+            Ok(())
+        }
+    }
+}
+
+/// Open the fetched pages of the selected results, or of all results, as
+/// the editor's quickfix list
+///
+/// A Neovim server loads the list; otherwise the editor starts with `-q`.
+async fn open_quickfix<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    events: &mut EventStream,
+) -> Result<()> {
+    let Some((file, note)) = app.prepare_quickfix().await else {
+        return Ok(());
+    };
+    let mut sent = false;
+    if let Some(server) = &app.config.editor.nvim_server {
+        match app::send_quickfix_to_nvim_server(server, &file).await {
+            Ok(()) => {
+                app.set_status("✓ Sent the quickfix list to running Neovim");
+                sent = true;
+            }
+            Err(e) => tracing::warn!(error = %e, "falling back to spawning the editor"),
+        }
+    }
+    if !sent {
+        spawn_editor(terminal, app, events, &[file], Some("-q")).await?;
+    }
+    if let Some(note) = note {
+        app.set_status(note);
+    }
+    Ok(())
+}
+
+/// Suspend the TUI while the editor shows `paths`
+///
+/// With a Neovim server configured the files are sent there instead and
+/// the TUI keeps running. `jump` is passed on to the editor, like `+42`.
+async fn open_files_in_editor<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    events: &mut EventStream,
+    paths: &[PathBuf],
+    jump: Option<&str>,
+) -> Result<()> {
+    let editor = &app.config.editor;
+    if let Some(server) = &editor.nvim_server {
+        match app::send_to_nvim_server(server, paths, editor.remote_tab, jump).await {
+            Ok(()) => {
+                app.set_status("✓ Sent to running Neovim");
+                return Ok(());
+            }
+            Err(e) => tracing::warn!(error = %e, "falling back to spawning the editor"),
+        }
+    }
+    spawn_editor(terminal, app, events, paths, jump).await
+}
+
+/// Run the editor on `paths`, in a tmux window or pane or in place of the TUI
+///
+/// `jump` is put before the files, like `+42` or `-q`.
+async fn spawn_editor<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    events: &mut EventStream,
+    paths: &[PathBuf],
+    jump: Option<&str>,
+) -> Result<()> {
+    // A missing editor is reported without leaving the TUI
+    if let Err(e) = app::check_editor(&app.config.editor) {
+        let message = if app.state == AppState::Results {
+            let browser = app.config.keymap.label(Action::OpenBrowser);
+            format!("⚠ {}, press {} to open in browser", e, browser)
+        } else {
+            format!("⚠ {}", e)
+        };
+        app.set_status(message);
+        return Ok(());
+    }
+
+    // Inside tmux the editor can get its own window and the TUI keeps running
+    let editor = &app.config.editor;
+    if editor.open_mode != OpenMode::Replace && std::env::var_os("TMUX").is_some() {
+        match app::open_in_tmux(editor, paths, jump) {
+            Ok(place) => app.set_status(format!("✓ Opened in a new {}", place)),
+            Err(e) => app.set_status(format!("⚠ {}", e)),
+        }
+        return Ok(());
+    }
+
+    // Replacing the stream stops its reader thread so it doesn't compete
+    // with the editor for stdin; the new one only starts reading once polled
+    *events = EventStream::new();
+
+    // Open in the editor (blocking); TUI mode comes back when the guard drops
+    let result = {
+        let _suspended = terminal::Suspended::new()?;
+        app::open_in_editor(&app.config.editor, paths, jump)
+    };
+    terminal.clear()?;
+
+    if let Err(e) = result {
+        let message = format!("{} error: {}", app.config.editor.name(), e);
+        app.show_error(Severity::Recoverable, &message);
+    }
+
+    Ok(())
+}
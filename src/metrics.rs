@@ -0,0 +1,160 @@
+//! How long searches and their prefetching take
+//!
+//! The app times each search from its start to the results, and the
+//! prefetch batch that follows. The results list shows both, like `10
+//! results in 0.84s (brave) · prefetch 9/10 in 5.2s`. With `search.metrics
+//! = true` each search is also appended to `metrics.csv` in the data
+//! directory, once its prefetching is done or another search replaces it.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::Engine;
+use crate::paths;
+
+/// Timings appended with `search.metrics`
+const METRICS_FILE: &str = "metrics.csv";
+
+/// Columns of `metrics.csv`; the timestamp is in Unix seconds
+const HEADER: &str =
+    "timestamp,engine,query_length,results,search_secs,prefetch_ready,prefetch_total,prefetch_secs";
+
+/// Timings of one search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchTiming {
+    /// Unix time the search finished
+    pub finished_at: u64,
+    pub engine: Engine,
+    /// Characters of the query as typed
+    pub query_length: usize,
+    /// Results shown
+    pub results: usize,
+    /// From starting the search to its results
+    pub elapsed: Duration,
+    /// The prefetch batch, once it finished
+    pub prefetch: Option<PrefetchTiming>,
+}
+
+/// Timing of a prefetch batch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrefetchTiming {
+    /// Pages saved or already cached
+    pub ready: usize,
+    pub total: usize,
+    pub elapsed: Duration,
+}
+
+impl SearchTiming {
+    /// Like `10 results in 0.84s (brave) · prefetch 9/10 in 5.2s`
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} results in {:.2}s ({})",
+            self.results,
+            self.elapsed.as_secs_f64(),
+            self.engine.short_name()
+        );
+        if let Some(prefetch) = &self.prefetch {
+            summary.push_str(&format!(
+                " · prefetch {}/{} in {:.1}s",
+                prefetch.ready,
+                prefetch.total,
+                prefetch.elapsed.as_secs_f64()
+            ));
+        }
+        summary
+    }
+
+    /// Line of `metrics.csv`; prefetch columns are empty when unfinished
+    fn csv_row(&self) -> String {
+        let prefetch = match &self.prefetch {
+            Some(p) => format!("{},{},{:.3}", p.ready, p.total, p.elapsed.as_secs_f64()),
+            None => ",,".to_string(),
+        };
+        format!(
+            "{},{},{},{},{:.3},{}",
+            self.finished_at,
+            self.engine.short_name(),
+            self.query_length,
+            self.results,
+            self.elapsed.as_secs_f64(),
+            prefetch
+        )
+    }
+}
+
+/// Append `timing` to `metrics.csv` in the data directory
+pub fn append(timing: &SearchTiming) -> Result<()> {
+    append_to(&paths::data_dir().join(METRICS_FILE), timing)
+}
+
+/// Append `timing` to the CSV file at `path`, with the header if it's new
+fn append_to(path: &Path, timing: &SearchTiming) -> Result<()> {
+    let new = !path.exists();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    if new {
+        writeln!(file, "{}", HEADER)?;
+    }
+    writeln!(file, "{}", timing.csv_row())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing() -> SearchTiming {
+        SearchTiming {
+            finished_at: 1_718_000_000,
+            engine: Engine::Brave,
+            query_length: 12,
+            results: 10,
+            elapsed: Duration::from_millis(840),
+            prefetch: None,
+        }
+    }
+
+    #[test]
+    fn test_summary_adds_the_prefetch_once_done() {
+        let mut timing = timing();
+        assert_eq!(timing.summary(), "10 results in 0.84s (brave)");
+        timing.prefetch = Some(PrefetchTiming {
+            ready: 9,
+            total: 10,
+            elapsed: Duration::from_millis(5_230),
+        });
+        assert_eq!(timing.summary(), "10 results in 0.84s (brave) · prefetch 9/10 in 5.2s");
+    }
+
+    #[test]
+    fn test_rows_follow_one_header() {
+        let name = format!("websearch-metrics-{}.csv", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&path);
+
+        let mut done = timing();
+        done.engine = Engine::DuckDuckGo;
+        done.prefetch = Some(PrefetchTiming {
+            ready: 9,
+            total: 10,
+            elapsed: Duration::from_millis(5_230),
+        });
+        append_to(&path, &timing()).unwrap();
+        append_to(&path, &done).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            [
+                HEADER,
+                "1718000000,brave,12,10,0.840,,,",
+                "1718000000,ddg,12,10,0.840,9,10,5.230",
+            ]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+//! Notes added to saved pages with `a`
+//!
+//! A note is appended to the page's `## Notes` section, which is added at
+//! the end of the page the first time. The note is also kept with the
+//! page's cache index entry and its bookmark, if it has one.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::cache_index::unix_now;
+use crate::export::format_utc;
+
+/// Heading of the section notes are appended to
+const NOTES_HEADING: &str = "## Notes";
+
+/// A note and when it was written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub text: String,
+    /// Unix timestamp (seconds)
+    pub written_at: u64,
+}
+
+impl Note {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.trim().to_string(),
+            written_at: unix_now(),
+        }
+    }
+
+    /// Markdown list item like `- 2026-10-16 14:02 UTC: text`
+    pub fn markdown(&self) -> String {
+        let (date, time) = format_utc(self.written_at);
+        format!("- {} {} UTC: {}", date, time, self.text)
+    }
+}
+
+/// Append `note` to the markdown page at `path`
+pub fn append_to_page(path: &Path, note: &Note) -> Result<()> {
+    let markdown = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    std::fs::write(path, add_to_markdown(&markdown, &note.markdown()))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// `markdown` with `item` at the end of its notes section
+///
+/// The section ends at the next heading of level 1 or 2; without one it is
+/// added at the end of the page.
+fn add_to_markdown(markdown: &str, item: &str) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let Some(heading) = lines.iter().position(|line| line.trim_end() == NOTES_HEADING) else {
+        return format!("{}\n\n{}\n\n{}\n", markdown.trim_end(), NOTES_HEADING, item);
+    };
+
+    let end = lines[heading + 1..]
+        .iter()
+        .position(|line| line.starts_with("# ") || line.starts_with("## "))
+        .map_or(lines.len(), |i| heading + 1 + i);
+    // After the last item, not after the blank lines before the next section
+    let mut insert_at = end;
+    while insert_at > heading + 1 && lines[insert_at - 1].trim().is_empty() {
+        insert_at -= 1;
+    }
+
+    let mut out: Vec<&str> = lines[..insert_at].to_vec();
+    if insert_at == heading + 1 {
+        out.push("");
+    }
+    out.push(item);
+    if insert_at < lines.len() {
+        out.push("");
+    }
+    out.extend(lines[insert_at..].iter().skip_while(|line| line.trim().is_empty()));
+    let mut markdown = out.join("\n");
+    markdown.push('\n');
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_to_markdown_creates_then_extends_the_section() {
+        let page = "---\ntitle: \"Pin\"\n---\n\n# Pin\n\nBody text.\n\n";
+        let once = add_to_markdown(page, "- first");
+        assert_eq!(once, "---\ntitle: \"Pin\"\n---\n\n# Pin\n\nBody text.\n\n## Notes\n\n- first\n");
+
+        let twice = add_to_markdown(&once, "- second");
+        assert!(twice.ends_with("## Notes\n\n- first\n- second\n"));
+
+        // A section followed by another one keeps it in place
+        let middle = "# Pin\n\n## Notes\n\n- first\n\n## Links\n\n- a\n";
+        assert_eq!(
+            add_to_markdown(middle, "- second"),
+            "# Pin\n\n## Notes\n\n- first\n- second\n\n## Links\n\n- a\n"
+        );
+        assert_eq!(
+            add_to_markdown("## Notes\n## Links\n", "- first"),
+            "## Notes\n\n- first\n\n## Links\n"
+        );
+    }
+
+    #[test]
+    fn test_note_markdown() {
+        let note = Note {
+            text: "covers the Pin API changes in 1.68".to_string(),
+            written_at: 1_700_000_000,
+        };
+        assert_eq!(note.markdown(), "- 2023-11-14 22:13 UTC: covers the Pin API changes in 1.68");
+    }
+}
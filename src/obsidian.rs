@@ -0,0 +1,235 @@
+//! Copies of saved pages in an Obsidian vault
+//!
+//! `O` in the results list copies the selected page into
+//! `paths.obsidian_vault`, named after the page's title. The frontmatter is
+//! replaced with the properties Obsidian knows (`tags`, `source`,
+//! `created`). Links to pages already clipped into the vault become
+//! wiki-links, and links to files on disk are made relative to the vault.
+//! Existing notes are never overwritten.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::cache_index::{
+    normalize_url, parse_frontmatter, read_frontmatter, unix_now, yaml_quote, yaml_scalar,
+};
+use crate::config::OutputFormat;
+use crate::export::{format_utc, relative_to};
+use crate::extract_clean_md::rewrite_links;
+
+/// Tag put on every clipped page
+const TAG: &str = "websearch";
+
+/// Longest note name, in characters
+const MAX_NAME_LEN: usize = 100;
+
+/// Copy the page at `page` into `vault`, returning the note written
+pub fn export_page(page: &Path, vault: &Path) -> Result<PathBuf> {
+    if OutputFormat::of_path(page) != Some(OutputFormat::Markdown) {
+        anyhow::bail!("only pages saved as markdown can be clipped");
+    }
+    let markdown = std::fs::read_to_string(page)
+        .with_context(|| format!("Failed to read {}", page.display()))?;
+    std::fs::create_dir_all(vault)
+        .with_context(|| format!("Failed to create {}", vault.display()))?;
+
+    let clippings = vault_sources(vault);
+    let (date, _) = format_utc(unix_now());
+    let (name, note) = to_obsidian(&markdown, &date, vault, &clippings);
+
+    let path = unique_note_path(vault, &name);
+    std::fs::write(&path, note).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Note name and contents of a saved page
+///
+/// `clippings` maps the normalized source URL of each note already in the
+/// vault to its name.
+fn to_obsidian(
+    markdown: &str,
+    date: &str,
+    vault: &Path,
+    clippings: &HashMap<String, String>,
+) -> (String, String) {
+    let frontmatter = parse_frontmatter(markdown);
+    let body = strip_frontmatter(markdown).trim_start();
+
+    // The page's own `# Title` line, as it reads in the editor
+    let title = body
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("# "))
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .or(frontmatter.title.as_deref())
+        .unwrap_or("Untitled");
+    let name = note_name(title);
+
+    let mut note = String::from("---\n");
+    note.push_str(&format!("title: {}\n", yaml_quote(title)));
+    if let Some(url) = &frontmatter.url {
+        note.push_str(&format!("source: {}\n", yaml_scalar(url)));
+    }
+    if let Some(author) = &frontmatter.author {
+        note.push_str(&format!("author: {}\n", yaml_quote(author)));
+    }
+    note.push_str(&format!("created: {}\n", date));
+    note.push_str(&format!("tags: [{}]\n", TAG));
+    note.push_str("---\n\n");
+    note.push_str(&rewrite_links(body, |text, target, image| {
+        link_for_vault(text, target, image, vault, clippings)
+    }));
+    (name, note)
+}
+
+/// Replacement for a link in a clipped page, `None` to keep it
+fn link_for_vault(
+    text: &str,
+    target: &str,
+    image: bool,
+    vault: &Path,
+    clippings: &HashMap<String, String>,
+) -> Option<String> {
+    let local = match Url::parse(target) {
+        Ok(url) if url.scheme() == "file" => url.to_file_path().ok(),
+        Ok(url) if !image && matches!(url.scheme(), "http" | "https") => {
+            let name = clippings.get(&normalize_url(target))?;
+            return Some(if text.is_empty() || text == name {
+                format!("[[{}]]", name)
+            } else {
+                format!("[[{}|{}]]", name, text)
+            });
+        }
+        Ok(_) => None,
+        Err(_) => Some(PathBuf::from(target)).filter(|path| path.is_absolute() && path.exists()),
+    }?;
+
+    let relative = relative_to(vault, &local).to_string_lossy().replace('\\', "/");
+    let relative = if relative.contains(' ') {
+        format!("<{}>", relative)
+    } else {
+        relative
+    };
+    Some(format!("{}[{}]({})", if image { "!" } else { "" }, text, relative))
+}
+
+/// Normalized source URL → note name, for the notes already in `vault`
+fn vault_sources(vault: &Path) -> HashMap<String, String> {
+    let Ok(entries) = std::fs::read_dir(vault) else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "md"))
+        .filter_map(|path| {
+            // Clipped notes keep the page URL in `source`
+            let source = read_frontmatter(&path)?.source?;
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            Some((normalize_url(&source), name))
+        })
+        .collect()
+}
+
+/// `markdown` without its leading `---` frontmatter block
+fn strip_frontmatter(markdown: &str) -> &str {
+    let Some(rest) = markdown.strip_prefix("---\n") else {
+        return markdown;
+    };
+    match rest.find("\n---\n") {
+        Some(end) => &rest[end + "\n---\n".len()..],
+        None => markdown,
+    }
+}
+
+/// Note name for a title, without the characters Obsidian can't link to
+fn note_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let name: String = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_NAME_LEN)
+        .collect();
+    // A leading dot would hide the note
+    let name = name.trim().trim_start_matches('.').to_string();
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name
+    }
+}
+
+/// `vault/name.md`, or `vault/name (2).md` and so on if that is taken
+fn unique_note_path(vault: &Path, name: &str) -> PathBuf {
+    let mut path = vault.join(format!("{}.md", name));
+    let mut n = 2;
+    while path.exists() {
+        path = vault.join(format!("{} ({}).md", name, n));
+        n += 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_obsidian_rewrites_frontmatter_and_links() {
+        let dir = std::env::temp_dir().join(format!("websearch-obsidian-{}", std::process::id()));
+        let vault = dir.join("vault/Clippings");
+        std::fs::create_dir_all(&vault).unwrap();
+        let image = dir.join("cache/diagram.png");
+        std::fs::create_dir_all(image.parent().unwrap()).unwrap();
+        std::fs::write(&image, "").unwrap();
+
+        let page = format!(
+            "---\ntitle: \"Pin: a guide\"\nurl: https://blog.example/pin\nauthor: \"Ann \\\"A\\\" Lee\"\n\
+             source: \"Blog\"\n---\n\n# Pin: a guide / 2024\n\nSee [the intro](https://docs.rs/pin/#top), \
+             [std](https://doc.rust-lang.org/std/) and ![a diagram]({}).\n",
+            image.display()
+        );
+        let clippings = HashMap::from([("https://docs.rs/pin".to_string(), "Pin docs".to_string())]);
+        let (name, note) = to_obsidian(&page, "2026-10-16", &vault, &clippings);
+
+        assert_eq!(name, "Pin a guide 2024");
+        assert_eq!(
+            note,
+            "---\ntitle: \"Pin: a guide / 2024\"\nsource: https://blog.example/pin\n\
+             author: \"Ann \\\"A\\\" Lee\"\ncreated: 2026-10-16\ntags: [websearch]\n---\n\n\
+             # Pin: a guide / 2024\n\nSee [[Pin docs|the intro]], [std](https://doc.rust-lang.org/std/) \
+             and ![a diagram](../../cache/diagram.png).\n"
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_export_page_never_overwrites_notes() {
+        let dir = std::env::temp_dir().join(format!("websearch-obsidian-export-{}", std::process::id()));
+        let vault = dir.join("vault");
+        std::fs::create_dir_all(&dir).unwrap();
+        let page = dir.join("page.md");
+        std::fs::write(&page, "---\nurl: https://a.example/\n---\n\n# Notes?\n\nBody\n").unwrap();
+
+        let first = export_page(&page, &vault).unwrap();
+        let second = export_page(&page, &vault).unwrap();
+        assert_eq!(first, vault.join("Notes.md"));
+        assert_eq!(second, vault.join("Notes (2).md"));
+        assert!(std::fs::read_to_string(&second).unwrap().contains("source: https://a.example/\n"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
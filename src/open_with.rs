@@ -0,0 +1,136 @@
+//! "Open with" menu for sending a result to other programs
+//!
+//! `o` lists the commands of the `[open_with]` config table, like
+//! `mpv = "mpv {url}"`. `{url}` and `{path}` are replaced with the quoted
+//! result URL and saved page, as in [`crate::hooks`], and the command runs
+//! detached through the shell. Commands using `{path}` can't be chosen
+//! until the page has been fetched.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::hooks::command_line;
+
+/// A configured command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenWithCommand {
+    /// Name shown in the menu
+    pub label: String,
+    /// Shell command with `{url}` and `{path}` placeholders
+    pub template: String,
+}
+
+impl OpenWithCommand {
+    /// Whether the command needs the saved page
+    pub fn needs_path(&self) -> bool {
+        self.template.contains("{path}")
+    }
+}
+
+/// Menu popup over the results list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenWithMenu {
+    pub url: String,
+    /// Saved page of the result, `None` until it is fetched
+    pub path: Option<PathBuf>,
+    /// Commands sorted by label
+    pub commands: Vec<OpenWithCommand>,
+    pub selected: usize,
+}
+
+impl OpenWithMenu {
+    pub fn new(url: &str, path: Option<PathBuf>, commands: &BTreeMap<String, String>) -> Self {
+        Self {
+            url: url.to_string(),
+            path,
+            commands: commands
+                .iter()
+                .map(|(label, template)| OpenWithCommand {
+                    label: label.clone(),
+                    template: template.clone(),
+                })
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    /// Move the selection down (wraps)
+    pub fn next(&mut self) {
+        if !self.commands.is_empty() {
+            self.selected = (self.selected + 1) % self.commands.len();
+        }
+    }
+
+    /// Move the selection up (wraps)
+    pub fn previous(&mut self) {
+        let count = self.commands.len();
+        if count > 0 {
+            self.selected = (self.selected + count - 1) % count;
+        }
+    }
+
+    /// Whether `command` can run for this result now
+    pub fn is_available(&self, command: &OpenWithCommand) -> bool {
+        self.path.is_some() || !command.needs_path()
+    }
+
+    /// Label and shell command line of the selected command
+    ///
+    /// `None` when nothing is selected or the command needs the page and
+    /// it isn't fetched yet.
+    pub fn selected_command(&self) -> Option<(&str, String)> {
+        let command = self.commands.get(self.selected)?;
+        if !self.is_available(command) {
+            return None;
+        }
+        let line = command_line(&command.template, self.path.as_deref(), &self.url);
+        Some((&command.label, line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("mpv".to_string(), "mpv --no-terminal {url}".to_string()),
+            ("glow".to_string(), "glow {path}".to_string()),
+            ("wget".to_string(), "wget -P ~/Downloads {url}".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_commands_needing_the_page_wait_for_it() {
+        let url = "https://youtu.be/x?t=1&list=y";
+        let mut menu = OpenWithMenu::new(url, None, &commands());
+        let labels: Vec<_> = menu.commands.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["glow", "mpv", "wget"]);
+
+        // glow needs {path}, which doesn't exist yet
+        assert_eq!(menu.selected_command(), None);
+        menu.next();
+        assert_eq!(
+            menu.selected_command(),
+            Some(("mpv", "mpv --no-terminal 'https://youtu.be/x?t=1&list=y'".to_string()))
+        );
+
+        menu.path = Some(PathBuf::from("/tmp/my page.md"));
+        menu.previous();
+        assert_eq!(menu.selected_command(), Some(("glow", "glow '/tmp/my page.md'".to_string())));
+    }
+
+    #[test]
+    fn test_navigation_wraps() {
+        let mut menu = OpenWithMenu::new("https://a.example/", None, &commands());
+        menu.previous();
+        assert_eq!(menu.selected, 2);
+        menu.next();
+        assert_eq!(menu.selected, 0);
+
+        let mut empty = OpenWithMenu::new("https://a.example/", None, &BTreeMap::new());
+        empty.next();
+        empty.previous();
+        assert_eq!((empty.selected, empty.selected_command()), (0, None));
+    }
+}
@@ -0,0 +1,173 @@
+//! Headings of a saved page, for opening it at a section
+//!
+//! The page is read when `:` is pressed, a line at a time. Lines inside
+//! the frontmatter and fenced code blocks are skipped, so shell comments in
+//! code samples don't show up as headings.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// A markdown heading of a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Number of `#`, 1 to 6
+    pub level: usize,
+    /// Heading text without the `#`s
+    pub text: String,
+    /// The whole line as written in the file
+    pub raw: String,
+    /// 1-based line number
+    pub line: usize,
+}
+
+impl Heading {
+    /// Vim `+/{pattern}` argument that finds this heading's line
+    ///
+    /// The pattern is anchored to the whole line and matched literally
+    /// (`\V`), so only backslashes and the `/` delimiter need escaping.
+    pub fn vim_search(&self) -> String {
+        let escaped = self.raw.replace('\\', "\\\\").replace('/', "\\/");
+        format!("+/^\\V{}\\$", escaped)
+    }
+}
+
+/// Headings popup over the results list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outline {
+    /// Page the headings were read from
+    pub path: PathBuf,
+    pub headings: Vec<Heading>,
+    pub selected: usize,
+}
+
+impl Outline {
+    /// Move the selection down (wraps)
+    pub fn next(&mut self) {
+        if !self.headings.is_empty() {
+            self.selected = (self.selected + 1) % self.headings.len();
+        }
+    }
+
+    /// Move the selection up (wraps)
+    pub fn previous(&mut self) {
+        let count = self.headings.len();
+        if count > 0 {
+            self.selected = (self.selected + count - 1) % count;
+        }
+    }
+
+    pub fn selected_heading(&self) -> Option<&Heading> {
+        self.headings.get(self.selected)
+    }
+}
+
+/// Headings of the markdown file at `path`, in order
+pub fn read_headings(path: &Path) -> std::io::Result<Vec<Heading>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = Vec::new();
+    let mut headings = Vec::new();
+    let mut in_frontmatter = false;
+    let mut fence: Option<String> = None;
+    let mut number = 0;
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        number += 1;
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end();
+
+        if number == 1 && line == "---" {
+            in_frontmatter = true;
+            continue;
+        }
+        if in_frontmatter {
+            in_frontmatter = line != "---";
+            continue;
+        }
+
+        // A fence is closed by the same characters it was opened with
+        let trimmed = line.trim_start();
+        if let Some(open) = &fence {
+            if trimmed.starts_with(open.as_str()) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(trimmed[..3].to_string());
+            continue;
+        }
+
+        if let Some((level, text)) = parse_heading(line) {
+            headings.push(Heading {
+                level,
+                text,
+                raw: line.to_string(),
+                line: number,
+            });
+        }
+    }
+
+    Ok(headings)
+}
+
+/// Level and text of an ATX heading like `## Install`
+pub fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    // Closing `#`s are decoration, but only after a space (`C#` stays)
+    let text = rest.trim();
+    let text = match text.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => stripped.trim_end(),
+        _ => text,
+    };
+    (!text.is_empty()).then(|| (level, text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_headings_skips_frontmatter_and_code() {
+        let path = std::env::temp_dir().join(format!("websearch-outline-{}.md", std::process::id()));
+        std::fs::write(
+            &path,
+            "---\ntitle: \"# Not a heading\"\n---\n\n# Tokio ##\n\n```sh\n# install\ncargo add tokio\n```\n\n\
+             ## Select\n#hashtag\n####### Too deep\n### \n~~~\n## fenced\n~~~\n### Cancel safety\n## Why C#\n",
+        )
+        .unwrap();
+
+        let headings = read_headings(&path).unwrap();
+        let found: Vec<_> = headings.iter().map(|h| (h.level, h.text.as_str(), h.line)).collect();
+        assert_eq!(
+            found,
+            [(1, "Tokio", 5), (2, "Select", 12), (3, "Cancel safety", 19), (2, "Why C#", 20)]
+        );
+        assert_eq!(headings[0].raw, "# Tokio ##");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_vim_search_escapes_slashes_and_backslashes() {
+        let heading = |raw: &str| Heading {
+            level: 2,
+            text: String::new(),
+            raw: raw.to_string(),
+            line: 1,
+        };
+        assert_eq!(heading("## Install").vim_search(), "+/^\\V## Install\\$");
+        assert_eq!(
+            heading("## I/O and \"quotes\" in C:\\dir [1].*").vim_search(),
+            "+/^\\V## I\\/O and \"quotes\" in C:\\\\dir [1].*\\$"
+        );
+    }
+}
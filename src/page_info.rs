@@ -0,0 +1,267 @@
+//! Publish date and language of an extracted page
+//!
+//! The date is looked for in `article:published_time` meta tags, JSON-LD
+//! `datePublished`, `<time datetime>` elements and `/2023/05/` style URLs,
+//! in that order, and written as RFC 3339. Only as much as the page says is
+//! kept: a date without a time stays a date, a time without a zone is
+//! dropped, and the URL's `/2023/05/` becomes `2023-05`. Dates in any other
+//! shape are ambiguous (is `05/06/2023` May or June?) and are left out.
+//!
+//! The language is detected from the extracted text with `whatlang`. The
+//! page's own `lang` attribute is kept when it agrees or when the text is
+//! too short to tell.
+
+use dom_query::Document;
+use serde_json::Value;
+use whatlang::Lang;
+
+use crate::cache_index::unix_now;
+use crate::export::format_utc;
+
+/// Characters of text the language is detected from
+const DETECT_CHARS: usize = 4000;
+
+/// ISO 639-1 codes of the languages `whatlang` detects
+#[rustfmt::skip]
+const LANG_CODES: [(Lang, &str); 69] = [
+    (Lang::Epo, "eo"), (Lang::Eng, "en"), (Lang::Rus, "ru"), (Lang::Cmn, "zh"),
+    (Lang::Spa, "es"), (Lang::Por, "pt"), (Lang::Ita, "it"), (Lang::Ben, "bn"),
+    (Lang::Fra, "fr"), (Lang::Deu, "de"), (Lang::Ukr, "uk"), (Lang::Kat, "ka"),
+    (Lang::Ara, "ar"), (Lang::Hin, "hi"), (Lang::Jpn, "ja"), (Lang::Heb, "he"),
+    (Lang::Yid, "yi"), (Lang::Pol, "pl"), (Lang::Amh, "am"), (Lang::Jav, "jv"),
+    (Lang::Kor, "ko"), (Lang::Nob, "nb"), (Lang::Dan, "da"), (Lang::Swe, "sv"),
+    (Lang::Fin, "fi"), (Lang::Tur, "tr"), (Lang::Nld, "nl"), (Lang::Hun, "hu"),
+    (Lang::Ces, "cs"), (Lang::Ell, "el"), (Lang::Bul, "bg"), (Lang::Bel, "be"),
+    (Lang::Mar, "mr"), (Lang::Kan, "kn"), (Lang::Ron, "ro"), (Lang::Slv, "sl"),
+    (Lang::Hrv, "hr"), (Lang::Srp, "sr"), (Lang::Mkd, "mk"), (Lang::Lit, "lt"),
+    (Lang::Lav, "lv"), (Lang::Est, "et"), (Lang::Tam, "ta"), (Lang::Vie, "vi"),
+    (Lang::Urd, "ur"), (Lang::Tha, "th"), (Lang::Guj, "gu"), (Lang::Uzb, "uz"),
+    (Lang::Pan, "pa"), (Lang::Aze, "az"), (Lang::Ind, "id"), (Lang::Tel, "te"),
+    (Lang::Pes, "fa"), (Lang::Mal, "ml"), (Lang::Ori, "or"), (Lang::Mya, "my"),
+    (Lang::Nep, "ne"), (Lang::Sin, "si"), (Lang::Khm, "km"), (Lang::Tuk, "tk"),
+    (Lang::Aka, "ak"), (Lang::Zul, "zu"), (Lang::Sna, "sn"), (Lang::Afr, "af"),
+    (Lang::Lat, "la"), (Lang::Slk, "sk"), (Lang::Cat, "ca"), (Lang::Tgl, "tl"),
+    (Lang::Hye, "hy"),
+];
+
+/// When the page was published, as RFC 3339 or a shorter date
+///
+/// Must run before Readability, which removes the `<head>` and scripts.
+pub fn published_date(document: &Document, url: &str) -> Option<String> {
+    let meta = [
+        "meta[property='article:published_time']",
+        "meta[name='article:published_time']",
+        "meta[itemprop='datePublished']",
+    ];
+    let from_meta = meta.iter().find_map(|selector| {
+        let content = document.select(selector).attr("content")?;
+        normalize_date(&content)
+    });
+
+    from_meta
+        .or_else(|| {
+            document
+                .select("script[type='application/ld+json']")
+                .iter()
+                .filter_map(|script| serde_json::from_str::<Value>(&script.text()).ok())
+                .find_map(|json| json_ld_date(&json))
+        })
+        .or_else(|| time_element_date(document))
+        .or_else(|| url_date(url))
+}
+
+/// First `datePublished` of a JSON-LD block, looking into `@graph` and lists
+fn json_ld_date(json: &Value) -> Option<String> {
+    match json {
+        Value::Object(object) => object
+            .get("datePublished")
+            .and_then(Value::as_str)
+            .and_then(normalize_date)
+            .or_else(|| object.values().find_map(json_ld_date)),
+        Value::Array(items) => items.iter().find_map(json_ld_date),
+        _ => None,
+    }
+}
+
+/// Date of the page's `<time datetime>` elements
+///
+/// One marked as the publish date wins. Otherwise all of them must agree:
+/// pages listing comments or related posts have several, and any of them
+/// could be the one.
+fn time_element_date(document: &Document) -> Option<String> {
+    let marked = document.select("time[itemprop='datePublished'], time[pubdate]");
+    if let Some(date) = marked.attr("datetime").and_then(|date| normalize_date(&date)) {
+        return Some(date);
+    }
+
+    let mut dates = document
+        .select("time[datetime]")
+        .iter()
+        .filter_map(|time| normalize_date(&time.attr("datetime")?));
+    let first = dates.next()?;
+    dates.all(|date| date == first).then_some(first)
+}
+
+/// `2023-05` or `2023-05-04` from URLs like `/2023/05/04/post`
+fn url_date(url: &str) -> Option<String> {
+    let path = url::Url::parse(url).ok()?.path().to_string();
+    let parts: Vec<&str> = path.split('/').collect();
+    let this_year: u32 = format_utc(unix_now()).0.get(..4)?.parse().ok()?;
+
+    parts.windows(2).enumerate().find_map(|(i, pair)| {
+        let year = number(pair[0], 4).filter(|year| (1990..=this_year).contains(year))?;
+        let month = number(pair[1], 2).filter(|month| (1..=12).contains(month))?;
+        // Only a day that is a whole path segment is taken
+        let day = parts
+            .get(i + 2)
+            .and_then(|day| number(day, 2))
+            .filter(|&day| day >= 1 && day <= days_in_month(year, month));
+        Some(match day {
+            Some(day) => format!("{:04}-{:02}-{:02}", year, month, day),
+            None => format!("{:04}-{:02}", year, month),
+        })
+    })
+}
+
+/// `text` as RFC 3339, `None` unless it is an ISO 8601 date
+///
+/// `2023-05-04T10:00:00.000+0200` becomes `2023-05-04T10:00:00+02:00` and
+/// `2023-05-04 10:00` (no zone) becomes `2023-05-04`.
+pub fn normalize_date(text: &str) -> Option<String> {
+    let text = text.trim();
+    let year = number(text.get(..4)?, 4)?;
+    let month = number(text.get(5..7)?, 2).filter(|month| (1..=12).contains(month))?;
+    let day = number(text.get(8..10)?, 2)
+        .filter(|&day| day >= 1 && day <= days_in_month(year, month))?;
+    if &text[4..5] != "-" || &text[7..8] != "-" {
+        return None;
+    }
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+
+    let rest = &text[10..];
+    if rest.is_empty() {
+        return Some(date);
+    }
+    let rest = rest.strip_prefix(['T', 't', ' '])?;
+    let hour = number(rest.get(..2)?, 2).filter(|&hour| hour < 24)?;
+    let minute = number(rest.get(3..5)?, 2).filter(|&minute| minute < 60)?;
+    if &rest[2..3] != ":" {
+        return None;
+    }
+    let mut rest = &rest[5..];
+    let mut second = 0;
+    if let Some(after) = rest.strip_prefix(':') {
+        second = number(after.get(..2)?, 2).filter(|&second| second < 61)?;
+        rest = after[2..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    }
+
+    let zone = match rest {
+        "" => return Some(date),
+        "Z" | "z" => "Z".to_string(),
+        zone => {
+            let sign = zone.get(..1).filter(|sign| matches!(*sign, "+" | "-"))?;
+            let digits: String = zone[1..].chars().filter(|&c| c != ':').collect();
+            let hours = number(digits.get(..2)?, 2).filter(|&hours| hours < 24)?;
+            let minutes = match digits.get(2..) {
+                Some("") | None => 0,
+                Some(minutes) => number(minutes, 2).filter(|&minutes| minutes < 60)?,
+            };
+            format!("{}{:02}:{:02}", sign, hours, minutes)
+        }
+    };
+    Some(format!("{}T{:02}:{:02}:{:02}{}", date, hour, minute, second, zone))
+}
+
+/// `text` as a number, when it is exactly `len` ASCII digits
+fn number(text: &str, len: usize) -> Option<u32> {
+    (text.len() == len && text.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| text.parse().ok())
+        .flatten()
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Language of `text`, or the page's `declared` one
+///
+/// The declared tag, like `en-US`, is more precise and is kept unless the
+/// text is confidently in another language, which is then written as its
+/// two-letter code.
+pub fn detect_language(text: &str, declared: Option<String>) -> Option<String> {
+    let declared = declared.filter(|lang| !lang.trim().is_empty());
+    let sample: String = text.chars().take(DETECT_CHARS).collect();
+    let detected = whatlang::detect(&sample)
+        .filter(|info| info.is_reliable())
+        .and_then(|info| LANG_CODES.iter().find(|(lang, _)| *lang == info.lang()))
+        .map(|(_, code)| *code);
+
+    match (detected, declared) {
+        (Some(code), Some(declared)) => {
+            let primary = declared.split(['-', '_']).next().unwrap_or_default();
+            if primary.eq_ignore_ascii_case(code) {
+                Some(declared)
+            } else {
+                Some(code.to_string())
+            }
+        }
+        (Some(code), None) => Some(code.to_string()),
+        (None, declared) => declared,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_published_date_sources_and_normalization() {
+        let page = |head: &str, body: &str| {
+            Document::from(format!("<html><head>{}</head><body>{}</body></html>", head, body))
+        };
+        let meta = page(
+            "<meta property=\"article:published_time\" content=\"2023-05-04T10:00:00.000+0200\">",
+            "<time datetime=\"2020-01-01\">",
+        );
+        let url = "https://blog.example/2019/07/post";
+        assert_eq!(published_date(&meta, url).as_deref(), Some("2023-05-04T10:00:00+02:00"));
+
+        let json_ld = r#"<script type="application/ld+json">{"@graph": [{"@type": "WebSite"},
+            {"@type": "Article", "datePublished": "2021-03-09T08:15Z"}]}</script>"#;
+        let graph = page(json_ld, "");
+        assert_eq!(published_date(&graph, url).as_deref(), Some("2021-03-09T08:15:00Z"));
+
+        // Comment timestamps disagree, so only the URL is left
+        let times = "<time datetime=\"2022-02-02\"></time><time datetime=\"2022-02-03\">";
+        let comments = page("", times);
+        assert_eq!(published_date(&comments, url).as_deref(), Some("2019-07"));
+        let one = page("", "<time datetime=\"2022-02-02 14:30\"></time>");
+        assert_eq!(published_date(&one, "https://a.example/").as_deref(), Some("2022-02-02"));
+
+        let dated = url_date("https://a.example/news/2018/02/28/x");
+        assert_eq!(dated.as_deref(), Some("2018-02-28"));
+        assert_eq!(url_date("https://a.example/2018/13/x"), None);
+        assert_eq!(url_date("https://a.example/1024/768/"), None);
+        for ambiguous in ["05/06/2023", "May 5, 2023", "2023-02-30", "2023-05-04T25:00Z", "2023"] {
+            assert_eq!(normalize_date(ambiguous), None, "{}", ambiguous);
+        }
+    }
+
+    #[test]
+    fn test_detect_language_prefers_confident_detection() {
+        let german = "Die Stadt liegt am Ufer des Flusses und ist für ihre alten Brücken \
+                      bekannt. Im Sommer kommen viele Besucher, um die Altstadt zu sehen.";
+        let english = "The city lies on the bank of the river and is known for its old \
+                       bridges. In summer many visitors come to see the old town.";
+        assert_eq!(detect_language(german, Some("en-US".to_string())).as_deref(), Some("de"));
+        assert_eq!(detect_language(english, Some("en-GB".to_string())).as_deref(), Some("en-GB"));
+        assert_eq!(detect_language(english, None).as_deref(), Some("en"));
+        assert_eq!(detect_language("ok", Some("fr".to_string())).as_deref(), Some("fr"));
+        assert_eq!(detect_language("", Some(" ".to_string())), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! Where websearch-tui keeps its files
+//!
+//! Cached pages, the index, cookies and the debug log live in the platform
+//! data directory (`$XDG_DATA_HOME/websearch-tui` on Linux) unless
+//! `paths.data_dir` or `WEBSEARCH_DATA_DIR` points elsewhere. Configuration
+//! lives in the platform config directory (`$XDG_CONFIG_HOME/websearch-tui`).
+//!
+//! Older versions used `./websearch` relative to the launch directory; such a
+//! directory is moved into the data directory on startup.
+
+use anyhow::{Context, Result};
+use directories::{BaseDirs, ProjectDirs};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Application name used for the platform directories
+const APP_NAME: &str = "websearch-tui";
+
+/// Data directory of older versions, relative to the working directory
+const LEGACY_DIR: &str = "websearch";
+
+/// Resolved data directory
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Resolve the data directory and move a legacy `./websearch` into it
+///
+/// `configured` comes from the config file or environment. Returns the
+/// legacy directory when one was migrated.
+pub fn init(configured: Option<&Path>) -> Result<Option<PathBuf>> {
+    let target = DATA_DIR.get_or_init(|| {
+        resolve_data_dir(configured.map(Path::to_path_buf), platform_data_dir())
+    });
+    let legacy = PathBuf::from(LEGACY_DIR);
+    Ok(migrate_legacy(&legacy, target)?.then_some(legacy))
+}
+
+/// Directory for cached pages and other app data
+pub fn data_dir() -> &'static Path {
+    DATA_DIR.get_or_init(|| resolve_data_dir(None, platform_data_dir()))
+}
+
+/// Directory for configuration files, if the platform has one
+pub fn config_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+/// `path` with a leading `~` replaced by the home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), BaseDirs::new()) {
+        (Ok(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", APP_NAME)
+}
+
+fn platform_data_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.data_dir().to_path_buf())
+}
+
+/// The configured directory when set, else the platform directory, else `./websearch`
+fn resolve_data_dir(configured: Option<PathBuf>, platform: Option<PathBuf>) -> PathBuf {
+    configured
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .or(platform)
+        .unwrap_or_else(|| PathBuf::from(LEGACY_DIR))
+}
+
+/// Move `legacy` to `target` unless `target` already holds data
+///
+/// Falls back to copy-and-delete when the two are on different filesystems.
+fn migrate_legacy(legacy: &Path, target: &Path) -> Result<bool> {
+    if !legacy.is_dir() || is_same_dir(legacy, target) {
+        return Ok(false);
+    }
+    if target.exists() {
+        let empty = std::fs::read_dir(target)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        if !empty {
+            return Ok(false);
+        }
+        std::fs::remove_dir(target).context("Failed to prepare data directory")?;
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create data directory")?;
+    }
+
+    if std::fs::rename(legacy, target).is_err() {
+        copy_dir(legacy, target).with_context(|| {
+            format!("Failed to move {} to {}", legacy.display(), target.display())
+        })?;
+        std::fs::remove_dir_all(legacy)
+            .with_context(|| format!("Failed to remove {}", legacy.display()))?;
+    }
+    Ok(true)
+}
+
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else {
+            std::fs::copy(entry.path(), &dest)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("websearch-paths-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolve_data_dir() {
+        let platform = Some(PathBuf::from("/home/u/.local/share/websearch-tui"));
+        assert_eq!(
+            resolve_data_dir(Some(PathBuf::from("/tmp/ws")), platform.clone()),
+            PathBuf::from("/tmp/ws")
+        );
+        assert_eq!(
+            resolve_data_dir(Some(PathBuf::new()), platform.clone()),
+            PathBuf::from("/home/u/.local/share/websearch-tui")
+        );
+        assert_eq!(resolve_data_dir(None, None), PathBuf::from("websearch"));
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = BaseDirs::new().unwrap().home_dir().to_path_buf();
+        assert_eq!(expand_home(Path::new("~/ws")), home.join("ws"));
+        assert_eq!(expand_home(Path::new("/tmp/~ws")), PathBuf::from("/tmp/~ws"));
+    }
+
+    #[test]
+    fn test_migrate_legacy_moves_once() {
+        let root = temp_dir("migrate");
+        let legacy = root.join("websearch");
+        let target = root.join("share").join("websearch-tui");
+        std::fs::create_dir_all(legacy.join("pinned")).unwrap();
+        std::fs::write(legacy.join("pinned").join("page.md"), "pinned").unwrap();
+        std::fs::write(legacy.join("index.json"), "{}").unwrap();
+
+        assert!(migrate_legacy(&legacy, &target).unwrap());
+        assert!(!legacy.exists());
+        assert_eq!(
+            std::fs::read_to_string(target.join("pinned").join("page.md")).unwrap(),
+            "pinned"
+        );
+
+        // Nothing left to move, and existing data is never overwritten
+        assert!(!migrate_legacy(&legacy, &target).unwrap());
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(legacy.join("index.json"), "stale").unwrap();
+        assert!(!migrate_legacy(&legacy, &target).unwrap());
+        assert_eq!(std::fs::read_to_string(target.join("index.json")).unwrap(), "{}");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
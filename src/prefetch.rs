@@ -1,34 +1,86 @@
 //! Background prefetching of search results
 //!
 //! After search completes, this module downloads and processes results
-//! in parallel (12 concurrent), with intelligent caching and 8-second timeouts.
+//! in parallel, with intelligent caching and per-page timeouts; how many
+//! run at once and how long each may take come from `[prefetch]` in the
+//! config (`concurrency` and `timeout_secs`).
+//! Pages wait in a shared queue drained by a fixed pool of workers, so the
+//! result under the cursor can jump ahead of the rest. At most two requests
+//! go to the same host at once, with a small random delay between them.
+//! When a batch finishes, least-recently-opened pages are evicted until the
+//! cache fits under its size cap. Pages that fail with an HTTP error, time
+//! out or turn out to be a bot wall are fetched from the Wayback Machine
+//! instead, when it has a copy. With `extraction.images = "download"` the
+//! images of a page are saved into `assets/` within the page's time budget.
+//! With `extraction.keep_html` the downloaded HTML is kept beside the page,
+//! and goes wherever the page goes, so it can be extracted again offline.
+//! Each search saves its pages in its own `current_search/<query-hash>/`,
+//! so starting a new one leaves the pages of the last few, and of those
+//! still in the history, where they are.
 
 use anyhow::{Context, Result};
-use futures::stream::{self, StreamExt};
-use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::{Duration, SystemTime};
-use tokio::sync::RwLock;
-use tokio::time::timeout;
+use rand::Rng;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{Notify, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::time::{sleep, timeout};
 use url::Url;
 
-use crate::extract_clean_md::extract_clean_markdown;
-use crate::globals::get_http_client;
+use crate::app::AppMessage;
+use crate::bot_wall::detect_wall;
+use crate::cache_index::{
+    normalize_url, parse_frontmatter, read_frontmatter, stable_hash, unix_now, unix_seconds,
+    CacheEntry, CacheIndex,
+};
+use crate::canonical;
+use crate::config::{
+    CacheConfig, Config, Engine, ExtractionConfig, HooksConfig, ImageMode, OutputFormat,
+    PrefetchConfig,
+};
+use crate::extract_clean_md::{extract_clean_markdown, reading_minutes, ExtractedContent};
+use crate::globals::{get_scrape_client, MAX_REDIRECTS};
+use crate::hooks;
+use crate::images;
+use crate::library::format_size;
+use crate::notes::Note;
+use crate::raw_html::{self, RawHtml};
+use crate::robots::{self, RobotsCache};
 use crate::search::SearchResult;
+use crate::wayback::{self, Snapshot};
 
-/// Concurrency limit for parallel downloads
-const CONCURRENT_LIMIT: usize = 12;
+/// Earlier searches whose directories are kept besides those in the history
+const KEPT_SEARCHES: usize = 5;
 
-/// Per-page timeout (fail fast on slow sites)
-const PAGE_TIMEOUT: Duration = Duration::from_secs(8);
+/// File in each search directory holding its query; its age orders the searches
+const QUERY_FILE: &str = ".query";
 
-/// Maximum cache age in days
-const CACHE_MAX_AGE_DAYS: u64 = 5;
+/// Per-page timeout when retrying pages that already timed out once
+const RETRY_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Timeout for the Wayback Machine fallback (on top of the page timeout)
+const WAYBACK_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Failure reported for pages that aren't cached in offline mode
+const OFFLINE: &str = "offline";
+
+/// Failure reported for pages robots.txt keeps us from fetching
+const DISALLOWED: &str = "disallowed by robots.txt";
+
+/// Maximum concurrent requests to a single host
+const PER_HOST_LIMIT: usize = 2;
+
+/// Requests to a host this soon after the previous one get a random delay
+const HOST_JITTER_WINDOW: Duration = Duration::from_secs(1);
+
+/// Range of the random delay between requests to the same host
+const HOST_JITTER: std::ops::Range<Duration> =
+    Duration::from_millis(100)..Duration::from_millis(400);
 
 /// Status of a prefetched page
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub enum PrefetchStatus {
     /// Not yet started
     Pending,
@@ -36,190 +88,976 @@ pub enum PrefetchStatus {
     InProgress,
     /// Successfully prefetched, file path stored
     Ready(PathBuf),
+    /// Page was unreachable; prefetched from a Wayback Machine snapshot
+    ReadyArchived(PathBuf),
+    /// Non-text document (PDF) saved as-is, opened with the system viewer
+    Binary(PathBuf),
     /// Already existed on disk (cached)
     Cached(PathBuf),
-    /// Failed with error message
-    Failed(String),
-    /// Timed out after 8 seconds
+    /// Failed, see [`Failure`]
+    Failed(Failure),
+    /// Got a bot check, consent page or paywall instead of content
+    Blocked(String),
+    /// Timed out (8 seconds, or 20 seconds on retry)
     Timeout,
 }
 
+impl PrefetchStatus {
+    /// The saved file, for the statuses that have one
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            PrefetchStatus::Ready(path)
+            | PrefetchStatus::ReadyArchived(path)
+            | PrefetchStatus::Cached(path)
+            | PrefetchStatus::Binary(path) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Why a page couldn't be prefetched
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Failure {
+    /// The server answered with this non-success status
+    Http(u16),
+    /// The host name didn't resolve
+    Dns,
+    /// The connection was refused, reset or failed its TLS handshake
+    Connect,
+    /// Downloaded but couldn't be converted, like an image or broken HTML
+    Extract(String),
+    /// Anything else: offline mode, robots.txt, size limits, redirects
+    Other(String),
+}
+
+impl Failure {
+    /// Classify a failed download or conversion
+    fn from_error(error: &anyhow::Error) -> Self {
+        if let Some(HttpStatusError(status)) = error.downcast_ref() {
+            return Failure::Http(status.as_u16());
+        }
+        if let Some(ExtractError(message)) = error.downcast_ref() {
+            return Failure::Extract(message.clone());
+        }
+        let request = error.chain().find_map(|e| e.downcast_ref::<reqwest::Error>());
+        if let Some(request) = request
+            && request.is_connect()
+        {
+            // hyper reports failed lookups as a "dns error" somewhere below
+            let mut source = std::error::Error::source(request);
+            while let Some(cause) = source {
+                if cause.to_string().starts_with("dns error") {
+                    return Failure::Dns;
+                }
+                source = cause.source();
+            }
+            return Failure::Connect;
+        }
+        Failure::Other(error.to_string())
+    }
+
+    /// Whether fetching again could help; 404 and 410 pages are gone
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, Failure::Http(404 | 410))
+    }
+
+    /// Whether the host couldn't be reached at all
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, Failure::Dns | Failure::Connect)
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Http(code) => match reqwest::StatusCode::from_u16(*code) {
+                Ok(status) => write!(f, "HTTP {}", status),
+                Err(_) => write!(f, "HTTP {}", code),
+            },
+            Failure::Dns => write!(f, "DNS lookup failed"),
+            Failure::Connect => write!(f, "connection failed"),
+            Failure::Extract(message) | Failure::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Size and fetch time of a downloaded page, shown beside its result
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub struct PageStats {
+    /// Time from the first request until the page was saved
+    pub elapsed: Duration,
+    /// Bytes downloaded
+    pub bytes: u64,
+    /// Words of extracted text, `None` for PDFs
+    pub words: Option<usize>,
+    /// Publish date from the frontmatter
+    pub published: Option<String>,
+    /// Language from the frontmatter
+    pub lang: Option<String>,
+    /// Cut at `extraction.max_markdown_kb`
+    pub truncated: bool,
+}
+
+impl PageStats {
+    /// Short summary like `2019 · de · 1.2k words · 6 min · 0.8s`
+    ///
+    /// Only the year of the publish date is shown, and the language only
+    /// when it isn't English.
+    pub fn summary(&self) -> String {
+        let mut about = String::new();
+        if let Some(year) = self.published.as_deref().and_then(|date| date.get(..4)) {
+            about.push_str(&format!("{} · ", year));
+        }
+        if let Some(lang) = &self.lang {
+            let primary = lang.split(['-', '_']).next().unwrap_or_default();
+            if !primary.eq_ignore_ascii_case("en") {
+                about.push_str(&format!("{} · ", primary.to_ascii_lowercase()));
+            }
+        }
+
+        let size = match self.words {
+            Some(words) if words >= 1000 => {
+                let thousands = words as f64 / 1000.0;
+                let precision = if words < 10_000 { 1 } else { 0 };
+                format!("{:.*}k words · {} min", precision, thousands, reading_minutes(words))
+            }
+            Some(words) => format!("{} words · {} min", words, reading_minutes(words)),
+            None => format_size(self.bytes),
+        };
+        format!("{}{} · {:.1}s", about, size, self.elapsed.as_secs_f64())
+    }
+}
+
+/// A page waiting to be fetched
+#[derive(Debug, Clone)]
+struct QueuedPage {
+    result: SearchResult,
+    timeout: Duration,
+    /// File to overwrite; `None` saves a new file in the search directory
+    target: Option<PathBuf>,
+    /// Engine that found the result, noted in the frontmatter
+    engine: Option<Engine>,
+}
+
+/// A page taken from the queue together with its host slot
+struct ClaimedPage {
+    page: QueuedPage,
+    /// Frees the host slot when dropped
+    permit: OwnedSemaphorePermit,
+    /// Politeness delay before sending the request
+    delay: Duration,
+}
+
+/// Pages waiting for a worker, front first
+#[derive(Debug, Default)]
+struct FetchQueue {
+    pending: VecDeque<QueuedPage>,
+    /// Pages currently being downloaded by a worker
+    in_flight: usize,
+    /// Request slots per host
+    hosts: HashMap<String, Arc<Semaphore>>,
+    /// When the latest request to each host was scheduled to start
+    last_request: HashMap<String, Instant>,
+}
+
+impl FetchQueue {
+    /// Take the first page whose host has a free slot and count it as in flight
+    fn pop(&mut self) -> Option<ClaimedPage> {
+        let now = Instant::now();
+
+        for pos in 0..self.pending.len() {
+            let host = host_key(&self.pending[pos].result.url);
+            let slots = self
+                .hosts
+                .entry(host.clone())
+                .or_insert_with(|| Arc::new(Semaphore::new(PER_HOST_LIMIT)))
+                .clone();
+            let Ok(permit) = slots.try_acquire_owned() else {
+                continue;
+            };
+
+            let delay = match self.last_request.get(&host) {
+                Some(&last) if now < last + HOST_JITTER_WINDOW => {
+                    rand::thread_rng().gen_range(HOST_JITTER)
+                }
+                _ => Duration::ZERO,
+            };
+            self.last_request.insert(host, now + delay);
+
+            let page = self.pending.remove(pos)?;
+            self.in_flight += 1;
+            return Some(ClaimedPage {
+                page,
+                permit,
+                delay,
+            });
+        }
+
+        None
+    }
+
+    /// Mark an in-flight page as finished; returns true when the queue is idle
+    fn finish(&mut self) -> bool {
+        self.in_flight -= 1;
+        self.pending.is_empty() && self.in_flight == 0
+    }
+
+    /// Move a waiting page to the front; no-op if it isn't queued
+    fn boost(&mut self, url: &str) {
+        if let Some(pos) = self.pending.iter().position(|p| p.result.url == url)
+            && let Some(page) = self.pending.remove(pos)
+        {
+            self.pending.push_front(page);
+        }
+    }
+}
+
 /// Manages prefetching of search results
 #[derive(Clone)]
 pub struct PrefetchManager {
-    /// Directory for current search results
+    /// `current_search/`, holding a directory per search
     current_search_dir: PathBuf,
+    /// Directory of the search on screen, where new pages are saved
+    search_dir: Arc<Mutex<PathBuf>>,
     /// Directory for active tabs (opened in neovim)
     active_tabs_dir: PathBuf,
+    /// Directory for pinned pages, never cleaned up
+    pinned_dir: PathBuf,
+    /// Directory for downloaded images, one subdirectory per page
+    assets_dir: PathBuf,
     /// Status of each URL being prefetched
     status: Arc<RwLock<HashMap<String, PrefetchStatus>>>,
-    /// Counter for completed prefetches
-    completed_count: Arc<RwLock<usize>>,
-    /// Total number of items to prefetch
-    total_count: Arc<RwLock<usize>>,
+    /// Status changes are pushed to the UI through this channel
+    events: UnboundedSender<AppMessage>,
+    /// Pages waiting for a worker
+    queue: Arc<Mutex<FetchQueue>>,
+    /// Wakes an idle worker when a page is queued
+    queued: Arc<Notify>,
+    /// Persistent URL → cached file index (`index.json`)
+    index: Arc<Mutex<CacheIndex>>,
+    /// Concurrency, timeout and page size settings
+    prefetch: PrefetchConfig,
+    /// How pages are converted to markdown
+    extraction: ExtractionConfig,
+    /// Cache age and size limits
+    cache: CacheConfig,
+    /// Commands run on fetched and opened pages
+    hooks: Arc<HooksConfig>,
+    /// Never download; pages that aren't cached fail right away
+    offline: bool,
+    /// Engine of the results on screen, see [`Self::set_engine`]
+    engine: Arc<Mutex<Option<Engine>>>,
+    /// robots.txt rules by origin, with `prefetch.respect_robots`
+    robots: Arc<Mutex<RobotsCache>>,
+    /// When the latest batch of pages was queued, to time it
+    batch_started: Arc<Mutex<Instant>>,
+    /// Pages removed from the list while a worker was downloading them
+    forgotten: Arc<Mutex<HashSet<String>>>,
 }
 
 impl PrefetchManager {
     /// Create a new prefetch manager
-    pub fn new(base_dir: PathBuf) -> Result<Self> {
+    pub fn new(
+        base_dir: PathBuf,
+        config: &Config,
+        events: UnboundedSender<AppMessage>,
+    ) -> Result<Self> {
         let current_search_dir = base_dir.join("current_search");
+        let search_dir = current_search_dir.join(stable_hash(""));
         let active_tabs_dir = base_dir.join("active_tabs");
+        let pinned_dir = base_dir.join("pinned");
+        let assets_dir = base_dir.join("assets");
 
         // Create directories
-        std::fs::create_dir_all(&current_search_dir)
+        std::fs::create_dir_all(&search_dir)
             .context("Failed to create current_search directory")?;
         std::fs::create_dir_all(&active_tabs_dir)
             .context("Failed to create active_tabs directory")?;
+        std::fs::create_dir_all(&pinned_dir).context("Failed to create pinned directory")?;
+
+        // Files from before the index existed are re-keyed on first run
+        let index_path = base_dir.join("index.json");
+        let first_run = !index_path.exists();
+        let mut index = CacheIndex::load(index_path);
+        if first_run {
+            migrate_legacy_files(&mut index, &[&active_tabs_dir, &current_search_dir]);
+            index.save()?;
+        }
 
-        Ok(Self {
+        let manager = Self {
             current_search_dir,
+            search_dir: Arc::new(Mutex::new(search_dir)),
             active_tabs_dir,
+            pinned_dir,
+            assets_dir,
             status: Arc::new(RwLock::new(HashMap::new())),
-            completed_count: Arc::new(RwLock::new(0)),
-            total_count: Arc::new(RwLock::new(0)),
-        })
+            events,
+            queue: Arc::new(Mutex::new(FetchQueue::default())),
+            queued: Arc::new(Notify::new()),
+            index: Arc::new(Mutex::new(index)),
+            prefetch: config.prefetch,
+            extraction: config.extraction,
+            cache: config.cache,
+            hooks: Arc::new(config.hooks.clone()),
+            offline: config.network.offline,
+            engine: Arc::new(Mutex::new(None)),
+            robots: Arc::new(Mutex::new(RobotsCache::default())),
+            batch_started: Arc::new(Mutex::new(Instant::now())),
+            forgotten: Arc::new(Mutex::new(HashSet::new())),
+        };
+
+        for _ in 0..config.prefetch.concurrency {
+            let worker = manager.clone();
+            tokio::spawn(async move { worker.run_worker().await });
+        }
+
+        Ok(manager)
     }
 
-    /// Clear previous search results and prepare for new search
-    pub async fn clear_current_search(&self) -> Result<()> {
-        // Clear status
-        {
-            let mut status = self.status.write().await;
-            status.clear();
+    /// Directories holding cached pages, `pinned/` and `active_tabs/` first
+    pub fn cache_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.pinned_dir.clone(), self.active_tabs_dir.clone()];
+        dirs.extend(self.search_dirs());
+        dirs
+    }
+
+    /// Directory the pages of the current search are saved in
+    pub fn search_dir(&self) -> PathBuf {
+        self.search_dir.lock().unwrap().clone()
+    }
+
+    /// Directories in `current_search/`, the current search first, then newest first
+    fn search_dirs(&self) -> Vec<PathBuf> {
+        let current = self.search_dir();
+        let mut earlier: Vec<(SystemTime, PathBuf)> = std::fs::read_dir(&self.current_search_dir)
+            .into_iter()
+            .flat_map(|entries| entries.flatten())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && *path != current)
+            .map(|path| {
+                let started = std::fs::metadata(path.join(QUERY_FILE))
+                    .or_else(|_| std::fs::metadata(&path))
+                    .and_then(|metadata| metadata.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (started, path)
+            })
+            .collect();
+        earlier.sort_by_key(|(started, _)| std::cmp::Reverse(*started));
+        std::iter::once(current)
+            .chain(earlier.into_iter().map(|(_, path)| path))
+            .collect()
+    }
+
+    /// Save the pages fetched from now on in the directory of `query`
+    ///
+    /// Rewriting its query file makes it the newest search.
+    fn enter_search_dir(&self, query: &str) -> Result<()> {
+        let query = query.trim();
+        let dir = self.current_search_dir.join(stable_hash(query));
+        std::fs::create_dir_all(&dir).context("Failed to create search directory")?;
+        std::fs::write(dir.join(QUERY_FILE), query).context("Failed to write search query")?;
+        *self.search_dir.lock().unwrap() = dir;
+        Ok(())
+    }
+
+    /// Extension of the pages saved in the configured format
+    fn extension(&self) -> &'static str {
+        self.extraction.format.extension()
+    }
+
+    /// Whether a cached file lives in `pinned/`
+    pub fn is_pinned(&self, path: &Path) -> bool {
+        path.starts_with(&self.pinned_dir)
+    }
+
+    /// Pin a cached file by moving it to `pinned/`, or unpin it back to `active_tabs/`
+    ///
+    /// Returns the new path and whether the file is now pinned.
+    pub fn toggle_pin_file(&self, path: &Path) -> Result<(PathBuf, bool)> {
+        let filename = path.file_name().context("Invalid filename")?;
+        let pin = !self.is_pinned(path);
+        let dest_dir = if pin {
+            &self.pinned_dir
+        } else {
+            &self.active_tabs_dir
+        };
+        let dest_path = dest_dir.join(filename);
+        move_page(path, &dest_path)?;
+        Ok((dest_path, pin))
+    }
+
+    /// Pin or unpin the prefetched page of a search result
+    ///
+    /// Returns whether the page is now pinned.
+    pub async fn toggle_pin(&self, url: &str) -> Result<bool> {
+        let (path, pinned) = match self.get_status(url).await {
+            PrefetchStatus::Ready(path)
+            | PrefetchStatus::ReadyArchived(path)
+            | PrefetchStatus::Cached(path) => {
+                let (path, pinned) = self.toggle_pin_file(&path)?;
+                (PrefetchStatus::Cached(path), pinned)
+            }
+            PrefetchStatus::Binary(path) => {
+                let (path, pinned) = self.toggle_pin_file(&path)?;
+                (PrefetchStatus::Binary(path), pinned)
+            }
+            _ => anyhow::bail!("Page is not prefetched yet"),
+        };
+        self.set_status(url, path).await;
+        Ok(pinned)
+    }
+
+    /// Note `engine` as the source of the pages queued from now on
+    pub fn set_engine(&self, engine: Option<Engine>) {
+        *self.engine.lock().unwrap() = engine;
+    }
+
+    /// Stop tracking the previous results and switch to the directory of `query`
+    ///
+    /// Directories of earlier searches stay while they are among the
+    /// [`KEPT_SEARCHES`] newest or hold the page of a URL in `keep` (earlier
+    /// searches still in the history); the others are deleted.
+    pub async fn begin_search<'a>(
+        &self,
+        query: &str,
+        keep: impl IntoIterator<Item = &'a str>,
+    ) -> Result<()> {
+        self.forget_current_search().await;
+        self.enter_search_dir(query)?;
+
+        let kept: HashSet<String> = {
+            let index = self.index.lock().unwrap();
+            keep.into_iter()
+                .filter_map(|url| index.get(url).map(|entry| entry.filename.clone()))
+                .collect()
+        };
+
+        for (i, dir) in self.search_dirs().into_iter().enumerate() {
+            let referenced = i <= KEPT_SEARCHES || kept.iter().any(|name| dir.join(name).exists());
+            if referenced {
+                remove_partial_writes(&dir);
+            } else if let Err(e) = std::fs::remove_dir_all(&dir) {
+                tracing::warn!("Failed to remove {}: {}", dir.display(), e);
+            }
+        }
+
+        // Pages saved straight into current_search/ by older versions
+        let entries = std::fs::read_dir(&self.current_search_dir)?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if is_partial_write(&path) || is_page_file(&path) {
+                let _ = remove_page(&path);
+            }
         }
+        remove_orphaned_html(&self.current_search_dir);
+        images::remove_orphans(&self.assets_dir, &self.cache_dirs());
+
+        Ok(())
+    }
+
+    /// Stop tracking a result removed from the list
+    ///
+    /// A page still waiting is not downloaded, and one already fetched into
+    /// the search directory is deleted. A page being downloaded is deleted
+    /// once its worker is done. Copies in `active_tabs/`, `pinned/` or the
+    /// directories of other searches stay.
+    pub async fn forget(&self, url: &str) {
+        let waiting = {
+            let pending = &mut self.queue.lock().unwrap().pending;
+            let count = pending.len();
+            pending.retain(|page| page.result.url != url);
+            pending.len() < count
+        };
+        let status = self.status.write().await.remove(url);
+        if !waiting
+            && matches!(status, Some(PrefetchStatus::Pending | PrefetchStatus::InProgress))
         {
-            let mut count = self.completed_count.write().await;
-            *count = 0;
+            self.forgotten.lock().unwrap().insert(url.to_string());
         }
+        if let Some(path) = status.as_ref().and_then(PrefetchStatus::path)
+            && path.starts_with(self.search_dir())
         {
-            let mut total = self.total_count.write().await;
-            *total = 0;
+            let _ = remove_page(path);
+        }
+    }
+
+    /// Drop waiting pages and statuses of the search being replaced
+    async fn forget_current_search(&self) {
+        self.queue.lock().unwrap().pending.clear();
+        self.status.write().await.clear();
+    }
+
+    /// Show an earlier result set of `query` again without downloading it anew
+    ///
+    /// Pages still on disk come back as cached and failures keep their
+    /// `statuses` entry; anything else is queued again, into the search's
+    /// own directory.
+    pub async fn restore(
+        &self,
+        query: &str,
+        results: &[SearchResult],
+        statuses: &HashMap<String, PrefetchStatus>,
+    ) {
+        self.forget_current_search().await;
+        if let Err(e) = self.enter_search_dir(query) {
+            tracing::warn!("{:#}", e);
         }
 
-        // Remove old files from current_search
-        if self.current_search_dir.exists() {
-            let entries = std::fs::read_dir(&self.current_search_dir)?;
-            for entry in entries.flatten() {
-                if entry.path().extension().map_or(false, |e| e == "md") {
-                    let _ = std::fs::remove_file(entry.path());
+        let mut to_fetch = Vec::new();
+        for result in results {
+            let status = self.find_cached(&result.url).or_else(|| {
+                statuses.get(&result.url).cloned().filter(|status| {
+                    matches!(
+                        status,
+                        PrefetchStatus::Failed(_)
+                            | PrefetchStatus::Blocked(_)
+                            | PrefetchStatus::Timeout
+                    )
+                })
+            });
+            match status {
+                Some(status) => self.set_status(&result.url, status).await,
+                None => {
+                    self.set_status(&result.url, PrefetchStatus::Pending).await;
+                    to_fetch.push(result.clone());
                 }
             }
         }
 
-        Ok(())
+        self.enqueue(to_fetch, self.prefetch.timeout());
     }
 
     /// Start prefetching search results with intelligent caching
     ///
-    /// Checks if files already exist before downloading.
-    /// Runs with 12 concurrent tasks and 8-second per-page timeout.
+    /// Looks up each URL in the cache index before downloading.
+    /// Concurrency and the per-page timeout come from `[prefetch]` in the
+    /// config.
     pub async fn prefetch_all(&self, results: &[SearchResult]) {
-        // Set total count
-        {
-            let mut total = self.total_count.write().await;
-            *total = results.len();
-        }
+        *self.batch_started.lock().unwrap() = Instant::now();
 
         // Check which files already exist (caching)
         let mut to_fetch = Vec::new();
         let mut cached = Vec::new();
 
         for result in results {
-            let filename = url_to_filename(&result.url, &result.title);
-
-            // Check active_tabs first
-            let active_path = self.active_tabs_dir.join(&filename);
-            if active_path.exists() {
-                cached.push((result.clone(), active_path));
-                continue;
+            match self.find_cached(&result.url) {
+                Some(status) => cached.push((result.clone(), status)),
+                None => to_fetch.push(result.clone()),
             }
+        }
 
-            // Check current_search
-            let current_path = self.current_search_dir.join(&filename);
-            if current_path.exists() {
-                cached.push((result.clone(), current_path));
-                continue;
-            }
+        // Mark cached items immediately
+        for (result, status) in cached {
+            self.set_status(&result.url, status).await;
+        }
 
-            // Need to fetch
-            to_fetch.push(result.clone());
+        // Mark items to fetch as Pending
+        for result in &to_fetch {
+            self.set_status(&result.url, PrefetchStatus::Pending).await;
         }
 
-        // Mark cached items as Cached immediately
-        {
-            let mut status = self.status.write().await;
-            for (result, path) in cached {
-                status.insert(result.url.clone(), PrefetchStatus::Cached(path));
+        self.enqueue(to_fetch, self.prefetch.timeout());
+    }
+
+    /// Time since the latest batch of [`prefetch_all`](Self::prefetch_all)
+    /// or [`retry`](Self::retry) was queued
+    pub fn batch_elapsed(&self) -> Duration {
+        self.batch_started.lock().unwrap().elapsed()
+    }
+
+    /// Re-queue failed or timed-out results for prefetching
+    ///
+    /// With `extended_timeout`, each page gets 20 seconds instead of the
+    /// configured timeout.
+    pub async fn retry(&self, results: &[SearchResult], extended_timeout: bool) {
+        *self.batch_started.lock().unwrap() = Instant::now();
+        for result in results {
+            self.set_status(&result.url, PrefetchStatus::Pending).await;
+        }
+
+        let page_timeout = if extended_timeout {
+            RETRY_TIMEOUT.max(self.prefetch.timeout())
+        } else {
+            self.prefetch.timeout()
+        };
+        self.enqueue(results.to_vec(), page_timeout);
+    }
+
+    /// Move a waiting page to the front of the queue
+    ///
+    /// Pages already being downloaded are left alone.
+    pub fn boost(&self, url: &str) {
+        self.queue.lock().unwrap().boost(url);
+    }
+
+    /// Drop the cached copy of a result and download it again, ahead of the queue
+    ///
+    /// A page in `active_tabs/` or `pinned/` is overwritten in place so
+    /// Neovim sees the fresh content; any other copies are deleted.
+    pub async fn refresh(&self, result: &SearchResult) -> Result<()> {
+        anyhow::ensure!(!self.offline, "Can't download pages again in offline mode");
+        let current = match self.get_status(&result.url).await {
+            PrefetchStatus::Pending | PrefetchStatus::InProgress => {
+                anyhow::bail!("Page is already loading")
             }
+            PrefetchStatus::Ready(path)
+            | PrefetchStatus::ReadyArchived(path)
+            | PrefetchStatus::Cached(path)
+            | PrefetchStatus::Binary(path) => Some(path),
+            PrefetchStatus::Failed(_) | PrefetchStatus::Blocked(_) | PrefetchStatus::Timeout => None,
+        };
+
+        let indexed = self
+            .index
+            .lock()
+            .unwrap()
+            .get(&result.url)
+            .map(|entry| entry.filename.clone());
+        let mut copies: Vec<PathBuf> = current.into_iter().collect();
+        if let Some(filename) = indexed {
+            copies.extend(
+                self.cache_dirs()
+                    .iter()
+                    .map(|dir| dir.join(&filename))
+                    .filter(|path| holds_url(path, &result.url)),
+            );
         }
 
-        // Update completed count (cached items are already "done")
-        {
-            let mut completed = self.completed_count.write().await;
-            *completed = results.len() - to_fetch.len();
+        let target = copies
+            .iter()
+            .find(|path| !path.starts_with(&self.current_search_dir) && path.exists())
+            .cloned();
+        for path in &copies {
+            if Some(path) != target.as_ref() {
+                let _ = remove_page(path);
+            }
         }
 
-        // Mark items to fetch as Pending
+        self.set_status(&result.url, PrefetchStatus::Pending).await;
         {
-            let mut status = self.status.write().await;
-            for result in &to_fetch {
-                status.insert(result.url.clone(), PrefetchStatus::Pending);
+            let mut queue = self.queue.lock().unwrap();
+            queue.pending.retain(|page| page.result.url != result.url);
+            queue.pending.push_front(QueuedPage {
+                result: result.clone(),
+                timeout: self.prefetch.timeout(),
+                target,
+                engine: *self.engine.lock().unwrap(),
+            });
+        }
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    /// Extract a cached page again from its kept HTML, without downloading it
+    ///
+    /// Returns the path of the page, which changes with `extraction.format`.
+    pub async fn re_extract(&self, url: &str) -> Result<PathBuf> {
+        let status = self.get_status(url).await;
+        if matches!(status, PrefetchStatus::InProgress) {
+            anyhow::bail!("Page is still loading...");
+        }
+        let entry = self.index.lock().unwrap().get(url).cloned();
+        let path = entry
+            .as_ref()
+            .and_then(|entry| {
+                self.cache_dirs()
+                    .into_iter()
+                    .map(|dir| dir.join(&entry.filename))
+                    .find(|path| path.exists())
+            })
+            .context("Page isn't cached")?;
+
+        let started = Instant::now();
+        let saved = re_extract_page(&path, &self.extraction).await?;
+        if let Some(mut entry) = entry {
+            let mut index = self.index.lock().unwrap();
+            entry.filename = saved.path.file_name().unwrap_or_default().to_string_lossy().into();
+            entry.size = std::fs::metadata(&saved.path).map(|m| m.len()).unwrap_or(0);
+            index.insert(url, entry);
+            let _ = index.save();
+        }
+
+        let moved = match status {
+            PrefetchStatus::Ready(_) => Some(PrefetchStatus::Ready(saved.path.clone())),
+            PrefetchStatus::ReadyArchived(_) => {
+                Some(PrefetchStatus::ReadyArchived(saved.path.clone()))
             }
+            PrefetchStatus::Cached(_) => Some(PrefetchStatus::Cached(saved.path.clone())),
+            _ => None,
+        };
+        if let Some(status) = moved {
+            self.set_status(url, status).await;
         }
+        let _ = self.events.send(AppMessage::PageStats {
+            url: url.to_string(),
+            stats: PageStats {
+                elapsed: started.elapsed(),
+                bytes: saved.bytes,
+                words: saved.words,
+                published: saved.published,
+                lang: saved.lang,
+                truncated: saved.truncated,
+            },
+        });
+        Ok(saved.path)
+    }
 
-        // Clone what we need for the async tasks
-        let status = Arc::clone(&self.status);
-        let completed_count = Arc::clone(&self.completed_count);
-        let current_search_dir = self.current_search_dir.clone();
+    /// URLs of the cached pages whose HTML is kept
+    pub fn kept_html_urls(&self) -> Vec<String> {
+        self.cache_dirs()
+            .into_iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.flatten())
+            .map(|entry| entry.path())
+            .filter(|path| raw_html::is_raw_html(path) && !raw_html::is_orphan(path))
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .filter_map(|file| RawHtml::parse(&file).map(|raw| raw.url))
+            .collect()
+    }
 
-        // Spawn prefetch tasks with concurrency limit and timeout
-        tokio::spawn(async move {
-            stream::iter(to_fetch.into_iter())
-                .for_each_concurrent(CONCURRENT_LIMIT, |result| {
-                    let status = Arc::clone(&status);
-                    let completed_count = Arc::clone(&completed_count);
-                    let dir = current_search_dir.clone();
-
-                    async move {
-                        // Mark as in progress
-                        {
-                            let mut s = status.write().await;
-                            s.insert(result.url.clone(), PrefetchStatus::InProgress);
-                        }
-
-                        // Wrap in timeout
-                        let fetch_result = timeout(
-                            PAGE_TIMEOUT,
-                            prefetch_single_page(&result, &dir)
-                        ).await;
-
-                        // Update status
-                        {
-                            let mut s = status.write().await;
-                            match fetch_result {
-                                Ok(Ok(path)) => {
-                                    s.insert(result.url.clone(), PrefetchStatus::Ready(path));
-                                }
-                                Ok(Err(e)) => {
-                                    s.insert(
-                                        result.url.clone(),
-                                        PrefetchStatus::Failed(e.to_string()),
-                                    );
-                                }
-                                Err(_) => {
-                                    s.insert(result.url.clone(), PrefetchStatus::Timeout);
-                                }
-                            }
-                        }
-
-                        // Increment completed count
-                        {
-                            let mut count = completed_count.write().await;
-                            *count += 1;
-                        }
+    /// Status for a URL whose file is in the cache index and still on disk
+    ///
+    /// Checked in `pinned/`, `active_tabs/`, then the search directories.
+    /// Truncated files are deleted and count as misses, and so do pages
+    /// saved in another format than `extraction.format` and pages of
+    /// another URL under the same name.
+    pub fn find_cached(&self, url: &str) -> Option<PrefetchStatus> {
+        let filename = self.index.lock().unwrap().get(url)?.filename.clone();
+        let format = OutputFormat::of_path(Path::new(&filename));
+        if format.is_some_and(|format| format != self.extraction.format) {
+            return None;
+        }
+
+        self.cache_dirs()
+            .into_iter()
+            .map(|dir| dir.join(&filename))
+            .filter(|path| path.exists() && holds_url(path, url))
+            .find(|path| {
+                let complete = is_complete_page(path);
+                if !complete {
+                    let _ = remove_page(path);
+                }
+                complete
+            })
+            .map(|path| {
+                if path.extension().is_some_and(|e| e == "pdf") {
+                    PrefetchStatus::Binary(path)
+                } else {
+                    PrefetchStatus::Cached(path)
+                }
+            })
+    }
+
+    /// Keep a note with the cache index entry of `url`, if it has one
+    pub fn add_note(&self, url: &str, note: Note) -> bool {
+        let mut index = self.index.lock().unwrap();
+        let added = index.add_note(url, note);
+        if added {
+            let _ = index.save();
+        }
+        added
+    }
+
+    /// Record a freshly saved page in the cache index
+    fn record_in_index(&self, result: &SearchResult, saved: &SavedPage) {
+        let Some(filename) = saved.path.file_name() else {
+            return;
+        };
+        let size = std::fs::metadata(&saved.path).map(|m| m.len()).unwrap_or(0);
+
+        let mut index = self.index.lock().unwrap();
+        index.insert(
+            &result.url,
+            CacheEntry {
+                filename: filename.to_string_lossy().into_owned(),
+                title: result.title.clone(),
+                fetched_at: unix_now(),
+                size,
+                etag: saved.etag.clone(),
+                last_opened: None,
+                notes: Vec::new(),
+            },
+        );
+        // A stale index only costs a re-download, so don't fail the page
+        let _ = index.save();
+    }
+
+    /// Queue pages for the workers
+    fn enqueue(&self, results: Vec<SearchResult>, timeout: Duration) {
+        let count = results.len();
+        let engine = *self.engine.lock().unwrap();
+        self.queue
+            .lock()
+            .unwrap()
+            .pending
+            .extend(results.into_iter().map(|result| QueuedPage {
+                result,
+                timeout,
+                target: None,
+                engine,
+            }));
+
+        for _ in 0..count {
+            self.queued.notify_one();
+        }
+    }
+
+    /// Whether robots.txt lets us fetch `url`, if `prefetch.respect_robots`
+    ///
+    /// A host's rules are fetched within `budget` and reused for a few
+    /// minutes. A robots.txt that can't be read in time allows the page.
+    async fn robots_allow(&self, url: &str, budget: Duration) -> bool {
+        if !self.prefetch.respect_robots {
+            return true;
+        }
+        let Ok(url) = Url::parse(url) else {
+            return true;
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return true;
+        }
+        let origin = robots::origin(&url);
+        let cached = self.robots.lock().unwrap().get(&origin, Instant::now());
+        let rules = match cached {
+            Some(rules) => rules,
+            None => match timeout(budget, robots::fetch(&origin)).await {
+                Ok(rules) => {
+                    let rules = Arc::new(rules);
+                    let mut cache = self.robots.lock().unwrap();
+                    cache.insert(&origin, Arc::clone(&rules), Instant::now());
+                    rules
+                }
+                Err(_) => {
+                    tracing::debug!(%origin, "robots.txt timed out, allowing");
+                    return true;
+                }
+            },
+        };
+        rules.is_allowed(&robots::path_of(&url))
+    }
+
+    /// Fetch queued pages forever
+    ///
+    /// Sends `PrefetchDone` whenever the last in-flight page finishes and
+    /// nothing else is waiting.
+    async fn run_worker(&self) {
+        loop {
+            let next = self.queue.lock().unwrap().pop();
+            let Some(ClaimedPage {
+                mut page,
+                permit,
+                delay,
+            }) = next
+            else {
+                self.queued.notified().await;
+                continue;
+            };
+
+            let url = &page.result.url;
+            let started = Instant::now();
+            self.set_status(url, PrefetchStatus::InProgress).await;
+
+            if !delay.is_zero() && !self.offline {
+                sleep(delay).await;
+            }
+            // robots.txt comes out of the page's own time budget
+            let robots_started = Instant::now();
+            let allowed = self.offline
+                || self.robots_allow(&download_url(url, self.prefetch), page.timeout).await;
+            page.timeout = page.timeout.saturating_sub(robots_started.elapsed());
+            let fetch_started = Instant::now();
+
+            let path = page.target.clone().unwrap_or_else(|| {
+                let dirs = self.cache_dirs();
+                let filename =
+                    unique_filename(&dirs, &page.result.url, &page.result.title, self.extension());
+                self.search_dir().join(filename)
+            });
+
+            // Update status
+            let fetched = if self.offline {
+                Err(PrefetchStatus::Failed(Failure::Other(OFFLINE.to_string())))
+            } else if !allowed {
+                Err(PrefetchStatus::Failed(Failure::Other(DISALLOWED.to_string())))
+            } else {
+                fetch_with_fallback(&page, &path, self.prefetch, &self.extraction).await
+            };
+            let forgotten = self.forgotten.lock().unwrap().remove(url.as_str());
+            let status = match fetched {
+                // Removed from the list while downloading
+                Ok(saved) if forgotten => {
+                    if saved.path.starts_with(self.search_dir()) {
+                        let _ = remove_page(&saved.path);
                     }
-                })
-                .await;
+                    None
+                }
+                Err(_) if forgotten => None,
+                Ok(saved) => {
+                    self.record_in_index(&page.result, &saved);
+                    let _ = self.events.send(AppMessage::PageStats {
+                        url: url.clone(),
+                        stats: PageStats {
+                            elapsed: fetch_started.elapsed(),
+                            bytes: saved.bytes,
+                            words: saved.words,
+                            published: saved.published.clone(),
+                            lang: saved.lang.clone(),
+                            truncated: saved.truncated,
+                        },
+                    });
+                    if self.extraction.images == ImageMode::Download
+                        && self.extraction.format == OutputFormat::Markdown
+                        && !saved.binary
+                    {
+                        let budget = page.timeout.saturating_sub(fetch_started.elapsed());
+                        self.download_images(url, &saved.path, budget).await;
+                    }
+                    if let Some(hook) = &self.hooks.post_fetch_hook
+                        && !saved.binary
+                    {
+                        let limit = self.hooks.timeout();
+                        hooks::spawn("post_fetch_hook", hook, &saved.path, url, limit);
+                    }
+                    Some(if saved.binary {
+                        PrefetchStatus::Binary(saved.path)
+                    } else if saved.archived {
+                        PrefetchStatus::ReadyArchived(saved.path)
+                    } else {
+                        PrefetchStatus::Ready(saved.path)
+                    })
+                }
+                Err(status) => Some(status),
+            };
+            if let Some(status) = status {
+                tracing::info!(
+                    url = url.as_str(),
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    ?status,
+                    "prefetch finished"
+                );
+                self.set_status(url, status).await;
+            } else {
+                self.status.write().await.remove(url.as_str());
+            }
+
+            drop(permit);
+            if self.queue.lock().unwrap().finish() {
+                let evicted = self.enforce_cache_limit().await.unwrap_or(0);
+                let _ = self.events.send(AppMessage::PrefetchDone { evicted });
+            }
+        }
+    }
+
+    /// Record a status change and push it to the UI
+    async fn set_status(&self, url: &str, status: PrefetchStatus) {
+        self.status
+            .write()
+            .await
+            .insert(url.to_string(), status.clone());
+
+        let _ = self.events.send(AppMessage::PrefetchUpdate {
+            url: url.to_string(),
+            status,
         });
     }
 
@@ -232,70 +1070,98 @@ impl PrefetchManager {
             .unwrap_or(PrefetchStatus::Pending)
     }
 
-    /// Get all current statuses (for UI rendering)
-    pub async fn get_all_statuses(&self) -> HashMap<String, PrefetchStatus> {
-        let status = self.status.read().await;
-        status.clone()
-    }
-
-    /// Get progress as (completed, total)
-    pub async fn get_progress(&self) -> (usize, usize) {
-        let completed = *self.completed_count.read().await;
-        let total = *self.total_count.read().await;
-        (completed, total)
-    }
-
-    /// Move a prefetched file from current_search to active_tabs
+    /// Move a prefetched file from its search directory to active_tabs
     ///
     /// Returns the final path in active_tabs/
     pub async fn activate_page(&self, url: &str) -> Result<PathBuf> {
         let status = self.get_status(url).await;
 
         match status {
-            PrefetchStatus::Ready(source_path) | PrefetchStatus::Cached(source_path) => {
+            PrefetchStatus::Ready(source_path)
+            | PrefetchStatus::ReadyArchived(source_path)
+            | PrefetchStatus::Cached(source_path) => {
+                {
+                    let mut index = self.index.lock().unwrap();
+                    index.touch(url);
+                    let _ = index.save();
+                }
+
                 let filename = source_path
                     .file_name()
                     .context("Invalid filename")?;
                 let dest_path = self.active_tabs_dir.join(filename);
 
-                // If already in active_tabs, just return path
-                if source_path.starts_with(&self.active_tabs_dir) {
+                // If already in active_tabs (or pinned), just return path
+                if source_path.starts_with(&self.active_tabs_dir) || self.is_pinned(&source_path) {
+                    self.run_on_open_hook(url, &source_path);
                     return Ok(source_path);
                 }
 
-                // Move file (or copy if on different filesystem)
-                if std::fs::rename(&source_path, &dest_path).is_err() {
-                    // Fallback to copy + delete
-                    std::fs::copy(&source_path, &dest_path)?;
-                    let _ = std::fs::remove_file(&source_path);
-                }
+                move_page(&source_path, &dest_path)?;
 
                 // Renew status to Cached with new path
-                {
-                    let mut statuses = self.status.write().await;
-                    statuses.insert(url.to_string(), PrefetchStatus::Cached(dest_path.clone()));
-                }
+                self.set_status(url, PrefetchStatus::Cached(dest_path.clone()))
+                    .await;
 
+                self.run_on_open_hook(url, &dest_path);
                 Ok(dest_path)
             }
+            PrefetchStatus::Binary(_) => {
+                anyhow::bail!("Not a text page, it opens in the system viewer")
+            }
             PrefetchStatus::InProgress => {
                 anyhow::bail!("Page is still loading...")
             }
             PrefetchStatus::Pending => {
                 anyhow::bail!("Page prefetch not started")
             }
-            PrefetchStatus::Failed(err) => {
-                anyhow::bail!("Prefetch failed: {}", err)
+            PrefetchStatus::Failed(failure) if !failure.is_retryable() => {
+                anyhow::bail!("Page is gone ({})", failure)
+            }
+            PrefetchStatus::Failed(failure) => {
+                anyhow::bail!("Prefetch failed: {}", failure)
+            }
+            PrefetchStatus::Blocked(reason) => {
+                anyhow::bail!("Page is behind a {}", reason)
             }
             PrefetchStatus::Timeout => {
-                anyhow::bail!("Page timed out after 8 seconds")
+                anyhow::bail!("Page timed out")
             }
         }
     }
 
-    /// Clean up files older than CACHE_MAX_AGE_DAYS
+    /// Start the `hooks.on_open` command for a page about to be opened
+    fn run_on_open_hook(&self, url: &str, path: &Path) {
+        if let Some(hook) = &self.hooks.on_open {
+            hooks::spawn("on_open", hook, path, url, self.hooks.timeout());
+        }
+    }
+
+    /// Save the images of a fetched page into `assets/` and link to them
+    ///
+    /// Images still missing after `budget` keep their web address.
+    async fn download_images(&self, url: &str, path: &Path, budget: Duration) {
+        let Ok(markdown) = tokio::fs::read_to_string(path).await else {
+            return;
+        };
+        let hash = stable_hash(url);
+        // Pages in a search directory sit a level deeper
+        let up = if path.starts_with(&self.current_search_dir) { "../../" } else { "../" };
+        let prefix = format!("{}assets/{}/", up, hash);
+        let dir = self.assets_dir.join(&hash);
+        let linked = images::download(&markdown, &dir, &prefix, budget).await;
+        if linked != markdown
+            && let Err(e) = write_atomic(path, linked.as_bytes()).await
+        {
+            tracing::warn!(url, "Failed to link downloaded images: {:#}", e);
+        }
+    }
+
+    /// Clean up files older than `cache.max_age_days`
+    ///
+    /// Image directories of pages no longer cached go too.
     pub async fn cleanup_old_files(&self) -> Result<usize> {
-        let max_age = Duration::from_secs(CACHE_MAX_AGE_DAYS * 24 * 60 * 60);
+        let max_age = self.cache.max_age();
         let now = SystemTime::now();
         let mut removed_count = 0;
 
@@ -304,17 +1170,104 @@ impl PrefetchManager {
             .cleanup_directory(&self.active_tabs_dir, now, max_age)
             .await?;
 
-        // Clean current_search
-        removed_count += self
-            .cleanup_directory(&self.current_search_dir, now, max_age)
-            .await?;
+        // Clean the search directories
+        for dir in self.search_dirs() {
+            removed_count += self.cleanup_directory(&dir, now, max_age).await?;
+        }
+
+        // Forget index entries whose files are gone
+        if removed_count > 0 {
+            let mut index = self.index.lock().unwrap();
+            index.retain_existing(&self.cache_dirs());
+            index.save()?;
+        }
+        images::remove_orphans(&self.assets_dir, &self.cache_dirs());
 
         Ok(removed_count)
     }
 
+    /// Evict least-recently-opened pages while the cache is over its size cap
+    ///
+    /// The cap is `cache.max_size_mb` (200 MB by default). Pinned
+    /// pages and pages of the current search count toward it but are never
+    /// evicted; those of earlier searches can be, and are fetched again if
+    /// their search is shown again. Returns the number of files removed.
+    pub async fn enforce_cache_limit(&self) -> Result<usize> {
+        self.evict_over(self.cache.max_size_bytes()).await
+    }
+
+    async fn evict_over(&self, limit: u64) -> Result<usize> {
+        // Current results may have been moved out of the search directory
+        let search_dir = self.search_dir();
+        let in_use: HashSet<PathBuf> = self
+            .status
+            .read()
+            .await
+            .values()
+            .filter_map(|status| match status {
+                PrefetchStatus::Ready(path)
+                | PrefetchStatus::ReadyArchived(path)
+                | PrefetchStatus::Cached(path)
+                | PrefetchStatus::Binary(path) => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let files: Vec<CachedFile> = {
+            let index = self.index.lock().unwrap();
+            let last_used = index.last_used_by_filename();
+
+            self.cache_dirs()
+                .into_iter()
+                .filter_map(|dir| std::fs::read_dir(dir).ok())
+                .flat_map(|entries| entries.flatten())
+                .filter(|entry| is_page_file(&entry.path()))
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let metadata = entry.metadata().ok()?;
+                    let filename = path.file_name()?.to_string_lossy();
+                    let last_used = last_used.get(filename.as_ref()).copied().unwrap_or_else(|| {
+                        metadata.modified().map(unix_seconds).unwrap_or(0)
+                    });
+                    let protected = self.is_pinned(&path)
+                        || path.starts_with(&search_dir)
+                        || in_use.contains(&path);
+
+                    // Kept HTML counts toward its page
+                    let html_size = std::fs::metadata(raw_html::path_for(&path))
+                        .map(|m| m.len())
+                        .unwrap_or(0);
+
+                    Some(CachedFile {
+                        size: metadata.len() + html_size,
+                        last_used,
+                        protected,
+                        path,
+                    })
+                })
+                .collect()
+        };
+
+        let evicted = plan_eviction(files, limit)
+            .iter()
+            .filter(|file| remove_page(&file.path).is_ok())
+            .count();
+
+        if evicted > 0 {
+            {
+                let mut index = self.index.lock().unwrap();
+                index.retain_existing(&self.cache_dirs());
+                index.save()?;
+            }
+            images::remove_orphans(&self.assets_dir, &self.cache_dirs());
+        }
+
+        Ok(evicted)
+    }
+
     async fn cleanup_directory(
         &self,
-        dir: &PathBuf,
+        dir: &Path,
         now: SystemTime,
         max_age: Duration,
     ) -> Result<usize> {
@@ -326,70 +1279,662 @@ impl PrefetchManager {
 
         let entries = std::fs::read_dir(dir)?;
         for entry in entries.flatten() {
-            if !entry.path().extension().map_or(false, |e| e == "md") {
+            if !is_page_file(&entry.path()) {
                 continue;
             }
 
-            if let Ok(metadata) = entry.metadata() {
-                if let Ok(modified) = metadata.modified() {
-                    if let Ok(age) = now.duration_since(modified) {
-                        if age > max_age {
-                            if std::fs::remove_file(entry.path()).is_ok() {
-                                removed += 1;
-                            }
-                        }
-                    }
-                }
+            if let Ok(metadata) = entry.metadata()
+                && let Ok(modified) = metadata.modified()
+                && let Ok(age) = now.duration_since(modified)
+                && age > max_age
+                && remove_page(&entry.path()).is_ok()
+            {
+                removed += 1;
             }
         }
+        remove_orphaned_html(dir);
 
         Ok(removed)
     }
 }
 
-/// Prefetch a single page
-async fn prefetch_single_page(result: &SearchResult, dir: &PathBuf) -> Result<PathBuf> {
-    let client = get_http_client();
-
-    // Download HTML
-    let response = client
-        .get(&result.url)
-        .header("Accept", "text/html,application/xhtml+xml")
-        .header("Accept-Language", "en-US,en;q=0.9")
-        .send()
-        .await
-        .context("Failed to download page")?;
+/// A page written to the cache directory
+struct SavedPage {
+    path: PathBuf,
+    /// Saved as-is (PDF) rather than as markdown
+    binary: bool,
+    /// Taken from a Wayback Machine snapshot
+    archived: bool,
+    etag: Option<String>,
+    /// Bytes downloaded
+    bytes: u64,
+    /// Words of extracted text, `None` for PDFs
+    words: Option<usize>,
+    /// Publish date and language of the text
+    published: Option<String>,
+    lang: Option<String>,
+    /// Cut at `extraction.max_markdown_kb`
+    truncated: bool,
+    /// Canonical URL the page declares, when it is another page
+    canonical: Option<String>,
+}
 
-    if !response.status().is_success() {
-        anyhow::bail!("HTTP {}", response.status());
+/// Fetch a queued page, falling back to the Wayback Machine
+///
+/// The fallback runs after HTTP errors, bot walls and timeouts, with its
+/// own timeout. When it finds nothing, the original failure is reported.
+async fn fetch_with_fallback(
+    page: &QueuedPage,
+    path: &Path,
+    settings: PrefetchConfig,
+    extraction: &ExtractionConfig,
+) -> std::result::Result<SavedPage, PrefetchStatus> {
+    let max_bytes = settings.max_page_bytes();
+    let fetched = prefetch_single_page(page, path, settings, extraction);
+    let failure = match timeout(page.timeout, fetched).await {
+        Ok(Ok(saved)) => return Ok(saved),
+        Ok(Err(e)) => match e.downcast::<BlockedError>() {
+            Ok(BlockedError(reason)) => PrefetchStatus::Blocked(reason.to_string()),
+            Err(e) => match Failure::from_error(&e) {
+                failure @ Failure::Http(_) => PrefetchStatus::Failed(failure),
+                failure => return Err(PrefetchStatus::Failed(failure)),
+            },
+        },
+        Err(_) => PrefetchStatus::Timeout,
+    };
+
+    if !settings.wayback {
+        return Err(failure);
     }
+    let archived = prefetch_from_wayback(page, path, max_bytes, extraction);
+    match timeout(WAYBACK_TIMEOUT, archived).await {
+        Ok(Ok(saved)) => Ok(saved),
+        _ => Err(failure),
+    }
+}
 
-    let html = response
-        .text()
-        .await
-        .context("Failed to read response body")?;
+/// Prefetch a single page
+///
+/// The page is downloaded from its [`download_url`], and downloaded again
+/// from its canonical URL with `prefetch.follow_canonical`. A canonical
+/// page that fails to download leaves the first one saved.
+async fn prefetch_single_page(
+    queued: &QueuedPage,
+    path: &Path,
+    settings: PrefetchConfig,
+    extraction: &ExtractionConfig,
+) -> Result<SavedPage> {
+    let max_bytes = settings.max_page_bytes();
+    let page = download_page(&download_url(&queued.result.url, settings), max_bytes).await?;
+    let saved = save_page(queued, page, path, None, extraction).await?;
+    let Some(canonical) = saved.canonical.clone().filter(|_| settings.follow_canonical) else {
+        return Ok(saved);
+    };
+    tracing::debug!(url = %queued.result.url, %canonical, "fetching the canonical page");
+    let refetched = async {
+        let page = download_page(&canonical, max_bytes).await?;
+        save_page(queued, page, path, None, extraction).await
+    };
+    match refetched.await {
+        Ok(refetched) => Ok(refetched),
+        Err(e) => {
+            tracing::debug!(%canonical, "canonical page failed: {:#}", e);
+            Ok(saved)
+        }
+    }
+}
 
-    // Extract content (now using dom_smoothie)
-    let content = extract_clean_markdown(&html, &result.url)
-        .context("Failed to extract content")?;
+/// URL a result is downloaded from, see [`canonical::rewrite`]
+fn download_url(url: &str, settings: PrefetchConfig) -> String {
+    if settings.normalize_urls {
+        canonical::rewrite(url)
+    } else {
+        url.to_string()
+    }
+}
 
-    // Generate filename using new format: {domain}_{hash}_{title}.md
-    let filename = url_to_filename(&result.url, &result.title);
-    let filepath = dir.join(&filename);
+/// Prefetch the closest Wayback Machine snapshot of a page
+async fn prefetch_from_wayback(
+    queued: &QueuedPage,
+    path: &Path,
+    max_bytes: u64,
+    extraction: &ExtractionConfig,
+) -> Result<SavedPage> {
+    let url = &queued.result.url;
+    let snapshot = wayback::find_snapshot(url)
+        .await?
+        .context("no archived snapshot")?;
+    let page = download_page(&snapshot.raw_url(url), max_bytes).await?;
+    save_page(queued, page, path, Some(&snapshot), extraction).await
+}
+
+/// Convert a downloaded page and write it to `path`
+///
+/// HTML goes through Readability, plain text is wrapped as-is, and PDFs
+/// are saved unchanged with a `.pdf` extension. Archived pages resolve
+/// links against the original URL and note the snapshot in the frontmatter.
+/// Bot walls and paywalls fail with `BlockedError` and aren't saved. The
+/// HTML is kept beside the page with `extraction.keep_html`.
+async fn save_page(
+    queued: &QueuedPage,
+    page: DownloadedPage,
+    path: &Path,
+    snapshot: Option<&Snapshot>,
+    extraction: &ExtractionConfig,
+) -> Result<SavedPage> {
+    let result = &queued.result;
+    let filepath = path.with_extension(extraction.format.extension());
+    let final_url = match snapshot {
+        Some(_) => result.url.as_str(),
+        None => page.final_url.as_str(),
+    };
+
+    let mut kept_html = None;
+    let mut content = match classify_content_type(page.content_type.as_deref()) {
+        PageKind::Html => {
+            // Extract content (now using dom_smoothie)
+            let html = String::from_utf8_lossy(&page.body);
+            // Relative links resolve against where the page actually lives
+            let content = extract_clean_markdown(&html, &result.url, final_url, extraction)
+                .map_err(|e| ExtractError(format!("Failed to extract content: {:#}", e)))?;
+            if let Some(reason) = detect_wall(&html, &content.title, &content.markdown) {
+                return Err(BlockedError(reason).into());
+            }
+            if extraction.keep_html {
+                kept_html = Some(html.into_owned());
+            }
+            content
+        }
+        PageKind::PlainText => {
+            let text = String::from_utf8_lossy(&page.body);
+            let mut content =
+                ExtractedContent::from_plain_text(&text, &result.url, final_url, &result.title);
+            content.truncate(extraction.max_markdown_bytes());
+            content
+        }
+        PageKind::Pdf => {
+            let pdf_path = filepath.with_extension("pdf");
+            write_atomic(&pdf_path, &page.body).await?;
+            return Ok(SavedPage {
+                path: pdf_path,
+                binary: true,
+                archived: snapshot.is_some(),
+                etag: page.etag,
+                bytes: page.body.len() as u64,
+                words: None,
+                published: None,
+                lang: None,
+                truncated: false,
+                canonical: None,
+            });
+        }
+        PageKind::Unsupported(content_type) => {
+            let message = format!("unsupported content type: {}", content_type);
+            return Err(ExtractError(message).into());
+        }
+    };
+
+    if let Some(snapshot) = snapshot {
+        content.archived_snapshot = Some(snapshot.url.clone());
+        content.archived_date = Some(snapshot.date());
+    }
+    content.engine = queued.engine.map(|engine| engine.name().to_string());
 
     // Save to file
-    tokio::fs::write(&filepath, content.to_formatted_markdown())
+    write_atomic(&filepath, content.to_formatted(extraction).as_bytes()).await?;
+    let html_path = raw_html::path_for(&filepath);
+    match kept_html {
+        Some(html) => {
+            let raw = RawHtml {
+                url: result.url.clone(),
+                final_url: final_url.to_string(),
+                fetched_at: content.fetched_at,
+                engine: content.engine.clone(),
+                archived_snapshot: content.archived_snapshot.clone(),
+                archived_date: content.archived_date.clone(),
+                html,
+            };
+            write_atomic(&html_path, raw.to_file().as_bytes()).await?;
+        }
+        // Don't leave HTML from before the page was fetched again
+        None => {
+            let _ = tokio::fs::remove_file(&html_path).await;
+        }
+    }
+    // A page refreshed after `extraction.format` changed leaves its old copy
+    if filepath != path && OutputFormat::of_path(path).is_some() {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+
+    let canonical = Some(content.canonical_url.clone())
+        .filter(|canonical| snapshot.is_none() && canonical::differs(canonical, final_url));
+    Ok(SavedPage {
+        path: filepath,
+        binary: false,
+        archived: snapshot.is_some(),
+        etag: page.etag,
+        bytes: page.body.len() as u64,
+        words: Some(content.word_count()),
+        published: content.published,
+        lang: content.lang,
+        truncated: content.truncated,
+        canonical,
+    })
+}
+
+/// Extract a saved page again from the HTML kept beside it
+///
+/// Nothing is downloaded: the page is rebuilt with the current extraction
+/// settings and saved in `extraction.format`, keeping its fetch time,
+/// engine and snapshot. Images aren't downloaded again.
+async fn re_extract_page(path: &Path, extraction: &ExtractionConfig) -> Result<SavedPage> {
+    let file = tokio::fs::read_to_string(raw_html::path_for(path))
+        .await
+        .context("No HTML kept for this page, set extraction.keep_html and refresh it")?;
+    let raw = RawHtml::parse(&file).context("Kept HTML has no source header")?;
+
+    let mut content = extract_clean_markdown(&raw.html, &raw.url, &raw.final_url, extraction)
+        .context("Failed to extract content")?;
+    content.fetched_at = raw.fetched_at;
+    content.engine = raw.engine;
+    content.archived_snapshot = raw.archived_snapshot;
+    content.archived_date = raw.archived_date;
+
+    let filepath = path.with_extension(extraction.format.extension());
+    write_atomic(&filepath, content.to_formatted(extraction).as_bytes()).await?;
+    if filepath != path {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+
+    Ok(SavedPage {
+        path: filepath,
+        binary: false,
+        archived: content.archived_snapshot.is_some(),
+        etag: None,
+        bytes: raw.html.len() as u64,
+        words: Some(content.word_count()),
+        published: content.published,
+        lang: content.lang,
+        truncated: content.truncated,
+        canonical: None,
+    })
+}
+
+/// Non-success HTTP status from a page download
+#[derive(Debug)]
+struct HttpStatusError(reqwest::StatusCode);
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP {}", self.0)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// Downloaded page that couldn't be converted
+#[derive(Debug)]
+struct ExtractError(String);
+
+impl std::fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+/// Page turned out to be a bot wall, consent page or paywall
+#[derive(Debug)]
+struct BlockedError(&'static str);
+
+impl std::fmt::Display for BlockedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "blocked by a {}", self.0)
+    }
+}
+
+impl std::error::Error for BlockedError {}
+
+/// How a downloaded page is handled, by its content type
+#[derive(Debug, PartialEq)]
+enum PageKind {
+    Html,
+    PlainText,
+    Pdf,
+    /// Images, JSON, archives, ... (the MIME type is kept for the message)
+    Unsupported(String),
+}
+
+/// Classify a `Content-Type` header value; a missing one is treated as HTML
+fn classify_content_type(content_type: Option<&str>) -> PageKind {
+    let Some(content_type) = content_type else {
+        return PageKind::Html;
+    };
+
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    match mime.as_str() {
+        "" | "text/html" | "application/xhtml+xml" => PageKind::Html,
+        "text/plain" => PageKind::PlainText,
+        "application/pdf" => PageKind::Pdf,
+        _ => PageKind::Unsupported(mime),
+    }
+}
+
+/// Downloaded response body with its content type
+#[derive(Debug)]
+struct DownloadedPage {
+    /// URL the page was served from, after redirects
+    final_url: String,
+    content_type: Option<String>,
+    etag: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Download a page body, refusing anything over `max_bytes`
+///
+/// Fails fast on a too-large `Content-Length`, and otherwise streams the
+/// body so an oversized page is never fully buffered.
+async fn download_page(url: &str, max_bytes: u64) -> Result<DownloadedPage> {
+    let client = get_scrape_client()?;
+
+    tracing::debug!(url, "GET page");
+    let mut response = client
+        .get(url)
+        .header("Accept", "text/html,application/xhtml+xml")
+        .header("Accept-Language", "en-US,en;q=0.9")
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_redirect() {
+                anyhow::anyhow!("too many redirects (more than {})", MAX_REDIRECTS)
+            } else {
+                anyhow::Error::new(e).context("Failed to download page")
+            }
+        })?;
+
+    tracing::debug!(url, status = %response.status(), final_url = %response.url(), "page response");
+    if !response.status().is_success() {
+        return Err(HttpStatusError(response.status()).into());
+    }
+
+    let final_url = response.url().to_string();
+    let too_large = || anyhow::anyhow!("page too large (>{}MB)", max_bytes / (1024 * 1024));
+
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(too_large());
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let content_type = header(reqwest::header::CONTENT_TYPE);
+    let etag = header(reqwest::header::ETAG);
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
         .await
-        .context("Failed to save markdown file")?;
+        .context("Failed to read response body")?
+    {
+        if (body.len() + chunk.len()) as u64 > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    tracing::debug!(url, bytes = body.len(), "page body read");
+    Ok(DownloadedPage {
+        final_url,
+        content_type,
+        etag,
+        body,
+    })
+}
+
+/// Move a saved page and the HTML kept beside it
+fn move_page(source: &Path, dest: &Path) -> Result<()> {
+    move_file(source, dest)?;
+    let html = raw_html::path_for(source);
+    if html != source && html.exists() {
+        let _ = move_file(&html, &raw_html::path_for(dest));
+    }
+    if source.components().count() > dest.components().count() {
+        lift_asset_links(dest);
+    }
+    Ok(())
+}
+
+/// Point the images of a page moved out of a search directory at `assets/` again
+///
+/// Pages in `current_search/<query-hash>/` link a level further up.
+fn lift_asset_links(path: &Path) {
+    if OutputFormat::of_path(path) == Some(OutputFormat::Markdown)
+        && let Ok(markdown) = std::fs::read_to_string(path)
+        && markdown.contains("](../../assets/")
+    {
+        let lifted = markdown.replace("](../../assets/", "](../assets/");
+        if let Err(e) = write_atomic_blocking(path, lifted.as_bytes()) {
+            tracing::warn!("Failed to update image links in {}: {:#}", path.display(), e);
+        }
+    }
+}
+
+/// Delete a saved page and the HTML kept beside it
+pub fn remove_page(path: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(path)?;
+    let html = raw_html::path_for(path);
+    if html != path {
+        let _ = std::fs::remove_file(html);
+    }
+    Ok(())
+}
+
+/// Delete the unfinished `write_atomic` temporaries in `dir`
+fn remove_partial_writes(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if is_partial_write(&entry.path()) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Delete the kept HTML in `dir` whose page is gone
+fn remove_orphaned_html(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if raw_html::is_orphan(&entry.path()) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Move a file, falling back to copy + delete across filesystems
+fn move_file(source: &Path, dest: &Path) -> Result<()> {
+    if std::fs::rename(source, dest).is_err() {
+        std::fs::copy(source, dest).context("Failed to move file")?;
+        let _ = std::fs::remove_file(source);
+    }
+    Ok(())
+}
+
+/// A cached page considered for size-based eviction
+#[derive(Debug)]
+struct CachedFile {
+    path: PathBuf,
+    size: u64,
+    /// Unix timestamp of the last open (or download)
+    last_used: u64,
+    /// Pinned or part of the current search
+    protected: bool,
+}
+
+/// Files to delete so the cache fits in `limit` bytes, least recently used first
+///
+/// Protected files count toward the total but are never chosen.
+fn plan_eviction(mut files: Vec<CachedFile>, limit: u64) -> Vec<CachedFile> {
+    let mut total: u64 = files.iter().map(|f| f.size).sum();
+    files.retain(|f| !f.protected);
+    files.sort_by_key(|f| f.last_used);
+
+    let mut evict = Vec::new();
+    for file in files {
+        if total <= limit {
+            break;
+        }
+        total -= file.size;
+        evict.push(file);
+    }
+    evict
+}
+
+/// Write a file via a temporary file in the same directory and a rename
+///
+/// A crash or full disk leaves at most a stray `.tmp` file, never a
+/// truncated page. Errors name the io error kind (e.g. "storage full").
+async fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp_path = partial_write_path(path)?;
+    let written = match tokio::fs::write(&tmp_path, contents).await {
+        Ok(()) => tokio::fs::rename(&tmp_path, path).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        let kind = e.kind();
+        return Err(anyhow::Error::new(e).context(format!("failed to save page: {}", kind)));
+    }
+    Ok(())
+}
+
+/// Blocking [`write_atomic`], for code outside the async runtime
+fn write_atomic_blocking(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp_path = partial_write_path(path)?;
+    let written =
+        std::fs::write(&tmp_path, contents).and_then(|()| std::fs::rename(&tmp_path, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp_path);
+        let kind = e.kind();
+        return Err(anyhow::Error::new(e).context(format!("failed to save page: {}", kind)));
+    }
+    Ok(())
+}
+
+/// The `.tmp` file `path` is written to before it's renamed into place
+fn partial_write_path(path: &Path) -> Result<PathBuf> {
+    let mut tmp_name = path.file_name().context("Invalid filename")?.to_os_string();
+    tmp_name.push(".tmp");
+    Ok(path.with_file_name(tmp_name))
+}
+
+/// Whether a cached file was written completely
+///
+/// Empty files and markdown without frontmatter are leftovers from an
+/// interrupted write.
+fn is_complete_page(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+    if metadata.len() == 0 {
+        return false;
+    }
+    match OutputFormat::of_path(path) {
+        Some(OutputFormat::Markdown | OutputFormat::Org) => {
+            read_frontmatter(path).is_some_and(|f| f.url.is_some())
+        }
+        _ => true,
+    }
+}
+
+/// Whether a file is an unfinished `write_atomic` temporary
+fn is_partial_write(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "tmp")
+}
+
+/// Whether a cache file is one of ours (a saved page or PDF)
+fn is_page_file(path: &Path) -> bool {
+    OutputFormat::of_path(path).is_some() || path.extension().is_some_and(|e| e == "pdf")
+}
+
+/// Add markdown files from before the cache index to it
+///
+/// Legacy names used an unstable hash, so each file is renamed to its
+/// current name, computed from the URL in its frontmatter. Files without
+/// a URL are left for the age-based cleanup.
+fn migrate_legacy_files(index: &mut CacheIndex, dirs: &[&Path]) {
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|e| e != "md") {
+                continue;
+            }
+            let Ok(markdown) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let frontmatter = parse_frontmatter(&markdown);
+            let Some(url) = frontmatter.url else {
+                continue;
+            };
+            let title = frontmatter.title.unwrap_or_default();
+
+            let filename = url_to_filename(&url, &title, "md");
+            let new_path = dir.join(&filename);
+            if new_path != path && std::fs::rename(&path, &new_path).is_err() {
+                continue;
+            }
+
+            let metadata = entry.metadata().ok();
+            index.insert(
+                &url,
+                CacheEntry {
+                    filename,
+                    title,
+                    fetched_at: metadata
+                        .as_ref()
+                        .and_then(|m| m.modified().ok())
+                        .map(unix_seconds)
+                        .unwrap_or(0),
+                    size: metadata.map(|m| m.len()).unwrap_or(0),
+                    etag: None,
+                    last_opened: None,
+                    notes: Vec::new(),
+                },
+            );
+        }
+    }
+}
 
-    Ok(filepath)
+/// Key for per-host limits (the whole URL if it has no host)
+fn host_key(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(String::from))
+        .unwrap_or_else(|| url.to_string())
 }
 
 /// Generate deterministic filename from URL
 ///
-/// Format: {domain}_{hash_short}_{title}.md
+/// Format: {domain}_{hash_short}_{title}.{extension}
 /// Example: github_com_a3f8d912_Rust_Programming_Guide.md
-fn url_to_filename(url: &str, title: &str) -> String {
+fn url_to_filename(url: &str, title: &str, extension: &str) -> String {
     // Extract domain
     let domain = Url::parse(url)
         .ok()
@@ -401,20 +1946,51 @@ fn url_to_filename(url: &str, title: &str) -> String {
         .trim_start_matches("www.")
         .replace('.', "_");
 
-    // Generate short hash (8 hex chars)
-    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    url.hash(&mut hasher);
-    let hash = format!("{:08x}", hasher.finish() & 0xFFFFFFFF);
+    // Generate short hash (8 hex chars, stable across Rust releases)
+    let hash = stable_hash(url);
 
     // Clean title (max 30 chars)
     let safe_title = sanitize_filename(title);
     let truncated: String = safe_title.chars().take(30).collect();
 
-    format!("{}_{}_{}.md", clean_domain, hash, truncated)
+    format!("{}_{}_{}.{}", clean_domain, hash, truncated, extension)
+}
+
+/// [`url_to_filename`], numbered when that name holds another URL's page
+///
+/// Two URLs on a domain can share a name when their 8-character hashes
+/// collide and their titles start alike. The name is free when no page in
+/// `dirs` has it or the page there is of `url`; otherwise `_2`, `_3`, …
+/// go before the extension until one is.
+fn unique_filename(dirs: &[PathBuf], url: &str, title: &str, extension: &str) -> String {
+    let filename = url_to_filename(url, title, extension);
+    let taken = |name: &str| {
+        dirs.iter()
+            .map(|dir| dir.join(name))
+            .any(|path| path.exists() && !holds_url(&path, url))
+    };
+    if !taken(&filename) {
+        return filename;
+    }
+    let stem = filename.strip_suffix(&format!(".{}", extension)).unwrap_or(&filename);
+    (2..)
+        .map(|n| format!("{}_{}.{}", stem, n, extension))
+        .find(|name| !taken(name))
+        .expect("some number is free")
+}
+
+/// Whether the page at `path` was saved for `url`
+///
+/// Pages without a `url:` in their frontmatter, like PDFs, count as a
+/// match.
+fn holds_url(path: &Path, url: &str) -> bool {
+    read_frontmatter(path)
+        .and_then(|frontmatter| frontmatter.url)
+        .is_none_or(|saved| normalize_url(&saved) == normalize_url(url))
 }
 
 /// Create a safe filename from a title
-fn sanitize_filename(title: &str) -> String {
+pub fn sanitize_filename(title: &str) -> String {
     let safe: String = title
         .chars()
         .map(|c| {
@@ -458,14 +2034,716 @@ mod tests {
         assert_eq!(sanitize_filename("Multiple   Spaces"), "Multiple_Spaces");
     }
 
+    #[test]
+    fn test_page_stats_summary() {
+        let stats = |words, bytes| PageStats {
+            elapsed: Duration::from_millis(840),
+            bytes,
+            words,
+            published: None,
+            lang: None,
+            truncated: false,
+        };
+        assert_eq!(stats(Some(1234), 0).summary(), "1.2k words · 6 min · 0.8s");
+        assert_eq!(stats(Some(15_400), 0).summary(), "15k words · 70 min · 0.8s");
+        assert_eq!(stats(Some(180), 0).summary(), "180 words · 1 min · 0.8s");
+        assert_eq!(stats(None, 2048).summary(), "2.0 KB · 0.8s");
+
+        let old = PageStats {
+            published: Some("2019-07".to_string()),
+            lang: Some("de-AT".to_string()),
+            ..stats(Some(180), 0)
+        };
+        assert_eq!(old.summary(), "2019 · de · 180 words · 1 min · 0.8s");
+        let english = PageStats { lang: Some("en-US".to_string()), ..old };
+        assert_eq!(english.summary(), "2019 · 180 words · 1 min · 0.8s");
+    }
+
     #[test]
     fn test_url_to_filename() {
         let filename = url_to_filename(
             "https://github.com/rust-lang/rust",
-            "The Rust Programming Language"
+            "The Rust Programming Language",
+            "md"
         );
         assert!(filename.starts_with("github_com_"));
         assert!(filename.contains("The_Rust_Programming"));
         assert!(filename.ends_with(".md"));
+        let org = url_to_filename("https://github.com/rust-lang/rust", "The Rust", "org");
+        assert_eq!(org, filename.replace("_Programming_Language.md", ".org"));
+    }
+
+    fn queue_of(urls: &[&str]) -> FetchQueue {
+        let mut queue = FetchQueue::default();
+        queue.pending.extend(urls.iter().map(|url| QueuedPage {
+            result: result(url),
+            timeout: Duration::from_secs(8),
+            target: None,
+            engine: None,
+        }));
+        queue
+    }
+
+    fn result(url: &str) -> SearchResult {
+        SearchResult {
+            title: String::new(),
+            url: url.to_string(),
+            description: String::new(),
+            ..Default::default()
+        }
+    }
+
+    fn entry(filename: &str, title: &str) -> CacheEntry {
+        CacheEntry {
+            filename: filename.to_string(),
+            title: title.to_string(),
+            fetched_at: 0,
+            size: 0,
+            etag: None,
+            last_opened: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Wait until the manager reports its queue done
+    async fn wait_for_prefetch(rx: &mut tokio::sync::mpsc::UnboundedReceiver<AppMessage>) {
+        timeout(Duration::from_secs(20), async {
+            while let Some(msg) = rx.recv().await {
+                if matches!(msg, AppMessage::PrefetchDone { .. }) {
+                    break;
+                }
+            }
+        })
+        .await
+        .expect("prefetch did not finish");
+    }
+
+    fn ok_response(content_type: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            content_type,
+            body.len(),
+            body
+        )
+    }
+
+    fn pending_urls(queue: &FetchQueue) -> Vec<&str> {
+        queue.pending.iter().map(|p| p.result.url.as_str()).collect()
+    }
+
+    #[test]
+    fn test_boost_moves_page_to_front() {
+        let mut queue = queue_of(&["a", "b", "c", "d"]);
+        queue.boost("d");
+        queue.boost("c");
+        assert_eq!(pending_urls(&queue), ["c", "d", "a", "b"]);
+
+        // Unknown or already taken pages are ignored
+        queue.boost("x");
+        assert_eq!(queue.pop().unwrap().page.result.url, "c");
+        queue.boost("c");
+        assert_eq!(pending_urls(&queue), ["d", "a", "b"]);
+    }
+
+    #[test]
+    fn test_queue_idle_after_last_page() {
+        let mut queue = queue_of(&["a", "b"]);
+        let _first = queue.pop();
+        let _second = queue.pop();
+        assert_eq!(queue.in_flight, 2);
+        assert!(!queue.finish());
+        assert!(queue.finish());
+    }
+
+    #[test]
+    fn test_pop_skips_busy_hosts() {
+        let mut queue = queue_of(&[
+            "https://docs.rs/a",
+            "https://docs.rs/b",
+            "https://docs.rs/c",
+            "https://blog.example/post",
+        ]);
+        let first = queue.pop().unwrap();
+        let second = queue.pop().unwrap();
+        assert_eq!(first.delay, Duration::ZERO);
+        assert!(second.delay >= HOST_JITTER.start);
+
+        // docs.rs is at its limit, so the other host goes first
+        let third = queue.pop().unwrap();
+        assert_eq!(third.page.result.url, "https://blog.example/post");
+        assert!(queue.pop().is_none());
+
+        drop(first);
+        assert_eq!(queue.pop().unwrap().page.result.url, "https://docs.rs/c");
+    }
+
+    /// Serve raw HTTP responses built from the request path
+    async fn serve<F>(respond: F) -> std::net::SocketAddr
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        serve_slowly(Duration::ZERO, respond).await
+    }
+
+    /// Like [`serve`], sending each response `delay` after the request
+    async fn serve_slowly<F>(delay: Duration, respond: F) -> std::net::SocketAddr
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let respond = Arc::new(respond);
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let respond = respond.clone();
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]);
+                    let path = request.split_whitespace().nth(1).unwrap_or("/");
+                    let response = respond(path);
+                    sleep(delay).await;
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_oversized_pages() {
+        let max_bytes = 1024 * 1024;
+        let body = "x".repeat(max_bytes as usize + 1);
+
+        // Content-Length over the cap fails before reading the body
+        let with_length = ok_response("text/html", &body);
+        let addr = serve(move |_| with_length.clone()).await;
+        let err = download_page(&format!("http://{}/", addr), max_bytes)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "page too large (>1MB)");
+
+        // Without Content-Length the streamed body hits the cap
+        let without_length = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n{}",
+            body
+        );
+        let addr = serve(move |_| without_length.clone()).await;
+        let err = download_page(&format!("http://{}/", addr), max_bytes)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "page too large (>1MB)");
+
+        // Pages under the cap download normally
+        let addr = serve(|_| ok_response("text/plain", "hello")).await;
+        let page = download_page(&format!("http://{}/", addr), max_bytes).await.unwrap();
+        assert_eq!(page.body, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_classified() {
+        let addr = serve(|path| {
+            let status = if path == "/gone" { "410 Gone" } else { "503 Service Unavailable" };
+            format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status)
+        })
+        .await;
+        let failure = |url: String| async move {
+            Failure::from_error(&download_page(&url, 1024 * 1024).await.unwrap_err())
+        };
+
+        let gone = failure(format!("http://{}/gone", addr)).await;
+        assert_eq!(gone, Failure::Http(410));
+        assert!(!gone.is_retryable());
+        let busy = failure(format!("http://{}/busy", addr)).await;
+        assert_eq!(busy.to_string(), "HTTP 503 Service Unavailable");
+        assert!(busy.is_retryable());
+
+        // Nothing listens on a port that was just freed
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = listener.local_addr().unwrap();
+        drop(listener);
+        let refused = failure(format!("http://{}/", closed)).await;
+        assert_eq!(refused, Failure::Connect);
+        assert!(refused.is_unreachable() && refused.is_retryable());
+        assert_eq!(failure("http://no-such-host.invalid/".to_string()).await, Failure::Dns);
+
+        let unsupported = ExtractError("unsupported content type: image/png".to_string());
+        assert_eq!(
+            Failure::from_error(&unsupported.into()),
+            Failure::Extract("unsupported content type: image/png".to_string())
+        );
+        let too_large = anyhow::anyhow!("page too large (>5MB)");
+        assert_eq!(
+            Failure::from_error(&too_large),
+            Failure::Other("page too large (>5MB)".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_follows_redirects() {
+        let addr = serve(|path| match path {
+            "/short" => "HTTP/1.1 301 Moved Permanently\r\nLocation: /final\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            "/final" => "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok".to_string(),
+            // Every hop of /loop redirects to the next one
+            _ => {
+                let hop: u32 = path.trim_start_matches("/loop").parse().unwrap_or(0);
+                format!(
+                    "HTTP/1.1 302 Found\r\nLocation: /loop{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    hop + 1
+                )
+            }
+        })
+        .await;
+
+        let page = download_page(&format!("http://{}/short", addr), 1024)
+            .await
+            .unwrap();
+        assert_eq!(page.final_url, format!("http://{}/final", addr));
+        assert_eq!(page.body, b"ok");
+
+        let err = download_page(&format!("http://{}/loop", addr), 1024)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "too many redirects (more than 10)");
+    }
+
+    #[tokio::test]
+    async fn test_partially_written_pages_are_refetched() {
+        let addr = serve(|_| {
+            let body = "<html><head><title>Fresh</title></head><body><article><h1>Fresh</h1><p>Complete page body.</p></article></body></html>";
+            ok_response("text/html", body)
+        })
+        .await;
+
+        let dir = std::env::temp_dir().join(format!("websearch-partial-test-{}", std::process::id()));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &Config::default(), tx).unwrap();
+
+        // One page cut off mid-frontmatter, one left empty
+        let truncated = result(&format!("http://{}/truncated", addr));
+        let empty = result(&format!("http://{}/empty", addr));
+        for (page, contents) in [(&truncated, "---\ntitle: \"Fre"), (&empty, "")] {
+            let filename = url_to_filename(&page.url, &page.title, "md");
+            std::fs::write(dir.join("active_tabs").join(&filename), contents).unwrap();
+            manager.index.lock().unwrap().insert(&page.url, entry(&filename, ""));
+        }
+
+        let results = [truncated, empty];
+        manager.prefetch_all(&results).await;
+        for page in &results {
+            assert_eq!(manager.get_status(&page.url).await, PrefetchStatus::Pending);
+        }
+
+        wait_for_prefetch(&mut rx).await;
+
+        for page in &results {
+            let PrefetchStatus::Ready(path) = manager.get_status(&page.url).await else {
+                panic!("{} was not refetched", page.url);
+            };
+            assert!(is_complete_page(&path));
+        }
+        let leftovers = std::fs::read_dir(dir.join("active_tabs")).unwrap().count();
+        assert_eq!(leftovers, 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_colliding_filenames_keep_both_pages() {
+        let addr = serve(|_| ok_response("text/plain", "Second article")).await;
+
+        let dir =
+            std::env::temp_dir().join(format!("websearch-collide-test-{}", std::process::id()));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &Config::default(), tx).unwrap();
+
+        // Another URL's page already has the name the second page would get
+        let page = result(&format!("http://{}/second", addr));
+        let filename = url_to_filename(&page.url, &page.title, "md");
+        let first_path = dir.join("active_tabs").join(&filename);
+        let first = format!("---\nurl: http://{}/first\n---\n\nFirst article", addr);
+        std::fs::write(&first_path, &first).unwrap();
+        manager.index.lock().unwrap().insert(&page.url, entry(&filename, &page.title));
+        assert_eq!(manager.find_cached(&page.url), None);
+
+        manager.prefetch_all(std::slice::from_ref(&page)).await;
+        wait_for_prefetch(&mut rx).await;
+
+        let second_path = manager.search_dir().join(filename.replace(".md", "_2.md"));
+        assert_eq!(
+            manager.get_status(&page.url).await,
+            PrefetchStatus::Ready(second_path.clone())
+        );
+        assert_eq!(std::fs::read_to_string(&first_path).unwrap(), first);
+        assert!(std::fs::read_to_string(&second_path).unwrap().contains("Second article"));
+        assert_eq!(
+            manager.find_cached(&page.url),
+            Some(PrefetchStatus::Cached(second_path.clone()))
+        );
+        // Saving it again keeps its numbered name
+        assert_eq!(
+            unique_filename(&manager.cache_dirs(), &page.url, &page.title, "md"),
+            filename.replace(".md", "_2.md")
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_forgotten_page_is_dropped_after_download() {
+        let delay = Duration::from_millis(300);
+        let addr = serve_slowly(delay, |_| ok_response("text/plain", "Removed page")).await;
+
+        let dir =
+            std::env::temp_dir().join(format!("websearch-forget-test-{}", std::process::id()));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &Config::default(), tx).unwrap();
+
+        let page = result(&format!("http://{}/page", addr));
+        manager.prefetch_all(std::slice::from_ref(&page)).await;
+        while manager.get_status(&page.url).await != PrefetchStatus::InProgress {
+            sleep(Duration::from_millis(10)).await;
+        }
+        manager.forget(&page.url).await;
+        wait_for_prefetch(&mut rx).await;
+
+        assert!(!manager.status.read().await.contains_key(&page.url));
+        assert_eq!(manager.find_cached(&page.url), None);
+        assert_eq!(std::fs::read_dir(manager.search_dir()).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_overwrites_open_page_in_place() {
+        let addr = serve(|_| ok_response("text/plain", "Fresh content")).await;
+
+        let dir = std::env::temp_dir().join(format!("websearch-refresh-test-{}", std::process::id()));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &Config::default(), tx).unwrap();
+
+        let page = result(&format!("http://{}/page", addr));
+        let filename = url_to_filename(&page.url, "Old title", "md");
+        let open_path = dir.join("active_tabs").join(&filename);
+        std::fs::write(&open_path, format!("---\nurl: {}\n---\n\nStale content", page.url)).unwrap();
+        manager.index.lock().unwrap().insert(&page.url, entry(&filename, "Old title"));
+
+        manager.prefetch_all(std::slice::from_ref(&page)).await;
+        assert_eq!(
+            manager.get_status(&page.url).await,
+            PrefetchStatus::Cached(open_path.clone())
+        );
+
+        manager.refresh(&page).await.unwrap();
+        assert_eq!(manager.get_status(&page.url).await, PrefetchStatus::Pending);
+        assert!(manager.refresh(&page).await.is_err());
+
+        wait_for_prefetch(&mut rx).await;
+
+        assert_eq!(
+            manager.get_status(&page.url).await,
+            PrefetchStatus::Ready(open_path.clone())
+        );
+        let content = std::fs::read_to_string(&open_path).unwrap();
+        assert!(content.contains("Fresh content"));
+        assert!(!content.contains("Stale content"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_respects_robots_txt() {
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&requested);
+        let addr = serve(move |path| {
+            log.lock().unwrap().push(path.to_string());
+            let body = if path == "/robots.txt" {
+                "User-agent: *\nDisallow: /private/\n"
+            } else {
+                "Public content"
+            };
+            ok_response("text/plain", body)
+        })
+        .await;
+
+        let dir = std::env::temp_dir().join(format!("websearch-robots-test-{}", std::process::id()));
+        let mut config = Config::default();
+        config.prefetch.respect_robots = true;
+        config.prefetch.concurrency = 1;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &config, tx).unwrap();
+
+        let public = result(&format!("http://{}/blog/post", addr));
+        let private = result(&format!("http://{}/private/notes", addr));
+        manager.prefetch_all(&[public.clone(), private.clone()]).await;
+        wait_for_prefetch(&mut rx).await;
+
+        assert!(matches!(manager.get_status(&public.url).await, PrefetchStatus::Ready(_)));
+        assert_eq!(
+            manager.get_status(&private.url).await,
+            PrefetchStatus::Failed(Failure::Other(DISALLOWED.to_string()))
+        );
+        // robots.txt was read once and the disallowed page never requested
+        assert_eq!(*requested.lock().unwrap(), ["/robots.txt", "/blog/post"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_re_extract_from_kept_html_offline() {
+        let dir = std::env::temp_dir().join(format!("websearch-re-extract-{}", std::process::id()));
+        let mut config = Config::default();
+        // Any download would fail the page
+        config.network.offline = true;
+        config.extraction.format = OutputFormat::Org;
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &config, tx).unwrap();
+
+        let url = "http://127.0.0.1:9/post";
+        let filename = url_to_filename(url, "Post", "md");
+        let page_path = dir.join("active_tabs").join(&filename);
+        std::fs::write(&page_path, format!("---\nurl: {}\n---\n\nBroken extraction", url)).unwrap();
+        let raw = RawHtml {
+            url: url.to_string(),
+            final_url: "http://127.0.0.1:9/posts/post/".to_string(),
+            fetched_at: 1_718_000_000,
+            engine: Some("brave".to_string()),
+            archived_snapshot: None,
+            archived_date: None,
+            html: "<html><head><title>Post</title></head><body><article><h1>Post</h1>\
+                   <p>Kept <strong>content</strong>, see <a href=\"../notes\">notes</a>.</p>\
+                   </article></body></html>"
+                .to_string(),
+        };
+        std::fs::write(raw_html::path_for(&page_path), raw.to_file()).unwrap();
+        manager.index.lock().unwrap().insert(url, entry(&filename, "Post"));
+
+        let path = manager.re_extract(url).await.unwrap();
+        assert_eq!(path, page_path.with_extension("org"));
+        assert!(!page_path.exists());
+        let org = std::fs::read_to_string(&path).unwrap();
+        assert!(org.contains("Kept *content*"));
+        assert!(org.contains("[[http://127.0.0.1:9/posts/notes][notes]]"));
+        assert!(org.contains("#+PROPERTY: ENGINE brave\n"));
+        assert!(org.contains("#+PROPERTY: FETCHED_AT 2024-06-10T06:13:20Z\n"));
+        assert_eq!(manager.kept_html_urls(), [url]);
+
+        // The HTML follows its page around and goes with it
+        let (pinned, _) = manager.toggle_pin_file(&path).unwrap();
+        assert!(raw_html::path_for(&pinned).exists());
+        remove_page(&pinned).unwrap();
+        assert!(!raw_html::path_for(&pinned).exists());
+        assert!(manager.re_extract(url).await.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_write_atomic_reports_io_error_kind() {
+        let dir = std::env::temp_dir().join(format!("websearch-atomic-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("page.md");
+        write_atomic(&path, b"---\nurl: x\n---").await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"---\nurl: x\n---");
+        assert!(!dir.join("page.md.tmp").exists());
+
+        let err = write_atomic(&dir.join("missing/page.md"), b"x").await.unwrap_err();
+        assert_eq!(err.to_string(), "failed to save page: entity not found");
+        assert!(!dir.join("missing/page.md.tmp").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn cached_file(name: &str, size: u64, last_used: u64, protected: bool) -> CachedFile {
+        CachedFile {
+            path: PathBuf::from(name),
+            size,
+            last_used,
+            protected,
+        }
+    }
+
+    #[test]
+    fn test_plan_eviction_least_recently_used_first() {
+        let files = vec![
+            cached_file("new.md", 100, 300, false),
+            cached_file("old.md", 100, 100, false),
+            cached_file("pinned.md", 100, 50, true),
+            cached_file("mid.md", 100, 200, false),
+        ];
+
+        let evict = plan_eviction(files, 200);
+        let names: Vec<_> = evict.iter().map(|f| f.path.to_str().unwrap()).collect();
+        assert_eq!(names, ["old.md", "mid.md"]);
+
+        let under_limit = vec![cached_file("a.md", 100, 1, false)];
+        assert!(plan_eviction(under_limit, 100).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_enforce_cache_limit_skips_pinned_and_current_search() {
+        let dir = std::env::temp_dir().join(format!("websearch-evict-test-{}", std::process::id()));
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &Config::default(), tx).unwrap();
+
+        let write = |path: PathBuf, age_secs: u64| {
+            std::fs::write(&path, vec![b'x'; 100]).unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(age_secs);
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+            path
+        };
+        let pinned = write(dir.join("pinned/oldest.md"), 400);
+        let current = write(manager.search_dir().join("current.md"), 300);
+        let old = write(dir.join("active_tabs/old.md"), 200);
+        let recent = write(dir.join("active_tabs/recent.md"), 100);
+
+        // 400 bytes on disk; only unprotected pages go, oldest first
+        assert_eq!(manager.evict_over(300).await.unwrap(), 1);
+        assert!(pinned.exists());
+        assert!(current.exists());
+        assert!(!old.exists());
+        assert!(recent.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_searches_keep_their_own_directories() {
+        let dir =
+            std::env::temp_dir().join(format!("websearch-searches-test-{}", std::process::id()));
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &Config::default(), tx).unwrap();
+        let url = "https://example.com/first";
+        let page = |name: &str, contents: &str| {
+            let path = manager.search_dir().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+
+        manager.begin_search("first", []).await.unwrap();
+        let first = page("first.md", &format!("---\nurl: {}\n---\n\nText", url));
+        manager.index.lock().unwrap().insert(url, entry("first.md", "First"));
+        manager.begin_search("second", []).await.unwrap();
+        let second = page("second.md", "x");
+        assert_ne!(first.parent(), second.parent());
+        assert!(first.exists());
+
+        // Past the newest few, only searches the history still shows stay
+        for i in 0..KEPT_SEARCHES + 1 {
+            manager.begin_search(&format!("query {}", i), [url]).await.unwrap();
+        }
+        assert!(!second.exists());
+        assert_eq!(manager.find_cached(url), Some(PrefetchStatus::Cached(first.clone())));
+
+        // Images link one level less deep once the page leaves its search
+        let deep = page("deep.md", "![Chart](../../assets/0badcafe/01.png)");
+        let moved = dir.join("active_tabs/deep.md");
+        move_page(&deep, &moved).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&moved).unwrap(),
+            "![Chart](../assets/0badcafe/01.png)"
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrate_legacy_files() {
+        let dir = std::env::temp_dir().join(format!("websearch-migrate-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join("example_com_deadbeef_Old_Title.md");
+        std::fs::write(
+            &legacy,
+            "---\ntitle: \"Old Title\"\nurl: https://example.com/post\n---\n\nBody",
+        )
+        .unwrap();
+        std::fs::write(dir.join("no_frontmatter.md"), "Body").unwrap();
+
+        let mut index = CacheIndex::load(dir.join("index.json"));
+        migrate_legacy_files(&mut index, &[&dir]);
+
+        let entry = index.get("https://example.com/post").unwrap();
+        assert_eq!(entry.filename, url_to_filename("https://example.com/post", "Old Title", "md"));
+        assert_eq!(entry.title, "Old Title");
+        assert!(!legacy.exists());
+        assert!(dir.join(&entry.filename).exists());
+        assert!(dir.join("no_frontmatter.md").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_classify_content_type() {
+        assert_eq!(classify_content_type(None), PageKind::Html);
+        assert_eq!(
+            classify_content_type(Some("text/html; charset=utf-8")),
+            PageKind::Html
+        );
+        assert_eq!(
+            classify_content_type(Some("application/xhtml+xml")),
+            PageKind::Html
+        );
+        assert_eq!(
+            classify_content_type(Some("text/plain;charset=UTF-8")),
+            PageKind::PlainText
+        );
+        assert_eq!(
+            classify_content_type(Some("Application/PDF")),
+            PageKind::Pdf
+        );
+        assert_eq!(
+            classify_content_type(Some("image/png")),
+            PageKind::Unsupported("image/png".to_string())
+        );
+        assert_eq!(
+            classify_content_type(Some("application/json")),
+            PageKind::Unsupported("application/json".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_per_host_limit_against_local_server() {
+        // Server that records when each path was being served
+        let delay = Duration::from_millis(150);
+        let log: Arc<Mutex<Vec<(String, Instant, Instant)>>> = Arc::default();
+        let server_log = log.clone();
+        let addr = serve_slowly(delay, move |path| {
+            let start = Instant::now();
+            server_log.lock().unwrap().push((path.to_string(), start, start + delay));
+            let body = "<html><body><article><h1>Test</h1><p>Hello</p></article></body></html>";
+            ok_response("text/html", body)
+        })
+        .await;
+
+        let dir = std::env::temp_dir().join(format!("websearch-tui-test-{}", std::process::id()));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let manager = PrefetchManager::new(dir.clone(), &Config::default(), tx).unwrap();
+
+        let results: Vec<SearchResult> = (0..6)
+            .map(|i| result(&format!("http://{}/page{}", addr, i)))
+            .collect();
+        manager.prefetch_all(&results).await;
+
+        wait_for_prefetch(&mut rx).await;
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 6);
+        for (_, start, _) in log.iter() {
+            let overlapping = log
+                .iter()
+                .filter(|(_, s, e)| s <= start && start < e)
+                .count();
+            assert!(overlapping <= PER_HOST_LIMIT, "{} requests in flight", overlapping);
+        }
     }
 }
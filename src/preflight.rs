@@ -0,0 +1,217 @@
+//! Configuration checks at startup
+//!
+//! Before the first draw the app probes what it will need: an API key for
+//! each engine that takes one, the editor on `PATH` and a writable data
+//! directory, and with `--check-keys` a one-result Brave search to confirm
+//! the key works. [`check`] turns the findings into notices shown over the
+//! first screen; nothing here stops the app from starting.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::app::check_editor;
+use crate::config::{Config, Engine};
+use crate::globals::get_api_client;
+use crate::paths;
+use crate::search::SearchProviders;
+
+/// Longest wait for Brave to answer the key check
+const KEY_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the startup probes found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probes {
+    pub default_engine: Engine,
+    /// Engines that can't search until an API key is set
+    pub missing_keys: Vec<Engine>,
+    /// Why the editor can't be started, if it can't
+    pub editor_error: Option<String>,
+    pub data_dir: PathBuf,
+    /// Why the data directory isn't writable, if it isn't
+    pub data_dir_error: Option<String>,
+    /// Outcome of `--check-keys`, `None` when it didn't run
+    pub brave_key: Option<KeyCheck>,
+}
+
+/// Brave's answer to a search with the configured key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyCheck {
+    Valid,
+    /// The key was refused with this HTTP status
+    Rejected(u16),
+    /// Brave couldn't be asked
+    Failed(String),
+}
+
+/// Problems to show and engines to grey out in the help bar
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub notices: Vec<String>,
+    pub unusable: Vec<Engine>,
+}
+
+/// Notices for the problems in `probes`
+///
+/// A missing key is only a notice for the default engine; the others are
+/// just greyed out until used.
+pub fn check(probes: &Probes) -> Report {
+    let mut report = Report {
+        unusable: probes.missing_keys.clone(),
+        ..Report::default()
+    };
+    let engine = probes.default_engine;
+    if probes.missing_keys.contains(&engine) {
+        report.notices.push(format!(
+            "{}, the default engine, has no API key: set {} or pick another \
+             search.default_engine",
+            engine.name(),
+            key_setting(engine)
+        ));
+    }
+    match &probes.brave_key {
+        Some(KeyCheck::Rejected(status)) => {
+            report.unusable.push(Engine::Brave);
+            report.notices.push(format!(
+                "Brave rejected the API key (HTTP {}): check {}",
+                status,
+                key_setting(Engine::Brave)
+            ));
+        }
+        Some(KeyCheck::Failed(error)) => {
+            report.notices.push(format!("Couldn't check the Brave API key: {}", error));
+        }
+        Some(KeyCheck::Valid) | None => {}
+    }
+    if let Some(error) = &probes.editor_error {
+        report.notices.push(format!(
+            "{}: set editor.command or $EDITOR to open pages",
+            error
+        ));
+    }
+    if let Some(error) = &probes.data_dir_error {
+        report.notices.push(format!(
+            "Data directory {} isn't writable ({}): pages can't be saved",
+            probes.data_dir.display(),
+            error
+        ));
+    }
+    report
+}
+
+/// Where an engine's API key is configured
+fn key_setting(engine: Engine) -> &'static str {
+    match engine {
+        Engine::Brave => "BRAVE_SEARCH_API_KEY or search.brave_api_key",
+        _ => "its API key",
+    }
+}
+
+/// Run the probes, the Brave key check only if `check_keys`
+pub async fn probe(config: &Config, providers: &SearchProviders, check_keys: bool) -> Probes {
+    let missing_keys = Engine::ALL
+        .into_iter()
+        .filter(|&engine| providers.get(engine).requires_key())
+        .collect();
+    let data_dir = paths::data_dir();
+    let data_dir_error = writable(data_dir).err().map(|e| e.to_string());
+    let brave_key = match config.search.brave_api_key.as_deref() {
+        Some(key) if check_keys && !config.network.offline => Some(check_brave_key(key).await),
+        _ => None,
+    };
+    Probes {
+        default_engine: config.search.default_engine,
+        missing_keys,
+        editor_error: check_editor(&config.editor).err().map(|e| format!("{:#}", e)),
+        data_dir: data_dir.to_path_buf(),
+        data_dir_error,
+        brave_key,
+    }
+}
+
+/// Create `dir` if needed and write and remove a file in it
+fn writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".write-test");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
+
+/// Search Brave for one result with `key`
+async fn check_brave_key(key: &str) -> KeyCheck {
+    let client = match get_api_client() {
+        Ok(client) => client,
+        Err(e) => return KeyCheck::Failed(e.to_string()),
+    };
+    let response = client
+        .get("https://api.search.brave.com/res/v1/web/search?q=test&count=1")
+        .header("X-Subscription-Token", key)
+        .header("Accept", "application/json")
+        .timeout(KEY_CHECK_TIMEOUT)
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => KeyCheck::Valid,
+        Ok(response) if matches!(response.status().as_u16(), 401 | 403 | 422) => {
+            KeyCheck::Rejected(response.status().as_u16())
+        }
+        Ok(response) => KeyCheck::Failed(format!("HTTP {}", response.status())),
+        Err(e) => KeyCheck::Failed(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probes() -> Probes {
+        Probes {
+            default_engine: Engine::DuckDuckGo,
+            missing_keys: Vec::new(),
+            editor_error: None,
+            data_dir: PathBuf::from("/data"),
+            data_dir_error: None,
+            brave_key: None,
+        }
+    }
+
+    #[test]
+    fn test_check_reports_problems() {
+        assert_eq!(check(&probes()), Report::default());
+
+        // A missing key off the default engine only greys it out
+        let missing = Probes {
+            missing_keys: vec![Engine::Brave],
+            ..probes()
+        };
+        let report = check(&missing);
+        assert_eq!(report.unusable, [Engine::Brave]);
+        assert!(report.notices.is_empty());
+        let report = check(&Probes {
+            default_engine: Engine::Brave,
+            ..missing
+        });
+        assert_eq!(
+            report.notices,
+            ["Brave, the default engine, has no API key: set BRAVE_SEARCH_API_KEY or \
+              search.brave_api_key or pick another search.default_engine"]
+        );
+
+        let report = check(&Probes {
+            brave_key: Some(KeyCheck::Rejected(401)),
+            editor_error: Some("Editor hx not found on PATH".to_string()),
+            data_dir_error: Some("Permission denied (os error 13)".to_string()),
+            ..probes()
+        });
+        assert_eq!(report.unusable, [Engine::Brave]);
+        assert_eq!(
+            report.notices,
+            [
+                "Brave rejected the API key (HTTP 401): check BRAVE_SEARCH_API_KEY or \
+                 search.brave_api_key",
+                "Editor hx not found on PATH: set editor.command or $EDITOR to open pages",
+                "Data directory /data isn't writable (Permission denied (os error 13)): pages \
+                 can't be saved",
+            ]
+        );
+    }
+}
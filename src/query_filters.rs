@@ -0,0 +1,150 @@
+//! `-site:` and `+site:` operators, applied to the results by the TUI
+//!
+//! Engines honour `-site:` unevenly, so `-site:pinterest.com` and
+//! `+site:docs.rs` are taken out of the query before it is sent and applied
+//! to the results instead: those on an excluded domain are dropped and
+//! those on a preferred domain move to the top, keeping their order. A
+//! domain covers its subdomains. A plain `site:` still goes to the engine,
+//! which limits the search to that site.
+
+use crate::search::{site_host, SearchResult};
+
+/// A query split into what the engine gets and the domain operators
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryFilters {
+    /// The query without the operators
+    pub query: String,
+    /// Domains of `-site:`, lowercase and without `www.`
+    pub excluded: Vec<String>,
+    /// Domains of `+site:`
+    pub preferred: Vec<String>,
+}
+
+impl QueryFilters {
+    /// Take the `-site:` and `+site:` operators out of `query`
+    ///
+    /// An operator without a domain stays in the query as typed.
+    pub fn parse(query: &str) -> Self {
+        let mut filters = QueryFilters::default();
+        let mut terms = Vec::new();
+        for term in query.split_whitespace() {
+            let (list, domain) = if let Some(domain) = term.strip_prefix("-site:") {
+                (&mut filters.excluded, domain)
+            } else if let Some(domain) = term.strip_prefix("+site:") {
+                (&mut filters.preferred, domain)
+            } else {
+                terms.push(term);
+                continue;
+            };
+            match site_host(domain) {
+                Some(host) if !list.contains(&host) => list.push(host),
+                Some(_) => {}
+                None => terms.push(term),
+            }
+        }
+        filters.query = if filters.is_empty() {
+            query.trim().to_string()
+        } else {
+            terms.join(" ")
+        };
+        filters
+    }
+
+    /// Whether the query had no operators
+    pub fn is_empty(&self) -> bool {
+        self.excluded.is_empty() && self.preferred.is_empty()
+    }
+
+    /// Drop the results on excluded domains and move preferred ones up
+    pub fn apply(&self, results: &mut Vec<SearchResult>) {
+        if self.is_empty() {
+            return;
+        }
+        results.retain(|result| !self.matches(&self.excluded, &result.url));
+        // Stable, so each group keeps the engine's order
+        results.sort_by_key(|result| !self.matches(&self.preferred, &result.url));
+    }
+
+    fn matches(&self, domains: &[String], url: &str) -> bool {
+        site_host(url).is_some_and(|host| domains.iter().any(|domain| covers(domain, &host)))
+    }
+
+    /// The operators for the results title, like `-pinterest.com +docs.rs`
+    pub fn label(&self) -> String {
+        let excluded = self.excluded.iter().map(|domain| format!("-{}", domain));
+        let preferred = self.preferred.iter().map(|domain| format!("+{}", domain));
+        excluded.chain(preferred).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn covers(domain: &str, host: &str) -> bool {
+    host.strip_suffix(domain)
+        .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str) -> SearchResult {
+        SearchResult {
+            title: url.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let filters = QueryFilters::parse(
+            "tokio  -site:pinterest.com select +site:docs.rs -site:WWW.Quora.com/ -site:pinterest.com",
+        );
+        assert_eq!(filters.query, "tokio select");
+        assert_eq!(filters.excluded, ["pinterest.com", "quora.com"]);
+        assert_eq!(filters.preferred, ["docs.rs"]);
+        assert_eq!(filters.label(), "-pinterest.com -quora.com +docs.rs");
+
+        // No operators leave the query alone; plain site: goes to the engine
+        let plain = QueryFilters::parse(" \"exact  phrase\" site:docs.rs ");
+        assert_eq!(plain.query, "\"exact  phrase\" site:docs.rs");
+        assert!(plain.is_empty());
+        assert_eq!(plain.label(), "");
+
+        // Operators without a domain are just words
+        let bare = QueryFilters::parse("rust -site: +site:docs.rs");
+        assert_eq!(bare.query, "rust -site:");
+        assert_eq!(bare.preferred, ["docs.rs"]);
+        assert_eq!(QueryFilters::parse("-site:example.com").query, "");
+    }
+
+    #[test]
+    fn test_apply_drops_excluded_and_raises_preferred() {
+        let filters = QueryFilters::parse("async -site:example.com +site:rust-lang.org +site:docs.rs");
+        let mut results = vec![
+            result("https://blog.example.com/async"),
+            result("https://tokio.rs/tokio/tutorial"),
+            result("https://docs.rs/tokio"),
+            result("https://notexample.com/async"),
+            result("https://example.com/async"),
+            result("https://doc.rust-lang.org/book/ch17-00-async-await.html"),
+            result("https://rust-lang.org.evil.test/"),
+        ];
+        filters.apply(&mut results);
+        let urls: Vec<&str> = results.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://docs.rs/tokio",
+                "https://doc.rust-lang.org/book/ch17-00-async-await.html",
+                "https://tokio.rs/tokio/tutorial",
+                "https://notexample.com/async",
+                "https://rust-lang.org.evil.test/",
+            ]
+        );
+
+        assert!(covers("example.com", "a.b.example.com"));
+        assert!(!covers("example.com", "badexample.com"));
+        assert!(!covers("example.com", "example.com.au"));
+    }
+}
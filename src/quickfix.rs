@@ -0,0 +1,93 @@
+//! Fetched pages as a Vim or Neovim quickfix list
+//!
+//! `Q` writes one `path:1: title` line per fetched page to `quickfix.txt`
+//! in the data directory and starts the editor with `-q` on it, so `:cnext`
+//! and `:cprev` step through the pages. A running Neovim server loads the
+//! list with `:cfile` instead.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// Quickfix list in the data directory, rewritten by each `Q`
+const QUICKFIX_FILE: &str = "quickfix.txt";
+
+/// Quickfix line of a page, its first line titled with `title`
+///
+/// The path is made absolute so the list works from any directory, and the
+/// title is put on one line. `None` for a path with a line break, which
+/// the list can't hold.
+pub fn quickfix_line(path: &Path, title: &str) -> Option<String> {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = path.to_string_lossy();
+    if path.contains(['\n', '\r']) {
+        return None;
+    }
+    let title: String = title.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(format!("{}:1: {}", path, title))
+}
+
+/// Write the quickfix list of `pages`, each a path and title
+///
+/// Returns the absolute path of the list and how many pages it holds.
+pub fn write(pages: &[(PathBuf, String)]) -> Result<(PathBuf, usize)> {
+    let lines: Vec<String> = pages
+        .iter()
+        .filter_map(|(path, title)| quickfix_line(path, title))
+        .collect();
+    anyhow::ensure!(!lines.is_empty(), "No page can go into the quickfix list");
+    let file = std::path::absolute(paths::data_dir().join(QUICKFIX_FILE))
+        .context("Failed to find the data directory")?;
+    std::fs::write(&file, lines.join("\n") + "\n")
+        .with_context(|| format!("Failed to write {}", file.display()))?;
+    Ok((file, lines.len()))
+}
+
+/// Keys for `nvim --remote-send` that load `file` with `:cfile`
+///
+/// The file name is escaped like Vim's `fnameescape()`, and `<` is spelled
+/// `<lt>` so it isn't read as a key name.
+pub fn cfile_keys(file: &Path) -> String {
+    let mut escaped = String::new();
+    for c in file.to_string_lossy().chars() {
+        if " \t*?[{`$\\%#'\"|!<".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    format!("<C-\\><C-N>:cfile {}<CR>", escaped.replace('<', "<lt>"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quickfix_lines_are_absolute_and_single_line() {
+        let line = quickfix_line(Path::new("/data/active_tabs/select.md"), "select! in tokio");
+        assert_eq!(line.as_deref(), Some("/data/active_tabs/select.md:1: select! in tokio"));
+
+        // Titles with line breaks and tabs stay on their line
+        let line = quickfix_line(Path::new("/data/a b:c.md"), "Tokio\n\tselect!  docs\r");
+        assert_eq!(line.as_deref(), Some("/data/a b:c.md:1: Tokio select! docs"));
+        assert_eq!(quickfix_line(Path::new("/data/a\nb.md"), "title"), None);
+
+        let relative = quickfix_line(Path::new("websearch/page.md"), "").unwrap();
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(relative, format!("{}:1: ", cwd.join("websearch/page.md").display()));
+    }
+
+    #[test]
+    fn test_cfile_keys_escape_the_file_name() {
+        assert_eq!(
+            cfile_keys(Path::new("/home/me/my data/quickfix.txt")),
+            "<C-\\><C-N>:cfile /home/me/my\\ data/quickfix.txt<CR>"
+        );
+        assert_eq!(
+            cfile_keys(Path::new("/tmp/%#|<x>.txt")),
+            "<C-\\><C-N>:cfile /tmp/\\%\\#\\|\\<lt>x>.txt<CR>"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Downloaded HTML kept beside saved pages
+//!
+//! With `extraction.keep_html` on, the HTML of every page is written next
+//! to it, with the same name and an `.html` extension, so the page can be
+//! extracted again (`Ctrl+E`, `--re-extract`) after an extractor upgrade or
+//! an options change without downloading it. A comment at the top of the
+//! file records where the HTML came from:
+//!
+//! ```html
+//! <!-- websearch-tui
+//! url: https://example.com/post
+//! final_url: https://www.example.com/post/
+//! fetched_at: 1718000000
+//! engine: brave
+//! -->
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::config::OutputFormat;
+
+/// First line of the header comment
+const HEADER: &str = "<!-- websearch-tui";
+
+/// Extension of the HTML files
+const EXTENSION: &str = "html";
+
+/// A page's HTML and what its extraction needs besides
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawHtml {
+    /// URL of the search result
+    pub url: String,
+    /// Where the HTML was downloaded from, which links resolve against
+    pub final_url: String,
+    /// Unix timestamp (seconds) of the download
+    pub fetched_at: u64,
+    pub engine: Option<String>,
+    pub archived_snapshot: Option<String>,
+    pub archived_date: Option<String>,
+    pub html: String,
+}
+
+impl RawHtml {
+    /// The file contents: the header comment, then the HTML
+    pub fn to_file(&self) -> String {
+        let mut file = format!(
+            "{}\nurl: {}\nfinal_url: {}\nfetched_at: {}\n",
+            HEADER, self.url, self.final_url, self.fetched_at
+        );
+        let optional = [
+            ("engine", &self.engine),
+            ("archived_snapshot", &self.archived_snapshot),
+            ("archived_date", &self.archived_date),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                file.push_str(&format!("{}: {}\n", key, value));
+            }
+        }
+        file.push_str("-->\n");
+        file.push_str(&self.html);
+        file
+    }
+
+    /// Read a file written by [`Self::to_file`]
+    ///
+    /// `None` when the header is missing or has no URL.
+    pub fn parse(file: &str) -> Option<Self> {
+        let (header, html) = file.strip_prefix(HEADER)?.split_once("-->\n")?;
+        let mut raw = RawHtml {
+            url: String::new(),
+            final_url: String::new(),
+            fetched_at: 0,
+            engine: None,
+            archived_snapshot: None,
+            archived_date: None,
+            html: html.to_string(),
+        };
+        for line in header.lines() {
+            let Some((key, value)) = line.split_once(": ") else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key {
+                "url" => raw.url = value,
+                "final_url" => raw.final_url = value,
+                "fetched_at" => raw.fetched_at = value.parse().unwrap_or(0),
+                "engine" => raw.engine = Some(value),
+                "archived_snapshot" => raw.archived_snapshot = Some(value),
+                "archived_date" => raw.archived_date = Some(value),
+                _ => {}
+            }
+        }
+        if raw.url.is_empty() {
+            return None;
+        }
+        if raw.final_url.is_empty() {
+            raw.final_url = raw.url.clone();
+        }
+        Some(raw)
+    }
+}
+
+/// Where the HTML of the page saved at `page` is kept
+pub fn path_for(page: &Path) -> PathBuf {
+    page.with_extension(EXTENSION)
+}
+
+/// Whether a cache file is kept HTML
+pub fn is_raw_html(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == EXTENSION)
+}
+
+/// The saved page next to kept HTML, in whichever text format it is
+pub fn page_for(path: &Path) -> Option<PathBuf> {
+    [OutputFormat::Markdown, OutputFormat::Org, OutputFormat::Text]
+        .into_iter()
+        .map(|format| path.with_extension(format.extension()))
+        .find(|page| page.exists())
+}
+
+/// Whether `path` is kept HTML whose page is gone
+pub fn is_orphan(path: &Path) -> bool {
+    is_raw_html(path) && page_for(path).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() {
+        let raw = RawHtml {
+            url: "https://example.com/post".to_string(),
+            final_url: "https://www.example.com/post/".to_string(),
+            fetched_at: 1_718_000_000,
+            engine: Some("brave".to_string()),
+            archived_snapshot: None,
+            archived_date: None,
+            html: "<html><body><!-- a comment -->\n<p>Hi</p></body></html>".to_string(),
+        };
+        let file = raw.to_file();
+        assert!(file.starts_with("<!-- websearch-tui\nurl: https://example.com/post\n"));
+        assert_eq!(RawHtml::parse(&file), Some(raw));
+
+        let bare = "<!-- websearch-tui\nurl: https://a.com/\n-->\n<p>A</p>";
+        let bare = RawHtml::parse(bare).unwrap();
+        assert_eq!(bare.final_url, "https://a.com/");
+        assert_eq!(bare.html, "<p>A</p>");
+        assert_eq!(RawHtml::parse("<html><p>No header</p></html>"), None);
+    }
+
+    #[test]
+    fn test_orphaned_html() {
+        let dir = std::env::temp_dir().join(format!("websearch-raw-html-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let page = dir.join("example_com_1a2b3c4d_Post.org");
+        std::fs::write(&page, "#+TITLE: Post\n").unwrap();
+        std::fs::write(path_for(&page), "<p>Post</p>").unwrap();
+        std::fs::write(dir.join("example_com_5e6f7a8b_Gone.html"), "<p>Gone</p>").unwrap();
+
+        assert_eq!(path_for(&page), dir.join("example_com_1a2b3c4d_Post.html"));
+        assert_eq!(page_for(&path_for(&page)), Some(page.clone()));
+        assert!(!is_orphan(&path_for(&page)));
+        assert!(is_orphan(&dir.join("example_com_5e6f7a8b_Gone.html")));
+        assert!(!is_orphan(&page));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+//! Results already read
+//!
+//! `read.json` in the data directory maps the normalized URL of every page opened in
+//! the editor or browser to when it was opened, so results read before are
+//! dimmed and marked in later searches. `x` marks or unmarks one by hand;
+//! `ui.track_read = false` turns the log off. The newest [`MAX_ENTRIES`]
+//! are kept, and the file is only read when first needed.
+
+use anyhow::{Context, Result};
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::cache_index::normalize_url;
+use crate::paths;
+
+/// Read log file name inside the data directory
+const READ_FILE: &str = "read.json";
+
+/// Most URLs kept; the longest-read ones go first
+pub const MAX_ENTRIES: usize = 5000;
+
+/// Normalized URLs of read pages and when they were read
+#[derive(Debug)]
+pub struct ReadLog {
+    path: PathBuf,
+    /// Unix timestamps (seconds) by normalized URL, loaded on first use
+    entries: OnceCell<HashMap<String, u64>>,
+}
+
+impl ReadLog {
+    /// Where the read log is kept
+    pub fn path() -> PathBuf {
+        paths::data_dir().join(READ_FILE)
+    }
+
+    /// The log at `path`, not read until needed
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            entries: OnceCell::new(),
+        }
+    }
+
+    /// Entries, loaded from the file on first use
+    ///
+    /// A missing or unreadable file is an empty log.
+    fn entries(&self) -> &HashMap<String, u64> {
+        self.entries.get_or_init(|| {
+            std::fs::read_to_string(&self.path)
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default()
+        })
+    }
+
+    fn entries_mut(&mut self) -> &mut HashMap<String, u64> {
+        self.entries();
+        self.entries.get_mut().expect("loaded above")
+    }
+
+    /// Whether the page at `url` was read
+    pub fn contains(&self, url: &str) -> bool {
+        self.entries().contains_key(&normalize_url(url))
+    }
+
+    /// Record `url` as read at `now`, dropping the oldest past the cap
+    pub fn mark(&mut self, url: &str, now: u64) {
+        let entries = self.entries_mut();
+        entries.insert(normalize_url(url), now);
+        if entries.len() > MAX_ENTRIES
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, read_at)| **read_at)
+                .map(|(url, _)| url.clone())
+        {
+            entries.remove(&oldest);
+        }
+    }
+
+    /// Forget that `url` was read; false when it wasn't
+    pub fn unmark(&mut self, url: &str) -> bool {
+        self.entries_mut().remove(&normalize_url(url)).is_some()
+    }
+
+    /// Write the log atomically (temp file + rename)
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(self.entries()).context("Failed to serialize read log")?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create data directory")?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, json).context("Failed to write read log")?;
+        std::fs::rename(&tmp_path, &self.path).context("Failed to replace read log")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_log_round_trip_and_cap() {
+        let dir = std::env::temp_dir().join(format!("websearch-read-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join(READ_FILE);
+
+        let mut log = ReadLog::new(path.clone());
+        assert!(!log.contains("https://example.com/a"));
+        log.mark("https://example.com/a/", 10);
+        log.mark("https://example.com/b", 20);
+        // Matched on the normalized URL
+        assert!(log.contains("https://example.com/a#intro"));
+        assert!(log.unmark("https://example.com/b"));
+        assert!(!log.unmark("https://example.com/b"));
+        log.save().unwrap();
+
+        let mut log = ReadLog::new(path);
+        assert!(log.contains("https://example.com/a"));
+        assert!(!log.contains("https://example.com/b"));
+
+        // Past the cap the longest-read page goes
+        for i in 0..MAX_ENTRIES {
+            log.mark(&format!("https://example.com/{}", i), 100 + i as u64);
+        }
+        assert_eq!(log.entries().len(), MAX_ENTRIES);
+        assert!(!log.contains("https://example.com/a"));
+        assert!(log.contains("https://example.com/0"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
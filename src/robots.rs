@@ -0,0 +1,264 @@
+//! robots.txt rules, for `prefetch.respect_robots`
+//!
+//! With the option on, the prefetcher reads each host's `robots.txt` before
+//! fetching its pages and skips the ones it disallows for us. Rules come
+//! from the group naming [`AGENT`], else the `*` group; the longest
+//! matching `Allow` or `Disallow` path wins, `Allow` on a tie, and `*` and
+//! `$` work as in Google's parser. A robots.txt that can't be fetched
+//! allows everything. Parsed rules are kept in memory for a few minutes.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::globals::get_scrape_client;
+
+/// Product token matched against `User-agent` lines
+pub const AGENT: &str = "websearch-tui";
+
+/// How long parsed rules are reused
+const TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Larger robots.txt files are cut here, like Google does
+const MAX_BYTES: usize = 500 * 1024;
+
+/// Allow and Disallow paths that apply to us on one host
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsRules {
+    /// `(allow, pattern)` in file order
+    rules: Vec<(bool, String)>,
+}
+
+impl RobotsRules {
+    /// Rules of `robots` for `agent`
+    pub fn parse(robots: &str, agent: &str) -> Self {
+        let agent = agent.to_lowercase();
+        let mut ours = Vec::new();
+        let mut wildcard = Vec::new();
+        // Set by any group naming us, even an empty one: it replaces `*`
+        let mut named = false;
+
+        // A group is a run of User-agent lines followed by its rules
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        for line in robots.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    let value = value.to_lowercase();
+                    named |= names(&value, &agent);
+                    agents.push(value);
+                }
+                field @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = (field == "allow", value.to_string());
+                    if agents.iter().any(|a| names(a, &agent)) {
+                        ours.push(rule);
+                    } else if agents.iter().any(|a| a == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            rules: if named { ours } else { wildcard },
+        }
+    }
+
+    /// Whether `path` (with its query) may be fetched
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let mut best: Option<(usize, bool)> = None;
+        for (allow, pattern) in &self.rules {
+            if !matches(pattern, path) {
+                continue;
+            }
+            let length = pattern.len();
+            best = match best {
+                Some((best_length, best_allow))
+                    if best_length > length || (best_length == length && best_allow) =>
+                {
+                    Some((best_length, best_allow))
+                }
+                _ => Some((length, *allow)),
+            };
+        }
+        best.is_none_or(|(_, allow)| allow)
+    }
+}
+
+/// Whether the lowercase `User-agent` value `value` names `agent`
+fn names(value: &str, agent: &str) -> bool {
+    !value.is_empty() && value != "*" && agent.contains(value)
+}
+
+/// Whether robots.txt `pattern` matches the start of `path`
+///
+/// `*` matches any run of characters and a trailing `$` anchors the end.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// Parsed rules by origin, each reused for [`TTL`]
+#[derive(Debug, Default)]
+pub struct RobotsCache {
+    entries: HashMap<String, (Instant, Arc<RobotsRules>)>,
+}
+
+impl RobotsCache {
+    /// Rules of `origin` fetched less than [`TTL`] before `now`
+    pub fn get(&self, origin: &str, now: Instant) -> Option<Arc<RobotsRules>> {
+        let (fetched, rules) = self.entries.get(origin)?;
+        (now.duration_since(*fetched) < TTL).then(|| Arc::clone(rules))
+    }
+
+    pub fn insert(&mut self, origin: &str, rules: Arc<RobotsRules>, now: Instant) {
+        self.entries.retain(|_, (fetched, _)| now.duration_since(*fetched) < TTL);
+        self.entries.insert(origin.to_string(), (now, rules));
+    }
+}
+
+/// Origin of `url`, like `https://example.com:8443`, the cache key
+pub fn origin(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+/// Path and query of `url`, as matched against the rules
+pub fn path_of(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// Fetch and parse the robots.txt of `origin`
+///
+/// Anything but a successful response allows everything.
+pub async fn fetch(origin: &str) -> RobotsRules {
+    let url = format!("{}/robots.txt", origin);
+    tracing::debug!(%url, "GET robots.txt");
+    let Ok(client) = get_scrape_client() else {
+        return RobotsRules::default();
+    };
+    let response = match client.get(&url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::debug!(%url, status = %response.status(), "no robots.txt");
+            return RobotsRules::default();
+        }
+        Err(e) => {
+            tracing::debug!(%url, error = %e, "robots.txt unreachable");
+            return RobotsRules::default();
+        }
+    };
+    let mut body = response.text().await.unwrap_or_default();
+    if body.len() > MAX_BYTES {
+        let mut cut = MAX_BYTES;
+        while !body.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        body.truncate(cut);
+    }
+    RobotsRules::parse(&body, AGENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+# Example robots.txt
+User-agent: *
+Disallow: /private/
+Disallow: /*.pdf$
+Allow: /private/press/
+Disallow: /search?
+
+User-agent: Googlebot
+User-agent: Bingbot
+Disallow: /
+";
+
+    #[test]
+    fn test_rule_matching() {
+        let rules = RobotsRules::parse(ROBOTS, AGENT);
+        assert!(rules.is_allowed("/"));
+        assert!(rules.is_allowed("/blog/post"));
+        assert!(!rules.is_allowed("/private/notes"));
+        // The longer Allow wins
+        assert!(rules.is_allowed("/private/press/release"));
+        assert!(!rules.is_allowed("/docs/manual.pdf"));
+        assert!(rules.is_allowed("/docs/manual.pdf.html"));
+        assert!(!rules.is_allowed("/search?q=rust"));
+        assert!(rules.is_allowed("/search"));
+
+        // Ties go to Allow; the wildcard may span slashes
+        let tie = RobotsRules::parse("User-agent: *\nDisallow: /a\nAllow: /a\n", AGENT);
+        assert!(tie.is_allowed("/a/b"));
+        assert!(matches("/*/edit$", "/wiki/Page/edit"));
+        assert!(!matches("/*/edit$", "/wiki/Page/edit/history"));
+        assert!(matches("/", "/anything"));
+
+        // Empty Disallow and missing files allow everything
+        let open = RobotsRules::parse("User-agent: *\nDisallow:\n", AGENT);
+        assert!(open.is_allowed("/private/"));
+        assert!(RobotsRules::default().is_allowed("/private/"));
+    }
+
+    #[test]
+    fn test_group_for_our_agent_replaces_wildcard() {
+        let robots = "User-agent: *\nDisallow: /\n\nUser-agent: WebSearch-TUI\nDisallow: /tmp\n";
+        let rules = RobotsRules::parse(robots, AGENT);
+        assert!(rules.is_allowed("/docs"));
+        assert!(!rules.is_allowed("/tmp/x"));
+
+        // Other bots' groups don't apply
+        let rules = RobotsRules::parse(ROBOTS, "otherbot");
+        assert!(rules.is_allowed("/blog"));
+        let google = RobotsRules::parse(ROBOTS, "googlebot");
+        assert!(!google.is_allowed("/blog"));
+
+        let mut cache = RobotsCache::default();
+        let now = Instant::now();
+        let url = Url::parse("https://example.com:8443/a/b?c=d#e").unwrap();
+        assert_eq!(origin(&url), "https://example.com:8443");
+        assert_eq!(path_of(&url), "/a/b?c=d");
+        cache.insert(&origin(&url), Arc::new(rules), now);
+        assert!(cache.get("https://example.com:8443", now + TTL / 2).is_some());
+        assert!(cache.get("https://example.com:8443", now + TTL).is_none());
+        assert!(cache.get("https://example.com", now).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+//! Built lines of the result rows, kept between frames
+//!
+//! The results list is redrawn on every tick and key press, but a row only
+//! changes when its result, prefetch status, marking or the density does.
+//! Each row is kept under its URL with a hash of those inputs ([`row_key`])
+//! and rebuilt only when the hash changes, so an unchanged frame just
+//! copies the kept lines into the buffer.
+
+use ratatui::text::Line;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Lines of each result row by URL
+#[derive(Debug, Default)]
+pub struct RowCache {
+    rows: HashMap<String, Row>,
+}
+
+#[derive(Debug)]
+struct Row {
+    key: u64,
+    lines: Vec<Line<'static>>,
+}
+
+impl RowCache {
+    /// Lines of the row for `url`, built by `build` unless kept under `key`
+    pub fn get_or_build(
+        &mut self,
+        url: &str,
+        key: u64,
+        build: impl FnOnce() -> Vec<Line<'static>>,
+    ) -> &[Line<'static>] {
+        if self.rows.get(url).is_none_or(|row| row.key != key) {
+            let row = Row {
+                key,
+                lines: build(),
+            };
+            self.rows.insert(url.to_string(), row);
+        }
+        &self.rows[url].lines
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Drop the rows of URLs `keep` says no longer show
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.rows.retain(|url, _| keep(url));
+    }
+}
+
+/// Hash of everything a row is built from
+pub fn row_key(inputs: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    inputs.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::text::Span;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts the allocations of the current thread
+    struct Counting;
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: Counting = Counting;
+
+    fn allocations() -> usize {
+        ALLOCATIONS.with(Cell::get)
+    }
+
+    #[test]
+    fn test_unchanged_rows_are_reused_without_allocating() {
+        let urls: Vec<String> = (0..20).map(|i| format!("https://example.com/{}", i)).collect();
+        let mut cache = RowCache::default();
+        let mut builds = 0;
+        let frame = |cache: &mut RowCache, builds: &mut usize, marked: usize| {
+            let mut lines = 0;
+            for (i, url) in urls.iter().enumerate() {
+                let key = row_key((i, url, i == marked));
+                lines += cache
+                    .get_or_build(url, key, || {
+                        *builds += 1;
+                        vec![Line::from(Span::raw(format!("{}. {}", i + 1, url)))]
+                    })
+                    .len();
+            }
+            lines
+        };
+
+        assert_eq!(frame(&mut cache, &mut builds, 0), 20);
+        assert_eq!(builds, 20);
+
+        // The same frame again builds nothing and allocates nothing
+        let before = allocations();
+        assert_eq!(frame(&mut cache, &mut builds, 0), 20);
+        assert_eq!(allocations(), before);
+        assert_eq!(builds, 20);
+
+        // Marking another row rebuilds the two rows it changes
+        frame(&mut cache, &mut builds, 3);
+        assert_eq!(builds, 22);
+
+        cache.retain(|url| url.ends_with("/1"));
+        assert_eq!(cache.len(), 1);
+    }
+}
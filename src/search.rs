@@ -1,28 +1,91 @@
-//! Brave Search API integration
+//! Search providers and the Brave Search API
 //!
-//! Uses the global HTTP client for connection pooling and reuse.
+//! Every engine implements [`SearchProvider`], and the app looks them up
+//! in [`SearchProviders`] by [`Engine`]. Uses the global HTTP client for
+//! connection pooling and reuse.
 
 use anyhow::{Context, Result};
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use url::Url;
 
-use crate::globals::get_http_client;
+use crate::config::{Engine, SearchConfig};
+use crate::duckduckgo_search::DuckDuckGo;
+use crate::globals::get_api_client;
+use crate::goggles::{self, Goggle};
+use crate::paths;
+use crate::searxng_health::HEALTH_FILE;
+use crate::searxng_search::SearXNG;
+use crate::startpage_search::Startpage;
 
 /// Maximum number of search results to fetch
 pub const MAX_RESULTS: usize = 10;
 
 /// Search result from Brave API
-#[derive(Debug, Clone)]
+///
+/// Only Brave fills in the optional metadata; the other engines leave it
+/// `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SearchResult {
     pub title: String,
     pub url: String,
     pub description: String,
+    /// How old the page is, as the engine puts it, like `2 days ago`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age: Option<String>,
+    /// Language code of the page, like `en`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Name of the site, like `Rust Programming Language`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    /// Kind of result other than a plain web page, like `video` or `faq`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_type: Option<String>,
+}
+
+impl SearchResult {
+    /// Badge shown next to the title for videos and FAQs
+    pub fn badge(&self) -> Option<&'static str> {
+        match self.result_type.as_deref()? {
+            "video" => Some("video"),
+            "faq" => Some("FAQ"),
+            _ => None,
+        }
+    }
+}
+
+/// What the engine said about a query besides its results
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchMeta {
+    /// Query searched instead, when the engine corrected the spelling
+    pub altered: Option<String>,
+    /// Other queries the engine proposes, corrections first
+    pub suggestions: Vec<String>,
+}
+
+impl SearchMeta {
+    /// Whether the engine had nothing to say
+    pub fn is_empty(&self) -> bool {
+        self.altered.is_none() && self.suggestions.is_empty()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct BraveSearchResponse {
+    query: Option<BraveQuery>,
     web: Option<WebResults>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct BraveQuery {
+    /// The corrected query the results are for, if it was corrected
+    altered: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct WebResults {
     results: Vec<BraveResult>,
@@ -33,22 +96,317 @@ struct BraveResult {
     title: String,
     url: String,
     description: Option<String>,
+    age: Option<String>,
+    language: Option<String>,
+    profile: Option<BraveProfile>,
+    /// `search_result` for web results
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    /// `generic`, or `video`, `faq` and the like
+    subtype: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BraveProfile {
+    name: Option<String>,
+}
+
+impl BraveResult {
+    /// The result's kind, `None` for a plain web page
+    fn result_type(&self) -> Option<String> {
+        let subtype = self.subtype.as_deref().filter(|s| *s != "generic");
+        let kind = self.kind.as_deref().filter(|k| *k != "search_result");
+        subtype.or(kind).map(str::to_string)
+    }
+}
+
+impl From<BraveResult> for SearchResult {
+    fn from(r: BraveResult) -> Self {
+        let result_type = r.result_type();
+        SearchResult {
+            title: r.title,
+            url: r.url,
+            description: r.description.unwrap_or_else(|| String::from("No description")),
+            age: r.age,
+            language: r.language,
+            site_name: r.profile.and_then(|profile| profile.name),
+            result_type,
+        }
+    }
+}
+
+/// Receives what a running search is doing, like `trying searx.be…`
+pub type Progress = Arc<dyn Fn(String) + Send + Sync>;
+
+/// A search engine backend
+pub trait SearchProvider: Send + Sync {
+    /// Results for `query`, best first
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<SearchResult>>>;
+
+    /// Like [`search`](Self::search), reporting each attempt to `progress`
+    ///
+    /// Engines that make a single request have nothing to report.
+    fn search_with_progress<'a>(
+        &'a self,
+        query: &'a str,
+        _progress: Progress,
+    ) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+        self.search(query)
+    }
+
+    /// Like [`search_with_progress`](Self::search_with_progress), with what
+    /// the engine said about the query
+    ///
+    /// `verbatim` asks engines that correct spelling to search `query` as
+    /// typed. Engines without corrections or suggestions say nothing.
+    fn search_with_meta<'a>(
+        &'a self,
+        query: &'a str,
+        _verbatim: bool,
+        progress: Progress,
+    ) -> BoxFuture<'a, Result<(Vec<SearchResult>, SearchMeta)>> {
+        Box::pin(async move {
+            let results = self.search_with_progress(query, progress).await?;
+            Ok((results, SearchMeta::default()))
+        })
+    }
+
+    /// Results on page `page` of `query`, the first page being 0
+    ///
+    /// Engines without paging only have the first page.
+    fn search_page<'a>(
+        &'a self,
+        query: &'a str,
+        page: usize,
+    ) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+        if page == 0 {
+            return self.search(query);
+        }
+        let name = self.name();
+        Box::pin(async move { anyhow::bail!("{} has no further pages of results", name) })
+    }
+
+    /// Display name, like "DuckDuckGo"
+    fn name(&self) -> &'static str;
+
+    /// Whether searches fail until an API key is configured
+    fn requires_key(&self) -> bool {
+        false
+    }
+
+    /// Whether the engine takes an API key at all
+    fn uses_key(&self) -> bool {
+        false
+    }
+}
+
+/// The provider behind each [`Engine`]
+#[derive(Clone)]
+pub struct SearchProviders {
+    providers: HashMap<Engine, Arc<dyn SearchProvider>>,
+    /// Goggle of Brave searches, shared with the Brave provider
+    goggle: Arc<Mutex<Option<Goggle>>>,
+}
+
+impl SearchProviders {
+    /// The built-in engines, Brave with the configured API key
+    pub fn new(config: &SearchConfig) -> Self {
+        let goggle = Arc::new(Mutex::new(goggles::initial(config)));
+        let brave = Brave {
+            api_key: config.brave_api_key.clone(),
+            goggle: Arc::clone(&goggle),
+        };
+        let duckduckgo = DuckDuckGo {
+            region: config.region.clone(),
+        };
+        let searxng = SearXNG::new(
+            config
+                .remember_searxng_health
+                .then(|| paths::data_dir().join(HEALTH_FILE)),
+        );
+        let providers: [(Engine, Arc<dyn SearchProvider>); 4] = [
+            (Engine::Brave, Arc::new(brave)),
+            (Engine::DuckDuckGo, Arc::new(duckduckgo)),
+            (Engine::SearXNG, Arc::new(searxng)),
+            (Engine::Startpage, Arc::new(Startpage::default())),
+        ];
+        Self {
+            providers: providers.into_iter().collect(),
+            goggle,
+        }
+    }
+
+    /// Goggle Brave searches use, if any
+    pub fn goggle(&self) -> Option<Goggle> {
+        self.goggle.lock().unwrap().clone()
+    }
+
+    /// Use `goggle` for the following Brave searches, or none
+    pub fn set_goggle(&self, goggle: Option<Goggle>) {
+        *self.goggle.lock().unwrap() = goggle;
+    }
+
+    /// Provider for `engine`
+    pub fn get(&self, engine: Engine) -> Arc<dyn SearchProvider> {
+        Arc::clone(&self.providers[&engine])
+    }
+}
+
+/// A search the engine refused because this client sends too many
+///
+/// The app rests the engine for a while before using it again, see
+/// [`crate::cooldown`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimited {
+    message: String,
+    /// Wait the engine asked for in `Retry-After`
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimited {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    /// A 429 response, with the wait from its `Retry-After` header
+    ///
+    /// Only a number of seconds is understood; an HTTP date leaves the wait
+    /// to the backoff.
+    pub fn from_response(message: impl Into<String>, response: &reqwest::Response) -> Self {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        Self {
+            retry_after,
+            ..Self::new(message)
+        }
+    }
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// The rate limit a search failed on, if that's why it failed
+pub fn rate_limited(error: &anyhow::Error) -> Option<&RateLimited> {
+    error.chain().find_map(|cause| cause.downcast_ref::<RateLimited>())
+}
+
+/// Whether a search failed because the engine couldn't be reached at all
+///
+/// Connection and DNS failures, unlike HTTP errors or odd pages, suggest
+/// that there is no network.
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(reqwest::Error::is_connect)
+}
+
+/// Brave Search API, with the key from `BRAVE_SEARCH_API_KEY` or the config
+pub struct Brave {
+    api_key: Option<String>,
+    goggle: Arc<Mutex<Option<Goggle>>>,
+}
+
+impl SearchProvider for Brave {
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+        Box::pin(async move {
+            let (results, _) = self.search_with_meta(query, false, Arc::new(|_| {})).await?;
+            Ok(results)
+        })
+    }
+
+    fn search_with_meta<'a>(
+        &'a self,
+        query: &'a str,
+        verbatim: bool,
+        _progress: Progress,
+    ) -> BoxFuture<'a, Result<(Vec<SearchResult>, SearchMeta)>> {
+        Box::pin(async move {
+            let api_key = self.api_key.as_deref().context("BRAVE_SEARCH_API_KEY not set")?;
+            let goggle = self.goggle.lock().unwrap().clone();
+            brave_search(api_key, query, goggle.as_ref(), !verbatim).await
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        Engine::Brave.name()
+    }
+
+    fn requires_key(&self) -> bool {
+        self.api_key.is_none()
+    }
+
+    fn uses_key(&self) -> bool {
+        true
+    }
+}
+
+/// Host of `url` for a `site:` search, lowercased and without `www.`
+///
+/// URLs without a scheme are accepted; `None` when there is no host.
+pub fn site_host(url: &str) -> Option<String> {
+    let url = url.trim();
+    let parsed = match Url::parse(url) {
+        Err(url::ParseError::RelativeUrlWithoutBase) => Url::parse(&format!("https://{}", url)),
+        parsed => parsed,
+    }
+    .ok()?;
+    let host = parsed.host_str()?.trim_end_matches('.').to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+/// `query` limited to `host`, replacing any `site:` it already had
+///
+/// Every engine understands the `site:` operator, so it goes into the
+/// query as-is.
+pub fn site_query(host: &str, query: &str) -> String {
+    let terms: Vec<&str> = query
+        .split_whitespace()
+        .filter(|term| !term.starts_with("site:"))
+        .collect();
+    format!("site:{} {}", host, terms.join(" "))
 }
 
 /// Perform search using Brave Search API
 ///
 /// Uses the global HTTP client with connection pooling.
-/// Returns up to MAX_RESULTS results.
-pub async fn brave_search(api_key: &str, query: &str) -> Result<Vec<SearchResult>> {
-    let client = get_http_client();
+/// Returns up to MAX_RESULTS results, ranked by `goggle` if given, and the
+/// corrected query when `spellcheck` lets Brave search that instead.
+pub async fn brave_search(
+    api_key: &str,
+    query: &str,
+    goggle: Option<&Goggle>,
+    spellcheck: bool,
+) -> Result<(Vec<SearchResult>, SearchMeta)> {
+    let client = get_api_client()?;
 
     // Request exactly MAX_RESULTS
-    let url = format!(
+    let mut url = format!(
         "https://api.search.brave.com/res/v1/web/search?q={}&count={}",
         urlencoding::encode(query),
         MAX_RESULTS
     );
+    if let Some(goggle) = goggle {
+        url.push_str(&format!("&goggles_id={}", urlencoding::encode(&goggle.id)));
+    }
+    if !spellcheck {
+        url.push_str("&spellcheck=0");
+    }
 
+    tracing::debug!(engine = "brave", %url, "GET");
     let response = client
         .get(&url)
         .header("X-Subscription-Token", api_key)
@@ -57,14 +415,40 @@ pub async fn brave_search(api_key: &str, query: &str) -> Result<Vec<SearchResult
         .await
         .context("Failed to send search request")?;
 
+    tracing::debug!(engine = "brave", status = %response.status(), "response");
+    if let Some(goggle) = goggle
+        && response.status() == reqwest::StatusCode::UNPROCESSABLE_ENTITY
+    {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!(goggles::rejection_message(goggle, &body));
+    }
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        anyhow::bail!(RateLimited::from_response(
+            "Brave is rate-limiting this API key (HTTP 429)",
+            &response
+        ));
+    }
     if !response.status().is_success() {
         anyhow::bail!("API returned status: {}", response.status());
     }
 
-    let search_response: BraveSearchResponse = response
-        .json()
+    let body = response
+        .text()
         .await
-        .context("Failed to parse search response")?;
+        .context("Failed to read search response")?;
+    tracing::debug!(engine = "brave", bytes = body.len(), "body read");
+    parse_brave_response(&body)
+}
+
+/// Web results of a Brave API response, with their metadata, and the
+/// corrected query
+fn parse_brave_response(body: &str) -> Result<(Vec<SearchResult>, SearchMeta)> {
+    let search_response: BraveSearchResponse =
+        serde_json::from_str(body).context("Failed to parse search response")?;
+    let meta = SearchMeta {
+        altered: search_response.query.and_then(|query| query.altered),
+        suggestions: Vec::new(),
+    };
 
     let results = search_response
         .web
@@ -72,16 +456,12 @@ pub async fn brave_search(api_key: &str, query: &str) -> Result<Vec<SearchResult
             web.results
                 .into_iter()
                 .take(MAX_RESULTS) // Ensure we don't exceed limit
-                .map(|r| SearchResult {
-                    title: r.title,
-                    url: r.url,
-                    description: r.description.unwrap_or_else(|| String::from("No description")),
-                })
+                .map(SearchResult::from)
                 .collect()
         })
         .unwrap_or_default();
 
-    Ok(results)
+    Ok((results, meta))
 }
 
 #[cfg(test)]
@@ -92,4 +472,176 @@ mod tests {
     fn test_max_results_constant() {
         assert_eq!(MAX_RESULTS, 10);
     }
+
+    /// Answers every query with one result named after it
+    struct Echo;
+
+    impl SearchProvider for Echo {
+        fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+            Box::pin(async move {
+                Ok(vec![SearchResult {
+                    title: query.to_string(),
+                    url: "https://echo.example/".to_string(),
+                    description: String::new(),
+                    ..Default::default()
+                }])
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "Echo"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_providers_dispatch_by_engine() {
+        let mut providers = SearchProviders::new(&SearchConfig::default());
+        assert_eq!(providers.get(Engine::Startpage).name(), "Startpage");
+
+        // Brave can't search without a key, and says why
+        let brave = providers.get(Engine::Brave);
+        assert!(brave.requires_key());
+        let error = brave.search("rust").await.unwrap_err();
+        assert_eq!(error.to_string(), "BRAVE_SEARCH_API_KEY not set");
+
+        providers.providers.insert(Engine::DuckDuckGo, Arc::new(Echo));
+        let results = providers.get(Engine::DuckDuckGo).search("tokio").await.unwrap();
+        assert_eq!(results[0].title, "tokio");
+        assert!(!providers.get(Engine::DuckDuckGo).requires_key());
+    }
+
+    #[tokio::test]
+    async fn test_is_unreachable_only_for_connect_errors() {
+        // Nothing listens on the port once the listener is dropped
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let error = reqwest::get(format!("http://127.0.0.1:{}/", port)).await.unwrap_err();
+        let error = anyhow::Error::new(error).context("Failed to send search request");
+        assert!(is_unreachable(&error));
+
+        assert!(!is_unreachable(&anyhow::anyhow!("API returned status: 429")));
+    }
+
+    #[test]
+    fn test_site_host_of_messy_urls() {
+        let host = |url| site_host(url);
+        assert_eq!(host("https://www.rust-lang.org/learn"), Some("rust-lang.org".to_string()));
+        assert_eq!(host("HTTP://WWW.Example.COM:8080/a?b#c"), Some("example.com".to_string()));
+        assert_eq!(host("https://user:pw@docs.rs./tokio"), Some("docs.rs".to_string()));
+        assert_eq!(host("  www.github.com/tokio-rs  "), Some("github.com".to_string()));
+        assert_eq!(host("https://blog.www.example/"), Some("blog.www.example".to_string()));
+        assert_eq!(host("mailto:someone@example.com"), None);
+        assert_eq!(host(""), None);
+    }
+
+    #[test]
+    fn test_parse_brave_response_metadata() {
+        let body = r#"{
+          "type": "search",
+          "query": {"original": "tokio select", "more_results_available": true},
+          "mixed": {"type": "mixed", "main": [{"type": "web", "index": 0, "all": false}]},
+          "web": {
+            "type": "search",
+            "family_friendly": true,
+            "results": [
+              {
+                "title": "select in tokio - Rust",
+                "url": "https://docs.rs/tokio/latest/tokio/macro.select.html",
+                "is_source_local": false,
+                "is_source_both": false,
+                "description": "Waits on multiple <strong>concurrent</strong> branches.",
+                "page_age": "2024-05-30T00:00:00",
+                "age": "May 30, 2024",
+                "language": "en",
+                "profile": {
+                  "name": "Docs.rs",
+                  "url": "https://docs.rs",
+                  "long_name": "docs.rs",
+                  "img": "https://imgs.search.brave.com/abc/favicon.png"
+                },
+                "meta_url": {"scheme": "https", "netloc": "docs.rs", "hostname": "docs.rs", "path": "› tokio › latest"},
+                "type": "search_result",
+                "subtype": "generic"
+              },
+              {
+                "title": "Tokio select! explained",
+                "url": "https://www.youtube.com/watch?v=abc123",
+                "description": "A walkthrough of select!.",
+                "age": "2 days ago",
+                "language": "en",
+                "profile": {"name": "YouTube", "url": "https://www.youtube.com"},
+                "video": {"duration": "12:04", "views": 5400},
+                "type": "search_result",
+                "subtype": "video"
+              },
+              {
+                "title": "Tokio FAQ",
+                "url": "https://tokio.rs/tokio/faq",
+                "type": "search_result",
+                "subtype": "faq"
+              },
+              {
+                "title": "Bare result",
+                "url": "https://example.com/"
+              }
+            ]
+          }
+        }"#;
+        let (results, meta) = parse_brave_response(body).unwrap();
+        assert!(meta.is_empty());
+        assert_eq!(
+            results[0],
+            SearchResult {
+                title: "select in tokio - Rust".to_string(),
+                url: "https://docs.rs/tokio/latest/tokio/macro.select.html".to_string(),
+                description: "Waits on multiple <strong>concurrent</strong> branches.".to_string(),
+                age: Some("May 30, 2024".to_string()),
+                language: Some("en".to_string()),
+                site_name: Some("Docs.rs".to_string()),
+                result_type: None,
+            }
+        );
+        assert_eq!(results[0].badge(), None);
+        assert_eq!(results[1].result_type.as_deref(), Some("video"));
+        assert_eq!(results[1].badge(), Some("video"));
+        assert_eq!(results[1].age.as_deref(), Some("2 days ago"));
+        assert_eq!(results[2].badge(), Some("FAQ"));
+        assert_eq!(results[2].description, "No description");
+        assert_eq!(
+            results[3],
+            SearchResult {
+                title: "Bare result".to_string(),
+                url: "https://example.com/".to_string(),
+                description: "No description".to_string(),
+                ..Default::default()
+            }
+        );
+
+        // Results without metadata serialize as before, and still parse
+        let json = serde_json::to_string(&results[3]).unwrap();
+        assert_eq!(
+            json,
+            r#"{"title":"Bare result","url":"https://example.com/","description":"No description"}"#
+        );
+        assert_eq!(serde_json::from_str::<SearchResult>(&json).unwrap(), results[3]);
+    }
+
+    #[test]
+    fn test_parse_brave_altered_query() {
+        let body = r#"{
+          "type": "search",
+          "query": {"original": "tokio runtiem", "altered": "tokio runtime", "spellcheck_off": false},
+          "web": {"type": "search", "results": [{"title": "Runtime", "url": "https://tokio.rs/"}]}
+        }"#;
+        let (results, meta) = parse_brave_response(body).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(meta.altered.as_deref(), Some("tokio runtime"));
+        assert!(meta.suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_site_query_replaces_site_terms() {
+        assert_eq!(site_query("docs.rs", "tokio select"), "site:docs.rs tokio select");
+        assert_eq!(site_query("docs.rs", "site:github.com  tokio"), "site:docs.rs tokio");
+        assert_eq!(site_query("docs.rs", ""), "site:docs.rs ");
+    }
 }
@@ -0,0 +1,193 @@
+//! Reachability and latency of the public SearXNG instances
+//!
+//! The first SearXNG search of a session probes every instance at once
+//! with a cheap request. Searches then try instances in order of health:
+//! those that answered, fastest first, then those not heard from yet, then
+//! those that didn't answer. An instance that fails several times in a row
+//! is left out for an hour. With `search.remember_searxng_health` on, the
+//! table is kept in `searxng_health.json` in the data directory.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Health file name inside the data directory
+pub const HEALTH_FILE: &str = "searxng_health.json";
+
+/// How long a probe may take before the instance counts as down
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Failures in a row that quarantine an instance
+const QUARANTINE_AFTER: u32 = 3;
+
+/// How long a quarantined instance is skipped, in seconds
+const QUARANTINE_SECS: u64 = 60 * 60;
+
+/// What is known about one instance
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstanceHealth {
+    /// Whether the last probe or search got an answer
+    pub reachable: bool,
+    /// Response time of the last answer
+    pub latency_ms: Option<u64>,
+    /// Probes and searches failed in a row
+    pub failures: u32,
+    /// Unix timestamp (seconds) of the last probe or search
+    pub checked_at: u64,
+    /// Unix timestamp (seconds) until which the instance is skipped
+    pub quarantined_until: Option<u64>,
+}
+
+/// Health of every instance heard from, by URL
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HealthTable {
+    instances: BTreeMap<String, InstanceHealth>,
+}
+
+impl HealthTable {
+    /// Load the table at `path`; a missing or unreadable file is an empty table
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(self).context("Failed to serialize SearXNG health")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create data directory")?;
+        }
+        std::fs::write(path, json).context("Failed to save SearXNG health")
+    }
+
+    /// Record a probe or search of `instance` at `now`
+    ///
+    /// `latency` is how long an answer took, `None` when there was none.
+    pub fn record(&mut self, instance: &str, latency: Option<Duration>, now: u64) {
+        let health = self.instances.entry(instance.to_string()).or_default();
+        health.checked_at = now;
+        health.reachable = latency.is_some();
+        match latency {
+            Some(latency) => {
+                health.latency_ms = Some(latency.as_millis() as u64);
+                health.failures = 0;
+                health.quarantined_until = None;
+            }
+            None => {
+                health.failures += 1;
+                if health.failures >= QUARANTINE_AFTER {
+                    health.quarantined_until = Some(now + QUARANTINE_SECS);
+                }
+            }
+        }
+    }
+
+    /// Whether `instance` is being skipped at `now`
+    pub fn is_quarantined(&self, instance: &str, now: u64) -> bool {
+        self.instances
+            .get(instance)
+            .and_then(|health| health.quarantined_until)
+            .is_some_and(|until| now < until)
+    }
+
+    /// `instances` in the order to try them at `now`
+    ///
+    /// Quarantined instances are left out, unless all of them are.
+    pub fn order<'a>(&self, instances: &[&'a str], now: u64) -> Vec<&'a str> {
+        let mut ordered: Vec<&'a str> = instances
+            .iter()
+            .copied()
+            .filter(|instance| !self.is_quarantined(instance, now))
+            .collect();
+        if ordered.is_empty() {
+            ordered = instances.to_vec();
+        }
+        // Answered (fastest first), then unknown, then unreachable
+        ordered.sort_by_key(|instance| match self.instances.get(*instance) {
+            Some(health) if health.reachable => (0, health.latency_ms.unwrap_or(u64::MAX)),
+            None => (1, 0),
+            Some(health) => (2, u64::from(health.failures)),
+        });
+        ordered
+    }
+
+    /// Write the table to the debug log
+    pub fn log(&self) {
+        for (instance, health) in &self.instances {
+            tracing::debug!(
+                engine = "searxng",
+                instance = instance.as_str(),
+                reachable = health.reachable,
+                latency_ms = ?health.latency_ms,
+                failures = health.failures,
+                quarantined_until = ?health.quarantined_until,
+                "instance health"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSTANCES: &[&str] =
+        &["https://a.org", "https://b.org", "https://c.org", "https://d.org"];
+
+    fn ms(millis: u64) -> Option<Duration> {
+        Some(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn test_order_by_health_and_latency() {
+        // Probe results: c fast, a slow, b down, d never answered a probe
+        let mut table = HealthTable::default();
+        table.record("https://a.org", ms(900), 100);
+        table.record("https://b.org", None, 100);
+        table.record("https://c.org", ms(150), 100);
+        assert_eq!(
+            table.order(INSTANCES, 100),
+            ["https://c.org", "https://a.org", "https://d.org", "https://b.org"]
+        );
+
+        // A later answer from b puts it back by its latency
+        table.record("https://b.org", ms(400), 200);
+        assert_eq!(
+            table.order(INSTANCES, 200),
+            ["https://c.org", "https://b.org", "https://a.org", "https://d.org"]
+        );
+    }
+
+    #[test]
+    fn test_quarantine_after_repeated_failures() {
+        let mut table = HealthTable::default();
+        table.record("https://a.org", ms(100), 0);
+        for now in [10, 20] {
+            table.record("https://a.org", None, now);
+        }
+        assert!(!table.is_quarantined("https://a.org", 20));
+        table.record("https://a.org", None, 30);
+        assert!(table.is_quarantined("https://a.org", 30));
+        assert_eq!(table.order(&INSTANCES[..2], 30), ["https://b.org"]);
+
+        // All quarantined: try them anyway
+        assert_eq!(table.order(&INSTANCES[..1], 30), ["https://a.org"]);
+
+        // An hour later it is tried again, last
+        let later = 30 + QUARANTINE_SECS;
+        assert!(!table.is_quarantined("https://a.org", later));
+        assert_eq!(table.order(&INSTANCES[..2], later), ["https://b.org", "https://a.org"]);
+
+        let path = std::env::temp_dir()
+            .join(format!("websearch-searxng-health-{}", std::process::id()))
+            .join(HEALTH_FILE);
+        table.save(&path).unwrap();
+        assert_eq!(HealthTable::load(&path), table);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(HealthTable::load(&path), HealthTable::default());
+    }
+}
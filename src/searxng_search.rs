@@ -1,17 +1,25 @@
 //! SearXNG search integration with fallback mechanism
 //!
 //! Uses a curated list of reliable public SearXNG instances with:
-//! - Random instance selection for load distribution
+//! - Instances ordered by health and latency (see [`crate::searxng_health`])
 //! - Automatic fallback to other instances on failure
 //! - Multiple engine strategy (not just Google to avoid blocks)
 //! - JSON API for structured responses
 
 use anyhow::{Context, Result};
-use rand::{rngs::StdRng, SeedableRng, seq::SliceRandom};
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
-use crate::globals::get_http_client;
-use crate::search::SearchResult;
+use crate::cache_index::unix_now;
+use crate::config::Engine;
+use crate::globals::get_api_client;
+use crate::search::{Progress, SearchMeta, SearchProvider, SearchResult};
+use crate::searxng_health::{HealthTable, PROBE_TIMEOUT};
 
 /// Maximum number of search results to fetch
 pub const MAX_RESULTS: usize = 10;
@@ -33,6 +41,21 @@ const SEARXNG_INSTANCES: &[&str] = &[
     "https://search.bus-hit.me",
 ];
 
+/// An instance answered with an HTTP error status
+#[derive(Debug)]
+struct StatusError {
+    instance: String,
+    status: reqwest::StatusCode,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} returned status: {}", self.instance, self.status)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
 /// SearXNG JSON response structure
 #[derive(Debug, Serialize, Deserialize)]
 struct SearxngResponse {
@@ -40,6 +63,11 @@ struct SearxngResponse {
     results: Vec<SearxngResult>,
     #[serde(default)]
     number_of_results: Option<u32>,
+    /// "Did you mean" queries; the results are still for the query as typed
+    #[serde(default)]
+    corrections: Vec<String>,
+    #[serde(default)]
+    suggestions: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,65 +78,205 @@ struct SearxngResult {
     content: Option<String>,
 }
 
+/// Public SearXNG instances as a [`SearchProvider`]
+#[derive(Default)]
+pub struct SearXNG {
+    /// Where the health table is kept between sessions, if anywhere
+    health_file: Option<PathBuf>,
+    health: Mutex<HealthTable>,
+    /// Whether this session's probe has started
+    probed: AtomicBool,
+}
+
+impl SearXNG {
+    pub fn new(health_file: Option<PathBuf>) -> Self {
+        Self {
+            health_file,
+            ..Self::default()
+        }
+    }
+
+    /// Load the saved health table and probe every instance, once per session
+    async fn check_health(&self) {
+        if self.probed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if let Some(path) = &self.health_file {
+            *self.health.lock().unwrap() = HealthTable::load(path);
+        }
+
+        let now = unix_now();
+        let instances: Vec<&str> = {
+            let health = self.health.lock().unwrap();
+            SEARXNG_INSTANCES
+                .iter()
+                .copied()
+                .filter(|instance| !health.is_quarantined(instance, now))
+                .collect()
+        };
+        let latencies = futures::future::join_all(instances.iter().map(|i| probe(i))).await;
+        {
+            let mut health = self.health.lock().unwrap();
+            for (instance, latency) in instances.iter().zip(latencies) {
+                health.record(instance, latency, now);
+            }
+            health.log();
+        }
+        self.save_health();
+    }
+
+    fn save_health(&self) {
+        let Some(path) = &self.health_file else {
+            return;
+        };
+        let health = self.health.lock().unwrap().clone();
+        if let Err(e) = health.save(path) {
+            tracing::warn!(error = %e, "failed to save SearXNG health");
+        }
+    }
+}
+
+impl SearchProvider for SearXNG {
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+        self.search_with_progress(query, Arc::new(|_| {}))
+    }
+
+    fn search_with_progress<'a>(
+        &'a self,
+        query: &'a str,
+        progress: Progress,
+    ) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+        Box::pin(async move {
+            let (results, _) = self.search_with_meta(query, false, progress).await?;
+            Ok(results)
+        })
+    }
+
+    fn search_with_meta<'a>(
+        &'a self,
+        query: &'a str,
+        _verbatim: bool,
+        progress: Progress,
+    ) -> BoxFuture<'a, Result<(Vec<SearchResult>, SearchMeta)>> {
+        Box::pin(async move {
+            if !self.probed.load(Ordering::SeqCst) {
+                progress("checking SearXNG instances…".to_string());
+            }
+            self.check_health().await;
+            let instances = self.health.lock().unwrap().order(SEARXNG_INSTANCES, unix_now());
+            let result = searxng_search(query, &instances, &self.health, progress.as_ref()).await;
+            self.save_health();
+            result
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        Engine::SearXNG.name()
+    }
+}
+
+/// Whether `instance` answers a cheap request, and how fast
+async fn probe(instance: &str) -> Option<std::time::Duration> {
+    let started = Instant::now();
+    let response = get_api_client()
+        .ok()?
+        .get(format!("{}/healthz", instance))
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => Some(started.elapsed()),
+        Ok(response) => {
+            tracing::debug!(engine = "searxng", instance, status = %response.status(), "probe");
+            None
+        }
+        Err(e) => {
+            tracing::debug!(engine = "searxng", instance, error = %e, "probe failed");
+            None
+        }
+    }
+}
+
 /// Perform search using SearXNG with fallback mechanism
 ///
+/// Tries `instances` in order, recording in `health` which of them answered
+/// and telling `progress` about each attempt. The first instance with
+/// results gives them with its suggested queries.
+///
 /// Strategy:
 /// 1. Don't specify engines (let SearXNG aggregate from all available)
 /// 2. This avoids Google-specific rate limiting
 /// 3. SearXNG will use whatever engines are working for that instance
 /// 4. Results are still high quality due to aggregation
-pub async fn searxng_search(query: &str) -> Result<Vec<SearchResult>> {
-    let client = get_http_client();
-    
-    // Shuffle instances for random selection
-    let mut instances = SEARXNG_INSTANCES.to_vec();
-    let mut rng = StdRng::from_entropy();
-    instances.shuffle(&mut rng);
+pub async fn searxng_search(
+    query: &str,
+    instances: &[&str],
+    health: &Mutex<HealthTable>,
+    progress: &(dyn Fn(String) + Send + Sync),
+) -> Result<(Vec<SearchResult>, SearchMeta)> {
+    let client = get_api_client()?;
 
     let mut last_error = None;
     let attempts = MAX_RETRY_ATTEMPTS.min(instances.len());
 
     // Try multiple instances until one succeeds
-    for instance_url in instances.iter().take(attempts) {
-        // Try with default engines first (better success rate)
-        match try_search_instance(&client, instance_url, query, None).await {
-            Ok(results) => {
-                if !results.is_empty() {
-                    return Ok(results);
-                }
-            }
-            Err(e) => {
-                // Store error but continue trying
-                last_error = Some(e);
-            }
+    for (attempt, instance_url) in instances.iter().take(attempts).enumerate() {
+        let name = instance_name(instance_url);
+        if attempt == 0 {
+            progress(format!("trying {}…", name));
         }
-        
-        // If default engines failed, try explicitly with common engines
-        match try_search_instance(&client, instance_url, query, Some("duckduckgo,bing")).await {
-            Ok(results) => {
-                if !results.is_empty() {
-                    return Ok(results);
+        let started = Instant::now();
+        let mut answered = false;
+        // Try with default engines first (better success rate), then
+        // explicitly with common engines
+        for engines in [None, Some("duckduckgo,bing")] {
+            match try_search_instance(client, instance_url, query, engines).await {
+                Ok((results, meta)) if !results.is_empty() => {
+                    let latency = Some(started.elapsed());
+                    health.lock().unwrap().record(instance_url, latency, unix_now());
+                    return Ok((results, meta));
+                }
+                Ok(_) => {
+                    // Reachable, just nothing for this query
+                    answered = true;
+                    last_error = Some(anyhow::anyhow!("No results found from {}", instance_url));
+                }
+                Err(e) => {
+                    // Store error but continue trying
+                    last_error = Some(e);
                 }
             }
-            Err(e) => {
-                last_error = Some(e);
-            }
+        }
+        let latency = answered.then(|| started.elapsed());
+        health.lock().unwrap().record(instance_url, latency, unix_now());
+
+        let reason = match &last_error {
+            Some(e) if !answered => failure_reason(e),
+            _ => "no results".to_string(),
+        };
+        if attempt + 1 < attempts {
+            let next = instance_name(instances[attempt + 1]);
+            progress(format!("{} failed: {}, trying {}…", name, reason, next));
+        } else {
+            progress(format!("{} failed: {}", name, reason));
         }
     }
 
     // All instances failed
     Err(last_error.unwrap_or_else(|| anyhow::anyhow!(
-        "All SearXNG instances failed. This may be due to rate limiting or temporary unavailability. Try DuckDuckGo (Ctrl+D) instead."
+        "All SearXNG instances failed. This may be due to rate limiting or temporary unavailability."
     )))
 }
 
 /// Try searching a specific SearXNG instance
+///
+/// An empty list means the instance answered but found nothing.
 async fn try_search_instance(
     client: &reqwest::Client,
     instance_url: &str,
     query: &str,
     engines: Option<&str>,
-) -> Result<Vec<SearchResult>> {
+) -> Result<(Vec<SearchResult>, SearchMeta)> {
     // Build search URL
     let mut url = format!(
         "{}/search?q={}&format=json&categories=general",
@@ -121,6 +289,7 @@ async fn try_search_instance(
         url.push_str(&format!("&engines={}", eng));
     }
 
+    tracing::debug!(engine = "searxng", %url, "GET");
     let response = client
         .get(&url)
         .header("Accept", "application/json")
@@ -130,12 +299,13 @@ async fn try_search_instance(
         .await
         .context(format!("Failed to connect to {}", instance_url))?;
 
+    tracing::debug!(engine = "searxng", instance = instance_url, status = %response.status(), "response");
     if !response.status().is_success() {
-        anyhow::bail!(
-            "{} returned status: {}",
-            instance_url,
-            response.status()
-        );
+        return Err(StatusError {
+            instance: instance_url.to_string(),
+            status: response.status(),
+        }
+        .into());
     }
 
     let text = response
@@ -143,15 +313,16 @@ async fn try_search_instance(
         .await
         .context("Failed to read response body")?;
 
-    // Debug: Log response for troubleshooting
+    tracing::debug!(engine = "searxng", instance = instance_url, bytes = text.len(), "body read");
     if text.len() < 100 {
-        eprintln!("⚠️  Short response from {}: {}", instance_url, text);
+        tracing::warn!(instance = instance_url, body = %text, "short SearXNG response");
     }
 
     // Parse JSON response with better error handling
     let searxng_response: SearxngResponse = serde_json::from_str(&text)
         .context(format!("Failed to parse JSON from {}. Response length: {} bytes", 
             instance_url, text.len()))?;
+    let meta = suggested_queries(&searxng_response, query);
 
     // Convert to our SearchResult format
     let results: Vec<SearchResult> = searxng_response
@@ -163,14 +334,53 @@ async fn try_search_instance(
             title: r.title,
             url: r.url,
             description: r.content.unwrap_or_else(|| String::from("No description")),
+            ..Default::default()
         })
         .collect();
 
-    if results.is_empty() {
-        anyhow::bail!("No results found from {}", instance_url);
+    Ok((results, meta))
+}
+
+/// Corrections and suggestions of a response, without repeats or `query`
+fn suggested_queries(response: &SearxngResponse, query: &str) -> SearchMeta {
+    let mut suggestions: Vec<String> = Vec::new();
+    for suggestion in response.corrections.iter().chain(&response.suggestions) {
+        let suggestion = suggestion.trim();
+        let repeated = suggestions.iter().any(|s| s.eq_ignore_ascii_case(suggestion));
+        if !suggestion.is_empty() && !repeated && !suggestion.eq_ignore_ascii_case(query.trim()) {
+            suggestions.push(suggestion.to_string());
+        }
+    }
+    SearchMeta {
+        altered: None,
+        suggestions,
     }
+}
+
+/// Host of `instance`, like `searx.be`
+fn instance_name(instance: &str) -> &str {
+    instance.split_once("://").map_or(instance, |(_, host)| host)
+}
 
-    Ok(results)
+/// Why an instance failed, short enough for the progress line
+fn failure_reason(error: &anyhow::Error) -> String {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<StatusError>() {
+            return e.status.as_u16().to_string();
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() {
+                return "timed out".to_string();
+            }
+            if e.is_connect() {
+                return "unreachable".to_string();
+            }
+        }
+        if cause.is::<serde_json::Error>() {
+            return "not JSON".to_string();
+        }
+    }
+    error.to_string()
 }
 
 #[cfg(test)]
@@ -188,6 +398,39 @@ mod tests {
         assert_eq!(MAX_RESULTS, 10);
     }
 
+    #[test]
+    fn test_progress_names_instance_and_reason() {
+        assert_eq!(instance_name("https://searx.be"), "searx.be");
+        let status = anyhow::Error::from(StatusError {
+            instance: "https://searx.be".to_string(),
+            status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+        });
+        assert_eq!(status.to_string(), "https://searx.be returned status: 429 Too Many Requests");
+        assert_eq!(failure_reason(&status), "429");
+        let json = serde_json::from_str::<SearxngResponse>("<html>").unwrap_err();
+        let parse = anyhow::Error::from(json).context("Failed to parse JSON from https://searx.be");
+        assert_eq!(failure_reason(&parse), "not JSON");
+    }
+
+    #[test]
+    fn test_suggested_queries_put_corrections_first() {
+        let json = r#"{
+          "query": "tokio runtiem",
+          "results": [],
+          "corrections": ["tokio runtime"],
+          "suggestions": ["tokio runtime tutorial", "Tokio Runtime", "tokio runtiem", " "],
+          "answers": [],
+          "infoboxes": []
+        }"#;
+        let response: SearxngResponse = serde_json::from_str(json).unwrap();
+        let meta = suggested_queries(&response, "tokio runtiem");
+        assert_eq!(meta.altered, None);
+        assert_eq!(meta.suggestions, ["tokio runtime", "tokio runtime tutorial"]);
+
+        let response: SearxngResponse = serde_json::from_str(r#"{"results": []}"#).unwrap();
+        assert!(suggested_queries(&response, "rust").is_empty());
+    }
+
     #[tokio::test]
     async fn test_url_encoding() {
         let query = "rust programming language";
@@ -0,0 +1,125 @@
+//! Results marked with Tab
+//!
+//! Marks are kept by URL rather than by position, so they stay on the same
+//! results when the list changes underneath them. Everything that reads the
+//! selection goes through the current results, in their order; marks on
+//! URLs no longer listed are ignored.
+
+use std::collections::HashSet;
+
+use crate::search::SearchResult;
+
+/// URLs of the marked results
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    urls: HashSet<String>,
+}
+
+impl Selection {
+    pub fn contains(&self, url: &str) -> bool {
+        self.urls.contains(url)
+    }
+
+    /// Mark `url`, or unmark it if it was marked
+    pub fn toggle(&mut self, url: &str) {
+        if !self.urls.remove(url) {
+            self.urls.insert(url.to_string());
+        }
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        self.urls.remove(url);
+    }
+
+    pub fn clear(&mut self) {
+        self.urls.clear();
+    }
+
+    /// Mark every result
+    pub fn select_all(&mut self, results: &[SearchResult]) {
+        self.urls = results.iter().map(|result| result.url.clone()).collect();
+    }
+
+    /// Mark the unmarked results and unmark the others
+    pub fn invert(&mut self, results: &[SearchResult]) {
+        self.urls = results
+            .iter()
+            .filter(|result| !self.urls.contains(&result.url))
+            .map(|result| result.url.clone())
+            .collect();
+    }
+
+    /// Positions of the marked results, in list order
+    pub fn indices(&self, results: &[SearchResult]) -> Vec<usize> {
+        results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| self.urls.contains(&result.url))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Number of marked results in `results`
+    pub fn count(&self, results: &[SearchResult]) -> usize {
+        self.indices(results).len()
+    }
+
+    /// Whether none of `results` is marked
+    pub fn is_empty(&self, results: &[SearchResult]) -> bool {
+        !results.iter().any(|result| self.urls.contains(&result.url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(urls: &[&str]) -> Vec<SearchResult> {
+        urls.iter()
+            .map(|url| SearchResult {
+                title: url.to_string(),
+                url: format!("https://{}/", url),
+                description: String::new(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_selection_follows_urls_when_results_change() {
+        let mut list = results(&["a", "b", "c", "d"]);
+        let mut selection = Selection::default();
+        selection.toggle(&list[1].url);
+        selection.toggle(&list[3].url);
+        assert_eq!(selection.indices(&list), [1, 3]);
+
+        // Reordered and shortened: the marks move with their results
+        list.reverse();
+        list.remove(1);
+        assert_eq!(list[0].url, "https://d/");
+        assert_eq!(list[1].url, "https://b/");
+        assert_eq!(selection.indices(&list), [0, 1]);
+
+        // Marks whose results are gone don't count
+        list.retain(|result| result.url == "https://a/");
+        assert_eq!(selection.count(&list), 0);
+        assert!(selection.is_empty(&list));
+    }
+
+    #[test]
+    fn test_select_all_and_invert() {
+        let list = results(&["a", "b", "c"]);
+        let mut selection = Selection::default();
+        selection.toggle(&list[0].url);
+
+        selection.invert(&list);
+        assert_eq!(selection.indices(&list), [1, 2]);
+        selection.toggle(&list[1].url);
+        assert_eq!(selection.indices(&list), [2]);
+
+        selection.select_all(&list);
+        assert_eq!(selection.count(&list), 3);
+        selection.invert(&list);
+        assert!(selection.is_empty(&list));
+    }
+}
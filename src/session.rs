@@ -0,0 +1,128 @@
+//! The last search, saved so it survives a restart
+//!
+//! Written to `session.json` in the data directory whenever a search
+//! completes and again on exit, with the selection at that point.
+//! `--resume` brings it back; sessions older than the cache max age are
+//! ignored because their pages are gone by then.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::Engine;
+use crate::paths;
+use crate::search::SearchResult;
+
+/// Session file name inside the data directory
+const SESSION_FILE: &str = "session.json";
+
+/// A search and where the user was in its results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub query: String,
+    pub engine: Engine,
+    pub results: Vec<SearchResult>,
+    /// Selected result index
+    pub selected: usize,
+    /// Unix timestamp (seconds) of the save
+    pub saved_at: u64,
+}
+
+impl Session {
+    pub fn new(query: String, engine: Engine, results: Vec<SearchResult>, selected: usize) -> Self {
+        Self {
+            query,
+            engine,
+            results,
+            selected,
+            saved_at: unix_now(),
+        }
+    }
+
+    /// Where the session is kept
+    pub fn path() -> PathBuf {
+        paths::data_dir().join(SESSION_FILE)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize session")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create data directory")?;
+        }
+        std::fs::write(path, json).context("Failed to save session")
+    }
+
+    /// Load the session at `path` unless it is older than `max_age`
+    ///
+    /// A missing, unreadable or empty session is `None`.
+    pub fn load(path: &Path, max_age: Duration) -> Option<Self> {
+        let json = std::fs::read_to_string(path).ok()?;
+        let session: Session = serde_json::from_str(&json).ok()?;
+        let age = unix_now().saturating_sub(session.saved_at);
+        (age <= max_age.as_secs() && !session.results.is_empty()).then_some(session)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        Session::new(
+            "rust async".to_string(),
+            Engine::DuckDuckGo,
+            vec![SearchResult {
+                title: "Async Rust".to_string(),
+                url: "https://example.com/async".to_string(),
+                description: "An intro".to_string(),
+                ..Default::default()
+            }],
+            0,
+        )
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!("websearch-session-{}", std::process::id()))
+            .join(SESSION_FILE);
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let saved = session();
+        saved.save(&path).unwrap();
+        assert_eq!(Session::load(&path, day), Some(saved.clone()));
+
+        // Stale and empty sessions are ignored
+        let stale = Session {
+            saved_at: saved.saved_at - 2 * day.as_secs(),
+            ..saved.clone()
+        };
+        stale.save(&path).unwrap();
+        assert_eq!(Session::load(&path, day), None);
+
+        let empty = Session {
+            results: Vec::new(),
+            ..saved
+        };
+        empty.save(&path).unwrap();
+        assert_eq!(Session::load(&path, day), None);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(Session::load(&path, day), None);
+    }
+
+    #[test]
+    fn test_session_json_shape() {
+        let json = serde_json::to_value(session()).unwrap();
+        assert_eq!(json["engine"], "duckduckgo");
+        assert_eq!(json["results"][0]["url"], "https://example.com/async");
+    }
+}
@@ -0,0 +1,150 @@
+//! Orders for the results list, cycled with `s`
+//!
+//! Sorting is stable, so results that compare equal keep the engine's
+//! order. Statuses are looked up by URL and the selection is keyed by URL,
+//! so neither is disturbed by a re-sort. Sorting by status uses the
+//! statuses at the moment `s` is pressed; the list doesn't reshuffle as
+//! pages finish.
+
+use std::collections::HashMap;
+
+use crate::prefetch::PrefetchStatus;
+use crate::search::{site_host, SearchResult};
+
+/// How the results list is ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortMode {
+    /// As the engine ranked them
+    #[default]
+    Original,
+    /// Grouped by site, sites in alphabetical order
+    Domain,
+    /// Pages ready to read first, then loading, then failed
+    Status,
+    /// Alphabetical by title
+    Title,
+}
+
+impl SortMode {
+    /// The mode after this one
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Original => SortMode::Domain,
+            SortMode::Domain => SortMode::Status,
+            SortMode::Status => SortMode::Title,
+            SortMode::Title => SortMode::Original,
+        }
+    }
+
+    /// Name for the status line and the results title
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Original => "original order",
+            SortMode::Domain => "by domain",
+            SortMode::Status => "by status",
+            SortMode::Title => "by title",
+        }
+    }
+}
+
+/// Sort `results` by `mode`
+///
+/// `original` lists the URLs in the engine's order; results missing from
+/// it go last.
+pub fn sort_results(
+    results: &mut [SearchResult],
+    mode: SortMode,
+    statuses: &HashMap<String, PrefetchStatus>,
+    original: &[String],
+) {
+    match mode {
+        SortMode::Original => {
+            let rank: HashMap<&str, usize> = original
+                .iter()
+                .enumerate()
+                .map(|(i, url)| (url.as_str(), i))
+                .collect();
+            results.sort_by_key(|result| rank.get(result.url.as_str()).copied().unwrap_or(usize::MAX));
+        }
+        SortMode::Domain => {
+            results.sort_by_cached_key(|result| site_host(&result.url).unwrap_or_default());
+        }
+        SortMode::Status => {
+            results.sort_by_key(|result| status_rank(statuses.get(&result.url)));
+        }
+        SortMode::Title => results.sort_by_cached_key(|result| result.title.to_lowercase()),
+    }
+}
+
+/// Ready pages first, then loading ones, then failures
+fn status_rank(status: Option<&PrefetchStatus>) -> u8 {
+    match status {
+        Some(
+            PrefetchStatus::Ready(_)
+            | PrefetchStatus::ReadyArchived(_)
+            | PrefetchStatus::Cached(_)
+            | PrefetchStatus::Binary(_),
+        ) => 0,
+        Some(PrefetchStatus::InProgress) => 1,
+        None | Some(PrefetchStatus::Pending) => 2,
+        Some(PrefetchStatus::Failed(_) | PrefetchStatus::Blocked(_) | PrefetchStatus::Timeout) => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn result(url: &str, title: &str) -> SearchResult {
+        SearchResult {
+            title: title.to_string(),
+            url: url.to_string(),
+            description: String::new(),
+            ..Default::default()
+        }
+    }
+
+    fn urls(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|result| result.url.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_modes_are_stable_and_reversible() {
+        let original = vec![
+            result("https://www.rust-lang.org/learn", "learn"),
+            result("https://docs.rs/tokio", "Tokio"),
+            result("https://rust-lang.org/tools", "Tools"),
+            result("https://blog.example/async", "async"),
+        ];
+        let order: Vec<String> = original.iter().map(|r| r.url.clone()).collect();
+        let statuses = HashMap::from([
+            (order[0].clone(), PrefetchStatus::Timeout),
+            (order[2].clone(), PrefetchStatus::Cached(PathBuf::from("/tools.md"))),
+            (order[3].clone(), PrefetchStatus::InProgress),
+        ]);
+        let mut results = original.clone();
+
+        // Same site together, in the engine's order within the site
+        sort_results(&mut results, SortMode::Domain, &statuses, &order);
+        assert_eq!(urls(&results), [&order[3], &order[1], &order[0], &order[2]]);
+
+        sort_results(&mut results, SortMode::Status, &statuses, &order);
+        assert_eq!(urls(&results), [&order[2], &order[3], &order[1], &order[0]]);
+
+        sort_results(&mut results, SortMode::Title, &statuses, &order);
+        assert_eq!(urls(&results), [&order[3], &order[0], &order[1], &order[2]]);
+
+        sort_results(&mut results, SortMode::Original, &statuses, &order);
+        assert_eq!(results, original);
+    }
+
+    #[test]
+    fn test_modes_cycle() {
+        let mut mode = SortMode::default();
+        for expected in [SortMode::Domain, SortMode::Status, SortMode::Title, SortMode::Original] {
+            mode = mode.next();
+            assert_eq!(mode, expected);
+        }
+    }
+}
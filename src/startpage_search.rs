@@ -7,13 +7,22 @@
 //! - Better error handling and logging
 //! - More reliable URL extraction
 //! - Flexible selector matching
+//!
+//! Queries are submitted the way the home page's search form does: the
+//! form's hidden inputs, including its session token, are fetched once and
+//! POSTed with every query to `/sp/search`. Startpage blocks clients that
+//! skip the form more readily; when it shows its CAPTCHA page anyway, the
+//! token is refreshed once before giving up with a clear error.
 
 use anyhow::{Context, Result};
+use futures::future::BoxFuture;
 use scraper::{Html, Selector, ElementRef};
 use std::collections::HashSet;
+use std::sync::Mutex;
 
-use crate::globals::get_http_client;
-use crate::search::SearchResult;
+use crate::config::Engine;
+use crate::globals::get_scrape_client;
+use crate::search::{RateLimited, SearchProvider, SearchResult};
 
 /// Maximum number of search results to fetch
 pub const MAX_RESULTS: usize = 10;
@@ -24,18 +33,118 @@ const MIN_TITLE_LENGTH: usize = 5;
 /// Maximum title length to avoid capturing navigation elements
 const MAX_TITLE_LENGTH: usize = 200;
 
-/// Perform search using Startpage
-pub async fn startpage_search(query: &str) -> Result<Vec<SearchResult>> {
-    let client = get_http_client();
+/// Home page with the search form
+const HOME_URL: &str = "https://www.startpage.com/";
+
+/// Where the search form posts to
+const SEARCH_URL: &str = "https://www.startpage.com/sp/search";
+
+/// Error for Startpage's CAPTCHA page
+const BLOCKED: &str =
+    "Startpage is blocking this client with a CAPTCHA — try again later or use another engine";
+
+/// Startpage (Google results) as a [`SearchProvider`]
+#[derive(Default)]
+pub struct Startpage {
+    /// Hidden inputs of the search form, fetched on the first search
+    form: Mutex<Option<Vec<(String, String)>>>,
+}
+
+impl SearchProvider for Startpage {
+    fn search<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<Vec<SearchResult>>> {
+        Box::pin(self.search_with_form(query))
+    }
+
+    fn name(&self) -> &'static str {
+        Engine::Startpage.name()
+    }
+}
+
+impl Startpage {
+    /// Search with the cached form fields, refreshing them once when blocked
+    async fn search_with_form(&self, query: &str) -> Result<Vec<SearchResult>> {
+        let cached = self.form.lock().unwrap().clone();
+        let fresh = cached.is_none();
+        let fields = match cached {
+            Some(fields) => fields,
+            None => self.fetch_form().await?,
+        };
+
+        let html = startpage_search(query, &fields).await?;
+        if !is_blocked(&Html::parse_document(&html)) {
+            return parse_startpage_html(&html);
+        }
+        *self.form.lock().unwrap() = None;
+        if fresh {
+            anyhow::bail!(RateLimited::new(BLOCKED));
+        }
+
+        tracing::info!(engine = "startpage", "blocked, refreshing the form token");
+        let fields = self.fetch_form().await?;
+        let html = startpage_search(query, &fields).await?;
+        if is_blocked(&Html::parse_document(&html)) {
+            *self.form.lock().unwrap() = None;
+            anyhow::bail!(RateLimited::new(BLOCKED));
+        }
+        parse_startpage_html(&html)
+    }
 
-    // Startpage search URL with English language
-    let url = format!(
-        "https://www.startpage.com/sp/search?q={}&language=english",
-        urlencoding::encode(query)
-    );
+    /// Fetch the home page's form fields and cache them for the session
+    async fn fetch_form(&self) -> Result<Vec<(String, String)>> {
+        let html = fetch_home().await?;
+        let document = Html::parse_document(&html);
+        if is_blocked(&document) {
+            anyhow::bail!(RateLimited::new(BLOCKED));
+        }
+        let fields = hidden_inputs(&document).unwrap_or_else(|| {
+            tracing::warn!(engine = "startpage", "no search form on the home page");
+            Vec::new()
+        });
+        *self.form.lock().unwrap() = Some(fields.clone());
+        Ok(fields)
+    }
+}
 
+/// Download the home page, for its search form
+async fn fetch_home() -> Result<String> {
+    tracing::debug!(engine = "startpage", url = HOME_URL, "GET");
+    let response = get_scrape_client()?
+        .get(HOME_URL)
+        .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
+        .header("Accept-Language", "en-US,en;q=0.9")
+        .timeout(std::time::Duration::from_secs(15))
+        .send()
+        .await
+        .context("Failed to load the Startpage home page")?;
+    if !response.status().is_success() {
+        anyhow::bail!("Startpage returned status: {}", response.status());
+    }
+    response
+        .text()
+        .await
+        .context("Failed to read the Startpage home page")
+}
+
+/// Submit `query` with the search form's `fields`, returning the page's HTML
+pub async fn startpage_search(query: &str, fields: &[(String, String)]) -> Result<String> {
+    let client = get_scrape_client()?;
+
+    // The form's own fields, then the query, in English unless it sets one
+    let mut form: Vec<(&str, &str)> = fields
+        .iter()
+        .filter(|(name, _)| name != "query")
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    form.push(("query", query));
+    if !form.iter().any(|(name, _)| *name == "language") {
+        form.push(("language", "english"));
+    }
+
+    tracing::debug!(engine = "startpage", url = SEARCH_URL, fields = form.len(), "POST");
     let response = client
-        .get(&url)
+        .post(SEARCH_URL)
+        .form(&form)
+        .header("Referer", HOME_URL)
         .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
         .header("Accept-Language", "en-US,en;q=0.9")
         .header("Accept-Encoding", "gzip, deflate, br")
@@ -47,6 +156,7 @@ pub async fn startpage_search(query: &str) -> Result<Vec<SearchResult>> {
         .await
         .context("Failed to send search request to Startpage")?;
 
+    tracing::debug!(engine = "startpage", status = %response.status(), "response");
     if !response.status().is_success() {
         anyhow::bail!("Startpage returned status: {}", response.status());
     }
@@ -55,29 +165,62 @@ pub async fn startpage_search(query: &str) -> Result<Vec<SearchResult>> {
         .text()
         .await
         .context("Failed to read Startpage response")?;
+    tracing::debug!(engine = "startpage", bytes = html.len(), "body read");
 
-    parse_startpage_html(&html)
+    Ok(html)
+}
+
+/// Names and values of the search form's hidden inputs
+///
+/// `None` when the page has no search form.
+fn hidden_inputs(document: &Html) -> Option<Vec<(String, String)>> {
+    let form_selector = Selector::parse("form[action*='/sp/search'], form#search").ok()?;
+    let input_selector = Selector::parse("input[type='hidden'][name]").ok()?;
+    let form = document.select(&form_selector).next()?;
+    let fields = form
+        .select(&input_selector)
+        .filter_map(|input| {
+            let name = input.value().attr("name")?;
+            let value = input.value().attr("value").unwrap_or_default();
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect();
+    Some(fields)
 }
 
+/// Whether Startpage answered with its CAPTCHA page instead of results
+fn is_blocked(document: &Html) -> bool {
+    let Ok(selector) = Selector::parse("form[action*='captcha'], #captcha, [class*='captcha']")
+    else {
+        return false;
+    };
+    document.select(&selector).next().is_some()
+}
+
+/// A way of finding the results on a page, `None` when it finds none
+type Strategy = fn(&Html) -> Option<Vec<SearchResult>>;
+
 /// Parse Startpage HTML results page using multiple strategies
 fn parse_startpage_html(html: &str) -> Result<Vec<SearchResult>> {
     let document = Html::parse_document(html);
 
     // Try strategies in order of reliability
-    let strategies: Vec<Box<dyn Fn(&Html) -> Option<Vec<SearchResult>>>> = vec![
-        Box::new(strategy_structured_results),
-        Box::new(strategy_link_clustering),
-        Box::new(strategy_generic_links),
+    let strategies: [Strategy; 3] = [
+        strategy_structured_results,
+        strategy_link_clustering,
+        strategy_generic_links,
     ];
 
-    for (_idx, strategy) in strategies.iter().enumerate() {
-        if let Some(results) = strategy(&document) {
-            if !results.is_empty() {
-                return Ok(results);
-            }
+    for (idx, strategy) in strategies.iter().enumerate() {
+        if let Some(results) = strategy(&document)
+            && !results.is_empty()
+        {
+            tracing::info!(strategy = idx + 1, results = results.len(), "Startpage parsing strategy succeeded");
+            return Ok(results);
         }
     }
 
+    tracing::warn!(bytes = html.len(), "all Startpage parsing strategies failed");
     anyhow::bail!(
         "All parsing strategies failed. Startpage's HTML structure may have changed significantly."
     )
@@ -141,6 +284,7 @@ fn extract_from_containers(containers: &[ElementRef]) -> Vec<SearchResult> {
                 title,
                 url,
                 description,
+                ..Default::default()
             });
 
             if results.len() >= MAX_RESULTS {
@@ -162,12 +306,11 @@ fn find_title_link(container: &ElementRef) -> Option<(String, String)> {
     ];
 
     for pattern in heading_link_patterns {
-        if let Ok(selector) = Selector::parse(pattern) {
-            if let Some(link_elem) = container.select(&selector).next() {
-                if let Some((title, url)) = extract_title_url(link_elem) {
-                    return Some((title, url));
-                }
-            }
+        if let Ok(selector) = Selector::parse(pattern)
+            && let Some(link_elem) = container.select(&selector).next()
+            && let Some((title, url)) = extract_title_url(link_elem)
+        {
+            return Some((title, url));
         }
     }
 
@@ -180,22 +323,20 @@ fn find_title_link(container: &ElementRef) -> Option<(String, String)> {
     ];
 
     for pattern in class_link_patterns {
-        if let Ok(selector) = Selector::parse(pattern) {
-            if let Some(link_elem) = container.select(&selector).next() {
-                if let Some((title, url)) = extract_title_url(link_elem) {
-                    return Some((title, url));
-                }
-            }
+        if let Ok(selector) = Selector::parse(pattern)
+            && let Some(link_elem) = container.select(&selector).next()
+            && let Some((title, url)) = extract_title_url(link_elem)
+        {
+            return Some((title, url));
         }
     }
 
     // Strategy 3: Any http link (least reliable)
-    if let Ok(selector) = Selector::parse("a[href^='http']") {
-        if let Some(link_elem) = container.select(&selector).next() {
-            if let Some((title, url)) = extract_title_url(link_elem) {
-                return Some((title, url));
-            }
-        }
+    if let Ok(selector) = Selector::parse("a[href^='http']")
+        && let Some(link_elem) = container.select(&selector).next()
+        && let Some((title, url)) = extract_title_url(link_elem)
+    {
+        return Some((title, url));
     }
 
     None
@@ -218,12 +359,12 @@ fn extract_title_url(link_elem: ElementRef) -> Option<(String, String)> {
     let mut title = String::new();
 
     // Try parent heading first
-    if let Some(parent) = link_elem.parent() {
-        if let Some(parent_elem) = ElementRef::wrap(parent) {
-            let tag_name = parent_elem.value().name();
-            if tag_name == "h1" || tag_name == "h2" || tag_name == "h3" {
-                title = extract_clean_text(&parent_elem);
-            }
+    if let Some(parent) = link_elem.parent()
+        && let Some(parent_elem) = ElementRef::wrap(parent)
+    {
+        let tag_name = parent_elem.value().name();
+        if tag_name == "h1" || tag_name == "h2" || tag_name == "h3" {
+            title = extract_clean_text(&parent_elem);
         }
     }
 
@@ -233,10 +374,10 @@ fn extract_title_url(link_elem: ElementRef) -> Option<(String, String)> {
     }
 
     // Last resort: title attribute
-    if title.is_empty() || title.len() < MIN_TITLE_LENGTH {
-        if let Some(title_attr) = link_elem.value().attr("title") {
-            title = title_attr.trim().to_string();
-        }
+    if (title.is_empty() || title.len() < MIN_TITLE_LENGTH)
+        && let Some(title_attr) = link_elem.value().attr("title")
+    {
+        title = title_attr.trim().to_string();
     }
 
     // Final validation
@@ -252,14 +393,19 @@ fn extract_clean_text(elem: &ElementRef) -> String {
     let text = elem
         .descendants()
         .filter_map(|node| {
-            // Skip script, style, noscript tags
-            if let Some(element) = ElementRef::wrap(node) {
-                let tag_name = element.value().name();
-                if tag_name == "script" || tag_name == "style" || tag_name == "noscript" {
-                    return None;
-                }
+            // Skip text inside script, style, noscript tags
+            let hidden = node
+                .ancestors()
+                .take_while(|ancestor| ancestor.id() != elem.id())
+                .any(|ancestor| {
+                    ElementRef::wrap(ancestor).is_some_and(|element| {
+                        matches!(element.value().name(), "script" | "style" | "noscript")
+                    })
+                });
+            if hidden {
+                return None;
             }
-            
+
             // Get text nodes
             node.value().as_text().map(|t| t.text.as_ref())
         })
@@ -286,12 +432,12 @@ fn find_description(container: &ElementRef) -> Option<String> {
     ];
 
     for pattern in desc_patterns {
-        if let Ok(selector) = Selector::parse(pattern) {
-            if let Some(desc_elem) = container.select(&selector).next() {
-                let text = extract_clean_text(&desc_elem);
-                if !text.is_empty() && text.len() > 10 {
-                    return Some(text);
-                }
+        if let Ok(selector) = Selector::parse(pattern)
+            && let Some(desc_elem) = container.select(&selector).next()
+        {
+            let text = extract_clean_text(&desc_elem);
+            if !text.is_empty() && text.len() > 10 {
+                return Some(text);
             }
         }
     }
@@ -382,6 +528,7 @@ fn extract_from_link_group(links: &[ElementRef]) -> Option<Vec<SearchResult>> {
             title,
             url,
             description,
+            ..Default::default()
         });
 
         if results.len() >= MAX_RESULTS {
@@ -400,23 +547,23 @@ fn extract_from_link_group(links: &[ElementRef]) -> Option<Vec<SearchResult>> {
 fn find_nearby_description(link: &ElementRef) -> Option<String> {
     // Try parent's next sibling
     if let Some(parent) = link.parent() {
-        if let Some(next_sib) = parent.next_sibling() {
-            if let Some(elem) = ElementRef::wrap(next_sib) {
-                let text = extract_clean_text(&elem);
-                if text.len() > 20 && text.len() < 500 {
-                    return Some(text);
-                }
+        if let Some(next_sib) = parent.next_sibling()
+            && let Some(elem) = ElementRef::wrap(next_sib)
+        {
+            let text = extract_clean_text(&elem);
+            if text.len() > 20 && text.len() < 500 {
+                return Some(text);
             }
         }
 
         // Try to find <p> in parent
-        if let Some(parent_elem) = ElementRef::wrap(parent) {
-            if let Ok(p_sel) = Selector::parse("p") {
-                for p in parent_elem.select(&p_sel) {
-                    let text = extract_clean_text(&p);
-                    if text.len() > 20 && text.len() < 500 {
-                        return Some(text);
-                    }
+        if let Some(parent_elem) = ElementRef::wrap(parent)
+            && let Ok(p_sel) = Selector::parse("p")
+        {
+            for p in parent_elem.select(&p_sel) {
+                let text = extract_clean_text(&p);
+                if text.len() > 20 && text.len() < 500 {
+                    return Some(text);
                 }
             }
         }
@@ -460,6 +607,7 @@ fn strategy_generic_links(document: &Html) -> Option<Vec<SearchResult>> {
             title,
             url,
             description: "No description available".to_string(),
+            ..Default::default()
         });
 
         if results.len() >= MAX_RESULTS {
@@ -467,7 +615,8 @@ fn strategy_generic_links(document: &Html) -> Option<Vec<SearchResult>> {
         }
     }
 
-    if results.len() >= 3 {
+    // Two results, like the structured strategy asks for
+    if results.len() >= 2 {
         Some(results)
     } else {
         None
@@ -672,16 +821,16 @@ mod tests {
         
         let doc = Html::parse_document(html);
         
-        if let Ok(container_sel) = Selector::parse(".result") {
-            if let Some(container) = doc.select(&container_sel).next() {
-                let title_link = find_title_link(&container);
-                
-                assert!(title_link.is_some());
-                let (title, _) = title_link.unwrap();
-                
-                // Should prefer heading-wrapped link
-                assert_eq!(title, "Correct Title from H2");
-            }
+        if let Ok(container_sel) = Selector::parse(".result")
+            && let Some(container) = doc.select(&container_sel).next()
+        {
+            let title_link = find_title_link(&container);
+
+            assert!(title_link.is_some());
+            let (title, _) = title_link.unwrap();
+
+            // Should prefer heading-wrapped link
+            assert_eq!(title, "Correct Title from H2");
         }
     }
 
@@ -731,18 +880,71 @@ mod tests {
         "#;
         
         let doc = Html::parse_document(html);
-        if let Ok(sel) = Selector::parse("div") {
-            if let Some(elem) = doc.select(&sel).next() {
-                let text = extract_clean_text(&elem);
-                
-                // Should not contain CSS, script, or noscript content
-                assert!(!text.contains("display"));
-                assert!(!text.contains("alert"));
-                assert!(!text.contains("No JS"));
-                
-                // Should contain actual text
-                assert!(text.contains("Good Title Text"));
-            }
+        if let Ok(sel) = Selector::parse("div")
+            && let Some(elem) = doc.select(&sel).next()
+        {
+            let text = extract_clean_text(&elem);
+
+            // Should not contain CSS, script, or noscript content
+            assert!(!text.contains("display"));
+            assert!(!text.contains("alert"));
+            assert!(!text.contains("No JS"));
+
+            // Should contain actual text
+            assert!(text.contains("Good Title Text"));
         }
     }
+
+    #[test]
+    fn test_hidden_inputs_from_home_page() {
+        let html = r#"<!DOCTYPE html>
+<html lang="en">
+<head><title>Startpage - Private Search Engine</title></head>
+<body>
+  <form id="settings" action="/do/settings" method="post">
+    <input type="hidden" name="prfe" value="abc">
+  </form>
+  <form id="search" class="search-form" action="/sp/search" method="post">
+    <input type="text" name="query" placeholder="Search privately" autocomplete="off">
+    <input type="hidden" name="t" value="device">
+    <input type="hidden" name="lui" value="english">
+    <input type="hidden" name="sc" value="Xk2pQ7vR0aBc20">
+    <input type="hidden" name="cat" value="web">
+    <input type="hidden" name="abp">
+    <button type="submit" class="search-btn">Search</button>
+  </form>
+</body>
+</html>"#;
+        let fields = hidden_inputs(&Html::parse_document(html)).unwrap();
+        let pairs: Vec<(&str, &str)> =
+            fields.iter().map(|(n, v)| (n.as_str(), v.as_str())).collect();
+        assert_eq!(
+            pairs,
+            [
+                ("t", "device"),
+                ("lui", "english"),
+                ("sc", "Xk2pQ7vR0aBc20"),
+                ("cat", "web"),
+                ("abp", ""),
+            ]
+        );
+        assert!(!is_blocked(&Html::parse_document(html)));
+
+        let no_form = Html::parse_document("<html><body><p>Maintenance</p></body></html>");
+        assert_eq!(hidden_inputs(&no_form), None);
+    }
+
+    #[test]
+    fn test_detects_captcha_page() {
+        let html = r#"<html><body>
+            <div class="main">
+              <h1>Please verify you are a human</h1>
+              <form action="/sp/captcha/verify" method="post">
+                <div class="g-recaptcha" data-sitekey="6Lc"></div>
+              </form>
+            </div>
+        </body></html>"#;
+        assert!(is_blocked(&Html::parse_document(html)));
+        assert!(parse_startpage_html(html).is_err());
+    }
 }
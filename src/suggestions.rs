@@ -0,0 +1,85 @@
+//! Corrected and suggested queries from the engine
+//!
+//! Brave searches the corrected spelling of a query and says so, and
+//! SearXNG proposes other queries ([`SearchMeta`]). A line under the search
+//! box shows which; Ctrl+Y searches the query as typed after a correction,
+//! or picks one of the suggestions from a menu. A picked suggestion keeps
+//! the `-site:` and `+site:` operators of the query.
+//!
+//! [`SearchMeta`]: crate::search::SearchMeta
+
+use crate::query_filters::QueryFilters;
+
+/// Suggestion picker over the results list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestionMenu {
+    pub queries: Vec<String>,
+    pub selected: usize,
+}
+
+impl SuggestionMenu {
+    pub fn new(queries: Vec<String>) -> Self {
+        Self {
+            queries,
+            selected: 0,
+        }
+    }
+
+    /// Move the selection down (wraps)
+    pub fn next(&mut self) {
+        if !self.queries.is_empty() {
+            self.selected = (self.selected + 1) % self.queries.len();
+        }
+    }
+
+    /// Move the selection up (wraps)
+    pub fn previous(&mut self) {
+        let count = self.queries.len();
+        if count > 0 {
+            self.selected = (self.selected + count - 1) % count;
+        }
+    }
+
+    /// The selected suggestion
+    pub fn selected_query(&self) -> Option<&str> {
+        self.queries.get(self.selected).map(String::as_str)
+    }
+}
+
+/// `suggestion` with the site operators of `query`, the search it replaces
+pub fn with_operators(suggestion: &str, query: &str) -> String {
+    let filters = QueryFilters::parse(query);
+    let operators = filters
+        .excluded
+        .iter()
+        .map(|host| format!("-site:{}", host))
+        .chain(filters.preferred.iter().map(|host| format!("+site:{}", host)));
+    std::iter::once(suggestion.trim().to_string())
+        .chain(operators)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions_keep_site_operators() {
+        let queries = vec!["tokio runtime".to_string(), "tokio rt".to_string()];
+        let mut menu = SuggestionMenu::new(queries);
+        assert_eq!(menu.selected_query(), Some("tokio runtime"));
+        menu.previous();
+        assert_eq!(menu.selected_query(), Some("tokio rt"));
+        menu.next();
+        assert_eq!(menu.selected_query(), Some("tokio runtime"));
+
+        let query = "tokio runtiem -site:www.pinterest.com +site:docs.rs";
+        assert_eq!(
+            with_operators("tokio runtime", query),
+            "tokio runtime -site:pinterest.com +site:docs.rs"
+        );
+        assert_eq!(with_operators(" tokio runtime ", "tokio runtiem"), "tokio runtime");
+        assert_eq!(SuggestionMenu::new(Vec::new()).selected_query(), None);
+    }
+}
@@ -0,0 +1,96 @@
+//! Switching the terminal between the TUI and normal mode
+//!
+//! The TUI runs in raw mode on the alternate screen with mouse capture and
+//! bracketed paste. Programs started in the foreground, like the editor,
+//! need all of that turned off, and turned back on however they end. The
+//! same goes for exiting, including by panic.
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io;
+
+/// Switch the terminal to TUI mode
+pub fn enter() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )
+}
+
+/// Switch the terminal back to normal mode
+pub fn leave() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        Show
+    )
+}
+
+/// TUI mode for as long as the guard lives
+///
+/// Dropping it leaves TUI mode, so early returns and panics in the main
+/// loop still restore the terminal.
+pub struct Tui(());
+
+impl Tui {
+    pub fn enter() -> io::Result<Self> {
+        let guard = Tui(());
+        enter()?;
+        Ok(guard)
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = leave();
+    }
+}
+
+/// Restore the terminal before a panic on the main thread is printed
+///
+/// Panics on other threads only fail their background task; they are
+/// logged instead of printed over the TUI.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() == Some("main") {
+            let _ = leave();
+            default_hook(info);
+        } else {
+            tracing::error!(panic = %info, "background task panicked");
+        }
+    }));
+}
+
+/// Normal mode for as long as the guard lives
+///
+/// Dropping it re-enters TUI mode, also when the code in between fails or
+/// panics, so the screen is never left half torn down.
+pub struct Suspended(());
+
+impl Suspended {
+    pub fn new() -> io::Result<Self> {
+        // Created first so a failure halfway through leaving is undone too
+        let guard = Suspended(());
+        leave()?;
+        Ok(guard)
+    }
+}
+
+impl Drop for Suspended {
+    fn drop(&mut self) {
+        if let Err(e) = enter() {
+            tracing::error!(error = %e, "failed to restore the terminal");
+        }
+    }
+}
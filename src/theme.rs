@@ -0,0 +1,409 @@
+//! Colors and icons of the terminal UI
+//!
+//! The `[theme]` section of `config.toml` picks a preset and overrides
+//! single colors. Colors are names (`blue`, `lightred`, `darkgray`), ANSI
+//! indexes (`"245"`) or hex (`"#202030"`):
+//!
+//! ```toml
+//! [theme]
+//! preset = "light"        # dark, light, mono
+//! ascii_icons = true
+//! selection_bg = "#dde4f0"
+//! url = "blue"
+//! ```
+//!
+//! `ascii_icons` swaps the emoji for single ASCII characters, for the Linux
+//! console and fonts without emoji.
+
+use ratatui::style::{Color, Modifier, Style};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::config::ThemeConfig;
+
+/// Icons drawn in the UI
+#[derive(Debug, PartialEq)]
+pub struct Icons {
+    pub ready: &'static str,
+    pub cached: &'static str,
+    pub pdf: &'static str,
+    pub archived: &'static str,
+    pub pinned: &'static str,
+    pub loading: &'static str,
+    pub failed: &'static str,
+    /// Pages that answered 404 or 410
+    pub gone: &'static str,
+    /// Hosts that didn't resolve or refused the connection
+    pub unreachable: &'static str,
+    pub blocked: &'static str,
+    pub timeout: &'static str,
+    pub pending: &'static str,
+    /// Pages cut at `extraction.max_markdown_kb`
+    pub truncated: &'static str,
+    /// Marks results selected with Tab
+    pub marked: &'static str,
+    /// Marks results whose page was opened before
+    pub read: &'static str,
+    pub error: &'static str,
+    /// Panel title prefixes, empty in ASCII mode
+    pub search: &'static str,
+    pub filter: &'static str,
+    pub library: &'static str,
+    pub results: &'static str,
+    /// Columns taken by `pinned`, to pad unpinned rows
+    pub width: usize,
+    pub spinner: &'static [char],
+}
+
+const EMOJI: Icons = Icons {
+    ready: "✓",
+    cached: "📄",
+    pdf: "📕",
+    archived: "🏛",
+    pinned: "📌",
+    loading: "⏳",
+    failed: "⚠",
+    gone: "✗",
+    unreachable: "🔌",
+    blocked: "🚧",
+    timeout: "⏱",
+    pending: "○",
+    truncated: "✂",
+    marked: "✓",
+    read: "✔",
+    error: "❌",
+    search: "🔍 ",
+    filter: "🔎 ",
+    library: "📚 ",
+    results: "📊 ",
+    width: 2,
+    spinner: &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'],
+};
+
+const ASCII: Icons = Icons {
+    ready: "+",
+    cached: "C",
+    pdf: "P",
+    archived: "A",
+    pinned: "*",
+    loading: "~",
+    failed: "!",
+    gone: "-",
+    unreachable: "N",
+    blocked: "#",
+    timeout: "T",
+    pending: "o",
+    truncated: "%",
+    marked: "x",
+    read: "r",
+    error: "X",
+    search: "",
+    filter: "",
+    library: "",
+    results: "",
+    width: 1,
+    spinner: &['|', '/', '-', '\\'],
+};
+
+/// Leading markers of status messages and their ASCII replacements
+const MESSAGE_MARKERS: &[(&str, &str)] = &[
+    ("⚠", "!"),
+    ("❌", "X"),
+    ("⏳", "~"),
+    ("✓", "+"),
+    ("📌", "*"),
+    ("🚧", "#"),
+    ("🧹", "-"),
+];
+
+impl Icons {
+    /// `message` with its leading emoji marker replaced in ASCII mode
+    pub fn message<'a>(&self, message: &'a str) -> Cow<'a, str> {
+        if *self == EMOJI {
+            return Cow::Borrowed(message);
+        }
+        MESSAGE_MARKERS
+            .iter()
+            .find_map(|(emoji, ascii)| {
+                message
+                    .strip_prefix(emoji)
+                    .map(|rest| Cow::Owned(format!("{}{}", ascii, rest)))
+            })
+            .unwrap_or(Cow::Borrowed(message))
+    }
+}
+
+/// Colors and icons, passed to every draw function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Background of the selected row; `reset` uses reverse video
+    pub selection_bg: Color,
+    /// Result and page titles
+    pub title: Color,
+    pub url: Color,
+    pub description: Color,
+    /// Panel titles
+    pub heading: Color,
+    /// Result numbers, box titles and other highlights
+    pub accent: Color,
+    /// Border of the focused panel
+    pub border: Color,
+    pub border_inactive: Color,
+    /// Typed text and plain status messages
+    pub text: Color,
+    /// Sizes, hints and other secondary text
+    pub muted: Color,
+    /// Help bar
+    pub help: Color,
+    pub ready: Color,
+    pub cached: Color,
+    pub pdf: Color,
+    pub archived: Color,
+    pub pinned: Color,
+    pub loading: Color,
+    pub failed: Color,
+    pub gone: Color,
+    pub unreachable: Color,
+    pub blocked: Color,
+    pub timeout: Color,
+    pub pending: Color,
+    pub icons: &'static Icons,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+impl Theme {
+    const DARK: Theme = Theme {
+        selection_bg: Color::Rgb(35, 35, 45),
+        title: Color::White,
+        url: Color::Blue,
+        description: Color::Gray,
+        heading: Color::Green,
+        accent: Color::Yellow,
+        border: Color::Cyan,
+        border_inactive: Color::Gray,
+        text: Color::White,
+        muted: Color::DarkGray,
+        help: Color::Cyan,
+        ready: Color::Green,
+        cached: Color::Blue,
+        pdf: Color::Magenta,
+        archived: Color::Cyan,
+        pinned: Color::Yellow,
+        loading: Color::Yellow,
+        failed: Color::Red,
+        gone: Color::DarkGray,
+        unreachable: Color::LightMagenta,
+        blocked: Color::LightRed,
+        timeout: Color::Red,
+        pending: Color::DarkGray,
+        icons: &EMOJI,
+    };
+
+    const LIGHT: Theme = Theme {
+        selection_bg: Color::Rgb(220, 226, 238),
+        title: Color::Black,
+        url: Color::Blue,
+        description: Color::DarkGray,
+        heading: Color::Rgb(0, 120, 40),
+        accent: Color::Rgb(170, 100, 0),
+        border: Color::Blue,
+        border_inactive: Color::DarkGray,
+        text: Color::Black,
+        muted: Color::DarkGray,
+        help: Color::Blue,
+        ready: Color::Rgb(0, 120, 40),
+        cached: Color::Blue,
+        pdf: Color::Magenta,
+        archived: Color::Rgb(0, 120, 140),
+        pinned: Color::Rgb(170, 100, 0),
+        loading: Color::Rgb(170, 100, 0),
+        failed: Color::Red,
+        gone: Color::DarkGray,
+        unreachable: Color::Magenta,
+        blocked: Color::Red,
+        timeout: Color::Red,
+        pending: Color::DarkGray,
+        icons: &EMOJI,
+    };
+
+    const MONO: Theme = Theme {
+        selection_bg: Color::Reset,
+        title: Color::Reset,
+        url: Color::Reset,
+        description: Color::Reset,
+        heading: Color::Reset,
+        accent: Color::Reset,
+        border: Color::Reset,
+        border_inactive: Color::Reset,
+        text: Color::Reset,
+        muted: Color::Reset,
+        help: Color::Reset,
+        ready: Color::Reset,
+        cached: Color::Reset,
+        pdf: Color::Reset,
+        archived: Color::Reset,
+        pinned: Color::Reset,
+        loading: Color::Reset,
+        failed: Color::Reset,
+        gone: Color::Reset,
+        unreachable: Color::Reset,
+        blocked: Color::Reset,
+        timeout: Color::Reset,
+        pending: Color::Reset,
+        icons: &EMOJI,
+    };
+
+    /// Build the theme from the `[theme]` section
+    ///
+    /// Errors are `(key, problem)` like `("theme.url", "...")`.
+    pub fn from_config(config: &ThemeConfig) -> Result<Self, (String, String)> {
+        let mut theme = match config.preset.as_str() {
+            "dark" => Self::DARK,
+            "light" => Self::LIGHT,
+            "mono" => Self::MONO,
+            other => {
+                return Err((
+                    "theme.preset".to_string(),
+                    format!("must be dark, light or mono, got {:?}", other),
+                ));
+            }
+        };
+        if config.ascii_icons {
+            theme.icons = &ASCII;
+        }
+        theme.apply(&config.colors)?;
+        Ok(theme)
+    }
+
+    fn apply(&mut self, colors: &HashMap<String, String>) -> Result<(), (String, String)> {
+        // Sorted so the reported problem doesn't depend on hash order
+        let mut colors: Vec<_> = colors.iter().collect();
+        colors.sort();
+        for (element, value) in colors {
+            let key = format!("theme.{}", element);
+            let Some(slot) = self.color_mut(element) else {
+                return Err((key, "is not a theme element".to_string()));
+            };
+            *slot = Color::from_str(value.trim())
+                .map_err(|_| (key, format!("is not a color: {:?}", value)))?;
+        }
+        Ok(())
+    }
+
+    fn color_mut(&mut self, element: &str) -> Option<&mut Color> {
+        Some(match element {
+            "selection_bg" => &mut self.selection_bg,
+            "title" => &mut self.title,
+            "url" => &mut self.url,
+            "description" => &mut self.description,
+            "heading" => &mut self.heading,
+            "accent" => &mut self.accent,
+            "border" => &mut self.border,
+            "border_inactive" => &mut self.border_inactive,
+            "text" => &mut self.text,
+            "muted" => &mut self.muted,
+            "help" => &mut self.help,
+            "ready" => &mut self.ready,
+            "cached" => &mut self.cached,
+            "pdf" => &mut self.pdf,
+            "archived" => &mut self.archived,
+            "pinned" => &mut self.pinned,
+            "loading" => &mut self.loading,
+            "failed" => &mut self.failed,
+            "gone" => &mut self.gone,
+            "unreachable" => &mut self.unreachable,
+            "blocked" => &mut self.blocked,
+            "timeout" => &mut self.timeout,
+            "pending" => &mut self.pending,
+            _ => return None,
+        })
+    }
+
+    /// Foreground `color`
+    pub fn fg(&self, color: Color) -> Style {
+        Style::default().fg(color)
+    }
+
+    /// Bold foreground `color`
+    pub fn bold(&self, color: Color) -> Style {
+        self.fg(color).add_modifier(Modifier::BOLD)
+    }
+
+    /// Border of a panel
+    pub fn border(&self, focused: bool) -> Style {
+        self.fg(if focused {
+            self.border
+        } else {
+            self.border_inactive
+        })
+    }
+
+    /// The selected row
+    pub fn selection(&self) -> Style {
+        if self.selection_bg == Color::Reset {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().bg(self.selection_bg)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme(
+        preset: &str,
+        ascii_icons: bool,
+        colors: &[(&str, &str)],
+    ) -> Result<Theme, (String, String)> {
+        Theme::from_config(&ThemeConfig {
+            preset: preset.to_string(),
+            ascii_icons,
+            colors: colors
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn test_presets_and_overrides() {
+        assert_eq!(theme("dark", false, &[]).unwrap(), Theme::default());
+        assert_eq!(
+            theme("mono", false, &[]).unwrap().selection(),
+            Style::default().add_modifier(Modifier::REVERSED)
+        );
+
+        let overrides = [("url", "lightblue"), ("selection_bg", "#102030")];
+        let light = theme("light", true, &overrides).unwrap();
+        assert_eq!(light.url, Color::LightBlue);
+        assert_eq!(light.selection_bg, Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(light.title, Color::Black);
+        assert_eq!(light.icons.cached, "C");
+
+        assert_eq!(theme("solarized", false, &[]).unwrap_err().0, "theme.preset");
+        assert_eq!(
+            theme("dark", false, &[("links", "blue")]).unwrap_err(),
+            ("theme.links".to_string(), "is not a theme element".to_string())
+        );
+        assert_eq!(
+            theme("dark", false, &[("url", "bleu")]).unwrap_err(),
+            ("theme.url".to_string(), "is not a color: \"bleu\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ascii_message_markers() {
+        assert_eq!(ASCII.message("⚠ Page timed out"), "! Page timed out");
+        assert_eq!(ASCII.message("📌 Pinned page"), "* Pinned page");
+        assert_eq!(ASCII.message("Copied URL"), "Copied URL");
+        assert_eq!(EMOJI.message("⚠ Page timed out"), "⚠ Page timed out");
+    }
+}
@@ -0,0 +1,172 @@
+//! Table of contents of long pages
+//!
+//! With `extraction.table_of_contents` on, a page with more than four `##`
+//! and `###` headings gets a "Contents" section after its metadata, with a
+//! nested list of links to the headings. Anchors are made the way GitHub
+//! makes them, which markdown previews and Neovim plugins follow:
+//! lowercase, spaces turned into dashes, punctuation dropped, and `-1`,
+//! `-2` added to repeated headings.
+
+use std::collections::HashSet;
+
+use crate::extract_clean_md::rewrite_links;
+use crate::outline::parse_heading;
+
+/// Pages with this many headings or fewer get no table of contents
+const MIN_HEADINGS: usize = 5;
+
+/// Heading of the table of contents
+const CONTENTS: &str = "Contents";
+
+/// Anchors of a document's headings, with repeats numbered
+#[derive(Debug, Default)]
+struct Slugger {
+    taken: HashSet<String>,
+}
+
+impl Slugger {
+    /// Anchor of the next heading reading `text`
+    fn slug(&mut self, text: &str) -> String {
+        let base = slug(text);
+        let mut candidate = base.clone();
+        let mut n = 0;
+        while self.taken.contains(&candidate) {
+            n += 1;
+            candidate = format!("{}-{}", base, n);
+        }
+        self.taken.insert(candidate.clone());
+        candidate
+    }
+}
+
+/// GitHub's anchor for a heading reading `text`
+fn slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The "Contents" section for `markdown`, under a page titled `title`
+///
+/// `None` when the page has too few `##` and `###` headings.
+pub fn table_of_contents(title: &str, markdown: &str) -> Option<String> {
+    let headings = headings(markdown);
+    if headings.iter().filter(|(level, _)| *level > 0).count() < MIN_HEADINGS {
+        return None;
+    }
+
+    // The page title and the contents heading come first in the file
+    let mut slugger = Slugger::default();
+    slugger.slug(&heading_text(title));
+    slugger.slug(CONTENTS);
+
+    let mut toc = format!("## {}\n\n", CONTENTS);
+    for (level, text) in headings {
+        let anchor = slugger.slug(&text);
+        if level == 0 {
+            continue;
+        }
+        let indent = if level == 3 { "  " } else { "" };
+        let label = text.replace('[', "\\[").replace(']', "\\]");
+        toc.push_str(&format!("{}- [{}](#{})\n", indent, label, anchor));
+    }
+    toc.push('\n');
+    Some(toc)
+}
+
+/// Level and plain text of the headings outside code blocks
+///
+/// Only `##` and `###` are listed, but the other levels take anchors too,
+/// so they come back with level 0.
+fn headings(markdown: &str) -> Vec<(usize, String)> {
+    let mut headings = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+
+        let Some((level, text)) = parse_heading(line) else {
+            continue;
+        };
+        let text = heading_text(&text);
+        if !text.is_empty() {
+            headings.push((if matches!(level, 2 | 3) { level } else { 0 }, text));
+        }
+    }
+
+    headings
+}
+
+/// The text of a markdown heading as it reads when rendered
+fn heading_text(heading: &str) -> String {
+    let unlinked = rewrite_links(heading, |text, _, image| (!image).then(|| text.to_string()));
+    let mut text = String::with_capacity(unlinked.len());
+    let mut chars = unlinked.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.extend(chars.next()),
+            '*' | '`' => {}
+            c => text.push(c),
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toc_of_fixture_document() {
+        let markdown = "Intro paragraph.\n\n\
+                        ## Getting Started\n\nText.\n\n\
+                        ### Install `cargo-watch` (v8.x)\n\n\
+                        ```sh\n## not a heading\ncargo install cargo-watch\n```\n\n\
+                        ### Usage\n\n\
+                        ## What's new in [Rust 1.80](https://blog.rust-lang.org/)? ##\n\n\
+                        #### Details\n\n\
+                        ### Usage\n\n\
+                        ## Contents\n\n\
+                        ## Émigré *café* \\[beta\\]\n";
+        assert_eq!(
+            table_of_contents("Rust Guide", markdown).unwrap(),
+            "## Contents\n\n\
+             - [Getting Started](#getting-started)\n  \
+             - [Install cargo-watch (v8.x)](#install-cargo-watch-v8x)\n  \
+             - [Usage](#usage)\n\
+             - [What's new in Rust 1.80?](#whats-new-in-rust-180)\n  \
+             - [Usage](#usage-1)\n\
+             - [Contents](#contents-1)\n\
+             - [Émigré café \\[beta\\]](#émigré-café-beta)\n\n"
+        );
+    }
+
+    #[test]
+    fn test_short_pages_and_repeated_slugs() {
+        let short = "## One\n\n## Two\n\n### Three\n\n## Four\n\n#### Five\n";
+        assert_eq!(table_of_contents("Title", short), None);
+
+        let mut slugger = Slugger::default();
+        let slugs: Vec<_> = ["Intro", "Intro", "intro-1", "Intro", "  C++ & Rust  "]
+            .iter()
+            .map(|text| slugger.slug(text))
+            .collect();
+        assert_eq!(slugs, ["intro", "intro-1", "intro-1-1", "intro-2", "c--rust"]);
+    }
+}
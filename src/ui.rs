@@ -1,97 +1,374 @@
 //! Terminal UI using ratatui
 
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier},
     text::{Line, Span},
-    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Wrap},
+    widgets::{
+        Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
     Frame,
 };
-use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
 
-use crate::app::{App, AppState};
-use crate::prefetch::PrefetchStatus;
+use crate::app::{visible_item_count, App, AppState, SPINNER_INTERVAL};
+use crate::config::{Density, Engine};
+use crate::cooldown::countdown_secs;
+use crate::display_url::{display_host, display_url};
+use crate::export::format_utc;
+use crate::globals::proxy_active;
+use crate::goggles::GoggleMenu;
+use crate::input::InputLine;
+use crate::keymap::Action;
+use crate::library::{format_age, format_size};
+use crate::prefetch::{Failure, PageStats, PrefetchStatus};
+use crate::query_filters::QueryFilters;
+use crate::row_cache::row_key;
+use crate::search::{site_host, SearchResult};
+use crate::sort::SortMode;
+use crate::suggestions::SuggestionMenu;
+use crate::logging;
+use crate::open_with::OpenWithMenu;
+use crate::outline::Outline;
+use crate::theme::Theme;
 
 /// Draw the main UI
-pub fn draw_ui(
-    f: &mut Frame,
-    app: &App,
-    prefetch_progress: (usize, usize),
-    statuses: &HashMap<String, PrefetchStatus>,
-) {
+pub fn draw_ui(f: &mut Frame, app: &mut App) {
+    let theme = app.config.style;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Search input
             Constraint::Length(1), // Progress bar
             Constraint::Min(10),   // Results
+            Constraint::Length(1), // Status line
             Constraint::Length(4), // Help bar (increased for status legend)
         ])
         .split(f.area());
 
-    // Draw search input
-    draw_search_input(f, app, chunks[0]);
+    app.input_area = chunks[0];
+    app.results_area = chunks[2];
 
-    // Draw prefetch progress bar
-    draw_progress_bar(f, prefetch_progress, chunks[1]);
+    // Draw search input (the filter box in the Library)
+    if app.state == AppState::Library {
+        draw_library_filter(f, app, &theme, chunks[0]);
+    } else {
+        draw_search_input(f, app, &theme, chunks[0]);
+    }
+
+    // Draw prefetch progress bar (scan progress while searching cached pages)
+    match app.local_progress {
+        Some(progress) if app.state == AppState::LocalSearch => {
+            draw_scan_progress(f, progress, &theme, chunks[1]);
+        }
+        _ => draw_progress_bar(f, app.prefetch_progress(), &theme, chunks[1]),
+    }
 
     // Draw main content
     match app.state {
         AppState::Input | AppState::Results => {
-            draw_results(f, app, chunks[2], statuses);
+            draw_results(f, app, &theme, chunks[2]);
         }
         AppState::Searching => {
-            draw_searching(f, chunks[2]);
+            draw_searching(f, app, &theme, chunks[2]);
         }
         AppState::Error => {
-            draw_error(f, app, chunks[2]);
+            draw_error(f, app, &theme, chunks[2]);
+        }
+        AppState::Library => {
+            draw_library(f, app, &theme, chunks[2]);
+        }
+        AppState::Bookmarks => {
+            draw_bookmarks(f, app, &theme, chunks[2]);
+        }
+        AppState::LocalSearch => {
+            draw_local_search(f, app, &theme, chunks[2]);
         }
     }
 
+    // Draw status line (the note prompt while typing a note)
+    match &app.note_input {
+        Some((_, input)) => draw_note_prompt(f, input, &theme, chunks[3]),
+        None => draw_status_line(f, app, &theme, chunks[3]),
+    }
+
     // Draw help bar
-    draw_help_bar(f, app, chunks[3]);
+    draw_help_bar(f, app, &theme, chunks[4]);
+
+    if let Some(outline) = &app.outline {
+        draw_outline(f, outline, &theme, chunks[2]);
+    }
+    if let Some(menu) = &app.open_with {
+        draw_open_with(f, menu, &theme, chunks[2]);
+    }
+    if let Some(menu) = &app.goggle_menu {
+        draw_goggle_menu(f, menu, &theme, chunks[2]);
+    }
+    if let Some(menu) = &app.suggestion_menu {
+        draw_suggestion_menu(f, menu, &theme, chunks[2]);
+    }
+    if app.engine_status {
+        draw_engine_status(f, app, &theme, chunks[2]);
+    }
+    if app.preview
+        && app.state == AppState::Results
+        && let Some(result) = app.results.get(app.selected_index)
+    {
+        draw_preview(f, app, result, &theme, chunks[2]);
+    }
+    if !app.notices.is_empty() {
+        draw_notices(f, &app.notices, &theme, chunks[2]);
+    }
+
+    // Recoverable errors float over whatever is shown
+    if let Some((message, _)) = &app.error_popup {
+        draw_error_popup(f, message, &theme, chunks[2]);
+    }
 }
 
 /// Draw search input field
-fn draw_search_input(f: &mut Frame, app: &App, area: Rect) {
+fn draw_search_input(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
     let is_focused = app.state == AppState::Input;
 
     let style = if is_focused {
-        Style::default()
-            .fg(Color::Cyan)
-            .add_modifier(Modifier::BOLD)
+        theme.bold(theme.border)
     } else {
-        Style::default().fg(Color::Gray)
+        theme.fg(theme.border_inactive)
     };
 
-    let input = Paragraph::new(app.input.as_str()).style(style).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(Span::styled(
-                " 🔍 Search ",
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD),
-            ))
-            .border_style(if is_focused {
-                Style::default().fg(Color::Cyan)
-            } else {
-                Style::default().fg(Color::Gray)
-            }),
-    );
+    // Horizontally scroll long queries so the cursor stays in view
+    let inner_width = area.width.saturating_sub(2) as usize;
+    let (visible, cursor_col) = app.input.visible_window(inner_width);
+
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(
+            format!(" {}Search ", theme.icons.search),
+            theme.bold(theme.accent),
+        ))
+        .border_style(theme.border(is_focused));
+    if app.state == AppState::Results
+        && let Some(line) = suggestion_line(app, theme)
+    {
+        block = block.title_bottom(line);
+    }
+    let input = Paragraph::new(visible).style(style).block(block);
 
     f.render_widget(input, area);
 
     if is_focused {
-        f.set_cursor_position((
-            area.x + app.cursor_pos as u16 + 1,
-            area.y + 1
-        ));
+        f.set_cursor_position((area.x + cursor_col as u16 + 1, area.y + 1));
+    }
+}
+
+/// The correction or suggestions of the engine, under the search box
+///
+/// Like "Showing results for *tokio runtime* — Ctrl+Y: search *tokio
+/// runtiem* instead".
+fn suggestion_line(app: &App, theme: &Theme) -> Option<Line<'static>> {
+    let meta = app.search_meta.as_ref()?;
+    let key = app.config.keymap.label(Action::Suggestions);
+    let text = theme.fg(theme.accent);
+    let query = theme.fg(theme.title).add_modifier(Modifier::ITALIC);
+    let spans = match &meta.altered {
+        Some(altered) => {
+            let typed = app.last_search.as_ref().map_or("", |(query, _)| query.as_str());
+            vec![
+                Span::styled(" Showing results for ", text),
+                Span::styled(altered.clone(), query),
+                Span::styled(format!(" — {}: search ", key), text),
+                Span::styled(typed.to_string(), query),
+                Span::styled(" instead ", text),
+            ]
+        }
+        None => {
+            let first = meta.suggestions.first()?;
+            let mut spans = vec![
+                Span::styled(" Did you mean ", text),
+                Span::styled(first.clone(), query),
+            ];
+            let others = meta.suggestions.len() - 1;
+            if others > 0 {
+                spans.push(Span::styled(format!(" or {} more", others), text));
+            }
+            spans.push(Span::styled(format!("? {}: pick one ", key), text));
+            spans
+        }
+    };
+    Some(Line::from(spans))
+}
+
+/// Draw the Library filter box
+fn draw_library_filter(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let inner_width = area.width.saturating_sub(2) as usize;
+    let (visible, cursor_col) = app.library_filter.visible_window(inner_width);
+
+    let input = Paragraph::new(visible)
+        .style(theme.fg(theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(
+                    format!(" {}Filter ", theme.icons.filter),
+                    theme.bold(theme.accent),
+                ))
+                .border_style(theme.border(app.library_filtering)),
+        );
+
+    f.render_widget(input, area);
+
+    if app.library_filtering {
+        f.set_cursor_position((area.x + cursor_col as u16 + 1, area.y + 1));
+    }
+}
+
+/// Draw the Library of cached pages
+fn draw_library(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let matches = app.library_matches();
+    let now = std::time::SystemTime::now();
+
+    let items: Vec<ListItem> = matches
+        .iter()
+        .map(|&i| {
+            let entry = &app.library[i];
+            let age = now.duration_since(entry.modified).unwrap_or_default();
+
+            let pin = if app.prefetch_manager.is_pinned(&entry.path) {
+                Span::styled(theme.icons.pinned, theme.fg(theme.pinned))
+            } else {
+                Span::raw(" ".repeat(theme.icons.width))
+            };
+
+            ListItem::new(Line::from(vec![
+                pin,
+                Span::styled(format!("{:>4} ", format_age(age)), theme.fg(theme.accent)),
+                Span::styled(format!("{:>9} ", format_size(entry.size)), theme.fg(theme.muted)),
+                Span::styled(truncate(entry.title(), 70), theme.bold(theme.title)),
+                Span::raw("  "),
+                Span::styled(entry.domain(), theme.fg(theme.url)),
+            ]))
+        })
+        .collect();
+
+    let icon = theme.icons.library;
+    let title = if matches.len() == app.library.len() {
+        format!(" {}Library ({}) ", icon, app.library.len())
+    } else {
+        format!(" {}Library ({}/{}) ", icon, matches.len(), app.library.len())
+    };
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(title, theme.bold(theme.heading)))
+        .border_style(theme.border(true));
+
+    if items.is_empty() {
+        let message = if app.library.is_empty() {
+            "No cached pages yet"
+        } else {
+            "No pages match the filter"
+        };
+        let paragraph = Paragraph::new(message)
+            .style(theme.fg(theme.description))
+            .block(block);
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(theme.selection());
+    let mut list_state = ListState::default().with_selected(Some(app.library_selected));
+    f.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// Draw the cached pages containing the query
+fn draw_local_search(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let items: Vec<ListItem> = app
+        .local_matches
+        .iter()
+        .map(|found| {
+            let filename = found
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            ListItem::new(vec![
+                Line::from(vec![
+                    Span::styled(truncate(&found.title, 70), theme.bold(theme.title)),
+                    Span::styled(
+                        format!("  {}:{}", filename, found.line),
+                        theme.fg(theme.muted),
+                    ),
+                ]),
+                Line::from(Span::styled(
+                    format!("  {}", found.snippet),
+                    theme.fg(theme.description),
+                )),
+            ])
+        })
+        .collect();
+
+    let title = if app.local_progress.is_some() {
+        format!(" {}Cached pages with \"{}\" (searching…) ", theme.icons.search, app.local_query)
+    } else {
+        format!(
+            " {}Cached pages with \"{}\" ({}) ",
+            theme.icons.search,
+            app.local_query,
+            app.local_matches.len()
+        )
+    };
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(title, theme.bold(theme.heading)))
+        .border_style(theme.border(true));
+
+    if items.is_empty() {
+        let message = if app.local_progress.is_some() {
+            "Searching..."
+        } else {
+            "No cached page contains all of these words"
+        };
+        let paragraph = Paragraph::new(message)
+            .style(theme.fg(theme.description))
+            .block(block);
+        f.render_widget(paragraph, area);
+        return;
     }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(theme.selection());
+    let mut list_state = ListState::default().with_selected(Some(app.local_selected));
+    f.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// Draw how far a search of the cached pages got
+fn draw_scan_progress(f: &mut Frame, progress: (usize, usize), theme: &Theme, area: Rect) {
+    let (scanned, total) = progress;
+    let ratio = if total > 0 {
+        scanned as f64 / total as f64
+    } else {
+        0.0
+    };
+
+    let gauge = Gauge::default()
+        .gauge_style(theme.fg(theme.loading))
+        .ratio(ratio)
+        .label(Span::styled(
+            format!("Searching cached pages: {}/{}", scanned, total),
+            theme.fg(theme.text),
+        ));
+
+    f.render_widget(gauge, area);
 }
 
 /// Draw prefetch progress bar
-fn draw_progress_bar(f: &mut Frame, progress: (usize, usize), area: Rect) {
+fn draw_progress_bar(f: &mut Frame, progress: (usize, usize), theme: &Theme, area: Rect) {
     let (completed, total) = progress;
 
     if total == 0 {
@@ -108,32 +385,101 @@ fn draw_progress_bar(f: &mut Frame, progress: (usize, usize), area: Rect) {
     };
 
     let color = if completed == total {
-        Color::Green
+        theme.ready
     } else {
-        Color::Yellow
+        theme.loading
     };
 
     let label = if completed == total {
-        format!("✓ All {} pages ready", total)
+        format!("{} All {} pages ready", theme.icons.ready, total)
     } else {
         format!("Prefetching: {}/{}", completed, total)
     };
 
     let gauge = Gauge::default()
-        .gauge_style(Style::default().fg(color))
+        .gauge_style(theme.fg(color))
         .ratio(ratio)
-        .label(Span::styled(label, Style::default().fg(Color::White)));
+        .label(Span::styled(label, theme.fg(theme.text)));
 
     f.render_widget(gauge, area);
 }
 
+/// Draw the saved bookmarks
+fn draw_bookmarks(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let items: Vec<ListItem> = app
+        .bookmarks
+        .entries()
+        .iter()
+        .map(|bookmark| {
+            let age = std::time::Duration::from_secs(now.saturating_sub(bookmark.saved_at));
+            let (icon, color) = match app.bookmark_statuses.get(&bookmark.url) {
+                Some(PrefetchStatus::Binary(_)) => (theme.icons.pdf, theme.pdf),
+                Some(
+                    PrefetchStatus::Ready(_)
+                    | PrefetchStatus::ReadyArchived(_)
+                    | PrefetchStatus::Cached(_),
+                ) => (theme.icons.cached, theme.cached),
+                Some(PrefetchStatus::InProgress) => (theme.icons.loading, theme.loading),
+                Some(PrefetchStatus::Failed(failure)) => failure_icon(theme, failure),
+                Some(PrefetchStatus::Timeout) => (theme.icons.failed, theme.failed),
+                Some(PrefetchStatus::Blocked(_)) => (theme.icons.blocked, theme.blocked),
+                Some(PrefetchStatus::Pending) | None => (theme.icons.pending, theme.pending),
+            };
+
+            ListItem::new(vec![
+                Line::from(vec![
+                    Span::styled(icon, theme.fg(color)),
+                    Span::styled(format!(" {:>4} ", format_age(age)), theme.fg(theme.accent)),
+                    Span::styled(truncate(&bookmark.title, 70), theme.bold(theme.title)),
+                ]),
+                Line::from(vec![
+                    Span::raw("      "),
+                    Span::styled(truncate(&display_url(&bookmark.url), 80), theme.fg(theme.url)),
+                    Span::styled(format!("  \"{}\"", bookmark.query), theme.fg(theme.muted)),
+                ]),
+                Line::from(vec![
+                    Span::raw("      "),
+                    Span::styled(
+                        truncate(&bookmark.description, 100),
+                        theme.fg(theme.description),
+                    ),
+                ]),
+            ])
+        })
+        .collect();
+
+    let title = format!(" Bookmarks ({}) ", app.bookmarks.entries().len());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(title, theme.bold(theme.heading)))
+        .border_style(theme.border(true));
+
+    if items.is_empty() {
+        let hint = format!(
+            "No bookmarks yet, press {} on a result to save it",
+            app.config.keymap.label(Action::Bookmark)
+        );
+        let paragraph = Paragraph::new(hint)
+            .style(theme.fg(theme.description))
+            .block(block);
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(theme.selection());
+    let mut list_state = ListState::default().with_selected(Some(app.bookmark_selected));
+    f.render_stateful_widget(list, area, &mut list_state);
+}
+
 /// Draw search results list with per-result status
-fn draw_results(
-    f: &mut Frame,
-    app: &App,
-    area: Rect,
-    statuses: &HashMap<String, PrefetchStatus>,
-) {
+fn draw_results(f: &mut Frame, app: &mut App, theme: &Theme, area: Rect) {
     if app.results.is_empty() {
         let message = if app.state == AppState::Input {
             "Enter your search query above and press Enter"
@@ -142,12 +488,12 @@ fn draw_results(
         };
 
         let paragraph = Paragraph::new(message)
-            .style(Style::default().fg(Color::Gray))
+            .style(theme.fg(theme.description))
             .block(
                 Block::default()
                     .borders(Borders::ALL)
                     .title(" Results ")
-                    .border_style(Style::default().fg(Color::Gray)),
+                    .border_style(theme.border(false)),
             )
             .wrap(Wrap { trim: true });
 
@@ -155,116 +501,248 @@ fn draw_results(
         return;
     }
 
-    let visible_height = area.height.saturating_sub(2) as usize;
-    let scroll_offset = app.get_scroll_offset(visible_height);
+    let density = app.config.ui.density;
+    let item_height = density.item_height();
+    let visible = visible_item_count(area.height, item_height);
+    let scroll_offset = app.update_scroll_offset(visible);
 
-    let items: Vec<ListItem> = app
-        .results
-        .iter()
-        .enumerate()
-        .skip(scroll_offset)
-        .take(visible_height / 4 + 1)
-        .map(|(i, result)| {
-            let is_selected = i == app.selected_index;
-            let is_marked = app.selected_items.contains(&i);
-
-            // Get status for this result
-            let status = statuses
-                .get(&result.url)
-                .cloned()
-                .unwrap_or(PrefetchStatus::Pending);
-
-            // Status icon and color
-            let (status_icon, status_color) = match status {
-                PrefetchStatus::Ready(_) => ("✓", Color::Green),
-                PrefetchStatus::Cached(_) => ("📄", Color::Blue),
-                PrefetchStatus::InProgress => ("⏳", Color::Yellow),
-                PrefetchStatus::Failed(_) => ("⚠", Color::Red),
-                PrefetchStatus::Timeout => ("⏱", Color::Red),
-                PrefetchStatus::Pending => ("○", Color::DarkGray),
-            };
+    let mut title = format!(" {}Results ({})", theme.icons.results, app.results.len());
+    let mut extras = String::new();
+    let marked = app.selected_items.count(&app.results);
+    if marked > 0 {
+        extras.push_str(&format!(" • {} selected", marked));
+    }
+    if app.sort_mode != SortMode::Original {
+        extras.push_str(&format!(" • {}", app.sort_mode.label()));
+    }
+    if let Some(goggle) = &app.results_goggle {
+        extras.push_str(&format!(" • Goggle: {}", goggle));
+    }
+    // Engine and query, the query cut to what is left of the border
+    if let Some((query, engine)) = &app.last_search {
+        let filters = QueryFilters::parse(query);
+        if !filters.is_empty() {
+            extras.insert_str(0, &format!(" • {}", filters.label()));
+        }
+        let query = &filters.query;
+        let source = format!(" — {}: ", engine.short_name());
+        let used = 4 + title.width() + extras.width() + source.width() + 2;
+        let room = (area.width as usize).saturating_sub(used);
+        if room >= 8 {
+            title.push_str(&format!("{}\"{}\"", source, truncate(query, room)));
+        }
+    }
+    title.push_str(&extras);
+    title.push(' ');
 
-            // Selection indicator
-            let select_char = if is_marked { "✓" } else { " " };
-            let number = format!("{:2}.", i + 1);
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(title, theme.bold(theme.heading)))
+        .border_style(theme.border(true));
 
-            let content = vec![
-                Line::from(vec![
-                    Span::styled(
-                        select_char,
-                        Style::default().fg(if is_marked {
-                            Color::Green
-                        } else {
-                            Color::DarkGray
-                        }),
-                    ),
-                    Span::styled(number, Style::default().fg(Color::Yellow)),
-                    Span::raw(" "),
-                    Span::styled(status_icon, Style::default().fg(status_color)),
-                    Span::raw(" "),
-                    Span::styled(
-                        &result.title,
-                        Style::default()
-                            .fg(Color::White)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                ]),
-                Line::from(vec![
-                    Span::raw("    "),
-                    Span::styled(truncate(&result.url, 80), Style::default().fg(Color::Blue)),
-                ]),
-                Line::from(vec![
-                    Span::raw("    "),
-                    Span::styled(
-                        truncate(&result.description, 100),
-                        Style::default().fg(Color::Gray),
-                    ),
-                ]),
-                Line::raw(""),
-            ];
+    // How long the search and its prefetching took
+    if let Some(timing) = &app.search_timing {
+        block = block.title_bottom(Span::styled(
+            format!(" {} ", timing.summary()),
+            theme.fg(theme.muted),
+        ));
+    }
 
-            let style = if is_selected {
-                Style::default()
-                    .bg(Color::Rgb(35, 35, 45))  // Dark blue
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default()
-            };
+    // Pending count / chord, like Vim's showcmd
+    if let Some(pending) = app.key_sequence.pending() {
+        block = block.title_bottom(
+            Line::from(Span::styled(format!(" {} ", pending), theme.fg(theme.accent)))
+            .right_aligned(),
+        );
+    }
 
-            ListItem::new(content).style(style)
-        })
-        .collect();
+    let inner = block.inner(area);
+    f.render_widget(block, area);
 
-    let title = format!(" 📊 Results ({}) ", app.results.len());
+    // Only the rows on screen are looked up, and rebuilt if they changed
+    let buf = f.buffer_mut();
+    for (row, i) in (scroll_offset..app.results.len()).take(visible).enumerate() {
+        let result = &app.results[i];
+        let status = app
+            .prefetch_statuses
+            .get(&result.url)
+            .unwrap_or(&PrefetchStatus::Pending);
+        let inputs = RowInputs {
+            number: i + 1,
+            result,
+            status,
+            pinned: status.path().is_some_and(|path| app.prefetch_manager.is_pinned(path)),
+            marked: app.selected_items.contains(&result.url),
+            stats: app.page_stats.get(&result.url),
+            read: app.config.ui.track_read && app.read_log.contains(&result.url),
+            density,
+        };
+        let lines = app
+            .row_cache
+            .get_or_build(&result.url, row_key(&inputs), || result_lines(&inputs, theme));
 
-    let list = List::new(items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(Span::styled(
-                title,
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD),
-            ))
-            .border_style(Style::default().fg(Color::Cyan)),
+        let y = inner.y + (row * item_height) as u16;
+        if i == app.selected_index {
+            let height = (item_height as u16).min(inner.bottom().saturating_sub(y));
+            let selected = Rect { y, height, ..inner };
+            buf.set_style(selected, theme.selection().add_modifier(Modifier::BOLD));
+        }
+        for (j, line) in lines.iter().enumerate() {
+            buf.set_line(inner.x, y + j as u16, line, inner.width);
+        }
+    }
+    // Forget the rows of earlier result sets
+    if app.row_cache.len() > app.results.len() {
+        let results = &app.results;
+        app.row_cache.retain(|url| results.iter().any(|r| r.url == url));
+    }
+
+    // Scrollbar on the right border reflects the selected position
+    let mut scrollbar_state =
+        ScrollbarState::new(app.results.len()).position(app.selected_index);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None);
+    f.render_stateful_widget(
+        scrollbar,
+        area.inner(Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut scrollbar_state,
     );
+}
 
-    f.render_widget(list, area);
+/// What a result row is built from, hashed to key the row cache
+#[derive(Hash)]
+struct RowInputs<'a> {
+    number: usize,
+    result: &'a SearchResult,
+    status: &'a PrefetchStatus,
+    pinned: bool,
+    marked: bool,
+    stats: Option<&'a PageStats>,
+    read: bool,
+    density: Density,
+}
+
+/// Lines of a result row: the title line with its status, then by density
+/// the URL and description
+fn result_lines(row: &RowInputs, theme: &Theme) -> Vec<Line<'static>> {
+    let RowInputs {
+        result,
+        status,
+        marked,
+        ..
+    } = *row;
+
+    // Status icon and color
+    let icons = theme.icons;
+    let (status_icon, status_color) = match status {
+        _ if row.pinned => (icons.pinned, theme.pinned),
+        PrefetchStatus::Ready(_) => (icons.ready, theme.ready),
+        PrefetchStatus::ReadyArchived(_) => (icons.archived, theme.archived),
+        PrefetchStatus::Cached(_) => (icons.cached, theme.cached),
+        PrefetchStatus::Binary(_) => (icons.pdf, theme.pdf),
+        PrefetchStatus::InProgress => (icons.loading, theme.loading),
+        PrefetchStatus::Failed(failure) => failure_icon(theme, failure),
+        PrefetchStatus::Blocked(_) => (icons.blocked, theme.blocked),
+        PrefetchStatus::Timeout => (icons.timeout, theme.timeout),
+        PrefetchStatus::Pending => (icons.pending, theme.pending),
+    };
+
+    // Selection indicator
+    let select_char = if marked { icons.marked } else { " " };
+    let number = format!("{:2}.", row.number);
+
+    let mut title_line = vec![
+        Span::styled(select_char, theme.fg(if marked { theme.ready } else { theme.muted })),
+        Span::styled(number, theme.fg(theme.accent)),
+        Span::raw(" "),
+        Span::styled(status_icon, theme.fg(status_color)),
+        Span::raw(" "),
+        Span::styled(
+            result.title.clone(),
+            if row.read { theme.fg(theme.muted) } else { theme.bold(theme.title) },
+        ),
+    ];
+    if row.read {
+        title_line.push(Span::styled(format!(" {}", icons.read), theme.fg(theme.muted)));
+    }
+    // What the minimal density keeps
+    let title_spans = title_line.len();
+    if let Some(badge) = result.badge() {
+        title_line.push(Span::styled(format!(" [{}]", badge), theme.fg(theme.accent)));
+    }
+    if let PrefetchStatus::Failed(Failure::Http(code)) = status {
+        title_line.push(Span::styled(format!(" · HTTP {}", code), theme.fg(status_color)));
+    }
+    // Pages cached by an earlier search have no stats
+    if let Some(stats) = row.stats {
+        if stats.truncated {
+            title_line.push(Span::raw(" "));
+            title_line.push(Span::styled(icons.truncated, theme.fg(theme.failed)));
+        }
+        title_line.push(Span::styled(format!(" · {}", stats.summary()), theme.fg(theme.muted)));
+    }
+
+    match row.density {
+        Density::Full => {
+            let mut description_line = vec![
+                Span::raw("    "),
+                Span::styled(truncate(&result.description, 100), theme.fg(theme.description)),
+            ];
+            if let Some(age) = &result.age {
+                description_line.push(Span::styled(format!(" · {}", age), theme.fg(theme.muted)));
+            }
+            vec![
+                Line::from(title_line),
+                Line::from(vec![
+                    Span::raw("    "),
+                    Span::styled(truncate(&display_url(&result.url), 80), theme.fg(theme.url)),
+                ]),
+                Line::from(description_line),
+                Line::raw(""),
+            ]
+        }
+        Density::Compact => {
+            let host = site_host(&result.url).map(|host| display_host(&host));
+            let host = host.unwrap_or_default();
+            let domain = Span::styled(format!(" · {}", host), theme.fg(theme.url));
+            title_line.insert(title_spans, domain);
+            vec![Line::from(title_line), Line::raw("")]
+        }
+        Density::Minimal => {
+            title_line.truncate(title_spans);
+            vec![Line::from(title_line)]
+        }
+    }
+}
+
+/// Icon and color of a failed page: gone, unreachable or other
+fn failure_icon(theme: &Theme, failure: &Failure) -> (&'static str, Color) {
+    if !failure.is_retryable() {
+        (theme.icons.gone, theme.gone)
+    } else if failure.is_unreachable() {
+        (theme.icons.unreachable, theme.unreachable)
+    } else {
+        (theme.icons.failed, theme.failed)
+    }
 }
 
 /// Draw searching indicator
-fn draw_searching(f: &mut Frame, area: Rect) {
-    let paragraph = Paragraph::new("⏳ Searching...")
-        .style(
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        )
+fn draw_searching(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let mut text = vec![Line::from(format!("{} Searching...", theme.icons.loading))];
+    if let Some(progress) = &app.search_progress {
+        text.push(Line::from(Span::styled(format!("   {}", progress), theme.fg(theme.muted))));
+    }
+    let paragraph = Paragraph::new(text)
+        .style(theme.bold(theme.loading))
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .title(" Status ")
-                .border_style(Style::default().fg(Color::Yellow)),
+                .border_style(theme.fg(theme.loading)),
         )
         .wrap(Wrap { trim: true });
 
@@ -272,53 +750,732 @@ fn draw_searching(f: &mut Frame, area: Rect) {
 }
 
 /// Draw error message
-fn draw_error(f: &mut Frame, app: &App, area: Rect) {
+fn draw_error(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
     let error_text = app.error_message.as_deref().unwrap_or("Unknown error");
 
     let paragraph = Paragraph::new(format!(
-        "❌ Error: {}\n\nPress any key to continue...",
-        error_text
+        "{} Error: {}\n\nPress any key to continue...",
+        theme.icons.error, error_text
     ))
-    .style(Style::default().fg(Color::Red))
+    .style(theme.fg(theme.failed))
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .title(Span::styled(
-                " Error ",
-                Style::default()
-                    .fg(Color::Red)
-                    .add_modifier(Modifier::BOLD),
-            ))
-            .border_style(Style::default().fg(Color::Red)),
+            .title(Span::styled(" Error ", theme.bold(theme.failed)))
+            .border_style(theme.fg(theme.failed)),
     )
     .wrap(Wrap { trim: true });
 
     f.render_widget(paragraph, area);
 }
 
-/// Draw help bar with status legend
-fn draw_help_bar(f: &mut Frame, app: &App, area: Rect) {
-    let help_text = match app.state {
-        AppState::Input => "Enter: Brave │ Ctrl+D: DuckDuckGo │ Ctrl+X: SearXNG │ Ctrl+Z: Startpage │ Esc: Clear │ Ctrl+Q: Quit",
-        AppState::Results => {
-            "↑/k ↓/j: Navigate │ gg/G: First/Last │ Tab: Select │ Enter: Neovim │ Ctrl+B: Browser │ Esc: New Search │ Ctrl+Q: Quit\nStatus: ✓=Ready 📄=Cached ⏳=Loading ⚠=Failed ⏱=Timeout"
+/// Draw a recoverable error in a small box centered over `area`
+fn draw_error_popup(f: &mut Frame, message: &str, theme: &Theme, area: Rect) {
+    let max_width = area.width.saturating_sub(4).max(20);
+    let width = (message.chars().count() as u16 + 4).clamp(20, max_width);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let lines = message.chars().count().div_ceil(inner_width).max(1) as u16;
+    let height = (lines + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width: width.min(area.width),
+        height,
+    };
+
+    let paragraph = Paragraph::new(message)
+        .style(theme.fg(theme.failed))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(
+                    format!(" {} Error ", theme.icons.error),
+                    theme.bold(theme.failed),
+                ))
+                .border_style(theme.fg(theme.failed)),
+        )
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
+}
+
+/// Draw the headings popup, indented by level
+fn draw_outline(f: &mut Frame, outline: &Outline, theme: &Theme, area: Rect) {
+    let items: Vec<ListItem> = outline
+        .headings
+        .iter()
+        .map(|heading| {
+            let indent = "  ".repeat(heading.level.saturating_sub(1));
+            ListItem::new(Line::from(vec![
+                Span::raw(indent),
+                Span::styled(truncate(&heading.text, 60), theme.fg(theme.title)),
+                Span::styled(format!("  :{}", heading.line), theme.fg(theme.muted)),
+            ]))
+        })
+        .collect();
+
+    let width = (area.width * 2 / 3).clamp(20.min(area.width), area.width);
+    let height = (items.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    let filename = outline
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(
+                    format!(" Headings of {} ", filename),
+                    theme.bold(theme.heading),
+                ))
+                .border_style(theme.border(true)),
+        )
+        .highlight_style(theme.selection());
+    let mut list_state = ListState::default().with_selected(Some(outline.selected));
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut list_state);
+}
+
+/// Draw the goggle picker, "no goggle" first
+fn draw_goggle_menu(f: &mut Frame, menu: &GoggleMenu, theme: &Theme, area: Rect) {
+    let name_width = menu.goggles.iter().map(|g| g.name.width()).max().unwrap_or(0);
+    let mut items = vec![ListItem::new(Span::styled("No goggle", theme.fg(theme.title)))];
+    items.extend(menu.goggles.iter().map(|goggle| {
+        let padding = " ".repeat(name_width.saturating_sub(goggle.name.width()));
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{}{}", goggle.name, padding), theme.fg(theme.title)),
+            Span::styled(format!("  {}", goggle.id), theme.fg(theme.muted)),
+        ]))
+    }));
+
+    let width = (area.width * 2 / 3).clamp(20.min(area.width), area.width);
+    let height = (items.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(" Brave goggle ", theme.bold(theme.heading)))
+                .border_style(theme.border(true)),
+        )
+        .highlight_style(theme.selection());
+    let mut list_state = ListState::default().with_selected(Some(menu.selected));
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut list_state);
+}
+
+/// Draw the suggestion picker
+fn draw_suggestion_menu(f: &mut Frame, menu: &SuggestionMenu, theme: &Theme, area: Rect) {
+    let items: Vec<ListItem> = menu
+        .queries
+        .iter()
+        .map(|query| ListItem::new(Span::styled(query.clone(), theme.fg(theme.title))))
+        .collect();
+
+    let longest = menu.queries.iter().map(|q| q.width()).max().unwrap_or(0) as u16 + 4;
+    let width = longest.clamp(30.min(area.width), area.width);
+    let height = (items.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(" Did you mean ", theme.bold(theme.heading)))
+                .border_style(theme.border(true)),
+        )
+        .highlight_style(theme.selection());
+    let mut list_state = ListState::default().with_selected(Some(menu.selected));
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut list_state);
+}
+
+/// Draw the engine status panel: key, latest search and error of each engine
+fn draw_engine_status(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let name_width = Engine::ALL.iter().map(|e| e.name().width()).max().unwrap_or(0);
+    let mut lines = Vec::new();
+    for engine in Engine::ALL {
+        let provider = app.providers.get(engine);
+        let key = if !provider.uses_key() {
+            Span::styled("no key needed", theme.fg(theme.muted))
+        } else if provider.requires_key() {
+            Span::styled("API key missing", theme.fg(theme.failed))
+        } else {
+            Span::styled("API key set", theme.fg(theme.ready))
+        };
+        let mut spans = vec![
+            Span::styled(
+                format!("{:<width$}  ", engine.name(), width = name_width),
+                theme.bold(theme.title),
+            ),
+            key,
+        ];
+
+        let Some(stats) = app.engine_stats.get(engine) else {
+            spans.push(Span::styled(" │ not used yet", theme.fg(theme.muted)));
+            lines.push(Line::from(spans));
+            continue;
+        };
+        let (_, time) = format_utc(stats.last_used.unwrap_or_default());
+        spans.push(Span::styled(format!(" │ {} UTC ", time), theme.fg(theme.text)));
+        spans.push(match stats.last_results {
+            Some(count) => Span::styled(
+                format!("{} {} results", theme.icons.ready, count),
+                theme.fg(theme.ready),
+            ),
+            None => Span::styled(format!("{} failed", theme.icons.failed), theme.fg(theme.failed)),
+        });
+        if let Some(latency) = stats.latency {
+            spans.push(Span::styled(
+                format!(" in {} ms", latency.as_millis()),
+                theme.fg(theme.muted),
+            ));
         }
-        AppState::Searching => "⏳ Please wait... │ Ctrl+Q: Quit",
-        AppState::Error => "Press any key to continue │ Ctrl+Q: Quit",
+        lines.push(Line::from(spans));
+
+        // The latest error stays listed, dimmed once the engine works again
+        if let Some(error) = &stats.last_error {
+            let color = if stats.failed() { theme.failed } else { theme.muted };
+            lines.push(Line::from(Span::styled(
+                format!("{}Last error: {}", " ".repeat(name_width + 2), error),
+                theme.fg(color),
+            )));
+        }
+    }
+
+    let width = (area.width * 4 / 5).clamp(40.min(area.width), area.width);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let rows: usize = lines.iter().map(|line| line.width().div_ceil(inner_width).max(1)).sum();
+    let height = (rows as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
     };
 
-    let paragraph = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::Cyan))
+    let paragraph = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::DarkGray)),
+                .title(Span::styled(" Search engines ", theme.bold(theme.heading)))
+                .border_style(theme.border(true)),
         )
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
+}
+
+/// Draw the startup notices about the configuration
+fn draw_notices(f: &mut Frame, notices: &[String], theme: &Theme, area: Rect) {
+    let mut lines: Vec<Line> = notices
+        .iter()
+        .map(|notice| Line::from(Span::styled(format!("⚠ {}", notice), theme.fg(theme.failed))))
+        .collect();
+    lines.push(Line::raw(""));
+    lines.push(Line::from(Span::styled("Press any key to continue", theme.fg(theme.muted))));
+
+    let width = (area.width * 4 / 5).clamp(40.min(area.width), area.width);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let rows: usize = lines.iter().map(|line| line.width().div_ceil(inner_width).max(1)).sum();
+    let height = (rows as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(" Configuration ", theme.bold(theme.heading)))
+                .border_style(theme.fg(theme.failed)),
+        )
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
+}
+
+/// Draw the selected result in full: title, URL, description, status and file
+///
+/// Sized to its text up to 80% of the results area; long values wrap.
+fn draw_preview(f: &mut Frame, app: &App, result: &SearchResult, theme: &Theme, area: Rect) {
+    let status = app.prefetch_statuses.get(&result.url).unwrap_or(&PrefetchStatus::Pending);
+    let field = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{}: ", label), theme.fg(theme.muted)),
+            Span::styled(value, theme.fg(theme.text)),
+        ])
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(result.title.clone(), theme.bold(theme.title))),
+        Line::from(Span::styled(display_url(&result.url), theme.fg(theme.url))),
+    ];
+    if display_url(&result.url) != result.url {
+        lines.push(Line::from(Span::styled(result.url.clone(), theme.fg(theme.muted))));
+    }
+    if !result.description.is_empty() {
+        lines.push(Line::raw(""));
+        lines.push(Line::from(Span::styled(
+            result.description.clone(),
+            theme.fg(theme.description),
+        )));
+    }
+    lines.push(Line::raw(""));
+    lines.push(field("Status", status_text(status)));
+    if let Some(path) = status.path() {
+        lines.push(field("File", path.display().to_string()));
+    }
+
+    let max_width = area.width * 4 / 5;
+    let longest = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+    let width = longest.clamp(40.min(max_width), max_width.max(1));
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let rows: usize = lines
+        .iter()
+        .map(|line| {
+            let text: String = line.spans.iter().map(|span| span.content.as_ref()).collect();
+            wrapped_rows(&text, inner_width)
+        })
+        .sum();
+    let height = (rows as u16 + 2).min(area.height * 4 / 5);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(" Result ", theme.bold(theme.heading)))
+                .border_style(theme.fg(theme.border)),
+        )
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
+}
+
+/// A prefetch status in words, with the reason of a failure
+fn status_text(status: &PrefetchStatus) -> String {
+    match status {
+        PrefetchStatus::Pending => "waiting to be fetched".to_string(),
+        PrefetchStatus::InProgress => "loading".to_string(),
+        PrefetchStatus::Ready(_) => "ready".to_string(),
+        PrefetchStatus::ReadyArchived(_) => "ready, from the Wayback Machine".to_string(),
+        PrefetchStatus::Binary(_) => "PDF, opens in the system viewer".to_string(),
+        PrefetchStatus::Cached(_) => "cached".to_string(),
+        PrefetchStatus::Failed(failure) => format!("failed: {}", failure),
+        PrefetchStatus::Blocked(reason) => format!("blocked by a {}", reason),
+        PrefetchStatus::Timeout => "timed out".to_string(),
+    }
+}
+
+/// Rows `text` takes when word-wrapped to `width` columns
+///
+/// Words wider than a row are broken across rows, as the paragraph does.
+fn wrapped_rows(text: &str, width: usize) -> usize {
+    let (mut rows, mut used) = (1, 0);
+    for word in text.split(' ') {
+        let word_width = word.width();
+        let needed = if used == 0 { word_width } else { used + 1 + word_width };
+        if needed <= width {
+            used = needed;
+            continue;
+        }
+        if used > 0 {
+            rows += 1;
+        }
+        let extra = word_width.saturating_sub(1) / width;
+        rows += extra;
+        used = word_width - extra * width;
+    }
+    rows
+}
+
+/// Draw the "open with" menu, greying out commands waiting for the page
+fn draw_open_with(f: &mut Frame, menu: &OpenWithMenu, theme: &Theme, area: Rect) {
+    let label_width = menu.commands.iter().map(|c| c.label.width()).max().unwrap_or(0);
+    let items: Vec<ListItem> = menu
+        .commands
+        .iter()
+        .map(|command| {
+            let (label, template) = if menu.is_available(command) {
+                (theme.fg(theme.title), theme.fg(theme.muted))
+            } else {
+                (theme.fg(theme.muted), theme.fg(theme.muted))
+            };
+            let padding = " ".repeat(label_width.saturating_sub(command.label.width()));
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{}{}", command.label, padding), label),
+                Span::styled(format!("  {}", truncate(&command.template, 60)), template),
+            ]))
+        })
+        .collect();
+
+    let width = (area.width * 2 / 3).clamp(20.min(area.width), area.width);
+    let height = (items.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    let title = if menu.path.is_some() {
+        " Open with ".to_string()
+    } else {
+        " Open with (⏳ page not fetched yet) ".to_string()
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(title, theme.bold(theme.heading)))
+                .border_style(theme.border(true)),
+        )
+        .highlight_style(theme.selection());
+    let mut list_state = ListState::default().with_selected(Some(menu.selected));
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut list_state);
+}
+
+/// Draw the prompt for a note on the selected page
+fn draw_note_prompt(f: &mut Frame, input: &InputLine, theme: &Theme, area: Rect) {
+    let label = " 📝 Note: ";
+    let label_width = label.width() as u16;
+    let width = area.width.saturating_sub(label_width) as usize;
+    let (visible, cursor_col) = input.visible_window(width);
+
+    let paragraph = Paragraph::new(Line::from(vec![
+        Span::styled(label, theme.bold(theme.accent)),
+        Span::styled(visible, theme.fg(theme.text)),
+    ]));
+    f.render_widget(paragraph, area);
+    f.set_cursor_position((area.x + label_width + cursor_col as u16, area.y));
+}
+
+/// Draw the one-line status message
+fn draw_status_line(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let waiting;
+    let message = match (app.open_wait_since, &app.pending_open) {
+        (_, Some(urls)) => {
+            waiting = format!(
+                "⏳ Open {} tabs in the browser? (y: Open, any other key: Cancel)",
+                urls.len()
+            );
+            waiting.as_str()
+        }
+        (Some(since), None) => {
+            let spinner = theme.icons.spinner;
+            let frame = since.elapsed().as_millis() / SPINNER_INTERVAL.as_millis();
+            waiting = format!(
+                "⏳ Waiting for page… {} (Esc: Cancel)",
+                spinner[frame as usize % spinner.len()]
+            );
+            waiting.as_str()
+        }
+        (None, None) => app.status_message.as_str(),
+    };
+
+    // Color by the message's leading marker
+    let color = if message.starts_with('⚠') || message.starts_with('❌') {
+        theme.failed
+    } else if message.starts_with('⏳') {
+        theme.loading
+    } else if message.starts_with('✓') {
+        theme.ready
+    } else {
+        theme.text
+    };
+
+    let paragraph = Paragraph::new(Line::from(vec![
+        Span::raw(" "),
+        Span::styled(theme.icons.message(message), theme.fg(color)),
+    ]));
+
+    f.render_widget(paragraph, area);
+}
+
+/// Draw help bar with status legend
+fn draw_help_bar(f: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    // Keys as configured, e.g. "Ctrl+B" or "j/↓"
+    let keys = &app.config.keymap;
+    let k = |action| keys.label(action);
+    let default_engine = app.providers.get(app.config.search.default_engine);
+    let help_text = match app.state {
+        AppState::Input | AppState::Results if app.goggle_menu.is_some() => format!(
+            "{} {}: Navigate │ {}: Use for Brave searches │ {}: Close │ {}: Quit",
+            k(Action::Prev),
+            k(Action::Next),
+            k(Action::OpenEditor),
+            k(Action::Back),
+            k(Action::Quit),
+        ),
+        AppState::Input => format!(
+            "{}: {} │ {}: DuckDuckGo │ {}: SearXNG │ {}: Startpage │ {}: Library │ {}: Search cached pages │ {}: Bookmarks │ {}: Engine status │ {} {}: Goggle/On-off │ {}: Clear │ {}: Quit\nCtrl+A/E: Start/End │ Ctrl+W: Delete word │ Ctrl+U/K: Kill to start/end │ Alt+B/F: Word left/right",
+            k(Action::Search),
+            if default_engine.requires_key() {
+                format!("{} (no API key)", default_engine.name())
+            } else {
+                default_engine.name().to_string()
+            },
+            k(Action::SearchDdg),
+            k(Action::SearchSearxng),
+            k(Action::SearchStartpage),
+            k(Action::Library),
+            k(Action::LocalSearch),
+            k(Action::Bookmarks),
+            k(Action::EngineStatus),
+            k(Action::Goggles),
+            k(Action::ToggleGoggle),
+            k(Action::Clear),
+            k(Action::Quit),
+        ),
+        AppState::Results if app.note_input.is_some() => {
+            "Type a note for this page │ Enter: Save │ Esc: Cancel".to_string()
+        }
+        AppState::Results if app.outline.is_some() => format!(
+            "{} {}: Navigate │ {}: {} at the heading │ {}: Close │ {}: Quit",
+            k(Action::Prev),
+            k(Action::Next),
+            k(Action::OpenEditor),
+            app.config.editor.name(),
+            k(Action::Back),
+            k(Action::Quit),
+        ),
+        AppState::Results if app.open_with.is_some() => format!(
+            "{} {}: Navigate │ {}: Run │ {}: Close │ {}: Quit",
+            k(Action::Prev),
+            k(Action::Next),
+            k(Action::OpenEditor),
+            k(Action::Back),
+            k(Action::Quit),
+        ),
+        AppState::Results if app.suggestion_menu.is_some() => format!(
+            "{} {}: Navigate │ {}: Search │ {}: Close │ {}: Quit",
+            k(Action::Prev),
+            k(Action::Next),
+            k(Action::OpenEditor),
+            k(Action::Back),
+            k(Action::Quit),
+        ),
+        AppState::Results => format!(
+            "{} {}: Navigate │ 5{}: Count │ {} {}: First/Last │ {} {}: Half page │ {} {} {}: Top/Mid/Bottom │ {} {} {}: Select/All/Invert │ {}: {} │ {}: Browser │ {} {}: Copy URL/Path │ {} {}: Retry/Retry all │ {} {}: Refresh/Re-extract │ {}: Pin │ {}: Bookmark │ {} {}: Remove/Undo │ {}: Sort │ {}: Search this site │ {}: Open at heading │ {}: Note │ {} {} {}: Export Markdown/JSON/Obsidian │ {}: Cite │ {}: Open with │ {}: Quickfix list │ {} {}: Previous/Next search │ {}: More results │ {}: Engine status │ {} {}: Goggle/On-off │ {}: Density │ {}: Read/unread │ {}: Details │ {}: Original/Suggested query │ {}: New Search │ {}: Quit\nStatus: {}",
+            k(Action::Prev),
+            k(Action::Next),
+            keys.primary(Action::Next),
+            k(Action::First),
+            k(Action::Last),
+            k(Action::HalfPageDown),
+            k(Action::HalfPageUp),
+            k(Action::WindowTop),
+            k(Action::WindowMiddle),
+            k(Action::WindowBottom),
+            k(Action::ToggleSelect),
+            k(Action::SelectAll),
+            k(Action::InvertSelection),
+            k(Action::OpenEditor),
+            app.config.editor.name(),
+            k(Action::OpenBrowser),
+            k(Action::CopyUrl),
+            k(Action::CopyPath),
+            k(Action::Retry),
+            k(Action::RetryAll),
+            k(Action::Refresh),
+            k(Action::ReExtract),
+            k(Action::Pin),
+            k(Action::Bookmark),
+            k(Action::Delete),
+            k(Action::Undo),
+            k(Action::Sort),
+            k(Action::SiteSearch),
+            k(Action::Outline),
+            k(Action::Annotate),
+            k(Action::Export),
+            k(Action::ExportJson),
+            k(Action::ExportObsidian),
+            k(Action::Cite),
+            k(Action::OpenWith),
+            k(Action::Quickfix),
+            k(Action::HistoryBack),
+            k(Action::HistoryForward),
+            k(Action::NextPage),
+            k(Action::EngineStatus),
+            k(Action::Goggles),
+            k(Action::ToggleGoggle),
+            k(Action::Density),
+            k(Action::ToggleRead),
+            k(Action::Preview),
+            k(Action::Suggestions),
+            k(Action::Back),
+            k(Action::Quit),
+            status_legend(theme),
+        ),
+        AppState::Searching => format!(
+            "{} Please wait... │ Esc: Cancel │ {}: Quit",
+            theme.icons.loading,
+            k(Action::Quit)
+        ),
+        AppState::Error => format!("Press any key to continue │ {}: Quit", k(Action::Quit)),
+        AppState::Bookmarks => format!(
+            "{} {}: Navigate │ {}: {} │ {}: Browser │ {}: Delete │ {}: Back to search │ {}: Quit",
+            k(Action::Prev),
+            k(Action::Next),
+            k(Action::OpenEditor),
+            app.config.editor.name(),
+            k(Action::OpenBrowser),
+            k(Action::Delete),
+            k(Action::Back),
+            k(Action::Quit),
+        ),
+        AppState::LocalSearch => format!(
+            "{} {}: Navigate │ {}: {} at the line │ {} {}: Back to search │ {}: Quit",
+            k(Action::Prev),
+            k(Action::Next),
+            k(Action::OpenEditor),
+            app.config.editor.name(),
+            k(Action::Back),
+            k(Action::LocalSearch),
+            k(Action::Quit),
+        ),
+        AppState::Library if app.library_filtering => {
+            "Type to filter │ Enter: Done │ Esc: Clear filter".to_string()
+        }
+        AppState::Library => format!(
+            "{} {}: Navigate │ {}: {} │ {}: Delete │ {}: Pin │ {}: Filter │ {} {}: Back to search │ {}: Quit",
+            k(Action::Prev),
+            k(Action::Next),
+            k(Action::OpenEditor),
+            app.config.editor.name(),
+            k(Action::Delete),
+            k(Action::Pin),
+            k(Action::Filter),
+            k(Action::Back),
+            k(Action::Library),
+            k(Action::Quit),
+        ),
+    };
+
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.fg(theme.muted));
+    let mut indicators = Vec::new();
+    if logging::enabled() {
+        indicators.push(Span::styled(
+            format!(" Debug log: {} ", logging::log_path().display()),
+            theme.fg(theme.accent),
+        ));
+    }
+    if proxy_active() {
+        indicators.push(Span::styled(" via proxy ", theme.fg(theme.ready)));
+    }
+    if !indicators.is_empty() {
+        block = block.title(Line::from(indicators).right_aligned());
+    }
+
+    let resting = app.cooldowns.resting(Instant::now());
+    let lines = grey_out_engines(&help_text, &app.unusable_engines, &resting, theme);
+    let paragraph = Paragraph::new(lines)
+        .style(theme.fg(theme.help))
+        .block(block)
         .wrap(Wrap { trim: true });
 
     f.render_widget(paragraph, area);
 }
 
+/// Help bar lines with the keys of `unusable` and `resting` engines greyed out
+///
+/// A key is an engine's when its description starts with the engine's
+/// name, like `Ctrl+D: DuckDuckGo` or `Enter: Brave (no API key)`. Resting
+/// engines count down, like `Enter: Brave available in 42s`.
+fn grey_out_engines<'a>(
+    help_text: &'a str,
+    unusable: &[Engine],
+    resting: &[(Engine, Duration)],
+    theme: &Theme,
+) -> Vec<Line<'a>> {
+    help_text
+        .lines()
+        .map(|line| {
+            let mut spans = Vec::new();
+            for (i, segment) in line.split(" │ ").enumerate() {
+                if i > 0 {
+                    spans.push(Span::raw(" │ "));
+                }
+                let (key, description) = segment.split_once(": ").unwrap_or((segment, ""));
+                let rest = resting
+                    .iter()
+                    .find(|(engine, _)| description.starts_with(engine.name()));
+                if let Some((engine, left)) = rest {
+                    let secs = countdown_secs(*left);
+                    spans.push(Span::styled(
+                        format!("{}: {} available in {}s", key, engine.name(), secs),
+                        theme.fg(theme.muted),
+                    ));
+                } else if unusable.iter().any(|engine| description.starts_with(engine.name())) {
+                    spans.push(Span::styled(segment, theme.fg(theme.muted)));
+                } else {
+                    spans.push(Span::raw(segment));
+                }
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Status icons and their meaning, for the help bar
+fn status_legend(theme: &Theme) -> String {
+    let icons = theme.icons;
+    [
+        (icons.ready, "Ready"),
+        (icons.cached, "Cached"),
+        (icons.pdf, "PDF"),
+        (icons.archived, "Archived"),
+        (icons.pinned, "Pinned"),
+        (icons.loading, "Loading"),
+        (icons.failed, "Failed"),
+        (icons.blocked, "Blocked"),
+        (icons.timeout, "Timeout"),
+        (icons.truncated, "Truncated"),
+    ]
+    .iter()
+    .map(|(icon, meaning)| format!("{}={}", icon, meaning))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
 /// Truncate string to max length
 fn truncate(s: &str, max_len: usize) -> String {
     let char_count = s.chars().count();
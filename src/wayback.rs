@@ -0,0 +1,127 @@
+//! Wayback Machine fallback for dead or blocked pages
+//!
+//! Looks up the closest archived snapshot of a URL through the availability
+//! API. Snapshots are downloaded in their raw form (`id_`), so the page
+//! comes back without the archive toolbar or rewritten links.
+//!
+//! The fallback is on by default; set `prefetch.wayback = false` or
+//! `WEBSEARCH_WAYBACK=0` to turn it off.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::globals::get_api_client;
+
+/// Wayback Machine availability API
+const AVAILABILITY_API: &str = "https://archive.org/wayback/available";
+
+/// An archived copy of a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Snapshot page on web.archive.org
+    pub url: String,
+    /// Capture time as `YYYYMMDDhhmmss`
+    pub timestamp: String,
+}
+
+impl Snapshot {
+    /// URL serving the archived bytes of `original` unmodified
+    pub fn raw_url(&self, original: &str) -> String {
+        format!("https://web.archive.org/web/{}id_/{}", self.timestamp, original)
+    }
+
+    /// Capture date as `YYYY-MM-DD`
+    pub fn date(&self) -> String {
+        match (
+            self.timestamp.get(0..4),
+            self.timestamp.get(4..6),
+            self.timestamp.get(6..8),
+        ) {
+            (Some(year), Some(month), Some(day)) => format!("{}-{}-{}", year, month, day),
+            _ => self.timestamp.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Availability {
+    #[serde(default)]
+    archived_snapshots: ArchivedSnapshots,
+}
+
+#[derive(Deserialize, Default)]
+struct ArchivedSnapshots {
+    closest: Option<Closest>,
+}
+
+#[derive(Deserialize)]
+struct Closest {
+    available: bool,
+    url: String,
+    timestamp: String,
+    #[serde(default)]
+    status: String,
+}
+
+/// Find the closest archived snapshot of a URL
+///
+/// Returns `None` when the page was never archived successfully.
+pub async fn find_snapshot(url: &str) -> Result<Option<Snapshot>> {
+    tracing::debug!(engine = "wayback", url, "availability lookup");
+    let json = get_api_client()?
+        .get(AVAILABILITY_API)
+        .query(&[("url", url)])
+        .send()
+        .await
+        .context("Failed to query the Wayback Machine")?
+        .error_for_status()
+        .context("Wayback Machine lookup failed")?
+        .text()
+        .await
+        .context("Failed to read Wayback Machine response")?;
+
+    let snapshot = parse_availability(&json)?;
+    tracing::debug!(url, snapshot = ?snapshot.as_ref().map(|s| &s.url), "availability result");
+    Ok(snapshot)
+}
+
+/// Parse an availability API response
+///
+/// Snapshots of error pages (non-2xx captures) are ignored.
+fn parse_availability(json: &str) -> Result<Option<Snapshot>> {
+    let availability: Availability =
+        serde_json::from_str(json).context("Invalid Wayback Machine response")?;
+
+    Ok(availability
+        .archived_snapshots
+        .closest
+        .filter(|c| c.available && c.status.starts_with('2'))
+        .map(|c| Snapshot {
+            url: c.url,
+            timestamp: c.timestamp,
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_availability() {
+        let json = r#"{"url": "example.com/post", "archived_snapshots": {"closest": {"status": "200", "available": true, "url": "http://web.archive.org/web/20190305121530/https://example.com/post", "timestamp": "20190305121530"}}}"#;
+        let snapshot = parse_availability(json).unwrap().unwrap();
+        assert_eq!(snapshot.date(), "2019-03-05");
+        assert_eq!(
+            snapshot.raw_url("https://example.com/post"),
+            "https://web.archive.org/web/20190305121530id_/https://example.com/post"
+        );
+
+        let missing = r#"{"url": "example.com/never", "archived_snapshots": {}}"#;
+        assert_eq!(parse_availability(missing).unwrap(), None);
+
+        let error_page = r#"{"archived_snapshots": {"closest": {"status": "404", "available": true, "url": "http://web.archive.org/web/2020/x", "timestamp": "20200101000000"}}}"#;
+        assert_eq!(parse_availability(error_page).unwrap(), None);
+
+        assert!(parse_availability("<html>").is_err());
+    }
+}